@@ -0,0 +1,315 @@
+//! Noise analysis of a sample history: overlapping Allan deviation and a
+//! Welch power spectral density estimate.
+//!
+//! Both work on equally spaced samples. `tau0` / `sample_interval` is the
+//! spacing in seconds; results keep the measurement unit (ADEV in units,
+//! PSD in units²/Hz), they are not normalised to fractional frequency.
+//!
+//! Non-finite values mark gaps (overload, dropped readings). They keep the
+//! sample spacing intact; averages and segments that span a gap are left
+//! out instead of stitching the samples around it together.
+
+/// One point of an Allan deviation curve.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdevPoint {
+    pub tau: f64,
+    pub adev: f64,
+    /// Number of second differences averaged for this tau.
+    pub terms: usize,
+}
+
+/// One bin of a one-sided power spectral density.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PsdPoint {
+    pub freq: f64,
+    pub psd: f64,
+}
+
+impl PsdPoint {
+    /// Amplitude spectral density, units/√Hz.
+    pub fn asd(&self) -> f64 {
+        self.psd.sqrt()
+    }
+}
+
+/// Overlapping Allan deviation at octave spaced averaging factors
+/// m = 1, 2, 4, ... as long as at least one second difference fits.
+pub fn overlapping_adev(values: &[f64], tau0: f64) -> Vec<AdevPoint> {
+    if values.len() < 3 || tau0 <= 0.0 {
+        return Vec::new();
+    }
+    // Integrate to "phase" so each tau is a sum of second differences.
+    // `gaps[i]` counts the gaps before sample i to tell which sums hold one.
+    let mut phase = Vec::with_capacity(values.len() + 1);
+    let mut gaps = Vec::with_capacity(values.len() + 1);
+    phase.push(0.0);
+    gaps.push(0);
+    let (mut acc, mut missing) = (0.0, 0);
+    for &y in values {
+        if y.is_finite() {
+            acc += y;
+        } else {
+            missing += 1;
+        }
+        phase.push(acc);
+        gaps.push(missing);
+    }
+    let n = phase.len();
+
+    let mut points = Vec::new();
+    let mut m = 1;
+    while 2 * m < n {
+        let (sum, terms) = (0..n - 2 * m)
+            .filter(|&i| gaps[i + 2 * m] == gaps[i])
+            .map(|i| {
+                let d = phase[i + 2 * m] - 2.0 * phase[i + m] + phase[i];
+                d * d
+            })
+            .fold((0.0, 0), |(sum, terms), d2| (sum + d2, terms + 1));
+        if terms > 0 {
+            let avar = sum / (2.0 * (m * m) as f64 * terms as f64);
+            points.push(AdevPoint {
+                tau: m as f64 * tau0,
+                adev: avar.sqrt(),
+                terms,
+            });
+        }
+        m *= 2;
+    }
+    points
+}
+
+/// Welch PSD: Hann windowed, mean removed per segment, 50 % overlap.
+/// Segment length is the largest power of two not above `max_segment`
+/// and the sample count. The DC bin is dropped, as are segments with a gap.
+pub fn welch_psd(values: &[f64], sample_interval: f64, max_segment: usize) -> Vec<PsdPoint> {
+    if sample_interval <= 0.0 {
+        return Vec::new();
+    }
+    let limit = values.len().min(max_segment);
+    if limit < 8 {
+        return Vec::new();
+    }
+    let seg_len = 1usize << (usize::BITS - 1 - limit.leading_zeros());
+    let step = seg_len / 2;
+    let fs = 1.0 / sample_interval;
+
+    let window: Vec<f64> = (0..seg_len)
+        .map(|i| {
+            let phase = 2.0 * std::f64::consts::PI * i as f64 / seg_len as f64;
+            0.5 * (1.0 - phase.cos())
+        })
+        .collect();
+    let window_power: f64 = window.iter().map(|w| w * w).sum();
+
+    let mut acc = vec![0.0; seg_len / 2 + 1];
+    let mut segments = 0;
+    let mut start = 0;
+    while start + seg_len <= values.len() {
+        let seg = &values[start..start + seg_len];
+        start += step;
+        if !seg.iter().all(|x| x.is_finite()) {
+            continue;
+        }
+        let mean = seg.iter().sum::<f64>() / seg_len as f64;
+        let mut buf: Vec<[f64; 2]> = seg
+            .iter()
+            .zip(&window)
+            .map(|(&x, &w)| [(x - mean) * w, 0.0])
+            .collect();
+        fft_in_place(&mut buf);
+        for (k, slot) in acc.iter_mut().enumerate() {
+            let [re, im] = buf[k];
+            *slot += re * re + im * im;
+        }
+        segments += 1;
+    }
+    if segments == 0 {
+        return Vec::new();
+    }
+
+    let scale = 1.0 / (fs * window_power * segments as f64);
+    acc.iter()
+        .enumerate()
+        .skip(1)
+        .map(|(k, &power)| {
+            // One-sided: fold negative frequencies, except at Nyquist
+            let fold = if k == seg_len / 2 { 1.0 } else { 2.0 };
+            PsdPoint {
+                freq: k as f64 * fs / seg_len as f64,
+                psd: power * scale * fold,
+            }
+        })
+        .collect()
+}
+
+/// Iterative radix-2 FFT on `[re, im]` pairs. Length must be a power of two.
+fn fft_in_place(buf: &mut [[f64; 2]]) {
+    let n = buf.len();
+    debug_assert!(n.is_power_of_two());
+    // Bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            buf.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f64::consts::PI / len as f64;
+        for chunk in buf.chunks_mut(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let [ore, oim] = chunk[k + len / 2];
+                let t = [ore * cos - oim * sin, ore * sin + oim * cos];
+                let u = chunk[k];
+                chunk[k] = [u[0] + t[0], u[1] + t[1]];
+                chunk[k + len / 2] = [u[0] - t[0], u[1] - t[1]];
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Typical spacing of a timestamp series in seconds (median of the
+/// positive differences), `None` if there is none.
+pub fn median_interval(timestamps: &[f64]) -> Option<f64> {
+    let mut diffs: Vec<f64> = timestamps
+        .windows(2)
+        .map(|w| w[1] - w[0])
+        .filter(|d| *d > 0.0)
+        .collect();
+    if diffs.is_empty() {
+        return None;
+    }
+    diffs.sort_by(f64::total_cmp);
+    Some(diffs[diffs.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic uniform noise in [-0.5, 0.5)
+    fn noise(n: usize) -> Vec<f64> {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        (0..n)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+            })
+            .collect()
+    }
+
+    #[test]
+    fn adev_of_constant_is_zero() {
+        let points = overlapping_adev(&[5.0; 64], 1.0);
+        assert!(!points.is_empty());
+        assert!(points.iter().all(|p| p.adev == 0.0));
+        assert_eq!(points[0].tau, 1.0);
+        assert_eq!(points[1].tau, 2.0);
+    }
+
+    #[test]
+    fn adev_alternating_signal_at_tau0() {
+        // y = +1, -1, ...: every first difference is ±2, so AVAR(τ0) = 2
+        let values: Vec<f64> = (0..100)
+            .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+        let points = overlapping_adev(&values, 0.5);
+        assert_eq!(points[0].tau, 0.5);
+        assert!((points[0].adev - 2f64.sqrt()).abs() < 1e-12);
+        assert_eq!(points[0].terms, 99);
+        // Averaging pairs cancels the alternation completely
+        assert!(points[1].adev.abs() < 1e-12);
+    }
+
+    #[test]
+    fn adev_white_noise_falls_with_sqrt_tau() {
+        let values = noise(16384);
+        let points = overlapping_adev(&values, 1.0);
+        let sigma = (1.0f64 / 12.0).sqrt();
+        assert!((points[0].adev / sigma - 1.0).abs() < 0.05);
+        // m = 16: expect σ / 4
+        assert!((points[4].adev / (sigma / 4.0) - 1.0).abs() < 0.15);
+    }
+
+    #[test]
+    fn adev_needs_three_samples() {
+        assert!(overlapping_adev(&[1.0, 2.0], 1.0).is_empty());
+        assert!(overlapping_adev(&[1.0, 2.0, 3.0], 0.0).is_empty());
+    }
+
+    #[test]
+    fn fft_matches_dft() {
+        let input: Vec<f64> = noise(16);
+        let mut buf: Vec<[f64; 2]> = input.iter().map(|&x| [x, 0.0]).collect();
+        fft_in_place(&mut buf);
+        for (k, &[re, im]) in buf.iter().enumerate() {
+            let (mut dre, mut dim) = (0.0, 0.0);
+            for (n, &x) in input.iter().enumerate() {
+                let a = -2.0 * std::f64::consts::PI * (k * n) as f64 / 16.0;
+                dre += x * a.cos();
+                dim += x * a.sin();
+            }
+            assert!((re - dre).abs() < 1e-12 && (im - dim).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn psd_finds_sine_and_integrates_to_variance() {
+        let fs = 100.0;
+        let values: Vec<f64> = (0..4096)
+            .map(|i| (2.0 * std::f64::consts::PI * 12.5 * i as f64 / fs).sin())
+            .collect();
+        let psd = welch_psd(&values, 1.0 / fs, 1024);
+        assert_eq!(psd.len(), 512);
+        let peak = psd.iter().max_by(|a, b| a.psd.total_cmp(&b.psd)).unwrap();
+        assert!((peak.freq - 12.5).abs() < fs / 1024.0);
+        // Parseval: area under the PSD is the signal variance (0.5)
+        let df = fs / 1024.0;
+        let power: f64 = psd.iter().map(|p| p.psd * df).sum();
+        assert!((power - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn psd_white_noise_level() {
+        let values = noise(8192);
+        let psd = welch_psd(&values, 0.01, 256);
+        // One-sided white noise density is 2σ²/fs
+        let expected = 2.0 * (1.0 / 12.0) / 100.0;
+        let mean = psd.iter().map(|p| p.psd).sum::<f64>() / psd.len() as f64;
+        assert!((mean / expected - 1.0).abs() < 0.1);
+        assert!(welch_psd(&values[..4], 0.01, 256).is_empty());
+    }
+
+    #[test]
+    fn gaps_drop_only_the_terms_and_segments_they_touch() {
+        let mut values = noise(64);
+        values[10] = f64::NAN;
+        let adev = overlapping_adev(&values, 1.0);
+        // m = 1 has 63 terms, sample 10 is part of the ones at i = 9 and i = 10
+        assert_eq!(adev[0].terms, 61);
+        assert!(adev.iter().all(|p| p.adev.is_finite()));
+
+        let psd = welch_psd(&values, 1.0, 16);
+        assert_eq!(psd.len(), 8);
+        assert!(psd.iter().all(|p| p.psd.is_finite()));
+        assert!(welch_psd(&[f64::NAN; 16], 1.0, 16).is_empty());
+    }
+
+    #[test]
+    fn median_interval_ignores_repeats() {
+        assert_eq!(median_interval(&[0.0, 1.0, 1.0, 2.0, 3.5]), Some(1.0));
+        assert_eq!(median_interval(&[3.0]), None);
+        assert_eq!(median_interval(&[3.0, 3.0]), None);
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::path::Path;
+
+use csv::{ReaderBuilder, WriterBuilder};
+use egui::{Color32, Slider, SliderClamping};
+use egui_plot::{HoverPosition, Legend, Line, Plot, PlotPoints, Points};
+use rfd::FileDialog;
+
+use crate::analysis::{AdevPoint, PsdPoint, median_interval, overlapping_adev, welch_psd};
+use crate::helpers::METER_OVERLOAD_VALUE;
+
+const CURVE_COLOR: Color32 = Color32::from_rgb(80, 140, 230);
+
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum AnalysisKind {
+    Adev,
+    Psd,
+}
+
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum AnalysisSource {
+    History,   // Histogram sample buffer
+    Recording, // Loaded CSV/JSON recording
+}
+
+// A recording file loaded for analysis
+pub struct LoadedRecording {
+    pub name: String,
+    pub unit: String,
+    pub values: Vec<f64>,
+    pub interval_s: Option<f64>, // Median timestamp spacing
+}
+
+pub enum AnalysisResult {
+    Adev(Vec<AdevPoint>),
+    Psd(Vec<PsdPoint>),
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AnalysisState {
+    pub kind: AnalysisKind,
+    pub source: AnalysisSource,
+    pub log_log: bool,
+    pub interval_override_s: f64, // 0 derives the spacing from the source
+    pub max_segment: usize,       // Welch segment length limit
+    #[serde(skip)]
+    pub recording: Option<LoadedRecording>,
+    #[serde(skip)]
+    pub result: Option<(AnalysisResult, String)>, // Curve and its unit
+    #[serde(skip)]
+    pub status: Option<String>,
+}
+
+impl Default for AnalysisState {
+    fn default() -> Self {
+        Self {
+            kind: AnalysisKind::Adev,
+            source: AnalysisSource::History,
+            log_log: true,
+            interval_override_s: 0.0,
+            max_segment: 1024,
+            recording: None,
+            result: None,
+            status: None,
+        }
+    }
+}
+
+fn usable(value: f64) -> bool {
+    value.is_finite() && value != METER_OVERLOAD_VALUE
+}
+
+/// Overload readings become gaps, so the samples around them keep their
+/// spacing.
+fn gap_if_unusable(value: f64) -> f64 {
+    if usable(value) { value } else { f64::NAN }
+}
+
+fn parse_timestamp(raw: &str) -> Option<f64> {
+    if let Ok(t) = chrono::DateTime::parse_from_rfc3339(raw) {
+        return Some(t.timestamp_micros() as f64 / 1e6);
+    }
+    raw.trim().parse::<f64>().ok()
+}
+
+/// Read a recording saved by the recording window (CSV or JSON).
+pub fn load_recording(path: &Path) -> Result<LoadedRecording, String> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut unit = String::new();
+    let mut values = Vec::new();
+    let mut timestamps = Vec::new();
+    let is_json = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+
+    if is_json {
+        let file = File::open(path).map_err(|e| format!("Failed to open {name}: {e}"))?;
+        let records: Vec<serde_json::Value> =
+            serde_json::from_reader(file).map_err(|e| format!("Failed to parse {name}: {e}"))?;
+        for record in records {
+            let Some(value) = record.get("value").and_then(|v| v.as_f64()) else {
+                continue;
+            };
+            if unit.is_empty() {
+                if let Some(u) = record.get("unit").and_then(|u| u.as_str()) {
+                    unit = u.to_owned();
+                }
+            }
+            values.push(value);
+            match record.get("timestamp") {
+                Some(serde_json::Value::String(s)) => timestamps.extend(parse_timestamp(s)),
+                Some(serde_json::Value::Number(n)) => timestamps.extend(n.as_f64()),
+                _ => {}
+            }
+        }
+    } else {
+        let mut reader = ReaderBuilder::new()
+            .from_path(path)
+            .map_err(|e| format!("Failed to open {name}: {e}"))?;
+        let headers = reader
+            .headers()
+            .map_err(|e| format!("Failed to read {name}: {e}"))?
+            .clone();
+        let column = |title: &str| headers.iter().position(|h| h == title);
+        let value_col = column("Value").ok_or_else(|| format!("{name} has no Value column"))?;
+        let unit_col = column("Unit");
+        let time_col = column("Timestamp");
+        for row in reader.records() {
+            let row = row.map_err(|e| format!("Failed to read {name}: {e}"))?;
+            let Some(value) = row.get(value_col).and_then(|v| v.parse::<f64>().ok()) else {
+                continue;
+            };
+            if unit.is_empty() {
+                if let Some(u) = unit_col.and_then(|c| row.get(c)) {
+                    unit = u.to_owned();
+                }
+            }
+            values.push(value);
+            if let Some(t) = time_col.and_then(|c| row.get(c)).and_then(parse_timestamp) {
+                timestamps.push(t);
+            }
+        }
+    }
+
+    if !values.iter().any(|&v| usable(v)) {
+        return Err(format!("{name} contains no values"));
+    }
+    values.iter_mut().for_each(|v| *v = gap_if_unusable(*v));
+    Ok(LoadedRecording {
+        name,
+        unit,
+        values,
+        interval_s: median_interval(&timestamps),
+    })
+}
+
+impl AnalysisState {
+    fn compute(
+        &mut self,
+        hist_values: &VecDeque<f64>,
+        hist_interval: Option<f64>,
+        curr_unit: &str,
+    ) {
+        let (values, derived_interval, unit): (Vec<f64>, Option<f64>, String) = match self.source {
+            AnalysisSource::History => (
+                hist_values.iter().map(|&v| gap_if_unusable(v)).collect(),
+                hist_interval,
+                curr_unit.to_owned(),
+            ),
+            AnalysisSource::Recording => match &self.recording {
+                Some(rec) => (rec.values.clone(), rec.interval_s, rec.unit.clone()),
+                None => {
+                    self.status = Some("Load a recording first".to_owned());
+                    return;
+                }
+            },
+        };
+        let interval = if self.interval_override_s > 0.0 {
+            self.interval_override_s
+        } else {
+            match derived_interval {
+                Some(i) if i > 0.0 => i,
+                _ => {
+                    self.status = Some("Sample interval unknown, enter it manually".to_owned());
+                    return;
+                }
+            }
+        };
+
+        let result = match self.kind {
+            AnalysisKind::Adev => AnalysisResult::Adev(overlapping_adev(&values, interval)),
+            AnalysisKind::Psd => {
+                AnalysisResult::Psd(welch_psd(&values, interval, self.max_segment))
+            }
+        };
+        let empty = match &result {
+            AnalysisResult::Adev(p) => p.is_empty(),
+            AnalysisResult::Psd(p) => p.is_empty(),
+        };
+        let gaps = values.iter().filter(|v| v.is_nan()).count();
+        self.status = Some(if empty {
+            format!("Not enough samples ({})", values.len() - gaps)
+        } else if gaps > 0 {
+            format!(
+                "{} samples at {interval:.3} s, {gaps} gaps",
+                values.len() - gaps
+            )
+        } else {
+            format!("{} samples at {interval:.3} s", values.len())
+        });
+        self.result = Some((result, unit));
+    }
+
+    fn export_csv(&mut self) {
+        let Some((result, unit)) = &self.result else {
+            return;
+        };
+        let Some(path) = FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name(match result {
+                AnalysisResult::Adev(_) => "adev.csv",
+                AnalysisResult::Psd(_) => "psd.csv",
+            })
+            .save_file()
+        else {
+            return;
+        };
+        let write = || -> Result<(), Box<dyn std::error::Error>> {
+            let mut writer = WriterBuilder::new().from_path(&path)?;
+            match result {
+                AnalysisResult::Adev(points) => {
+                    writer.write_record([
+                        "Tau (s)".to_owned(),
+                        format!("ADEV ({unit})"),
+                        "Terms".to_owned(),
+                    ])?;
+                    for p in points {
+                        writer.write_record([
+                            p.tau.to_string(),
+                            p.adev.to_string(),
+                            p.terms.to_string(),
+                        ])?;
+                    }
+                }
+                AnalysisResult::Psd(points) => {
+                    writer.write_record([
+                        "Frequency (Hz)".to_owned(),
+                        format!("PSD ({unit}^2/Hz)"),
+                        format!("ASD ({unit}/sqrt(Hz))"),
+                    ])?;
+                    for p in points {
+                        writer.write_record([
+                            p.freq.to_string(),
+                            p.psd.to_string(),
+                            p.asd().to_string(),
+                        ])?;
+                    }
+                }
+            }
+            writer.flush()?;
+            Ok(())
+        };
+        self.status = Some(match write() {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(e) => format!("Failed to write {}: {e}", path.display()),
+        });
+    }
+}
+
+// Label only whole decades of a log10 axis
+fn decade_label(value: f64) -> String {
+    if (value - value.round()).abs() < 1e-6 {
+        format!("1e{}", value.round() as i32)
+    } else {
+        String::new()
+    }
+}
+
+pub fn show_analysis(
+    ui: &mut egui::Ui,
+    state: &mut AnalysisState,
+    hist_values: &VecDeque<f64>,
+    hist_times: &VecDeque<f64>,
+    curr_unit: &str,
+) {
+    // Actual spacing of the collected samples, like for recordings
+    let hist_times: Vec<f64> = hist_times.iter().copied().collect();
+    let hist_interval = median_interval(&hist_times);
+
+    ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
+        ui.horizontal_wrapped(|ui| {
+            ui.radio_value(&mut state.kind, AnalysisKind::Adev, "Allan deviation");
+            ui.radio_value(&mut state.kind, AnalysisKind::Psd, "PSD");
+            ui.separator();
+            ui.radio_value(
+                &mut state.source,
+                AnalysisSource::History,
+                format!("Histogram samples ({})", hist_values.len()),
+            );
+            ui.radio_value(&mut state.source, AnalysisSource::Recording, "Recording");
+            if ui.button("Load recording").clicked() {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("Recordings", &["csv", "json"])
+                    .pick_file()
+                {
+                    match load_recording(&path) {
+                        Ok(rec) => {
+                            state.status =
+                                Some(format!("Loaded {} ({} values)", rec.name, rec.values.len()));
+                            state.recording = Some(rec);
+                            state.source = AnalysisSource::Recording;
+                        }
+                        Err(e) => state.status = Some(e),
+                    }
+                }
+            }
+            if let Some(rec) = &state.recording {
+                ui.label(&rec.name);
+            }
+            ui.separator();
+            let derived = match state.source {
+                AnalysisSource::History => hist_interval,
+                AnalysisSource::Recording => state.recording.as_ref().and_then(|r| r.interval_s),
+            };
+            ui.add(
+                egui::DragValue::new(&mut state.interval_override_s)
+                    .range(0.0..=86_400.0)
+                    .speed(0.01)
+                    .custom_formatter(|v, _| {
+                        if v <= 0.0 {
+                            "auto".to_owned()
+                        } else {
+                            format!("{v}")
+                        }
+                    }),
+            )
+            .on_hover_text("Sample spacing in seconds. 0 derives it from the source.");
+            ui.label(match derived {
+                Some(d) if state.interval_override_s <= 0.0 => {
+                    format!("Sample interval (s), auto: {d:.3}")
+                }
+                _ => "Sample interval (s)".to_owned(),
+            });
+            if state.kind == AnalysisKind::Psd {
+                ui.add(
+                    Slider::new(&mut state.max_segment, 64..=16384)
+                        .logarithmic(true)
+                        .text("Max segment")
+                        .clamping(SliderClamping::Always),
+                );
+            }
+            ui.checkbox(&mut state.log_log, "Log-log");
+            if ui.button("Compute").clicked() {
+                state.compute(hist_values, hist_interval, curr_unit);
+            }
+            if ui
+                .add_enabled(state.result.is_some(), egui::Button::new("Export CSV"))
+                .clicked()
+            {
+                state.export_csv();
+            }
+            if let Some(status) = &state.status {
+                ui.label(status);
+            }
+        });
+        ui.label("Analysis Adjustments");
+        ui.separator();
+
+        let log_log = state.log_log;
+        let axis = |v: f64| if log_log { v.log10() } else { v };
+        let (x_label, y_label, name, points): (String, String, &str, Vec<[f64; 2]>) =
+            match &state.result {
+                Some((AnalysisResult::Adev(points), unit)) => (
+                    "Tau (s)".to_owned(),
+                    format!("ADEV ({unit})"),
+                    "ADEV",
+                    points
+                        .iter()
+                        .filter(|p| !log_log || p.adev > 0.0)
+                        .map(|p| [axis(p.tau), axis(p.adev)])
+                        .collect(),
+                ),
+                Some((AnalysisResult::Psd(points), unit)) => (
+                    "Frequency (Hz)".to_owned(),
+                    format!("PSD ({unit}²/Hz)"),
+                    "PSD",
+                    points
+                        .iter()
+                        .filter(|p| !log_log || p.psd > 0.0)
+                        .map(|p| [axis(p.freq), axis(p.psd)])
+                        .collect(),
+                ),
+                None => (String::new(), String::new(), "", Vec::new()),
+            };
+
+        let mut plot = Plot::new("analysis")
+            .show_axes(true)
+            .show_grid(true)
+            .x_axis_label(x_label)
+            .y_axis_label(y_label)
+            .legend(Legend::default().text_style(egui::TextStyle::Monospace));
+        if log_log {
+            plot = plot
+                .x_axis_formatter(|mark, _range| decade_label(mark.value))
+                .y_axis_formatter(|mark, _range| decade_label(mark.value))
+                .label_formatter(|hover| {
+                    let (HoverPosition::NearDataPoint { position, .. }
+                    | HoverPosition::Elsewhere { position }) = hover;
+                    Some(format!(
+                        "{:.4e}\n{:.4e}",
+                        10f64.powf(position.x),
+                        10f64.powf(position.y)
+                    ))
+                });
+        }
+        plot.show(ui, |plot_ui| {
+            if points.is_empty() {
+                return;
+            }
+            plot_ui.line(
+                Line::new(name, PlotPoints::from(points.clone()))
+                    .stroke(egui::Stroke::new(2.0, CURVE_COLOR)),
+            );
+            plot_ui.points(
+                Points::new(name, PlotPoints::from(points))
+                    .radius(3.0)
+                    .color(CURVE_COLOR),
+            );
+        });
+    });
+}
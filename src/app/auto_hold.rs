@@ -0,0 +1,148 @@
+//! Handheld-style auto-hold: latch a settled main meter reading, optionally
+//! beep and record it, then re-arm once the probes are lifted. Also the
+//! manual Hold and REL keys, which only change what is shown.
+
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::{METER_OVERLOAD_VALUE, format_measurement};
+use crate::stable::{StableDetector, StableEvent};
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoHold {
+    pub enabled: bool,      // Persistent
+    pub tolerance_pct: f64, // Persistent settle band in percent
+    pub samples: usize,     // Persistent readings inside the band to latch
+    pub beep: bool,         // Persistent, beep SCPI meters on each latch
+    pub record: bool,       // Persistent, append latched values while recording in Manual mode
+    #[serde(skip)]
+    detector: Option<StableDetector>,
+    #[serde(skip)]
+    last_time: f64,
+    #[serde(skip)]
+    pub held: Option<f64>, // Latched value shown on screen
+    #[serde(skip)]
+    pub armed: bool, // Waiting for a new reading to settle
+}
+
+impl Default for AutoHold {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tolerance_pct: 0.1,
+            samples: 5,
+            beep: true,
+            record: true,
+            detector: None,
+            last_time: 0.0,
+            held: None,
+            armed: true,
+        }
+    }
+}
+
+impl super::MyApp {
+    /// Feed new main meter readings to the auto-hold detector.
+    pub fn auto_hold_tick(&mut self) {
+        let hold = &mut self.auto_hold;
+        if !hold.enabled || self.connection_state != super::ConnectionState::Connected {
+            hold.detector = None;
+            hold.held = None;
+            hold.armed = true;
+            return;
+        }
+        if self.curr_meas_time == hold.last_time {
+            return;
+        }
+        hold.last_time = self.curr_meas_time;
+        let detector = hold
+            .detector
+            .get_or_insert_with(|| StableDetector::new(hold.tolerance_pct, hold.samples));
+        detector.tolerance_pct = hold.tolerance_pct;
+        detector.samples = hold.samples;
+        // Open probes read as overload or NaN, which releases the hold
+        let value = if self.curr_meas == METER_OVERLOAD_VALUE {
+            f64::NAN
+        } else {
+            self.curr_meas
+        };
+        match detector.push(value) {
+            Some(StableEvent::Settled(value)) => {
+                hold.held = Some(value);
+                hold.armed = false;
+                let (beep, record) = (hold.beep, hold.record);
+                if beep {
+                    self.beep_meter();
+                }
+                if record
+                    && self.recording_active
+                    && matches!(self.recording_mode, super::RecordingMode::Manual)
+                {
+                    self.record_value(value);
+                }
+            }
+            Some(StableEvent::Released) => hold.armed = true,
+            None => {}
+        }
+    }
+
+    /// The main reading, unless there is none or it is overload.
+    fn live_reading(&self) -> Option<f64> {
+        (self.has_any_reading()
+            && self.curr_meas.is_finite()
+            && self.curr_meas != METER_OVERLOAD_VALUE)
+            .then_some(self.curr_meas)
+    }
+
+    /// Freeze the current reading on screen, or release it.
+    pub fn toggle_hold(&mut self) {
+        self.display_hold = match self.display_hold {
+            Some(_) => None,
+            None => self.live_reading().map(|v| (self.metermode, v)),
+        };
+    }
+
+    /// Show readings relative to the current one, or switch back.
+    pub fn toggle_rel(&mut self) {
+        self.rel_reference = match self.rel_reference {
+            Some(_) => None,
+            None => self.live_reading().map(|v| (self.metermode, v)),
+        };
+    }
+
+    /// Lines under the main reading for Hold and REL taken in this mode.
+    pub fn hold_rel_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some((mode, held)) = self.display_hold
+            && mode == self.metermode
+        {
+            lines.push(format!("■ HOLD {}", self.format_reading(held)));
+        }
+        if let Some((mode, reference)) = self.rel_reference
+            && mode == self.metermode
+        {
+            let delta = match self.live_reading() {
+                Some(value) => self.format_reading(value - reference),
+                None => "---".to_owned(),
+            };
+            lines.push(format!(
+                "REL Δ {delta} (ref {})",
+                self.format_reading(reference)
+            ));
+        }
+        lines
+    }
+
+    fn format_reading(&self, value: f64) -> String {
+        let (value, unit) = format_measurement(
+            value,
+            10,
+            1_000_000.0,
+            0.000001,
+            &self.metermode,
+            self.auto_scale_units(&self.metermode),
+            None,
+        );
+        format!("{} {unit}", value.trim())
+    }
+}
@@ -0,0 +1,410 @@
+//! Guided battery discharge test window.
+
+use std::fs::File;
+
+use csv::WriterBuilder;
+use egui::{Color32, Context, DragValue, RichText, Window};
+use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
+
+use crate::battery::{DischargeRun, current_from_load};
+use crate::integrator::format_elapsed;
+use crate::plot_export::{ExportSeries, PlotExport, SeriesKind};
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct BatteryTestState {
+    pub voltage_source: String, // Persistent channel variable measuring cell voltage
+    pub current_source: String, // Persistent channel variable for current, empty to use load_ohms
+    pub load_ohms: f64,         // Persistent nominal load resistance
+    pub cutoff_v: f64,          // Persistent cutoff voltage
+    pub confirm_samples: usize, // Persistent readings below cutoff before stopping
+    pub use_recorder: bool,     // Persistent, drive the recorder during the run
+    #[serde(skip)]
+    open_circuit_v: Option<f64>,
+    #[serde(skip)]
+    run: Option<DischargeRun>,
+    #[serde(skip)]
+    recorder_mode_before: Option<super::RecordingMode>, // Set while the test drives the recorder
+    #[serde(skip)]
+    message: Option<String>,
+}
+
+impl Default for BatteryTestState {
+    fn default() -> Self {
+        Self {
+            voltage_source: "m1".to_owned(),
+            current_source: String::new(),
+            load_ohms: 10.0,
+            cutoff_v: 3.0,
+            confirm_samples: 3,
+            use_recorder: true,
+            open_circuit_v: None,
+            run: None,
+            recorder_mode_before: None,
+            message: None,
+        }
+    }
+}
+
+impl BatteryTestState {
+    fn is_running(&self) -> bool {
+        self.run.as_ref().is_some_and(|r| !r.is_finished())
+    }
+}
+
+impl super::MyApp {
+    /// Latest value of a channel in its base unit (V, A), `None` while the
+    /// channel is unavailable or overloaded.
+    fn live_value(&self, var: &str) -> Option<crate::expr::TimedValue> {
+        let channel = self.live_channels.iter().find(|c| c.var == var)?;
+        channel
+            .value
+            .filter(|v| v.value != crate::helpers::METER_OVERLOAD_VALUE)
+            .map(|v| crate::expr::TimedValue {
+                value: v.value * channel.si_factor,
+                time: v.time,
+            })
+    }
+
+    /// Log the next sample of a running discharge test; stops the test and
+    /// the recorder it started once the cutoff is confirmed.
+    pub fn battery_tick(&mut self) {
+        if !self.battery.is_running() {
+            return;
+        }
+        let Some(voltage) = self.live_value(&self.battery.voltage_source) else {
+            return;
+        };
+        let current = if self.battery.current_source.is_empty() {
+            current_from_load(voltage.value, self.battery.load_ohms)
+        } else {
+            match self.live_value(&self.battery.current_source) {
+                Some(current) => current.value,
+                None => return,
+            }
+        };
+        let Some(run) = self.battery.run.as_mut() else {
+            return;
+        };
+        if run.add(voltage.time, voltage.value, current) {
+            self.battery.message = Some(format!(
+                "Cutoff of {:.3} V reached, test finished.",
+                self.battery.cutoff_v
+            ));
+            self.release_battery_recorder();
+        }
+    }
+
+    /// Stop the recording the test started and put the recording mode back.
+    fn release_battery_recorder(&mut self) {
+        let Some(mode) = self.battery.recorder_mode_before.take() else {
+            return;
+        };
+        if self.recording_active {
+            self.recording_active = false;
+            self.save_recording_data();
+        }
+        self.recording_mode = mode;
+    }
+
+    fn start_battery_test(&mut self) {
+        self.battery.run = Some(DischargeRun::new(
+            self.battery.cutoff_v,
+            self.battery.confirm_samples,
+            self.battery.open_circuit_v,
+        ));
+        self.battery.message = None;
+        self.battery.recorder_mode_before = None;
+        if self.battery.use_recorder && !self.recording_active {
+            if self.recording_file_path.is_empty() {
+                self.battery.message =
+                    Some("No recording file set, logging to the test curve only.".to_owned());
+            } else {
+                self.battery.recorder_mode_before = Some(std::mem::replace(
+                    &mut self.recording_mode,
+                    super::RecordingMode::FixedInterval,
+                ));
+                self.recording_active = true;
+            }
+        }
+    }
+
+    fn stop_battery_test(&mut self) {
+        // Keep the samples for the report, just stop logging
+        if let Some(run) = self.battery.run.as_mut() {
+            run.finish();
+        }
+        self.release_battery_recorder();
+        self.battery.message = Some("Test stopped manually.".to_owned());
+    }
+
+    fn battery_curve_plot(&self, run: &DischargeRun) -> PlotExport {
+        PlotExport {
+            title: "Discharge curve".to_owned(),
+            x_label: "Capacity (mAh)".to_owned(),
+            y_label: "V".to_owned(),
+            timestamp: super::graph::export_timestamp(),
+            series: vec![ExportSeries {
+                name: "Cell voltage".to_owned(),
+                color: self.graph_line_color.to_array()[..3].try_into().unwrap(),
+                kind: SeriesKind::Line(
+                    run.samples
+                        .iter()
+                        .map(|s| [s.ah * 1000.0, s.voltage])
+                        .collect(),
+                ),
+            }],
+            log_y: false,
+        }
+    }
+
+    fn save_battery_curve_csv(&mut self) {
+        let Some(run) = &self.battery.run else {
+            return;
+        };
+        let Some(path) = FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("discharge.csv")
+            .save_file()
+        else {
+            return;
+        };
+        let result = (|| -> Result<(), String> {
+            let file = File::create(&path).map_err(|e| format!("Failed to create file: {e}"))?;
+            let mut writer = WriterBuilder::new().from_writer(file);
+            writer
+                .write_record([
+                    "Time (s)",
+                    "Voltage (V)",
+                    "Current (A)",
+                    "Capacity (Ah)",
+                    "Energy (Wh)",
+                ])
+                .map_err(|e| format!("Failed to write CSV: {e}"))?;
+            for s in &run.samples {
+                writer
+                    .write_record([
+                        s.t.to_string(),
+                        s.voltage.to_string(),
+                        s.current.to_string(),
+                        s.ah.to_string(),
+                        s.wh.to_string(),
+                    ])
+                    .map_err(|e| format!("Failed to write CSV: {e}"))?;
+            }
+            writer
+                .flush()
+                .map_err(|e| format!("Failed to write CSV: {e}"))
+        })();
+        self.battery.message = Some(match result {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(e) => e,
+        });
+    }
+
+    fn save_battery_curve_image(&mut self) {
+        let Some(run) = &self.battery.run else {
+            return;
+        };
+        let plot = self.battery_curve_plot(run);
+        let Some(path) = FileDialog::new()
+            .add_filter("PNG image", &["png"])
+            .add_filter("SVG image", &["svg"])
+            .set_file_name("discharge.png")
+            .save_file()
+        else {
+            return;
+        };
+        self.battery.message = Some(
+            match crate::plot_export::save(
+                &plot,
+                &path,
+                self.plot_export.width,
+                self.plot_export.height,
+            ) {
+                Ok(()) => format!("Saved {}", path.display()),
+                Err(e) => e,
+            },
+        );
+    }
+
+    pub fn show_battery_test(&mut self, ctx: &Context) {
+        if !self.battery_open {
+            return;
+        }
+        let mut open = self.battery_open;
+        Window::new("Battery discharge test")
+            .open(&mut open)
+            .resizable(true)
+            .show(ctx, |ui| {
+                let running = self.battery.is_running();
+                ui.add_enabled_ui(!running, |ui| {
+                    egui::Grid::new("battery_settings")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Voltage channel:");
+                            self.channel_combo(ui, "battery_voltage", false);
+                            ui.end_row();
+                            ui.label("Current channel:");
+                            self.channel_combo(ui, "battery_current", true);
+                            ui.end_row();
+                            if self.battery.current_source.is_empty() {
+                                ui.label("Load resistance (Ω):");
+                                ui.add(
+                                    DragValue::new(&mut self.battery.load_ohms)
+                                        .range(0.001..=1e6)
+                                        .speed(0.1),
+                                );
+                                ui.end_row();
+                            }
+                            ui.label("Cutoff voltage (V):");
+                            ui.add(
+                                DragValue::new(&mut self.battery.cutoff_v)
+                                    .range(0.0..=1000.0)
+                                    .speed(0.01),
+                            );
+                            ui.end_row();
+                            ui.label("Confirm readings:");
+                            ui.add(
+                                DragValue::new(&mut self.battery.confirm_samples).range(1..=100),
+                            );
+                            ui.end_row();
+                        });
+                    ui.checkbox(
+                        &mut self.battery.use_recorder,
+                        "Record to the recording file while the test runs",
+                    );
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button("Capture open-circuit voltage")
+                            .on_hover_text("Read the cell voltage before connecting the load")
+                            .clicked()
+                        {
+                            self.battery.open_circuit_v = self
+                                .live_value(&self.battery.voltage_source)
+                                .map(|v| v.value)
+                                .filter(|v| v.is_finite());
+                        }
+                        match self.battery.open_circuit_v {
+                            Some(v) => ui.label(format!("OCV {v:.4} V")),
+                            None => ui.label("OCV not captured"),
+                        };
+                    });
+                });
+
+                ui.horizontal(|ui| {
+                    if running {
+                        if ui.button("Stop test").clicked() {
+                            self.stop_battery_test();
+                        }
+                    } else if ui.button("Start test").clicked() {
+                        self.start_battery_test();
+                    }
+                    if let Some(message) = &self.battery.message {
+                        ui.label(message);
+                    }
+                });
+                ui.separator();
+
+                let Some(run) = &self.battery.run else {
+                    ui.label("No test run yet.");
+                    return;
+                };
+                let Some(report) = run.report() else {
+                    ui.label("Waiting for the first reading...");
+                    return;
+                };
+                egui::Grid::new("battery_report")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Elapsed:");
+                        ui.label(format_elapsed(report.duration_s));
+                        ui.end_row();
+                        ui.label("Capacity:");
+                        ui.label(format!("{:.1} mAh", report.capacity_ah * 1000.0));
+                        ui.end_row();
+                        ui.label("Energy:");
+                        ui.label(format!("{:.3} Wh", report.energy_wh));
+                        ui.end_row();
+                        ui.label("Voltage:");
+                        ui.label(format!("{:.4} V → {:.4} V", report.start_v, report.end_v));
+                        ui.end_row();
+                        ui.label("Average current:");
+                        ui.label(format!("{:.1} mA", report.avg_current * 1000.0));
+                        ui.end_row();
+                        ui.label("Internal resistance:");
+                        match report.internal_resistance {
+                            Some(r) => ui.label(format!("{:.1} mΩ", r * 1000.0)),
+                            None => ui.label(
+                                RichText::new("capture OCV before starting").color(Color32::GRAY),
+                            ),
+                        };
+                        ui.end_row();
+                    });
+                let points: Vec<[f64; 2]> = run
+                    .samples
+                    .iter()
+                    .map(|s| [s.ah * 1000.0, s.voltage])
+                    .collect();
+                ui.horizontal(|ui| {
+                    if ui.button("Save curve (CSV)").clicked() {
+                        self.save_battery_curve_csv();
+                    }
+                    if ui.button("Save curve image").clicked() {
+                        self.save_battery_curve_image();
+                    }
+                });
+                egui_plot::Plot::new("battery_curve")
+                    .height(220.0)
+                    .x_axis_label("Capacity (mAh)")
+                    .y_axis_label("V")
+                    .show(ui, |plot_ui| {
+                        plot_ui.hline(
+                            egui_plot::HLine::new("Cutoff", self.battery.cutoff_v)
+                                .stroke(egui::Stroke::new(1.0, Color32::RED)),
+                        );
+                        plot_ui.line(
+                            egui_plot::Line::new(
+                                "Cell voltage",
+                                egui_plot::PlotPoints::from(points),
+                            )
+                            .stroke(egui::Stroke::new(2.0, self.graph_line_color)),
+                        );
+                    });
+            });
+        self.battery_open = open;
+    }
+
+    /// Channel picker bound to the battery voltage or current source.
+    fn channel_combo(&mut self, ui: &mut egui::Ui, id: &str, current: bool) {
+        let target = if current {
+            &mut self.battery.current_source
+        } else {
+            &mut self.battery.voltage_source
+        };
+        let selected = if current && target.is_empty() {
+            "From load resistance".to_owned()
+        } else {
+            self.live_channels
+                .iter()
+                .find(|c| c.var == *target)
+                .map(|c| format!("{} ({})", c.label, c.unit))
+                .unwrap_or_else(|| target.clone())
+        };
+        egui::ComboBox::from_id_salt(id)
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                if current {
+                    ui.selectable_value(target, String::new(), "From load resistance");
+                }
+                for channel in &self.live_channels {
+                    ui.selectable_value(
+                        target,
+                        channel.var.clone(),
+                        format!("{} ({})", channel.label, channel.unit),
+                    );
+                }
+            });
+    }
+}
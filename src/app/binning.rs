@@ -0,0 +1,366 @@
+//! Component binning window for `Res` and `Cap` modes: each part that
+//! settles on the probes is sorted into a tolerance bin and tallied.
+
+use std::fs::File;
+
+use chrono::{DateTime, Utc};
+use csv::WriterBuilder;
+use egui::{Color32, Context, DragValue, FontFamily, FontId, RichText, TextEdit, Window};
+use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
+
+use crate::binning::{self, Bin, ESeries};
+use crate::helpers::{METER_OVERLOAD_VALUE, format_measurement};
+use crate::multimeter::MeterMode;
+use crate::stable::{StableDetector, StableEvent};
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct BinningState {
+    pub nominal: f64,          // Persistent nominal value in base units (Ω / F)
+    pub auto_nominal: bool,    // Persistent, use the nearest E-series value per part
+    pub series: ESeries,       // Persistent
+    pub bands: String,         // Persistent tolerance bands in percent, e.g. "1, 2, 5"
+    pub stable_pct: f64,       // Persistent settle band in percent
+    pub stable_samples: usize, // Persistent readings inside the band to count as stable
+    pub beep: bool,            // Persistent, beep the meter on each sorted part
+    #[serde(skip)]
+    detector: Option<StableDetector>,
+    #[serde(skip)]
+    last_time: f64,
+    #[serde(skip)]
+    parts: Vec<BinnedPart>,
+    #[serde(skip)]
+    message: Option<String>,
+}
+
+impl Default for BinningState {
+    fn default() -> Self {
+        Self {
+            nominal: 1000.0,
+            auto_nominal: false,
+            series: ESeries::E24,
+            bands: "1, 2, 5".to_owned(),
+            stable_pct: 0.2,
+            stable_samples: 5,
+            beep: false,
+            detector: None,
+            last_time: 0.0,
+            parts: Vec::new(),
+            message: None,
+        }
+    }
+}
+
+struct BinnedPart {
+    timestamp: DateTime<Utc>,
+    value: f64,
+    nominal: f64,
+    bin: Bin,
+    label: String,
+}
+
+fn bin_color(bin: Bin) -> Color32 {
+    match bin {
+        Bin::Band(0) => Color32::from_rgb(0, 150, 0),
+        Bin::Band(_) => Color32::from_rgb(190, 140, 0),
+        Bin::Low | Bin::High => Color32::from_rgb(170, 0, 0),
+    }
+}
+
+fn binning_mode(mode: MeterMode) -> bool {
+    matches!(mode, MeterMode::Res | MeterMode::Cap)
+}
+
+impl super::MyApp {
+    /// Feed new main meter readings to the settle detector while the
+    /// binning window is open, and sort every part that settles.
+    pub fn binning_tick(&mut self) {
+        if !self.binning_open || !binning_mode(self.metermode) {
+            self.binning.detector = None;
+            return;
+        }
+        if self.curr_meas_time == self.binning.last_time {
+            return;
+        }
+        self.binning.last_time = self.curr_meas_time;
+        let Ok(bands) = binning::parse_bands(&self.binning.bands) else {
+            return;
+        };
+        let state = &mut self.binning;
+        let detector = state
+            .detector
+            .get_or_insert_with(|| StableDetector::new(state.stable_pct, state.stable_samples));
+        detector.tolerance_pct = state.stable_pct;
+        detector.samples = state.stable_samples;
+        // Nominals and the series are in Ω / F, the meter may show kΩ or nF
+        let value = if self.curr_meas == METER_OVERLOAD_VALUE {
+            f64::NAN
+        } else {
+            self.curr_meas * self.connection_type.si_factor(&self.curr_unit)
+        };
+        let Some(StableEvent::Settled(value)) = detector.push(value) else {
+            return;
+        };
+        let nominal = if state.auto_nominal {
+            state.series.nearest(value).unwrap_or(state.nominal)
+        } else {
+            state.nominal
+        };
+        let bin = binning::classify(value, nominal, &bands);
+        state.parts.push(BinnedPart {
+            timestamp: Utc::now(),
+            value,
+            nominal,
+            bin,
+            label: binning::bin_label(bin, &bands),
+        });
+        if state.beep {
+            self.beep_meter();
+        }
+    }
+
+    /// Part count per bin label, in band order with LOW / HIGH last.
+    fn binning_tally(&self) -> Vec<(String, usize)> {
+        let bands = binning::parse_bands(&self.binning.bands).unwrap_or_default();
+        let mut bins: Vec<Bin> = (0..bands.len()).map(Bin::Band).collect();
+        bins.extend([Bin::Low, Bin::High]);
+        bins.into_iter()
+            .map(|bin| {
+                let label = binning::bin_label(bin, &bands);
+                let count = self
+                    .binning
+                    .parts
+                    .iter()
+                    .filter(|p| p.label == label)
+                    .count();
+                (label, count)
+            })
+            .collect()
+    }
+
+    fn format_binning_value(&self, value: f64) -> String {
+        let (value, unit) = format_measurement(
+            value,
+            10,
+            1_000_000.0,
+            0.000001,
+            &self.metermode,
+            true,
+            None,
+        );
+        format!("{} {}", value.trim(), unit)
+    }
+
+    fn export_binning(&mut self) {
+        let Some(path) = FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("binning.csv")
+            .save_file()
+        else {
+            return;
+        };
+        let tally = self.binning_tally();
+        let result = (|| -> Result<(), String> {
+            let file = File::create(&path).map_err(|e| format!("Failed to create file: {e}"))?;
+            let mut writer = WriterBuilder::new().flexible(true).from_writer(file);
+            let err = |e: csv::Error| format!("Failed to write CSV: {e}");
+            writer
+                .write_record([
+                    "Index",
+                    "Timestamp",
+                    "Value",
+                    "Nominal",
+                    "Deviation (%)",
+                    "Bin",
+                ])
+                .map_err(err)?;
+            for (i, part) in self.binning.parts.iter().enumerate() {
+                writer
+                    .write_record([
+                        (i + 1).to_string(),
+                        part.timestamp.to_rfc3339(),
+                        part.value.to_string(),
+                        part.nominal.to_string(),
+                        format!("{:.4}", binning::deviation_pct(part.value, part.nominal)),
+                        part.label.clone(),
+                    ])
+                    .map_err(err)?;
+            }
+            writer.write_record([""]).map_err(err)?;
+            writer.write_record(["Bin", "Count"]).map_err(err)?;
+            for (label, count) in &tally {
+                writer
+                    .write_record([label.clone(), count.to_string()])
+                    .map_err(err)?;
+            }
+            writer
+                .flush()
+                .map_err(|e| format!("Failed to write CSV: {e}"))
+        })();
+        self.binning.message = Some(match result {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(e) => e,
+        });
+    }
+
+    pub fn show_binning(&mut self, ctx: &Context) {
+        if !self.binning_open {
+            return;
+        }
+        let mut open = self.binning_open;
+        Window::new("Component binning")
+            .open(&mut open)
+            .resizable(true)
+            .show(ctx, |ui| {
+                if !binning_mode(self.metermode) {
+                    ui.label(
+                        RichText::new("Switch the meter to Ohm or Cap mode to sort parts.")
+                            .color(Color32::GRAY),
+                    );
+                }
+                let unit = if self.metermode == MeterMode::Cap {
+                    "F"
+                } else {
+                    "Ω"
+                };
+                egui::Grid::new("binning_settings")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("E-series:");
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_salt("binning_series")
+                                .selected_text(self.binning.series.label())
+                                .show_ui(ui, |ui| {
+                                    for series in ESeries::ALL {
+                                        ui.selectable_value(
+                                            &mut self.binning.series,
+                                            series,
+                                            series.label(),
+                                        );
+                                    }
+                                });
+                            if ui
+                                .button(format!(
+                                    "Use ±{}% band",
+                                    self.binning.series.tolerance_pct()
+                                ))
+                                .clicked()
+                            {
+                                self.binning.bands =
+                                    self.binning.series.tolerance_pct().to_string();
+                            }
+                        });
+                        ui.end_row();
+                        ui.label(format!("Nominal ({unit}):"));
+                        ui.horizontal(|ui| {
+                            let nominal_speed = self.binning.nominal.abs() * 0.001;
+                            ui.add_enabled(
+                                !self.binning.auto_nominal,
+                                DragValue::new(&mut self.binning.nominal)
+                                    .range(1e-15..=1e12)
+                                    .speed(nominal_speed),
+                            );
+                            if ui.button("Snap to series").clicked() {
+                                if let Some(v) = self.binning.series.nearest(self.binning.nominal) {
+                                    self.binning.nominal = v;
+                                }
+                            }
+                            ui.checkbox(
+                                &mut self.binning.auto_nominal,
+                                "Nearest series value per part",
+                            );
+                        });
+                        ui.end_row();
+                        ui.label("Tolerance bands (%):");
+                        ui.add(
+                            TextEdit::singleline(&mut self.binning.bands)
+                                .desired_width(120.0)
+                                .hint_text("1, 2, 5"),
+                        );
+                        ui.end_row();
+                        ui.label("Stable within (%):");
+                        ui.add(
+                            DragValue::new(&mut self.binning.stable_pct)
+                                .range(0.001..=10.0)
+                                .speed(0.01),
+                        );
+                        ui.end_row();
+                        ui.label("for readings:");
+                        ui.add(DragValue::new(&mut self.binning.stable_samples).range(2..=100));
+                        ui.end_row();
+                    });
+                ui.add_enabled(
+                    !self.is_read_only(),
+                    egui::Checkbox::new(&mut self.binning.beep, "Beep the meter on each part"),
+                );
+                if let Err(e) = binning::parse_bands(&self.binning.bands) {
+                    ui.label(RichText::new(e).color(Color32::RED));
+                }
+                ui.separator();
+
+                match self.binning.parts.last() {
+                    Some(part) => {
+                        egui::Frame::new()
+                            .fill(bin_color(part.bin))
+                            .corner_radius(5.0)
+                            .inner_margin(12.0)
+                            .show(ui, |ui| {
+                                ui.set_min_width(280.0);
+                                ui.label(RichText::new(&part.label).color(Color32::WHITE).font(
+                                    FontId {
+                                        size: 48.0,
+                                        family: FontFamily::Name("B612Mono-Bold".into()),
+                                    },
+                                ));
+                                ui.label(
+                                    RichText::new(format!(
+                                        "{}  ({:+.3}% of {})",
+                                        self.format_binning_value(part.value),
+                                        binning::deviation_pct(part.value, part.nominal),
+                                        self.format_binning_value(part.nominal)
+                                    ))
+                                    .color(Color32::WHITE),
+                                );
+                            });
+                    }
+                    None => {
+                        ui.label(
+                            "Place a part on the probes, it is sorted once the reading settles.",
+                        );
+                    }
+                }
+                if self
+                    .binning
+                    .detector
+                    .as_ref()
+                    .is_some_and(|d| d.held().is_some())
+                {
+                    ui.label(
+                        RichText::new("Remove the part for the next one").color(Color32::GRAY),
+                    );
+                }
+
+                ui.separator();
+                ui.horizontal_wrapped(|ui| {
+                    for (label, count) in self.binning_tally() {
+                        ui.label(RichText::new(format!("{label}: {count}")).monospace());
+                    }
+                    ui.label(format!("Total: {}", self.binning.parts.len()));
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Export CSV").clicked() {
+                        self.export_binning();
+                    }
+                    if ui.button("Clear tally").clicked() {
+                        self.binning.parts.clear();
+                        self.binning.message = None;
+                    }
+                    if let Some(message) = &self.binning.message {
+                        ui.label(message);
+                    }
+                });
+            });
+        self.binning_open = open;
+    }
+}
@@ -0,0 +1,315 @@
+//! Opening the main meter link, and reopening it after link loss.
+//!
+//! Every backend task reports a dead port or device through
+//! `MeterShared::link_lost` and exits. The UI then shows "disconnected,
+//! retrying" and reopens the same port once a second, following a USB serial
+//! adapter or HID meter to its new port name or path if the OS hands out
+//! another one. The IDN is
+//! read again on the new link, so the dialect bootstrap and connect macros
+//! are re-applied. Recordings get a marker row on both ends of the gap.
+
+use std::sync::atomic::Ordering;
+
+use mio_serial::SerialPortType;
+
+use super::{ConnectionState, ConnectionType};
+
+/// Time between two attempts to reopen a lost link, in seconds.
+pub(super) const RECONNECT_INTERVAL_S: f64 = 1.0;
+
+/// USB identity of a serial port or HID device, to find it again under a
+/// new name.
+#[derive(Clone, Debug, PartialEq)]
+struct UsbIdentity {
+    vid: u16,
+    pid: u16,
+    serial_number: Option<String>,
+}
+
+impl UsbIdentity {
+    fn matches(&self, vid: u16, pid: u16, serial_number: Option<&str>) -> bool {
+        self.vid == vid && self.pid == pid && self.serial_number.as_deref() == serial_number
+    }
+}
+
+#[derive(Default)]
+pub struct ReconnectState {
+    usb: Option<UsbIdentity>,
+    /// UI time the link was lost; `None` once readings flow again.
+    lost_since: Option<f64>,
+    next_attempt: f64,
+    pub attempts: u32,
+}
+
+fn usb_identity(port_name: &str) -> Option<UsbIdentity> {
+    let ports = mio_serial::available_ports().ok()?;
+    let port = ports.into_iter().find(|p| p.port_name == port_name)?;
+    match port.port_type {
+        SerialPortType::UsbPort(info) => Some(UsbIdentity {
+            vid: info.vid,
+            pid: info.pid,
+            serial_number: info.serial_number,
+        }),
+        _ => None,
+    }
+}
+
+/// New port name of the adapter, if it moved. `current` is kept whenever it
+/// exists again. Without a serial number, or with several adapters sharing
+/// it, there is no telling which port is the meter, so nothing is followed.
+fn find_moved_usb_port(identity: &UsbIdentity, current: &str) -> Option<String> {
+    identity.serial_number.as_ref()?;
+    let ports = mio_serial::available_ports().ok()?;
+    if ports.iter().any(|p| p.port_name == current) {
+        return None;
+    }
+    let mut matching = ports.into_iter().filter(|p| match &p.port_type {
+        SerialPortType::UsbPort(info) => {
+            identity.matches(info.vid, info.pid, info.serial_number.as_deref())
+        }
+        _ => false,
+    });
+    match (matching.next(), matching.next()) {
+        (Some(port), None) => Some(port.port_name),
+        _ => None,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn hid_identity(path: &str) -> Option<UsbIdentity> {
+    let api = hidapi::HidApi::new().ok()?;
+    let device = api
+        .device_list()
+        .find(|d| d.path().to_string_lossy() == path)?;
+    Some(UsbIdentity {
+        vid: device.vendor_id(),
+        pid: device.product_id(),
+        serial_number: device.serial_number().map(str::to_owned),
+    })
+}
+
+/// New path of the HID meter, if it moved. Same rules as
+/// [`find_moved_usb_port`].
+#[cfg(not(target_arch = "wasm32"))]
+fn find_moved_hid_device(identity: &UsbIdentity, current: &str) -> Option<String> {
+    identity.serial_number.as_ref()?;
+    let api = hidapi::HidApi::new().ok()?;
+    if api
+        .device_list()
+        .any(|d| d.path().to_string_lossy() == current)
+    {
+        return None;
+    }
+    let mut matching = api
+        .device_list()
+        .filter(|d| identity.matches(d.vendor_id(), d.product_id(), d.serial_number()));
+    match (matching.next(), matching.next()) {
+        (Some(device), None) => Some(device.path().to_string_lossy().into_owned()),
+        _ => None,
+    }
+}
+
+impl super::MyApp {
+    /// Line settings for a Victor serial meter: the protocol default, unless
+    /// the advanced override is on.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) fn victor_line(
+        &self,
+        connection_type: ConnectionType,
+    ) -> crate::serial_line::SerialLine {
+        match (connection_type, self.victor_line_override) {
+            (ConnectionType::Victor86eSerial, true) => self.victor_86e_line.clone(),
+            (ConnectionType::Victor86eSerial, false) => super::VICTOR_86E_LINE,
+            (_, true) => self.victor_86bcd_line.clone(),
+            (_, false) => super::VICTOR_86BCD_LINE,
+        }
+    }
+
+    /// Open the selected connection for the main meter (the Connect button).
+    pub fn connect_main(&mut self) {
+        self.connection_state = ConnectionState::Connecting;
+        self.connection_error = None;
+        self.reconnect = ReconnectState {
+            usb: match self.connection_type {
+                #[cfg(not(target_arch = "wasm32"))]
+                ConnectionType::VictorHid => hid_identity(&self.hid_device_path),
+                _ => usb_identity(&self.serial_port),
+            },
+            ..Default::default()
+        };
+        match self.open_main_link() {
+            Ok(()) => self.connection_state = ConnectionState::Connected,
+            Err(e) => {
+                self.connection_state = ConnectionState::Disconnected;
+                self.connection_error = Some(e);
+            }
+        }
+    }
+
+    fn open_main_link(&mut self) -> Result<(), String> {
+        *self.link_lost_shared.lock().unwrap() = None;
+        match self.connection_type {
+            ConnectionType::ScpiSerial => {
+                let serial = super::open_serial_line(&self.serial_port, &self.serial_line)
+                    .map_err(|e| {
+                        format!("Failed to connect at {}: {e}", self.serial_line.label())
+                    })?;
+                self.serial = Some(serial);
+                self.curr_meter = "OWON XDM1041".to_owned();
+                self.spawn_serial_task();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            ConnectionType::VictorHid => {
+                if self.hid_device_path.is_empty() {
+                    return Err("No Victor HID device selected".to_owned());
+                }
+                self.curr_meter = "Victor 86B/C/D".to_owned();
+                self.rangecmd = None;
+                self.spawn_hid_task();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            ConnectionType::Victor86bcdSerial => {
+                let line = self.victor_line(ConnectionType::Victor86bcdSerial);
+                let serial = super::open_serial_line(&self.serial_port, &line)
+                    .map_err(|e| format!("Failed to connect at {}: {e}", line.label()))?;
+                self.serial = Some(serial);
+                self.curr_meter = "Victor 86B/C/D (DM1107)".to_owned();
+                self.rangecmd = None;
+                self.spawn_victor_86bcd_serial_task();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            ConnectionType::Victor86eSerial => {
+                let line = self.victor_line(ConnectionType::Victor86eSerial);
+                let serial = super::open_serial_line(&self.serial_port, &line)
+                    .map_err(|e| format!("Failed to connect at {}: {e}", line.label()))?;
+                self.serial = Some(serial);
+                self.curr_meter = "Victor 86E".to_owned();
+                self.rangecmd = None;
+                self.spawn_victor_86e_serial_task();
+            }
+        }
+        Ok(())
+    }
+
+    /// Stop the main meter task and drop its channels.
+    pub fn drop_main_link(&mut self) {
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            let _ = shutdown_tx.send(()); // Signal the serial task to shut down
+        }
+        self.serial_tx = None; // Drop sender to stop sending commands
+        self.serial_rx = None; // Drop receiver to stop receiving measurements
+        self.mode_rx = None; // Drop mode receiver
+        self.status_rx = None;
+        self.passthrough_tx = None;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.victor_86bcd_rx = None;
+            self.victor_lcd_display.clear();
+            self.victor_86bcd_capture_tx = None;
+        }
+        self.serial = None; // Clear serial port
+        *self.link_lost_shared.lock().unwrap() = None;
+        self.device.lock().unwrap().clear(); // The IDN is read again on the next link
+        self.applied_idn = None;
+        self.poll_ready.store(false, Ordering::SeqCst);
+        self.refresh_requested.store(false, Ordering::SeqCst);
+    }
+
+    /// Notice a lost main link, and reopen it until the device is back.
+    pub fn reconnect_tick(&mut self, now: f64) {
+        if self.connection_state == ConnectionState::Connected {
+            let lost = self.link_lost_shared.lock().unwrap().take();
+            if let Some(reason) = lost {
+                self.on_link_lost(reason, now);
+            } else if let Some(since) = self.reconnect.lost_since
+                && self.curr_meas_time > since
+            {
+                self.reconnect.lost_since = None;
+                self.reconnect.attempts = 0;
+                if self.recording_active {
+                    self.record_labelled("Reconnected", f64::NAN);
+                }
+            }
+            return;
+        }
+        if self.connection_state != ConnectionState::Reconnecting
+            || now < self.reconnect.next_attempt
+        {
+            return;
+        }
+        self.reconnect.attempts += 1;
+        self.reconnect.next_attempt = now + RECONNECT_INTERVAL_S;
+        self.follow_moved_device();
+        match self.open_main_link() {
+            Ok(()) => {
+                if self.value_debug {
+                    println!(
+                        "Reopened main meter link, attempt {}",
+                        self.reconnect.attempts
+                    );
+                }
+                self.connection_state = ConnectionState::Connected;
+                self.connection_error = None;
+            }
+            Err(e) => self.connection_error = Some(e),
+        }
+    }
+
+    fn on_link_lost(&mut self, reason: String, now: f64) {
+        if self.value_debug {
+            println!("Main meter link lost: {reason}");
+        }
+        self.drop_main_link();
+        self.curr_meas = f64::NAN;
+        // A reopened link that dies before any reading is the same gap
+        if self.reconnect.lost_since.is_none() {
+            self.reconnect.lost_since = Some(now);
+            if self.recording_active {
+                self.record_labelled("Link lost", f64::NAN);
+            }
+        }
+        self.connection_error = Some(reason);
+        if self.auto_reconnect {
+            self.connection_state = ConnectionState::Reconnecting;
+            self.reconnect.next_attempt = now + RECONNECT_INTERVAL_S;
+        } else {
+            self.connection_state = ConnectionState::Disconnected;
+            self.reconnect = ReconnectState::default();
+        }
+    }
+
+    /// Re-plugging can change the port name or HID path; pick the device up
+    /// under its new one.
+    fn follow_moved_device(&mut self) {
+        match self.connection_type {
+            #[cfg(not(target_arch = "wasm32"))]
+            ConnectionType::VictorHid => {
+                self.refresh_hid_devices();
+                if let Some(path) = self
+                    .reconnect
+                    .usb
+                    .as_ref()
+                    .and_then(|usb| find_moved_hid_device(usb, &self.hid_device_path))
+                {
+                    if self.value_debug {
+                        println!("Meter moved from {} to {path}", self.hid_device_path);
+                    }
+                    self.hid_device_path = path;
+                }
+            }
+            _ => {
+                if let Some(port) = self
+                    .reconnect
+                    .usb
+                    .as_ref()
+                    .and_then(|usb| find_moved_usb_port(usb, &self.serial_port))
+                {
+                    if self.value_debug {
+                        println!("Meter moved from {} to {port}", self.serial_port);
+                    }
+                    self.serial_port = port;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Interactive SCPI console. Lines go to the SCPI task through the same
+//! passthrough path as the TCP port, so a query gets the wire to itself and
+//! its reply is shown next to it instead of being classified as a poll reply.
+
+use egui::{Color32, Context, FontId, Key, RichText, TextEdit, Window};
+use tokio::sync::oneshot::{self, error::TryRecvError};
+
+use super::serial::PassthroughCmd;
+use crate::scpi_macro::{complete_scpi, ensure_newline, expects_reply, scpi_completions};
+
+/// Commands kept in the scrollback.
+const CONSOLE_MAX_LINES: usize = 500;
+/// Commands kept in the persisted history.
+const CONSOLE_MAX_HISTORY: usize = 100;
+
+enum ConsoleReply {
+    /// A set command; the meter does not answer.
+    Sent,
+    Pending(oneshot::Receiver<String>),
+    Reply(String),
+    NoReply,
+    Error(String),
+}
+
+struct ConsoleEntry {
+    command: String,
+    reply: ConsoleReply,
+}
+
+#[derive(Default)]
+pub struct ConsoleState {
+    input: String,
+    entries: Vec<ConsoleEntry>,
+    /// Position while browsing the history with the arrow keys.
+    history_pos: Option<usize>,
+}
+
+impl super::MyApp {
+    fn console_send(&mut self) {
+        let command = self.console.input.trim().to_owned();
+        if command.is_empty() {
+            return;
+        }
+        self.console.input.clear();
+        self.console.history_pos = None;
+        self.console_history.retain(|c| *c != command);
+        self.console_history.push(command.clone());
+        if self.console_history.len() > CONSOLE_MAX_HISTORY {
+            self.console_history.remove(0);
+        }
+
+        let reply = match &self.passthrough_tx {
+            None => ConsoleReply::Error("No SCPI meter connected".to_owned()),
+            Some(tx) => {
+                let cmd = ensure_newline(&command);
+                let (reply_tx, reply_rx) = oneshot::channel();
+                let wants_reply = expects_reply(&cmd);
+                let request = PassthroughCmd {
+                    cmd,
+                    reply: wants_reply.then_some(reply_tx),
+                };
+                match tx.try_send(request) {
+                    Ok(()) if wants_reply => ConsoleReply::Pending(reply_rx),
+                    Ok(()) => ConsoleReply::Sent,
+                    Err(e) => ConsoleReply::Error(format!("Failed to send command: {e}")),
+                }
+            }
+        };
+        self.console.entries.push(ConsoleEntry { command, reply });
+        if self.console.entries.len() > CONSOLE_MAX_LINES {
+            self.console.entries.remove(0);
+        }
+    }
+
+    /// Collect replies that arrived since the last frame. True while any
+    /// query is still waiting.
+    fn console_poll_replies(&mut self) -> bool {
+        let mut waiting = false;
+        for entry in &mut self.console.entries {
+            if let ConsoleReply::Pending(rx) = &mut entry.reply {
+                match rx.try_recv() {
+                    Ok(reply) => entry.reply = ConsoleReply::Reply(reply),
+                    Err(TryRecvError::Closed) => entry.reply = ConsoleReply::NoReply,
+                    Err(TryRecvError::Empty) => waiting = true,
+                }
+            }
+        }
+        waiting
+    }
+
+    fn console_browse_history(&mut self, older: bool) {
+        let len = self.console_history.len();
+        if len == 0 {
+            return;
+        }
+        let pos = match (self.console.history_pos, older) {
+            (None, true) => Some(len - 1),
+            (None, false) => None,
+            (Some(p), true) => Some(p.saturating_sub(1)),
+            (Some(p), false) if p + 1 < len => Some(p + 1),
+            (Some(_), false) => None,
+        };
+        self.console.history_pos = pos;
+        self.console.input = pos
+            .map(|p| self.console_history[p].clone())
+            .unwrap_or_default();
+    }
+
+    pub fn show_console(&mut self, ctx: &Context) {
+        if !self.console_open {
+            return;
+        }
+        if self.console_poll_replies() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
+
+        let mono = FontId::monospace(13.0);
+        let mut open = true;
+        Window::new("SCPI console")
+            .open(&mut open)
+            .default_size([560.0, 380.0])
+            .resizable(true)
+            .vscroll(false)
+            .show(ctx, |ui| {
+                if self.passthrough_tx.is_none() {
+                    ui.label(
+                        RichText::new("Connect a SCPI meter to send commands.")
+                            .color(Color32::YELLOW),
+                    );
+                }
+                ui.label(
+                    "Queries pause MEAS? polling until the reply arrives. \
+                     Tab completes, Up / Down browse the history.",
+                );
+                ui.separator();
+
+                egui::Panel::bottom("scpi_console_input").show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let edit_id = ui.make_persistent_id("scpi_console_edit");
+                        let response = ui.add(
+                            TextEdit::singleline(&mut self.console.input)
+                                .id(edit_id)
+                                .font(mono.clone())
+                                .lock_focus(true)
+                                .desired_width(ui.available_width() - 60.0)
+                                .hint_text("*IDN?"),
+                        );
+                        let mut moved_cursor = false;
+                        if response.has_focus() {
+                            if ui.input(|i| i.key_pressed(Key::Tab)) {
+                                if let Some(completed) = complete_scpi(&self.console.input) {
+                                    self.console.input = completed;
+                                    moved_cursor = true;
+                                }
+                            } else if ui.input(|i| i.key_pressed(Key::ArrowUp)) {
+                                self.console_browse_history(true);
+                                moved_cursor = true;
+                            } else if ui.input(|i| i.key_pressed(Key::ArrowDown)) {
+                                self.console_browse_history(false);
+                                moved_cursor = true;
+                            }
+                        }
+                        if moved_cursor {
+                            if let Some(mut state) = TextEdit::load_state(ui.ctx(), edit_id) {
+                                let end =
+                                    egui::text::CCursor::new(self.console.input.chars().count());
+                                state
+                                    .cursor
+                                    .set_char_range(Some(egui::text::CCursorRange::one(end)));
+                                state.store(ui.ctx(), edit_id);
+                            }
+                        }
+                        let enter =
+                            response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                        if ui.button("Send").clicked() || enter {
+                            self.console_send();
+                            response.request_focus();
+                        }
+                    });
+                    let matches = scpi_completions(&self.console.input);
+                    if !self.console.input.trim().is_empty() && !matches.is_empty() {
+                        ui.label(
+                            RichText::new(matches.join("   "))
+                                .font(FontId::monospace(11.0))
+                                .weak(),
+                        );
+                    }
+                });
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for entry in &self.console.entries {
+                            ui.label(
+                                RichText::new(format!("> {}", entry.command)).font(mono.clone()),
+                            );
+                            let (text, color) = match &entry.reply {
+                                ConsoleReply::Sent => continue,
+                                ConsoleReply::Pending(_) => ("…".to_owned(), Color32::GRAY),
+                                ConsoleReply::Reply(reply) => {
+                                    (reply.clone(), ui.visuals().strong_text_color())
+                                }
+                                ConsoleReply::NoReply => ("(no reply)".to_owned(), Color32::YELLOW),
+                                ConsoleReply::Error(e) => (e.clone(), Color32::RED),
+                            };
+                            ui.label(
+                                RichText::new(format!("  {text}"))
+                                    .font(mono.clone())
+                                    .color(color),
+                            );
+                        }
+                    });
+            });
+        if !open {
+            self.console_open = false;
+        }
+    }
+}
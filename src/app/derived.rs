@@ -0,0 +1,262 @@
+//! Derived (virtual) channels computed from the live meter readings.
+//!
+//! Inputs are `m1` for the main meter, `m2`, `m3`, ... for the additional
+//! meters in list order, and the names of derived channels defined above.
+
+use egui::{Color32, DragValue, RichText, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::expr::{self, Expr, ExprError, TimedValue, Unavailable};
+
+use super::ChannelValue;
+
+/// Trace colors handed out to newly added derived channels.
+const DERIVED_COLORS: [Color32; 4] = [
+    Color32::from_rgb(0, 200, 200),
+    Color32::from_rgb(200, 200, 0),
+    Color32::from_rgb(160, 120, 255),
+    Color32::from_rgb(255, 140, 180),
+];
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct DerivedChannel {
+    pub name: String,       // Persistent, also the variable name in later expressions
+    pub expression: String, // Persistent
+    pub unit: String,       // Persistent, free text shown next to the value
+    pub color: Color32,     // Persistent, combined graph trace color
+    #[serde(skip)]
+    parsed: Option<(String, Result<Expr, ExprError>)>, // `expression` as last parsed
+}
+
+impl Default for DerivedChannel {
+    fn default() -> Self {
+        Self {
+            name: "P".to_owned(),
+            expression: "m1 * m2".to_owned(),
+            unit: "W".to_owned(),
+            color: DERIVED_COLORS[0],
+            parsed: None,
+        }
+    }
+}
+
+impl DerivedChannel {
+    fn new(index: usize) -> Self {
+        Self {
+            name: if index == 0 {
+                "P".to_owned()
+            } else {
+                format!("d{}", index + 1)
+            },
+            color: DERIVED_COLORS[index % DERIVED_COLORS.len()],
+            ..Default::default()
+        }
+    }
+
+    /// The parsed expression, parsed again only after the text changed.
+    fn parsed(&mut self) -> &Result<Expr, ExprError> {
+        if self
+            .parsed
+            .as_ref()
+            .is_some_and(|(source, _)| *source != self.expression)
+        {
+            self.parsed = None;
+        }
+        &self
+            .parsed
+            .get_or_insert_with(|| (self.expression.clone(), expr::parse(&self.expression)))
+            .1
+    }
+}
+
+/// Latest evaluation of one derived channel.
+pub struct DerivedValue(pub Result<TimedValue, Unavailable>);
+
+impl DerivedValue {
+    /// The value, NaN while unavailable.
+    pub fn value(&self) -> f64 {
+        self.0.as_ref().map_or(f64::NAN, |v| v.value)
+    }
+}
+
+/// One selectable channel: a meter or a derived channel, with its latest
+/// reading when available.
+pub struct LiveChannel {
+    pub var: String,   // Variable name, m1, m2, ... or the derived channel name
+    pub label: String, // Display name
+    pub unit: String,
+    /// Multiply values by this to get the base unit of `unit`, e.g. A for
+    /// `mADC`.
+    pub si_factor: f64,
+    pub value: Option<TimedValue>,
+    /// Every meter reading since the last frame, oldest first. Derived
+    /// channels only have their current value.
+    pub readings: Vec<TimedValue>,
+}
+
+/// `m1`, `m2`, ... are reserved for the meters.
+fn is_meter_variable(name: &str) -> bool {
+    name.strip_prefix('m')
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+impl super::MyApp {
+    /// Re-evaluate every derived channel against the current readings and
+    /// refresh [`Self::live_channels`].
+    pub fn update_derived(&mut self, now: f64) {
+        let tolerance = self.derived_tolerance_ms as f64 / 1000.0;
+        let stale_after = self.derived_stale_ms as f64 / 1000.0;
+        let mut channels = vec![LiveChannel {
+            var: "m1".to_owned(),
+            label: "Meter 1".to_owned(),
+            unit: self.curr_unit.clone(),
+            si_factor: self.connection_type.si_factor(&self.curr_unit),
+            value: (self.connection_state == super::ConnectionState::Connected).then_some(
+                TimedValue {
+                    value: self.curr_meas,
+                    time: self.curr_meas_time,
+                },
+            ),
+            readings: self.frame_readings.clone(),
+        }];
+        for (idx, meter) in self.aux_meters.iter().enumerate() {
+            channels.push(LiveChannel {
+                var: format!("m{}", idx + 2),
+                label: meter.name.clone(),
+                unit: meter.unit.clone(),
+                si_factor: meter.connection_type.si_factor(&meter.unit),
+                value: meter.is_connected().then_some(TimedValue {
+                    value: meter.meas,
+                    time: meter.meas_time,
+                }),
+                readings: meter.frame_readings.clone(),
+            });
+        }
+
+        self.derived_values.clear();
+        for channel in &mut self.derived_channels {
+            let result = match channel.parsed() {
+                Ok(e) => {
+                    let lookup = |name: &str| {
+                        channels
+                            .iter()
+                            .find(|c| c.var == name)
+                            .and_then(|c| c.value)
+                    };
+                    expr::eval_aligned(e, &lookup, now, tolerance, stale_after)
+                }
+                Err(e) => Err(Unavailable::Error(e.clone())),
+            };
+            if expr::is_identifier(&channel.name) && !is_meter_variable(&channel.name) {
+                channels.push(LiveChannel {
+                    var: channel.name.clone(),
+                    label: channel.name.clone(),
+                    unit: channel.unit.clone(),
+                    si_factor: crate::multimeter::split_si_prefix(&channel.unit).0,
+                    value: result.as_ref().ok().copied(),
+                    readings: result.iter().copied().collect(),
+                });
+            }
+            self.derived_values.push(DerivedValue(result));
+        }
+        self.live_channels = channels;
+    }
+
+    /// Derived channel readings for the recorder.
+    pub fn derived_channel_values(&self) -> Vec<ChannelValue> {
+        self.derived_channels
+            .iter()
+            .zip(&self.derived_values)
+            .map(|(channel, value)| ChannelValue {
+                name: channel.name.clone(),
+                unit: channel.unit.clone(),
+                value: value.value(),
+            })
+            .collect()
+    }
+
+    pub fn show_derived_channels(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new(format!(
+            "Derived channels ({})",
+            self.derived_channels.len()
+        ))
+        .id_salt("derived_channels")
+        .show(ui, |ui| {
+            ui.label(
+                "Inputs: m1 = main meter, m2, m3, ... = additional meters, \
+                 and derived channels listed above. Operators + - * / ^, \
+                 functions abs sqrt ln log10 exp min max.",
+            );
+            let mut remove = None;
+            for (idx, channel) in self.derived_channels.iter_mut().enumerate() {
+                ui.push_id(idx, |ui| {
+                    ui.horizontal(|ui| {
+                        let name_ok =
+                            expr::is_identifier(&channel.name) && !is_meter_variable(&channel.name);
+                        let name_edit = ui.add(
+                            TextEdit::singleline(&mut channel.name)
+                                .desired_width(60.0)
+                                .text_color_opt((!name_ok).then_some(Color32::RED)),
+                        );
+                        if !name_ok {
+                            name_edit.on_hover_text(
+                                "Name must be a letter followed by letters, digits or _, \
+                                 and not m1, m2, ...",
+                            );
+                        }
+                        ui.label("=");
+                        ui.add(
+                            TextEdit::singleline(&mut channel.expression)
+                                .desired_width(160.0)
+                                .hint_text("m1 * m2"),
+                        );
+                        ui.add(
+                            TextEdit::singleline(&mut channel.unit)
+                                .desired_width(40.0)
+                                .hint_text("unit"),
+                        );
+                        egui::color_picker::color_edit_button_srgba(
+                            ui,
+                            &mut channel.color,
+                            egui::color_picker::Alpha::Opaque,
+                        );
+                        match self.derived_values.get(idx).map(|v| &v.0) {
+                            Some(Ok(v)) => {
+                                ui.label(
+                                    RichText::new(format!("{:.6} {}", v.value, channel.unit))
+                                        .monospace(),
+                                );
+                            }
+                            Some(Err(Unavailable::Error(e))) => {
+                                ui.label(RichText::new(e.to_string()).color(Color32::RED));
+                            }
+                            Some(Err(reason)) => {
+                                ui.label(RichText::new(reason.to_string()).color(Color32::GRAY));
+                            }
+                            None => {}
+                        }
+                        if ui.button("Remove").clicked() {
+                            remove = Some(idx);
+                        }
+                    });
+                });
+            }
+            if let Some(idx) = remove {
+                self.derived_channels.remove(idx);
+                self.combined_history.clear();
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Add channel").clicked() {
+                    self.derived_channels
+                        .push(DerivedChannel::new(self.derived_channels.len()));
+                    self.combined_history.clear();
+                }
+                ui.label("Alignment tolerance (ms):");
+                ui.add(DragValue::new(&mut self.derived_tolerance_ms).range(0..=60_000));
+                ui.label("Stale after (ms):");
+                ui.add(DragValue::new(&mut self.derived_stale_ms).range(100..=600_000));
+            });
+        });
+    }
+}
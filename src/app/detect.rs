@@ -0,0 +1,320 @@
+//! Auto-detect: probe every serial port and Victor HID device in the
+//! background and propose the connection type and port to use.
+//!
+//! Ports are listened on first, since Victor meters stream on their own and
+//! SCPI meters stay silent until asked. Only then is `*IDN?` sent.
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use egui::{Color32, Context, RichText, Window};
+use hidapi::HidApi;
+use mio_serial::{DataBits, Parity, SerialPort, StopBits};
+use tokio::sync::mpsc;
+
+use crate::detect::{
+    Candidate, Confidence, Protocol, SCPI_BAUD_RATES, hid_candidate, idn_candidate, rank,
+    stream_candidate,
+};
+use crate::serial_line::SerialLine;
+use crate::victor_dm1107::VICTOR_86BCD_BAUD;
+use crate::victor_es519xx::VICTOR_86E_BAUD;
+use crate::victor_fs9922::{VICTOR_PRODUCT_ID, VICTOR_VENDOR_ID};
+
+use super::ConnectionType;
+
+/// How long to listen for streamed Victor frames per line setting.
+const LISTEN_WINDOW: Duration = Duration::from_millis(1500);
+/// How long to wait for the `*IDN?` reply per baud rate.
+const IDN_WINDOW: Duration = Duration::from_millis(600);
+
+enum DetectMsg {
+    Progress(String),
+    Found(Candidate),
+    Done,
+}
+
+#[derive(Default)]
+pub struct DetectState {
+    rx: Option<mpsc::Receiver<DetectMsg>>,
+    progress: String,
+    candidates: Vec<Candidate>,
+    open: bool,
+}
+
+impl DetectState {
+    pub fn is_running(&self) -> bool {
+        self.rx.is_some()
+    }
+}
+
+fn open_line(
+    port: &str,
+    baud: u32,
+    data_bits: DataBits,
+    parity: Parity,
+) -> Option<Box<dyn SerialPort>> {
+    let mut serial = mio_serial::new(port, baud)
+        .data_bits(data_bits)
+        .parity(parity)
+        .stop_bits(StopBits::One)
+        .timeout(Duration::from_millis(50))
+        .open()
+        .ok()?;
+    // Some 86B/C/D CP2102 links only stream with DTR set
+    let _ = serial.write_data_terminal_ready(true);
+    let _ = serial.clear(mio_serial::ClearBuffer::Input);
+    Some(serial)
+}
+
+fn read_for(serial: &mut dyn SerialPort, window: Duration, until_newline: bool) -> Vec<u8> {
+    let deadline = Instant::now() + window;
+    let mut out = Vec::new();
+    let mut buf = [0u8; 256];
+    while Instant::now() < deadline {
+        match serial.read(&mut buf) {
+            Ok(n) => {
+                out.extend_from_slice(&buf[..n]);
+                if until_newline && out.contains(&b'\n') {
+                    break;
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+            Err(_) => break,
+        }
+    }
+    out
+}
+
+/// Listen with both Victor line settings, then ask `*IDN?` at each baud
+/// rate. Stops at the first certain answer.
+fn probe_port(port: &str, tx: &mpsc::Sender<DetectMsg>) {
+    let listens = [
+        (
+            Protocol::Dm1107,
+            VICTOR_86BCD_BAUD,
+            DataBits::Eight,
+            Parity::None,
+        ),
+        (
+            Protocol::Es519xx,
+            VICTOR_86E_BAUD,
+            DataBits::Seven,
+            Parity::Odd,
+        ),
+    ];
+    for (protocol, baud, data_bits, parity) in listens {
+        let _ = tx.blocking_send(DetectMsg::Progress(format!(
+            "{port}: listening at {baud} baud…"
+        )));
+        // Busy or vanished ports are skipped
+        let Some(mut serial) = open_line(port, baud, data_bits, parity) else {
+            return;
+        };
+        let bytes = read_for(serial.as_mut(), LISTEN_WINDOW, false);
+        if let Some(candidate) = stream_candidate(port, protocol, &bytes) {
+            let certain = candidate.confidence == Confidence::High;
+            let _ = tx.blocking_send(DetectMsg::Found(candidate));
+            if certain {
+                return;
+            }
+        }
+    }
+    for baud in SCPI_BAUD_RATES {
+        let _ = tx.blocking_send(DetectMsg::Progress(format!(
+            "{port}: *IDN? at {baud} baud…"
+        )));
+        let Some(mut serial) = open_line(port, baud, DataBits::Eight, Parity::None) else {
+            return;
+        };
+        if serial.write_all(b"*IDN?\n").is_err() {
+            continue;
+        }
+        let reply = read_for(serial.as_mut(), IDN_WINDOW, true);
+        if let Some(candidate) = idn_candidate(port, baud, &reply) {
+            let _ = tx.blocking_send(DetectMsg::Found(candidate));
+            return;
+        }
+    }
+}
+
+fn probe_hid(busy: &[String], tx: &mpsc::Sender<DetectMsg>) {
+    let Ok(api) = HidApi::new() else {
+        return;
+    };
+    for info in api
+        .device_list()
+        .filter(|d| d.vendor_id() == VICTOR_VENDOR_ID && d.product_id() == VICTOR_PRODUCT_ID)
+    {
+        let path = info.path().to_string_lossy().into_owned();
+        if busy.contains(&path) {
+            continue;
+        }
+        let _ = tx.blocking_send(DetectMsg::Progress("Reading Victor HID device…".to_owned()));
+        let label = info.product_string().unwrap_or("Multimeter").to_owned();
+        let mut reports = Vec::new();
+        if let Ok(device) = info.open_device(&api) {
+            let deadline = Instant::now() + LISTEN_WINDOW;
+            let mut buf = [0u8; 64];
+            while Instant::now() < deadline && reports.len() < 3 {
+                match device.read_timeout(&mut buf, 200) {
+                    Ok(0) => {}
+                    Ok(n) => reports.push(buf[..n].to_vec()),
+                    Err(_) => break,
+                }
+            }
+        }
+        let _ = tx.blocking_send(DetectMsg::Found(hid_candidate(&path, &label, &reports)));
+    }
+}
+
+fn confidence_color(confidence: Confidence) -> Color32 {
+    match confidence {
+        Confidence::High => Color32::GREEN,
+        Confidence::Medium => Color32::YELLOW,
+        Confidence::Low => Color32::GRAY,
+    }
+}
+
+impl super::MyApp {
+    /// Probe all ports and HID devices not in use by a connected meter.
+    pub fn start_detection(&mut self) {
+        let busy: Vec<String> = self
+            .aux_meters
+            .iter()
+            .filter(|m| m.is_connected())
+            .flat_map(|m| [m.serial_port.clone(), m.hid_device_path.clone()])
+            .collect();
+        let ports: Vec<String> = mio_serial::available_ports()
+            .map(|ports| {
+                ports
+                    .into_iter()
+                    .map(|p| p.port_name)
+                    .filter(|p| !busy.contains(p))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let (tx, rx) = mpsc::channel::<DetectMsg>(32);
+        self.detect = DetectState {
+            rx: Some(rx),
+            progress: "Scanning…".to_owned(),
+            candidates: Vec::new(),
+            open: true,
+        };
+        tokio::task::spawn_blocking(move || {
+            probe_hid(&busy, &tx);
+            for port in ports {
+                // Window closed: stop scanning
+                if tx.is_closed() {
+                    return;
+                }
+                probe_port(&port, &tx);
+            }
+            let _ = tx.blocking_send(DetectMsg::Done);
+        });
+    }
+
+    fn use_candidate(&mut self, candidate: &Candidate) {
+        match candidate.protocol {
+            Protocol::Scpi { baud } => {
+                self.connection_type = ConnectionType::ScpiSerial;
+                self.serial_port = candidate.port.clone();
+                // Detection asks at 8N1
+                self.serial_line = SerialLine {
+                    baud,
+                    ..SerialLine::default()
+                };
+            }
+            Protocol::Dm1107 => {
+                self.connection_type = ConnectionType::Victor86bcdSerial;
+                self.serial_port = candidate.port.clone();
+            }
+            Protocol::Es519xx => {
+                self.connection_type = ConnectionType::Victor86eSerial;
+                self.serial_port = candidate.port.clone();
+            }
+            Protocol::Fs9922Hid => {
+                self.connection_type = ConnectionType::VictorHid;
+                self.hid_device_path = candidate.port.clone();
+            }
+        }
+    }
+
+    pub fn show_detect(&mut self, ctx: &Context) {
+        if !self.detect.open {
+            return;
+        }
+        if let Some(rx) = self.detect.rx.as_mut() {
+            let mut done = false;
+            while let Ok(msg) = rx.try_recv() {
+                match msg {
+                    DetectMsg::Progress(text) => self.detect.progress = text,
+                    DetectMsg::Found(candidate) => {
+                        self.detect.candidates.push(candidate);
+                        rank(&mut self.detect.candidates);
+                    }
+                    DetectMsg::Done => done = true,
+                }
+            }
+            if done {
+                self.detect.rx = None;
+                self.detect.progress = if self.detect.candidates.is_empty() {
+                    "No meter found. Is it switched on and plugged in?".to_owned()
+                } else {
+                    "Scan finished.".to_owned()
+                };
+            } else {
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+        }
+
+        let mut open = true;
+        let mut chosen = None;
+        let mut rescan = false;
+        Window::new("Auto-detect")
+            .open(&mut open)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if self.detect.is_running() {
+                        ui.spinner();
+                    }
+                    ui.label(&self.detect.progress);
+                });
+                if !self.detect.candidates.is_empty() {
+                    ui.separator();
+                    egui::Grid::new("detect_candidates")
+                        .num_columns(5)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for candidate in &self.detect.candidates {
+                                ui.label(candidate.protocol.label());
+                                ui.monospace(&candidate.port);
+                                ui.label(
+                                    RichText::new(candidate.confidence.label())
+                                        .color(confidence_color(candidate.confidence)),
+                                );
+                                ui.weak(&candidate.detail);
+                                if ui.button("Use").clicked() {
+                                    chosen = Some(candidate.clone());
+                                }
+                                ui.end_row();
+                            }
+                        });
+                }
+                if !self.detect.is_running() && ui.button("Scan again").clicked() {
+                    rescan = true;
+                }
+            });
+        if let Some(candidate) = chosen {
+            self.use_candidate(&candidate);
+            open = false;
+        }
+        if rescan {
+            self.start_detection();
+        } else if !open {
+            // Dropping the receiver stops the scan after the current probe
+            self.detect = DetectState::default();
+        }
+    }
+}
@@ -1,14 +1,24 @@
 use egui::{Color32, Slider, SliderClamping};
-use egui_plot::{Bar, BarChart, Legend, Line, Plot, PlotPoints};
+use egui_plot::{Bar, BarChart, Legend, Line, LineStyle, Plot, PlotPoints, VLine};
+use rfd::FileDialog;
 use std::collections::VecDeque;
 
 use crate::multimeter::MeterMode;
+use crate::plot_export::{Dash, ExportBar, ExportSeries, PlotExport, SeriesKind};
+use crate::stats::{self, HistogramBins, Summary};
+
+use super::meters::CombinedSample;
 
 // Configuration for graph settings
 #[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct GraphConfig {
-    pub num_bins: usize, // Number of bins for histogram, 0 for auto
-    pub max_bins: usize, // Maximum number of bins for slider
+    pub num_bins: usize,  // Number of bins for histogram, 0 for auto
+    pub max_bins: usize,  // Maximum number of bins for slider
+    pub bin_width: f64,   // Histogram bin width in measurement units, 0 to use num_bins
+    pub log_y: bool,      // Logarithmic histogram count axis
+    pub show_stats: bool, // Mean and sigma lines on the histogram
+    pub show_fit: bool,   // Normal distribution fit over the histogram
 }
 
 impl Default for GraphConfig {
@@ -16,10 +26,120 @@ impl Default for GraphConfig {
         Self {
             num_bins: 0,   // 0 means auto
             max_bins: 100, // Default maximum bins
+            bin_width: 0.0,
+            log_y: false,
+            show_stats: true,
+            show_fit: false,
+        }
+    }
+}
+
+// Offscreen image export size, independent of the window
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    pub width: u32,
+    pub height: u32,
+    #[serde(skip)]
+    pub last_result: Option<String>, // Status line of the last export
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            width: 1600,
+            height: 900,
+            last_result: None,
         }
     }
 }
 
+/// Bin `values` per `graph_config`. `None` for an empty buffer.
+pub fn histogram_bins(values: &[f64], graph_config: &GraphConfig) -> Option<HistogramBins> {
+    stats::histogram_bins(
+        values,
+        graph_config.num_bins,
+        graph_config.max_bins,
+        graph_config.bin_width,
+    )
+}
+
+const MEAN_COLOR: Color32 = Color32::from_rgb(220, 60, 60);
+const SIGMA_COLOR: Color32 = Color32::from_rgb(230, 150, 40);
+const FIT_COLOR: Color32 = Color32::from_rgb(60, 170, 90);
+
+// Log Y plots log10(count) shifted up one decade, so a single sample still
+// shows as a bar and the axis bottoms out at 0.1 counts.
+fn log_height(count: f64) -> f64 {
+    count.log10() + 1.0
+}
+
+fn count_from_log_height(height: f64) -> f64 {
+    10f64.powf(height - 1.0)
+}
+
+/// Normal curve scaled to bin counts, sampled across mean ± 4σ.
+fn normal_fit_points(summary: &Summary, bin_width: f64) -> Vec<[f64; 2]> {
+    const POINTS: usize = 200;
+    if summary.std_dev <= 0.0 {
+        return Vec::new();
+    }
+    let from = summary.mean - 4.0 * summary.std_dev;
+    let step = 8.0 * summary.std_dev / (POINTS - 1) as f64;
+    (0..POINTS)
+        .map(|i| {
+            let x = from + i as f64 * step;
+            [x, summary.normal_count(x, bin_width)]
+        })
+        .collect()
+}
+
+pub(super) fn export_timestamp() -> String {
+    format!(
+        "Exported {}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    )
+}
+
+/// Ask for a target file and render `plot` into it at the configured size.
+fn export_with_dialog(plot: &PlotExport, export: &mut ExportSettings, default_name: &str) {
+    let Some(path) = FileDialog::new()
+        .add_filter("PNG image", &["png"])
+        .add_filter("SVG image", &["svg"])
+        .set_file_name(default_name)
+        .save_file()
+    else {
+        return;
+    };
+    export.last_result = Some(
+        match crate::plot_export::save(plot, &path, export.width, export.height) {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(e) => e,
+        },
+    );
+}
+
+fn show_export_controls(
+    ui: &mut egui::Ui,
+    export: &mut ExportSettings,
+    default_name: &str,
+    plot: impl FnOnce() -> PlotExport,
+) {
+    if ui
+        .button("Export image")
+        .on_hover_text(format!(
+            "Save as PNG or SVG at {}x{} px (change in Settings)",
+            export.width, export.height
+        ))
+        .clicked()
+    {
+        export_with_dialog(&plot(), export, default_name);
+    }
+    if let Some(result) = &export.last_result {
+        ui.label(result);
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn show_line_graph(
     ui: &mut egui::Ui,
@@ -32,6 +152,8 @@ pub fn show_line_graph(
     mem_depth_max: usize,
     graph_update_interval_max: u64,
     curr_unit: &str,
+    export: &mut ExportSettings,
+    export_title: &str,
 ) {
     let values: Vec<f64> = values.iter().copied().collect();
     let points: Vec<f64> = if reverse_graph {
@@ -39,6 +161,24 @@ pub fn show_line_graph(
     } else {
         values
     };
+    let export_plot = || PlotExport {
+        title: format!("{export_title} graph"),
+        x_label: "Samples".to_owned(),
+        y_label: curr_unit.to_owned(),
+        timestamp: export_timestamp(),
+        series: vec![ExportSeries {
+            name: curr_unit.to_owned(),
+            color: graph_line_color.to_array()[..3].try_into().unwrap(),
+            kind: SeriesKind::Line(
+                points
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &v)| [i as f64, v])
+                    .collect(),
+            ),
+        }],
+        log_y: false,
+    };
     let line = Line::new(curr_unit, PlotPoints::from_ys_f64(&points))
         .stroke(egui::Stroke::new(2.0, graph_line_color));
     let plot = Plot::new("graph")
@@ -65,6 +205,7 @@ pub fn show_line_graph(
                     .clamping(SliderClamping::Always),
             );
             ui.checkbox(reverse_graph_mut, "Reverse Graph (most recent on left)");
+            show_export_controls(ui, export, "graph.png", export_plot);
         });
         ui.label("Graph Adjustments");
         ui.separator();
@@ -85,10 +226,85 @@ pub fn show_line_graph(
     });
 }
 
+/// All meters and derived channels over time on one plot, with summary
+/// statistics per trace. X is seconds relative to the newest sample.
+pub fn show_combined_graph(
+    ui: &mut egui::Ui,
+    history: &VecDeque<CombinedSample>,
+    traces: &[(String, Color32)],
+    mem_depth: &mut usize,
+    graph_update_interval_ms: &mut u64,
+    mem_depth_max: usize,
+    graph_update_interval_max: u64,
+) {
+    let latest = history.back().map_or(0.0, |s| s.t);
+    let plot = Plot::new("combined_graph")
+        .legend(Legend::default().text_style(egui::TextStyle::Monospace))
+        .y_axis_min_width(4.0)
+        .x_axis_label("Time (s)")
+        .show_axes(true)
+        .show_grid(true);
+
+    ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
+        ui.horizontal_wrapped(|ui| {
+            ui.add(
+                Slider::new(mem_depth, 10..=mem_depth_max)
+                    .text("Memory Depth")
+                    .step_by(10.0)
+                    .clamping(SliderClamping::Always),
+            );
+            ui.add(
+                Slider::new(graph_update_interval_ms, 10..=graph_update_interval_max)
+                    .text("Update Interval (ms)")
+                    .step_by(10.0)
+                    .clamping(SliderClamping::Always),
+            );
+        });
+        // Bottom-up layout: walk the traces backwards so they read top to bottom
+        for (idx, (name, color)) in traces.iter().enumerate().rev() {
+            let column: Vec<f64> = history
+                .iter()
+                .filter_map(|s| s.values.get(idx).copied())
+                .collect();
+            if let Some(summary) = stats::summarize(&column) {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{name}: mean {:.6}  σ {:.3e}  min {:.6}  max {:.6}  n {}",
+                        summary.mean, summary.std_dev, summary.min, summary.max, summary.count
+                    ))
+                    .monospace()
+                    .color(*color),
+                );
+            }
+        }
+        ui.label("Graph Adjustments");
+        ui.separator();
+        plot.show(ui, |plot_ui| {
+            for (idx, (name, color)) in traces.iter().enumerate() {
+                let points: Vec<[f64; 2]> = history
+                    .iter()
+                    .filter_map(|s| {
+                        let v = *s.values.get(idx)?;
+                        v.is_finite().then_some([s.t - latest, v])
+                    })
+                    .collect();
+                if points.is_empty() {
+                    continue;
+                }
+                plot_ui.line(
+                    Line::new(name.as_str(), PlotPoints::from(points))
+                        .stroke(egui::Stroke::new(2.0, *color)),
+                );
+            }
+        });
+    });
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn show_histogram(
     ui: &mut egui::Ui,
     hist_values: &mut VecDeque<f64>,
+    hist_times: &mut VecDeque<f64>,
     curr_meas: f64,
     metermode: MeterMode,
     graph_config: &mut GraphConfig,
@@ -97,6 +313,8 @@ pub fn show_histogram(
     hist_collect_interval_ms: &mut u64,
     hist_mem_depth: &mut usize,
     hist_mem_depth_max: usize,
+    export: &mut ExportSettings,
+    export_title: &str,
 ) {
     // Format the latest measurement for display
     let (_formatted_value, display_unit) = crate::helpers::format_measurement(
@@ -108,104 +326,80 @@ pub fn show_histogram(
         false,
         None,
     );
+    let format_value = |value: f64| {
+        let (formatted, unit) = crate::helpers::format_measurement(
+            value,
+            10,
+            1_000_000.0,
+            0.0001,
+            &metermode,
+            false,
+            None,
+        );
+        format!("{} {}", formatted.trim_start(), unit)
+    };
+    let log_y = graph_config.log_y;
+    let show_stats = graph_config.show_stats;
 
     // Create bar chart data
     let hist_values_vec: Vec<f64> = hist_values.iter().copied().collect();
-    let (bar_chart, max_count, _num_bins, _bin_width, _range_start, _range_end) =
-        if hist_values_vec.is_empty() {
-            (
-                BarChart::new("Histogram (0 values, bin width: 0)".to_string(), vec![]),
-                0.0,
-                0,
-                0.0,
-                0.0,
-                0.0,
-            )
-        } else {
-            // Calculate min and max for binning
-            let (min, max) = hist_values_vec
-                .iter()
-                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &x| {
-                    (min.min(x), max.max(x))
-                });
-            // Ensure valid range, handle single-value case
-            let range_width = if min == max {
-                if min == 0.0 {
-                    1.0 // Avoid zero range for zero values
-                } else {
-                    min.abs() * 0.1 // 10% of value for single value
-                }
-            } else {
-                max - min
-            };
-            let range_start = if min == max {
-                min - range_width / 2.0
-            } else {
-                min
-            };
-            let range_end = range_start + range_width;
-
-            // Determine number of bins
-            let num_bins = if graph_config.num_bins == 0 {
-                // Auto-bin using square root rule, capped at max_bins
-                let sqrt_bins = (hist_values_vec.len() as f64).sqrt().ceil() as usize;
-                sqrt_bins.min(graph_config.max_bins).max(1) // Ensure at least one bin
-            } else {
-                graph_config.num_bins.max(1) // Ensure at least one bin
-            };
-
-            // Calculate bin width in data units
-            let bin_width = range_width / num_bins as f64;
-
-            // Create bins
-            let mut counts = vec![0; num_bins];
-            for &value in &hist_values_vec {
-                if value >= range_start && value <= range_end {
-                    let bin_index = ((value - range_start) / bin_width).floor() as usize;
-                    let bin_index = bin_index.min(num_bins - 1); // Clamp to last bin
-                    counts[bin_index] += 1;
-                }
-            }
+    let bins = histogram_bins(&hist_values_vec, graph_config);
+    let summary = stats::summarize(&hist_values_vec);
+    let (bar_chart, max_count) = match &bins {
+        None => (
+            BarChart::new("Histogram (0 values, bin width: 0)".to_string(), vec![]),
+            0.0,
+        ),
+        Some(bins) => {
+            let HistogramBins {
+                counts,
+                range_start,
+                bin_width,
+                min,
+                max,
+                ..
+            } = bins.clone();
 
             // Compute max_count separately
             let max_count = *counts.iter().max().unwrap_or(&0) as f64;
 
-            // Format bin width for legend
-            let (formatted_bin_width, bin_width_unit) = crate::helpers::format_measurement(
-                bin_width,
-                10,
-                1_000_000.0,
-                0.0001,
-                &metermode,
-                false,
-                None,
-            );
-            let chart_name = format!(
-                "  Samples: {}\nBin Width: {} {}\n      Min: {}\n      Max: {}",
+            let mut chart_name = format!(
+                "  Samples: {}\nBin Width: {}\n      Min: {}\n      Max: {}",
                 hist_values_vec.len(),
-                formatted_bin_width.trim_start(),
-                bin_width_unit,
-                min,
-                max
+                format_value(bin_width),
+                format_value(min),
+                format_value(max)
             );
+            if let Some(summary) = summary {
+                chart_name.push_str(&format!(
+                    "\n     Mean: {}\n        σ: {}",
+                    format_value(summary.mean),
+                    format_value(summary.std_dev)
+                ));
+                if let Some(ppm) = summary.relative_ppm() {
+                    chart_name.push_str(&format!("\n  σ/Mean: {ppm:.3} ppm"));
+                }
+            }
 
-            // Create bars in normalized canvas coordinates (0 to num_bins)
-            let display_bar_width = 1.0; // Width of 1.0 in normalized units
+            // Bars sit on the value axis, one per bin, centred on the bin
             let bars: Vec<Bar> = counts
-                .into_iter()
+                .iter()
                 .enumerate()
-                .map(|(i, count)| {
-                    let count_f64 = count as f64;
-                    // Center the bar at i + 0.5 in normalized coordinates
-                    let bar_center = i as f64 + 0.5;
+                .filter(|&(_, &count)| !log_y || count > 0)
+                .map(|(i, &count)| {
+                    let height = if log_y {
+                        log_height(count as f64)
+                    } else {
+                        count as f64
+                    };
                     // Directly initialize stroke based on theme
                     let stroke = if ui.ctx().theme().default_visuals().dark_mode {
                         egui::Stroke::new(0.5, Color32::from_rgb(255, 255, 255))
                     } else {
                         egui::Stroke::new(0.5, Color32::from_rgb(0, 0, 0))
                     };
-                    Bar::new(bar_center, count_f64)
-                        .width(display_bar_width * 0.95) // Slight gap between bars
+                    Bar::new(bins.bin_center(i), height)
+                        .width(bin_width * 0.95) // Slight gap between bars
                         .fill(hist_bar_color)
                         .stroke(stroke)
                 })
@@ -213,8 +407,8 @@ pub fn show_histogram(
 
             // Define element formatter for hover tooltip
             let formatter = Box::new(move |bar: &Bar, _chart: &BarChart| {
-                // Calculate bin index from bar center (subtract 0.5 to get zero-based index)
-                let bin_index = (bar.argument - 0.5).floor() as usize;
+                // Calculate bin index from bar center
+                let bin_index = ((bar.argument - range_start) / bin_width).floor() as usize;
                 // Calculate bin range
                 let bin_start = range_start + bin_index as f64 * bin_width;
                 let bin_end = bin_start + bin_width;
@@ -238,7 +432,11 @@ pub fn show_histogram(
                     None,
                 );
                 // Sample count is the bar's value (height)
-                let sample_count = bar.value as usize;
+                let sample_count = if log_y {
+                    count_from_log_height(bar.value).round() as usize
+                } else {
+                    bar.value as usize
+                };
                 format!(
                     "Bin Range: {} to {} {}\nSamples: {}",
                     formatted_start.trim_start(),
@@ -253,32 +451,77 @@ pub fn show_histogram(
                     .color(hist_bar_color)
                     .element_formatter(formatter),
                 max_count,
-                num_bins,
-                bin_width,
-                range_start,
-                range_end,
             )
-        };
+        }
+    };
+    let fit_points = match (&bins, summary) {
+        (Some(bins), Some(summary)) if graph_config.show_fit => {
+            normal_fit_points(&summary, bins.bin_width)
+        }
+        _ => Vec::new(),
+    };
+    let export_plot = || {
+        let mut series = vec![ExportSeries {
+            name: "Count".to_owned(),
+            color: hist_bar_color.to_array()[..3].try_into().unwrap(),
+            kind: SeriesKind::Bars(
+                bins.as_ref()
+                    .map(|b| {
+                        b.counts
+                            .iter()
+                            .enumerate()
+                            .map(|(i, &count)| ExportBar {
+                                center: b.bin_center(i),
+                                width: b.bin_width,
+                                height: count as f64,
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            ),
+        }];
+        if let Some(summary) = summary.filter(|_| show_stats) {
+            series.push(ExportSeries {
+                name: "Mean".to_owned(),
+                color: MEAN_COLOR.to_array()[..3].try_into().unwrap(),
+                kind: SeriesKind::VLines {
+                    xs: vec![summary.mean],
+                    dash: Dash::Solid,
+                },
+            });
+            for (name, k, dash) in [("±1σ", 1.0, Dash::Dashed), ("±3σ", 3.0, Dash::Dotted)] {
+                series.push(ExportSeries {
+                    name: name.to_owned(),
+                    color: SIGMA_COLOR.to_array()[..3].try_into().unwrap(),
+                    kind: SeriesKind::VLines {
+                        xs: vec![
+                            summary.mean - k * summary.std_dev,
+                            summary.mean + k * summary.std_dev,
+                        ],
+                        dash,
+                    },
+                });
+            }
+        }
+        if !fit_points.is_empty() {
+            series.push(ExportSeries {
+                name: "Normal fit".to_owned(),
+                color: FIT_COLOR.to_array()[..3].try_into().unwrap(),
+                kind: SeriesKind::Line(fit_points.clone()),
+            });
+        }
+        PlotExport {
+            title: format!("{export_title} histogram"),
+            x_label: metermode.default_unit().to_owned(),
+            y_label: if log_y { "Count (log)" } else { "Count" }.to_owned(),
+            timestamp: export_timestamp(),
+            series,
+            log_y,
+        }
+    };
 
     // Use bottom-up layout to place controls at bottom and plot above
     ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
-        // Diagnostic labels (bottom to top due to bottom_up layout)
-        // if num_bins > 0 {
-        //     let bin_ranges: Vec<String> = (0..num_bins)
-        //         .map(|i| {
-        //             let bin_start = range_start + i as f64 * bin_width;
-        //             let bin_end = bin_start + bin_width;
-        //             format!("Bin {}: {:.2} to {:.2}", i, bin_start, bin_end)
-        //         })
-        //         .collect();
-        //     ui.label(format!("Bin ranges: {:?}", bin_ranges));
-        // }
-        // ui.label(format!("Max count: {}", max_count));
-        // ui.label(format!("Data range: {:.2} to {:.2}", min, max));
-        // ui.label(format!("Bin width (data units): {:.6}", bin_width));
-        // ui.label(format!("Number of bins: {}", num_bins));
-        // ui.separator();
-
         ui.horizontal_wrapped(|ui| {
             // Histogram memory depth slider
             ui.add(
@@ -291,6 +534,7 @@ pub fn show_histogram(
             // Reset button
             if ui.button("Reset Histogram").clicked() {
                 hist_values.clear();
+                hist_times.clear();
             }
 
             // Start/Stop collection button
@@ -305,18 +549,45 @@ pub fn show_histogram(
                 *hist_collect_active = !*hist_collect_active;
             }
 
-            // Number of bins slider
+            // Number of bins slider, unused while a fixed bin width is set
             let num_bins_label = if graph_config.num_bins == 0 {
                 "Bins: Auto".to_string()
             } else {
                 format!("Bins: {}", graph_config.num_bins)
             };
-            ui.add(
+            ui.add_enabled(
+                graph_config.bin_width <= 0.0,
                 Slider::new(&mut graph_config.num_bins, 0..=graph_config.max_bins)
                     .text(num_bins_label)
                     .step_by(1.0)
                     .clamping(SliderClamping::Always),
             );
+
+            // Bin width in measurement units, 0 falls back to the bin count
+            let width_speed = summary
+                .map(|s| s.std_dev / 100.0)
+                .filter(|speed| *speed > 0.0)
+                .unwrap_or(1e-6);
+            ui.add(
+                egui::DragValue::new(&mut graph_config.bin_width)
+                    .range(0.0..=f64::MAX)
+                    .speed(width_speed)
+                    .custom_formatter(|v, _| {
+                        if v <= 0.0 {
+                            "by count".to_owned()
+                        } else {
+                            format!("{v:e}")
+                        }
+                    }),
+            )
+            .on_hover_text("Bin width in measurement units. 0 uses the bin count instead.");
+            ui.label(format!("Bin width ({})", metermode.default_unit()));
+            if bins.as_ref().is_some_and(|b| b.width_clamped) {
+                ui.label(format!(
+                    "(too narrow, using {} bins)",
+                    graph_config.max_bins
+                ));
+            }
             let mut interval_str = hist_collect_interval_ms.to_string();
 
             // Collection interval
@@ -335,29 +606,80 @@ pub fn show_histogram(
                 }
             }
             ui.label("Collection Interval (ms)");
+            ui.checkbox(&mut graph_config.log_y, "Log Y");
+            ui.checkbox(&mut graph_config.show_stats, "Mean/σ lines");
+            ui.checkbox(&mut graph_config.show_fit, "Normal fit");
+            show_export_controls(ui, export, "histogram.png", export_plot);
         });
         ui.label("Histogram Adjustments");
         ui.separator();
 
         // Plot the histogram above controls, taking remaining space
-        let plot = Plot::new("histogram")
+        let top = if log_y {
+            log_height(max_count.max(1.0)) + 0.5
+        } else {
+            max_count * 1.2
+        };
+        let mut plot = Plot::new("histogram")
             .show_axes(true)
             .show_grid(true)
-            .y_axis_label("Count")
-            .x_axis_label("Bin Index")
+            .y_axis_label(if log_y { "Count (log)" } else { "Count" })
+            .x_axis_label(metermode.default_unit())
             .allow_scroll(false) // Prevent scrolling to keep bins stable
             .default_y_bounds(-0.1, 1.0)
-            .include_y(max_count * 1.2)
+            .include_y(top)
             .legend(
                 Legend::default()
                     .position(egui_plot::Corner::RightTop)
                     .text_style(egui::TextStyle::Monospace),
             );
+        if log_y {
+            // Only label whole decades
+            plot = plot.y_axis_formatter(|mark, _range| {
+                if mark.value >= 0.0 && (mark.value - mark.value.round()).abs() < 1e-6 {
+                    format!("{}", count_from_log_height(mark.value.round()))
+                } else {
+                    String::new()
+                }
+            });
+        }
 
         plot.show(ui, |plot_ui| {
             // Auto-scale x, do y manually to leave space for legend
             plot_ui.set_auto_bounds([true, true]);
             plot_ui.bar_chart(bar_chart);
+            if let Some(summary) = summary.filter(|_| graph_config.show_stats) {
+                plot_ui.vline(
+                    VLine::new("Mean", summary.mean).stroke(egui::Stroke::new(1.5, MEAN_COLOR)),
+                );
+                for (name, k, style) in [
+                    ("±1σ", 1.0, LineStyle::dashed_loose()),
+                    ("±3σ", 3.0, LineStyle::dotted_loose()),
+                ] {
+                    for sign in [-1.0, 1.0] {
+                        plot_ui.vline(
+                            VLine::new(name, summary.mean + sign * k * summary.std_dev)
+                                .color(SIGMA_COLOR)
+                                .style(style),
+                        );
+                    }
+                }
+            }
+            if !fit_points.is_empty() {
+                let points: Vec<[f64; 2]> = if log_y {
+                    fit_points
+                        .iter()
+                        .filter(|p| log_height(p[1]) >= 0.0)
+                        .map(|p| [p[0], log_height(p[1])])
+                        .collect()
+                } else {
+                    fit_points.clone()
+                };
+                plot_ui.line(
+                    Line::new("Normal fit", PlotPoints::from(points))
+                        .stroke(egui::Stroke::new(2.0, FIT_COLOR)),
+                );
+            }
         });
     });
 }
@@ -373,12 +695,20 @@ impl super::MyApp {
             let hist_interval = self.hist_collect_interval_ms as f64 / 1000.0; // Convert ms to seconds
             if current_time - self.last_hist_collect_time >= hist_interval {
                 self.hist_values.push_back(meas);
-                // Respect hist_mem_depth for histogram
-                while self.hist_values.len() > self.hist_mem_depth {
-                    self.hist_values.pop_front();
-                }
+                self.hist_times.push_back(current_time);
+                self.trim_histogram();
                 self.last_hist_collect_time = current_time;
             }
         }
     }
+
+    /// Respect `hist_mem_depth`, dropping the oldest samples and their times.
+    pub fn trim_histogram(&mut self) {
+        while self.hist_values.len() > self.hist_mem_depth {
+            self.hist_values.pop_front();
+        }
+        while self.hist_times.len() > self.hist_mem_depth {
+            self.hist_times.pop_front();
+        }
+    }
 }
@@ -1,5 +1,5 @@
 use std::ffi::CString;
-use std::time::Duration;
+use std::time::Instant;
 
 use hidapi::HidApi;
 use tokio::sync::{mpsc, oneshot};
@@ -42,104 +42,119 @@ impl super::MyApp {
     }
 
     pub fn spawn_hid_task(&mut self) {
-        let (tx_data, rx_data) = mpsc::channel::<Option<f64>>(100);
-        let (tx_mode, rx_mode) = mpsc::channel::<(MeterMode, String)>(10);
-        let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
-
-        self.serial_rx = Some(rx_data);
-        self.mode_rx = Some(rx_mode);
-        self.shutdown_tx = Some(shutdown_tx);
-        // Victor meters are read-only; no command channel.
-        self.serial_tx = None;
-
-        let device_path = self.hid_device_path.clone();
-        let value_debug_shared = self.value_debug_shared.clone();
-        let poll_interval_shared = self.poll_interval_shared.clone();
-        let device_shared = self.device.clone();
-
-        tokio::task::spawn_blocking(move || {
-            let api = match HidApi::new() {
-                Ok(api) => api,
-                Err(e) => {
-                    if *value_debug_shared.lock().unwrap() {
-                        println!("Failed to create HID API: {}", e);
-                    }
-                    return;
+        let link = spawn_hid_reader(self.hid_device_path.clone(), self.meter_shared());
+        self.adopt_link(link);
+    }
+}
+
+/// Start the blocking Victor HID reader and hand back its channels.
+/// Victor meters are read-only, so there is no command channel.
+pub(super) fn spawn_hid_reader(
+    device_path: String,
+    shared: super::MeterShared,
+) -> super::MeterLink {
+    let (tx_data, rx_data) = mpsc::channel::<Option<super::Reading>>(100);
+    let (tx_mode, rx_mode) = mpsc::channel::<(MeterMode, String)>(10);
+    let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
+    let link = super::MeterLink {
+        data_rx: Some(rx_data),
+        mode_rx: Some(rx_mode),
+        shutdown_tx: Some(shutdown_tx),
+        ..Default::default()
+    };
+
+    let value_debug_shared = shared.value_debug;
+    let poll_interval_shared = shared.poll_interval;
+    let device_shared = shared.device;
+    let link_lost = shared.link_lost;
+
+    tokio::task::spawn_blocking(move || {
+        let api = match HidApi::new() {
+            Ok(api) => api,
+            Err(e) => {
+                if *value_debug_shared.lock().unwrap() {
+                    println!("Failed to create HID API: {}", e);
                 }
-            };
+                return;
+            }
+        };
 
-            let c_path = match CString::new(device_path.as_bytes()) {
-                Ok(path) => path,
-                Err(e) => {
-                    if *value_debug_shared.lock().unwrap() {
-                        println!("Invalid Victor HID device path: {}", e);
-                    }
-                    return;
+        let c_path = match CString::new(device_path.as_bytes()) {
+            Ok(path) => path,
+            Err(e) => {
+                if *value_debug_shared.lock().unwrap() {
+                    println!("Invalid Victor HID device path: {}", e);
                 }
-            };
-            let device = match api.open_path(&c_path) {
-                Ok(device) => device,
-                Err(e) => {
-                    if *value_debug_shared.lock().unwrap() {
-                        println!("Failed to open Victor HID device: {}", e);
-                    }
-                    return;
+                return;
+            }
+        };
+        let device = match api.open_path(&c_path) {
+            Ok(device) => device,
+            Err(e) => {
+                if *value_debug_shared.lock().unwrap() {
+                    println!("Failed to open Victor HID device: {}", e);
                 }
-            };
-
-            {
-                let mut dev = device_shared.lock().unwrap();
-                *dev = "Victor 86 series (read only)".to_owned();
+                *link_lost.lock().unwrap() = Some(format!("Failed to open Victor HID device: {e}"));
+                return;
             }
+        };
 
-            if *value_debug_shared.lock().unwrap() {
-                println!("Victor HID device opened");
-            }
+        {
+            let mut dev = device_shared.lock().unwrap();
+            *dev = "Victor 86 series (read only)".to_owned();
+        }
 
-            let mut readbuf = [0u8; 64];
-            let mut shutting_down = false;
-            let mut last_mode = None::<MeterMode>;
+        if *value_debug_shared.lock().unwrap() {
+            println!("Victor HID device opened");
+        }
 
-            loop {
-                if shutdown_rx.try_recv().is_ok() {
-                    shutting_down = true;
-                }
-                if shutting_down {
-                    break;
-                }
+        let mut readbuf = [0u8; 64];
+        let mut shutting_down = false;
+        let mut last_mode = None::<MeterMode>;
 
-                let interval = *poll_interval_shared.lock().unwrap();
-                let timeout_ms = interval.max(50) as i32;
+        loop {
+            if shutdown_rx.try_recv().is_ok() {
+                shutting_down = true;
+            }
+            if shutting_down {
+                break;
+            }
+
+            let interval = *poll_interval_shared.lock().unwrap();
+            let timeout_ms = interval.max(50) as i32;
 
-                match device.read_timeout(&mut readbuf, timeout_ms) {
-                    Ok(0) => continue,
-                    Ok(len) => {
+            match device.read_timeout(&mut readbuf, timeout_ms) {
+                Ok(0) => continue,
+                Ok(len) => {
+                    if *value_debug_shared.lock().unwrap() {
+                        println!("Victor HID received {} bytes", len);
+                    }
+                    if let Some(reading) = victor_fs9922::parse_hid_buffer(&readbuf[..len]) {
                         if *value_debug_shared.lock().unwrap() {
-                            println!("Victor HID received {} bytes", len);
+                            println!("Victor reading: {} {:?}", reading.value, reading.mode);
                         }
-                        if let Some(reading) = victor_fs9922::parse_hid_buffer(&readbuf[..len]) {
-                            if *value_debug_shared.lock().unwrap() {
-                                println!("Victor reading: {} {:?}", reading.value, reading.mode);
-                            }
-                            let _ = tx_data.blocking_send(Some(reading.value));
-                            if last_mode != Some(reading.mode) {
-                                last_mode = Some(reading.mode);
-                                let _ = tx_mode.blocking_send((reading.mode, reading.unit));
-                            }
+                        let _ = tx_data.blocking_send(Some((reading.value, Instant::now())));
+                        if last_mode != Some(reading.mode) {
+                            last_mode = Some(reading.mode);
+                            let _ = tx_mode.blocking_send((reading.mode, reading.unit));
                         }
                     }
-                    Err(e) => {
-                        if *value_debug_shared.lock().unwrap() {
-                            println!("Victor HID read error: {}", e);
-                        }
-                        std::thread::sleep(Duration::from_millis(interval));
+                }
+                Err(e) => {
+                    // hidapi only fails reads once the device is gone
+                    if *value_debug_shared.lock().unwrap() {
+                        println!("Victor HID read error: {}", e);
                     }
+                    *link_lost.lock().unwrap() = Some(format!("Victor HID read error: {e}"));
+                    break;
                 }
             }
+        }
 
-            if *value_debug_shared.lock().unwrap() {
-                println!("Victor HID task shutting down");
-            }
-        });
-    }
+        if *value_debug_shared.lock().unwrap() {
+            println!("Victor HID task shutting down");
+        }
+    });
+
+    link
 }
@@ -0,0 +1,625 @@
+//! Optional local HTTP server exposing the live reading as JSON, taking
+//! mode / range / rate / macro / recording commands, serving a stream
+//! overlay page that follows the reading over Server-Sent Events, and a
+//! Prometheus scrape endpoint.
+//!
+//! The server runs on the tokio runtime and never touches `MyApp` directly:
+//! while clients are connected the UI publishes a JSON snapshot every
+//! frame, and commands travel back
+//! over a channel that `http_api_tick` drains, so SCPI meters are driven
+//! through the same `queue_scpi` path as the buttons.
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use egui::{Color32, RichText};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{Notify, mpsc, oneshot, watch},
+};
+
+use crate::http_api::{
+    ApiCommand, EVENT_STREAM_HEAD, EVENT_STREAM_PING, MAX_BODY_BYTES, MAX_HEAD_BYTES, RequestHead,
+    Response, Route, check_request, head_end, parse_head, route, sse_event,
+};
+use crate::metrics::EXPOSITION_CONTENT_TYPE;
+use crate::multimeter::GenScpi;
+
+/// How long a client may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a command waits for the UI thread to apply it.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a GET waits for a fresh snapshot before serving the last one.
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_millis(500);
+/// Idle time after which an event stream gets a keep-alive comment.
+const STREAM_PING_INTERVAL: Duration = Duration::from_secs(15);
+/// Most recent graph values drawn as the overlay sparkline.
+const OVERLAY_SPARKLINE_POINTS: usize = 120;
+
+const OVERLAY_HTML: &str = include_str!("../../assets/overlay.html");
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpApiSettings {
+    pub enabled: bool,        // Persistent, off unless turned on in the settings
+    pub bind_address: String, // Persistent, localhost unless configured otherwise
+    pub port: u16,            // Persistent
+}
+
+impl Default for HttpApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "127.0.0.1".to_owned(),
+            port: 8780,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(super) enum ServerStatus {
+    Starting,
+    Listening(SocketAddr),
+    Failed(String),
+}
+
+type CommandReply = oneshot::Sender<Result<String, String>>;
+
+/// State handed to every connection task.
+#[derive(Clone)]
+struct ApiShared {
+    snapshot: Arc<Mutex<ApiSnapshot>>,
+    /// Open connections; the UI only builds snapshots while there are any.
+    clients: Arc<AtomicUsize>,
+    snapshot_ready: Arc<Notify>,
+    cmd_tx: mpsc::Sender<(ApiCommand, CommandReply)>,
+    overlay_rx: watch::Receiver<String>,
+    ctx: egui::Context,
+}
+
+/// JSON documents served by the GET endpoints, refreshed by the UI.
+#[derive(Default)]
+struct ApiSnapshot {
+    reading: Value,
+    status: Value,
+    stats: Value,
+    macros: Value,
+    metrics: String,
+}
+
+/// A running server. Dropping `shutdown_tx` stops the accept loop.
+pub struct HttpApiServer {
+    settings: HttpApiSettings,
+    status: Arc<Mutex<ServerStatus>>,
+    snapshot: Arc<Mutex<ApiSnapshot>>,
+    clients: Arc<AtomicUsize>,
+    snapshot_ready: Arc<Notify>,
+    cmd_rx: mpsc::Receiver<(ApiCommand, CommandReply)>,
+    overlay_tx: watch::Sender<String>,
+    _shutdown_tx: oneshot::Sender<()>,
+}
+
+impl HttpApiServer {
+    fn start(settings: &HttpApiSettings, ctx: &egui::Context) -> Self {
+        let status = Arc::new(Mutex::new(ServerStatus::Starting));
+        let snapshot = Arc::new(Mutex::new(ApiSnapshot::default()));
+        let clients = Arc::new(AtomicUsize::new(0));
+        let snapshot_ready = Arc::new(Notify::new());
+        let (cmd_tx, cmd_rx) = mpsc::channel(16);
+        let (overlay_tx, overlay_rx) = watch::channel(String::new());
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let shared = ApiShared {
+            snapshot: snapshot.clone(),
+            clients: clients.clone(),
+            snapshot_ready: snapshot_ready.clone(),
+            cmd_tx,
+            overlay_rx,
+            ctx: ctx.clone(),
+        };
+        tokio::spawn(serve(
+            settings.bind_address.clone(),
+            settings.port,
+            status.clone(),
+            shared,
+            shutdown_rx,
+        ));
+        Self {
+            settings: settings.clone(),
+            status,
+            snapshot,
+            clients,
+            snapshot_ready,
+            cmd_rx,
+            overlay_tx,
+            _shutdown_tx: shutdown_tx,
+        }
+    }
+}
+
+async fn serve(
+    address: String,
+    port: u16,
+    status: Arc<Mutex<ServerStatus>>,
+    shared: ApiShared,
+    mut shutdown_rx: oneshot::Receiver<()>,
+) {
+    let listener = match TcpListener::bind((address.as_str(), port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            *status.lock().unwrap() =
+                ServerStatus::Failed(format!("Failed to bind {address}:{port}: {e}"));
+            shared.ctx.request_repaint();
+            return;
+        }
+    };
+    if let Ok(addr) = listener.local_addr() {
+        *status.lock().unwrap() = ServerStatus::Listening(addr);
+        shared.ctx.request_repaint();
+    }
+    loop {
+        tokio::select! {
+            _ = &mut shutdown_rx => break,
+            accepted = listener.accept() => {
+                if let Ok((stream, _)) = accepted {
+                    tokio::spawn(handle_connection(stream, shared.clone()));
+                }
+            }
+        }
+    }
+}
+
+/// Counts a connection in [`ApiShared::clients`] while alive.
+struct ClientGuard(Arc<AtomicUsize>);
+
+impl ClientGuard {
+    fn new(clients: &Arc<AtomicUsize>) -> Self {
+        clients.fetch_add(1, Ordering::Relaxed);
+        Self(clients.clone())
+    }
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, shared: ApiShared) {
+    let _client = ClientGuard::new(&shared.clients);
+    let response = match tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok((head, body))) => match stream
+            .local_addr()
+            .map_err(|e| Response::error(400, format!("Failed to read local address: {e}")))
+            .and_then(|local| check_request(&head, local))
+            .and_then(|()| route(&head.method, &head.path, &body))
+        {
+            Ok(Route::Stream) => return stream_overlay(stream, shared).await,
+            Ok(route) => respond(route, &shared).await,
+            Err(response) => response,
+        },
+        Ok(Err(response)) => response,
+        Err(_) => Response::error(408, "Request timed out"),
+    };
+    let _ = stream.write_all(&response.to_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Push the overlay state to an event stream client until it disconnects or
+/// the server stops.
+async fn stream_overlay(mut stream: TcpStream, shared: ApiShared) {
+    let mut overlay_rx = shared.overlay_rx;
+    if stream.write_all(EVENT_STREAM_HEAD).await.is_err() {
+        return;
+    }
+    // The UI skips the overlay while nobody listens, have it catch up
+    shared.ctx.request_repaint();
+    // Send the current state right away rather than on the next reading
+    overlay_rx.mark_changed();
+    loop {
+        let message = tokio::select! {
+            changed = overlay_rx.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let state = overlay_rx.borrow_and_update().clone();
+                sse_event(&state).into_bytes()
+            }
+            _ = tokio::time::sleep(STREAM_PING_INTERVAL) => EVENT_STREAM_PING.to_vec(),
+        };
+        if stream.write_all(&message).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Read one request; errors come back as the response to send.
+async fn read_request(stream: &mut TcpStream) -> Result<(RequestHead, Vec<u8>), Response> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    let head_len = loop {
+        if let Some(end) = head_end(&buf) {
+            break end;
+        }
+        if buf.len() > MAX_HEAD_BYTES {
+            return Err(Response::error(431, "Request head too large"));
+        }
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return Err(Response::error(400, "Connection closed")),
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    };
+    let head = std::str::from_utf8(&buf[..head_len])
+        .map_err(|_| Response::error(400, "Request head is not UTF-8"))
+        .and_then(|head| parse_head(head).map_err(|e| Response::error(400, e)))?;
+    if head.content_length > MAX_BODY_BYTES {
+        return Err(Response::error(413, "Request body too large"));
+    }
+    let mut body = buf.split_off(head_len);
+    while body.len() < head.content_length {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return Err(Response::error(400, "Incomplete request body")),
+            Ok(n) => body.extend_from_slice(&chunk[..n]),
+        }
+    }
+    body.truncate(head.content_length);
+    Ok((head, body))
+}
+
+/// Wait until the UI publishes a snapshot taken after this point. The UI
+/// may be idle or not have built one while nobody was connected.
+async fn await_snapshot(shared: &ApiShared) {
+    let ready = shared.snapshot_ready.notified();
+    shared.ctx.request_repaint();
+    let _ = tokio::time::timeout(SNAPSHOT_TIMEOUT, ready).await;
+}
+
+async fn respond(route: Route, shared: &ApiShared) -> Response {
+    if matches!(
+        route,
+        Route::Reading | Route::Status | Route::Stats | Route::Macros | Route::Metrics
+    ) {
+        await_snapshot(shared).await;
+    }
+    let command = {
+        let snapshot = shared.snapshot.lock().unwrap();
+        match route {
+            Route::Overlay => return Response::html(OVERLAY_HTML),
+            Route::OverlayFont => {
+                return Response {
+                    status: 200,
+                    content_type: "font/ttf",
+                    body: super::B612_MONO_BOLD.to_vec(),
+                };
+            }
+            Route::Stream => unreachable!("event streams keep the connection"),
+            Route::Reading => return Response::json(200, &snapshot.reading),
+            Route::Status => return Response::json(200, &snapshot.status),
+            Route::Stats => return Response::json(200, &snapshot.stats),
+            Route::Macros => return Response::json(200, &snapshot.macros),
+            Route::Metrics => {
+                return Response {
+                    status: 200,
+                    content_type: EXPOSITION_CONTENT_TYPE,
+                    body: snapshot.metrics.clone().into_bytes(),
+                };
+            }
+            Route::Command(command) => command,
+        }
+    };
+    let (reply_tx, reply_rx) = oneshot::channel();
+    if shared.cmd_tx.send((command, reply_tx)).await.is_err() {
+        return Response::error(503, "API server is shutting down");
+    }
+    // The UI may be idle; wake it so the command is applied promptly
+    shared.ctx.request_repaint();
+    match tokio::time::timeout(COMMAND_TIMEOUT, reply_rx).await {
+        Ok(Ok(Ok(message))) => Response::json(200, &json!({ "ok": true, "message": message })),
+        Ok(Ok(Err(e))) => Response::error(409, e),
+        _ => Response::error(503, "The application did not respond"),
+    }
+}
+
+fn css_color(color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    format!("rgba({r}, {g}, {b}, {:.3})", a as f32 / 255.0)
+}
+
+fn summary_json(values: &[f64]) -> Value {
+    match crate::stats::summarize(values) {
+        Some(s) => json!({
+            "count": s.count,
+            "mean": s.mean,
+            "std_dev": s.std_dev,
+            "min": s.min,
+            "max": s.max,
+            "relative_ppm": s.relative_ppm(),
+        }),
+        None => Value::Null,
+    }
+}
+
+impl super::MyApp {
+    /// Start, stop or restart the server to match the settings, publish the
+    /// current state while clients are connected and apply pending commands.
+    pub fn http_api_tick(&mut self, ctx: &egui::Context, now: f64) {
+        if !self.http_api.enabled {
+            self.http_server = None;
+            return;
+        }
+        if self
+            .http_server
+            .as_ref()
+            .is_none_or(|s| s.settings != self.http_api)
+        {
+            self.http_server = Some(HttpApiServer::start(&self.http_api, ctx));
+        }
+        // Building the snapshot every frame is only worth it for a listener
+        let serving = self
+            .http_server
+            .as_ref()
+            .is_some_and(|s| s.clients.load(Ordering::Relaxed) > 0);
+        let published = serving.then(|| (self.api_snapshot(now), self.overlay_state()));
+        let mut commands = Vec::new();
+        if let Some(server) = self.http_server.as_mut() {
+            if let Some((snapshot, overlay)) = published {
+                *server.snapshot.lock().unwrap() = snapshot;
+                server.overlay_tx.send_if_modified(|current| {
+                    let changed = *current != overlay;
+                    if changed {
+                        *current = overlay;
+                    }
+                    changed
+                });
+                server.snapshot_ready.notify_waiters();
+            }
+            while let Ok(command) = server.cmd_rx.try_recv() {
+                commands.push(command);
+            }
+        }
+        for (command, reply) in commands {
+            let result = self.apply_api_command(command);
+            let _ = reply.send(result);
+        }
+    }
+
+    fn api_snapshot(&self, now: f64) -> ApiSnapshot {
+        let connected = self.connection_state == super::ConnectionState::Connected;
+        let finite =
+            self.curr_meas.is_finite() && self.curr_meas != crate::helpers::METER_OVERLOAD_VALUE;
+        let age_ms = ((now - self.curr_meas_time) * 1000.0).max(0.0) as i64;
+        let has_reading = connected && self.curr_meas_time > 0.0;
+        let timestamp = has_reading
+            .then(|| (chrono::Utc::now() - chrono::Duration::milliseconds(age_ms)).to_rfc3339());
+        let channels: Vec<Value> = self
+            .live_channels
+            .iter()
+            .map(|c| {
+                json!({
+                    "name": c.var,
+                    "label": c.label,
+                    "unit": c.unit,
+                    "value": c.value.map(|v| v.value).filter(|v| v.is_finite()),
+                })
+            })
+            .collect();
+        let reading = json!({
+            "connected": connected,
+            "value": (connected && finite).then_some(self.curr_meas),
+            "overload": connected && self.curr_meas == crate::helpers::METER_OVERLOAD_VALUE,
+            "unit": self.curr_unit,
+            "mode": self.metermode.button_label(),
+            "range": self.rangecmd.as_ref().map(|r| r.get_opt(self.curr_range).0),
+            "auto_range": self.meter_auto_range,
+            "rate": (!self.is_read_only()).then(|| self.ratecmd.get_opt(self.curr_rate).0),
+            "timestamp": timestamp,
+            "age_ms": has_reading.then_some(age_ms),
+            "channels": channels,
+        });
+
+        let device = self.device.lock().unwrap().clone();
+        let status = json!({
+            "connected": connected,
+            "connection": format!("{:?}", self.connection_type),
+            "device": device,
+            "read_only": self.is_read_only(),
+            "error": self.connection_error,
+            "recording": {
+                "active": self.recording_active,
+                "mode": format!("{:?}", self.recording_mode),
+                "file": self.recording_file_path,
+                "records": self.recording_data.len(),
+            },
+        });
+
+        let graph: Vec<f64> = self.values.iter().copied().collect();
+        let histogram: Vec<f64> = self.hist_values.iter().copied().collect();
+        let stats = json!({
+            "unit": self.curr_unit,
+            "graph": summary_json(&graph),
+            "histogram": summary_json(&histogram),
+        });
+
+        let macros: Vec<Value> = self
+            .scpi_macros
+            .iter()
+            .map(|m| {
+                json!({
+                    "name": m.name,
+                    "applies": !device.is_empty() && m.applies_to.matches(&device),
+                })
+            })
+            .collect();
+
+        ApiSnapshot {
+            reading,
+            status,
+            stats,
+            macros: Value::Array(macros),
+            metrics: self.metrics_exposition(),
+        }
+    }
+
+    /// Overlay page state: the main display text in the configured colors
+    /// plus the tail of the graph buffer for the sparkline.
+    fn overlay_state(&self) -> String {
+        let connected = self.connection_state == super::ConnectionState::Connected;
+        let (value, unit) = if connected {
+            self.main_display()
+        } else {
+            ("----".to_owned(), String::new())
+        };
+        let skip = self.values.len().saturating_sub(OVERLAY_SPARKLINE_POINTS);
+        let sparkline: Vec<f64> = self
+            .values
+            .iter()
+            .skip(skip)
+            .copied()
+            .filter(|v| v.is_finite() && *v != crate::helpers::METER_OVERLOAD_VALUE)
+            .collect();
+        json!({
+            "connected": connected,
+            "value": value.trim(),
+            "unit": unit.trim(),
+            "mode": self.metermode.button_label(),
+            "color": css_color(self.measurement_font_color),
+            "background": css_color(self.box_background_color),
+            "sparkline": sparkline,
+        })
+        .to_string()
+    }
+
+    fn api_meter_ready(&self) -> Result<(), String> {
+        if self.connection_state != super::ConnectionState::Connected {
+            return Err("No meter connected".to_owned());
+        }
+        if self.is_read_only() {
+            return Err("The connected meter is read only".to_owned());
+        }
+        Ok(())
+    }
+
+    pub fn apply_api_command(&mut self, command: ApiCommand) -> Result<String, String> {
+        match command {
+            ApiCommand::SetMode(mode) => {
+                self.api_meter_ready()?;
+                if !self.mode_visible_in_ui(mode) {
+                    return Err(format!("Mode {} is not available", mode.button_label()));
+                }
+                self.set_mode(mode);
+                Ok(format!("Mode set to {}", mode.button_label()))
+            }
+            ApiCommand::SetRange(range) => {
+                self.api_meter_ready()?;
+                let Some(rangecmd) = &self.rangecmd else {
+                    return Err("The current mode has no selectable ranges".to_owned());
+                };
+                let Some(idx) = (0..rangecmd.len())
+                    .find(|&i| rangecmd.get_opt(i).0.eq_ignore_ascii_case(range.trim()))
+                    .or_else(|| rangecmd.index_of_param(&range))
+                else {
+                    return Err(format!("Unknown range '{range}'"));
+                };
+                let label = rangecmd.get_opt(idx).0;
+                self.curr_range = idx;
+                self.meter_auto_range = idx == 0;
+                self.confstring = rangecmd.gen_scpi(label);
+                self.queue_scpi(self.confstring.clone(), true);
+                Ok(format!("Range set to {label}"))
+            }
+            ApiCommand::SetRate(rate) => {
+                self.api_meter_ready()?;
+                let Some(idx) = (0..self.ratecmd.len())
+                    .find(|&i| self.ratecmd.get_opt(i).0.eq_ignore_ascii_case(rate.trim()))
+                    .or_else(|| self.ratecmd.index_of_scpi(&rate))
+                else {
+                    return Err(format!("Unknown rate '{rate}'"));
+                };
+                let label = self.ratecmd.get_opt(idx).0;
+                self.curr_rate = idx;
+                self.confstring = self.ratecmd.gen_scpi(label);
+                self.queue_scpi(self.confstring.clone(), true);
+                Ok(format!("Rate set to {label}"))
+            }
+            ApiCommand::RunMacro(name) => {
+                self.api_meter_ready()?;
+                let Some(body) = self
+                    .scpi_macros
+                    .iter()
+                    .find(|m| m.name == name)
+                    .map(|m| m.body.clone())
+                else {
+                    return Err(format!("No macro named '{name}'"));
+                };
+                self.start_macro(&name, &body)?;
+                Ok(format!("Macro '{name}' started"))
+            }
+            ApiCommand::Recording(active) => self.api_set_recording(active),
+        }
+    }
+
+    fn api_set_recording(&mut self, active: bool) -> Result<String, String> {
+        match (active, self.recording_active) {
+            (true, true) => Ok("Already recording".to_owned()),
+            (true, false) if self.recording_file_path.is_empty() => {
+                Err("No recording file set".to_owned())
+            }
+            (true, false) => {
+                self.recording_active = true;
+                Ok("Recording started".to_owned())
+            }
+            (false, true) => {
+                self.recording_active = false;
+                self.save_recording_data();
+                Ok(format!("Recording saved to {}", self.recording_file_path))
+            }
+            (false, false) => Ok("Not recording".to_owned()),
+        }
+    }
+
+    pub fn show_http_api_settings(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.label("HTTP API:");
+        ui.checkbox(&mut self.http_api.enabled, "Enable local HTTP/JSON API");
+        ui.add_enabled_ui(!self.http_api.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Bind address:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.http_api.bind_address)
+                        .desired_width(140.0)
+                        .hint_text("127.0.0.1"),
+                );
+                ui.label("Port:");
+                ui.add(egui::DragValue::new(&mut self.http_api.port).range(1..=65535));
+            });
+        });
+        if !matches!(
+            self.http_api.bind_address.trim(),
+            "127.0.0.1" | "localhost" | "::1"
+        ) {
+            ui.label(
+                RichText::new("Other hosts on the network can control the meter.")
+                    .color(Color32::YELLOW),
+            );
+        }
+        let Some(server) = &self.http_server else {
+            return;
+        };
+        match &*server.status.lock().unwrap() {
+            ServerStatus::Starting => ui.label("Starting..."),
+            ServerStatus::Listening(addr) => ui.label(format!(
+                "Listening on http://{addr}/api/, stream overlay at http://{addr}/overlay, \
+                 metrics at http://{addr}/metrics"
+            )),
+            ServerStatus::Failed(e) => ui.label(RichText::new(e).color(Color32::RED)),
+        };
+    }
+}
@@ -0,0 +1,105 @@
+//! Charge / energy integrator panel on top of [`crate::integrator`].
+
+use egui::{FontFamily, FontId, RichText};
+
+use crate::integrator::{IntegralKind, format_elapsed};
+
+use super::ChannelValue;
+
+impl super::MyApp {
+    fn integrator_channel(&self) -> Option<&super::derived::LiveChannel> {
+        self.live_channels
+            .iter()
+            .find(|c| c.var == self.integrator_source)
+    }
+
+    /// Feed every reading the selected channel got this frame, so fast
+    /// meters are integrated sample by sample. Re-feeding a derived
+    /// channel's unchanged value is harmless, it does not advance time.
+    pub fn feed_integrator(&mut self) {
+        if !self.integrator.is_running() {
+            return;
+        }
+        let Some((readings, factor)) = self
+            .integrator_channel()
+            .map(|c| (c.readings.clone(), c.si_factor))
+        else {
+            return;
+        };
+        for sample in readings {
+            if sample.value != crate::helpers::METER_OVERLOAD_VALUE {
+                self.integrator.add(sample.time, sample.value * factor);
+            }
+        }
+    }
+
+    /// Running total for the recorder, once the integrator has been used.
+    pub fn integrator_channel_value(&self) -> Option<ChannelValue> {
+        if !self.integrator.is_running() && self.integrator.elapsed == 0.0 {
+            return None;
+        }
+        let channel = self.integrator_channel()?;
+        Some(ChannelValue {
+            name: format!("{} integral", channel.label),
+            unit: IntegralKind::from_unit(&channel.unit).hour_unit(&channel.unit),
+            value: self.integrator.hours(),
+        })
+    }
+
+    pub fn show_integrator(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Integrator (Ah / Wh)")
+            .id_salt("integrator")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Source:");
+                    let selected = self
+                        .integrator_channel()
+                        .map(|c| format!("{} ({})", c.label, c.unit))
+                        .unwrap_or_else(|| self.integrator_source.clone());
+                    ui.add_enabled_ui(!self.integrator.is_running(), |ui| {
+                        egui::ComboBox::from_id_salt("integrator_source")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                for channel in &self.live_channels {
+                                    ui.selectable_value(
+                                        &mut self.integrator_source,
+                                        channel.var.clone(),
+                                        format!("{} ({})", channel.label, channel.unit),
+                                    );
+                                }
+                            });
+                    });
+                    if self.integrator.is_running() {
+                        if ui.button("Stop").clicked() {
+                            self.integrator.stop();
+                        }
+                    } else if ui.button("Start").clicked() {
+                        self.integrator.start();
+                    }
+                    if ui.button("Reset").clicked() {
+                        self.integrator.reset();
+                    }
+                });
+                let (kind, unit) = self
+                    .integrator_channel()
+                    .map(|c| (IntegralKind::from_unit(&c.unit), c.unit.clone()))
+                    .unwrap_or((IntegralKind::Other, String::new()));
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(self.integrator.format_total(kind, &unit))
+                            .color(self.measurement_font_color)
+                            .font(FontId {
+                                size: 20.0,
+                                family: FontFamily::Name("B612Mono-Bold".into()),
+                            }),
+                    );
+                    ui.label(format!("over {}", format_elapsed(self.integrator.elapsed)));
+                    if self.integrator.is_running()
+                        && self.integrator_channel().and_then(|c| c.value).is_none()
+                    {
+                        ui.label(RichText::new("waiting for readings").color(egui::Color32::GRAY));
+                    }
+                });
+            });
+    }
+}
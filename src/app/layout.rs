@@ -0,0 +1,69 @@
+//! Plot tab layout: the dock is saved with the app state, and tabs can be
+//! torn off into their own OS windows for a second monitor.
+
+use egui::Ui;
+use egui_dock::DockState;
+
+use super::ui::PlotTab;
+
+impl super::MyApp {
+    /// Called once at startup. Tabs missing from the saved layout (new tab
+    /// types, or state from before layouts were saved) are docked again.
+    pub fn restore_plot_layout(&mut self) {
+        let torn_off = self.torn_off_tabs.clone();
+        self.plot_dock_state
+            .retain_tabs(|tab| !torn_off.contains(tab));
+        for tab in PlotTab::ALL {
+            let docked = self
+                .plot_dock_state
+                .iter_all_tabs()
+                .any(|(_, docked)| *docked == tab);
+            if !docked && !torn_off.contains(&tab) {
+                self.plot_dock_state
+                    .main_surface_mut()
+                    .push_to_first_leaf(tab);
+            }
+        }
+    }
+
+    /// Dock every tab again in the default order.
+    pub fn reset_plot_layout(&mut self) {
+        self.torn_off_tabs.clear();
+        self.plot_dock_state = DockState::new(PlotTab::ALL.to_vec());
+    }
+
+    fn tear_off_plot_tab(&mut self, tab: PlotTab) {
+        self.plot_dock_state.retain_tabs(|docked| *docked != tab);
+        if !self.torn_off_tabs.contains(&tab) {
+            self.torn_off_tabs.push(tab);
+        }
+    }
+
+    pub(super) fn dock_plot_tab(&mut self, tab: PlotTab) {
+        self.torn_off_tabs.retain(|&t| t != tab);
+        self.plot_dock_state
+            .main_surface_mut()
+            .push_to_first_leaf(tab);
+    }
+
+    /// Top bar "View" menu.
+    pub fn show_layout_menu(&mut self, ui: &mut Ui) {
+        ui.menu_button("View", |ui| {
+            ui.label("Own window:");
+            for tab in PlotTab::ALL {
+                let mut torn_off = self.torn_off_tabs.contains(&tab);
+                if ui.checkbox(&mut torn_off, tab.title()).changed() {
+                    if torn_off {
+                        self.tear_off_plot_tab(tab);
+                    } else {
+                        self.dock_plot_tab(tab);
+                    }
+                }
+            }
+            ui.separator();
+            if ui.button("Reset layout").clicked() {
+                self.reset_plot_layout();
+            }
+        });
+    }
+}
@@ -0,0 +1,439 @@
+//! Runs macros that use the macro language (`WAIT`, `REPEAT`, queries,
+//! `ASSERT`, `LOG`) one step per frame, and shows their progress.
+//!
+//! Everything goes to the SCPI task through the passthrough channel, so sets
+//! and queries reach the meter in macro order and each query reply comes
+//! back to the macro instead of the `MEAS?` poll. Plain macros keep the
+//! immediate path in `run_macro_body`.
+//!
+//! A checked run (the editor's "Run now") also asks `SYST:ERR?` after every
+//! set command and logs meter errors against the macro line that caused them.
+
+use std::collections::VecDeque;
+
+use egui::{Color32, Context, ProgressBar, RichText, Window};
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    oneshot::{self, error::TryRecvError},
+};
+
+use super::serial::PassthroughCmd;
+use crate::helpers::METER_OVERLOAD_VALUE;
+use crate::macro_lang::{Program, Runner, Step, reply_value};
+use crate::scpi_lint::scpi_error;
+
+/// How long a query may wait for its turn on the wire plus the reply.
+const REPLY_TIMEOUT: f64 = 10.0;
+/// Meters without an error queue never answer `SYST:ERR?`.
+const ERROR_CHECK_TIMEOUT: f64 = 2.0;
+const ERROR_QUERY: &str = "SYST:ERR?\n";
+/// Lines kept in the run log.
+const RUN_LOG_LINES: usize = 200;
+
+enum RunLog {
+    Info(String),
+    Pass(String),
+    Fail(String),
+}
+
+enum Outcome {
+    Done,
+    Failed(String),
+    Stopped,
+}
+
+/// What a pending reply is for.
+enum Awaiting {
+    Var(String),
+    /// `SYST:ERR?` after the set command on `line`.
+    ErrorCheck {
+        line: usize,
+        cmd: String,
+    },
+}
+
+struct PendingReply {
+    awaiting: Awaiting,
+    query: String,
+    rx: oneshot::Receiver<String>,
+    since: f64,
+}
+
+pub struct MacroRun {
+    name: String,
+    runner: Runner,
+    check_errors: bool,
+    resume_at: f64,
+    /// Commands the full channel did not take yet, in order.
+    outbox: VecDeque<PassthroughCmd>,
+    reply: Option<PendingReply>,
+    log: Vec<RunLog>,
+    passed: usize,
+    failed: usize,
+    outcome: Option<Outcome>,
+}
+
+impl MacroRun {
+    fn new(name: &str, program: Program, check_errors: bool) -> Self {
+        let mut outbox = VecDeque::new();
+        if check_errors {
+            // Clear errors left over from before the run.
+            outbox.push_back(PassthroughCmd {
+                cmd: "*CLS\n".to_owned(),
+                reply: None,
+            });
+        }
+        Self {
+            name: name.to_owned(),
+            runner: Runner::new(program),
+            check_errors,
+            resume_at: 0.0,
+            outbox,
+            reply: None,
+            log: Vec::new(),
+            passed: 0,
+            failed: 0,
+            outcome: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.outcome.is_none()
+    }
+
+    fn push_log(&mut self, entry: RunLog) {
+        self.log.push(entry);
+        if self.log.len() > RUN_LOG_LINES {
+            self.log.remove(0);
+        }
+    }
+
+    /// Queue a query whose reply the run waits for.
+    fn queue_query(&mut self, query: &str, awaiting: Awaiting, now: f64) {
+        let (reply_tx, rx) = oneshot::channel();
+        self.outbox.push_back(PassthroughCmd {
+            cmd: query.to_owned(),
+            reply: Some(reply_tx),
+        });
+        self.reply = Some(PendingReply {
+            awaiting,
+            query: query.trim_end().to_owned(),
+            rx,
+            since: now,
+        });
+    }
+
+    /// Hand queued commands to the SCPI task. `Err` once it is gone.
+    fn flush(&mut self, tx: &mpsc::Sender<PassthroughCmd>) -> Result<(), ()> {
+        while let Some(cmd) = self.outbox.pop_front() {
+            match tx.try_send(cmd) {
+                Ok(()) => {}
+                Err(TrySendError::Full(cmd)) => {
+                    self.outbox.push_front(cmd);
+                    break;
+                }
+                Err(TrySendError::Closed(_)) => return Err(()),
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, outcome: Outcome) {
+        self.reply = None;
+        self.outbox.clear();
+        self.outcome = Some(outcome);
+    }
+}
+
+impl super::MyApp {
+    /// Run a macro body: plain bodies are queued at once, bodies using the
+    /// macro language start a run shown in the "Macro run" window.
+    pub fn start_macro(&mut self, name: &str, body: &str) -> Result<(), String> {
+        self.start_macro_run(name, body, false)
+    }
+
+    fn start_macro_run(
+        &mut self,
+        name: &str,
+        body: &str,
+        check_errors: bool,
+    ) -> Result<(), String> {
+        let program = Program::parse(body).map_err(|e| format!("Macro '{name}': {e}"))?;
+        if program.is_plain() && !check_errors {
+            self.run_macro_body(body, false);
+            return Ok(());
+        }
+        if self.macro_run.as_ref().is_some_and(MacroRun::is_running) {
+            return Err("Another macro is still running".to_owned());
+        }
+        if self.passthrough_tx.is_none() {
+            return Err("No SCPI meter connected".to_owned());
+        }
+        self.macro_run = Some(MacroRun::new(name, program, check_errors));
+        Ok(())
+    }
+
+    /// Start a macro from a button or menu. Errors show in the run window.
+    pub fn start_macro_from_ui(&mut self, name: &str, body: &str) {
+        self.start_macro_reporting(name, body, false);
+    }
+
+    /// Run a macro from the editor, checking the meter's error queue after
+    /// each command when enabled.
+    pub fn run_macro_from_editor(&mut self, name: &str, body: &str) {
+        self.start_macro_reporting(name, body, self.macro_check_errors);
+    }
+
+    fn start_macro_reporting(&mut self, name: &str, body: &str, check_errors: bool) {
+        if let Err(e) = self.start_macro_run(name, body, check_errors) {
+            // Keep the running macro in the window and note the refusal there
+            if let Some(run) = self.macro_run.as_mut().filter(|run| run.is_running()) {
+                run.push_log(RunLog::Fail(format!("Macro '{name}' not started: busy")));
+                return;
+            }
+            let mut run = MacroRun::new(name, Program::default(), false);
+            run.finish(Outcome::Failed(e));
+            self.macro_run = Some(run);
+        }
+    }
+
+    fn macro_channel_value(&self, var: &str) -> Option<f64> {
+        self.live_channels
+            .iter()
+            .find(|c| c.var == var)
+            .and_then(|c| c.value)
+            .map(|v| v.value)
+            .filter(|&v| v != METER_OVERLOAD_VALUE)
+    }
+
+    /// Advance the running macro as far as it goes without waiting, then
+    /// start queued connect macros once it is done.
+    pub fn macro_run_tick(&mut self, ctx: &Context, now: f64) {
+        if self.passthrough_tx.is_none() {
+            self.macro_queue.clear();
+        }
+        while !self.macro_run.as_ref().is_some_and(MacroRun::is_running) {
+            let Some((name, body)) = self.macro_queue.pop_front() else {
+                break;
+            };
+            self.start_macro_from_ui(&name, &body);
+        }
+        let Some(mut run) = self.macro_run.take() else {
+            return;
+        };
+        if run.is_running() {
+            self.advance_macro(&mut run, now);
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
+        self.macro_run = Some(run);
+    }
+
+    fn advance_macro(&mut self, run: &mut MacroRun, now: f64) {
+        let Some(tx) = self.passthrough_tx.clone() else {
+            run.finish(Outcome::Failed("Meter disconnected".to_owned()));
+            return;
+        };
+        if now < run.resume_at {
+            return;
+        }
+        if run.flush(&tx).is_err() {
+            run.finish(Outcome::Failed("Meter disconnected".to_owned()));
+            return;
+        }
+        if !run.outbox.is_empty() {
+            return;
+        }
+        if let Some(pending) = &mut run.reply {
+            let timeout = match pending.awaiting {
+                Awaiting::Var(_) => REPLY_TIMEOUT,
+                Awaiting::ErrorCheck { .. } => ERROR_CHECK_TIMEOUT,
+            };
+            match (pending.rx.try_recv(), &pending.awaiting) {
+                (Ok(reply), Awaiting::Var(var)) => match reply_value(&reply) {
+                    Some(value) => {
+                        let var = var.clone();
+                        run.runner.set(&var, value);
+                        run.reply = None;
+                    }
+                    None => {
+                        let e = format!(
+                            "Reply '{}' to {var} = {} is not a number",
+                            reply.trim(),
+                            pending.query
+                        );
+                        run.finish(Outcome::Failed(e));
+                        return;
+                    }
+                },
+                (Ok(reply), Awaiting::ErrorCheck { line, cmd }) => {
+                    if let Some(error) = scpi_error(&reply) {
+                        let text = format!("Line {line}: {cmd} -> {error}");
+                        run.failed += 1;
+                        run.push_log(RunLog::Fail(text));
+                    }
+                    run.reply = None;
+                }
+                (Err(TryRecvError::Empty), _) if now - pending.since < timeout => return,
+                (Err(_), Awaiting::ErrorCheck { .. }) => {
+                    run.check_errors = false;
+                    run.reply = None;
+                    run.push_log(RunLog::Info(
+                        "No reply to SYST:ERR?, error checking is off for this run".to_owned(),
+                    ));
+                }
+                (Err(_), Awaiting::Var(var)) => {
+                    let e = format!("No reply to {var} = {}", pending.query);
+                    run.finish(Outcome::Failed(e));
+                    return;
+                }
+            }
+        }
+        loop {
+            let step = run.runner.next(&|name| self.macro_channel_value(name));
+            match step {
+                Step::Send(cmd) => {
+                    self.apply_scpi_hints(std::slice::from_ref(&cmd));
+                    let checked = run.check_errors.then(|| cmd.trim_end().to_owned());
+                    run.outbox.push_back(PassthroughCmd { cmd, reply: None });
+                    if let Some(cmd) = checked {
+                        let line = run.runner.line();
+                        run.queue_query(ERROR_QUERY, Awaiting::ErrorCheck { line, cmd }, now);
+                    }
+                    if run.flush(&tx).is_err() {
+                        run.finish(Outcome::Failed("Meter disconnected".to_owned()));
+                        return;
+                    }
+                    if run.reply.is_some() || !run.outbox.is_empty() {
+                        return;
+                    }
+                }
+                Step::Query { var, query } => {
+                    run.queue_query(&query, Awaiting::Var(var), now);
+                    if run.flush(&tx).is_err() {
+                        run.finish(Outcome::Failed("Meter disconnected".to_owned()));
+                    }
+                    return;
+                }
+                Step::Wait(seconds) => {
+                    run.resume_at = now + seconds;
+                    return;
+                }
+                Step::Log { label, value } => {
+                    self.record_labelled(&label, value);
+                    run.push_log(RunLog::Info(format!("{label}: {value} {}", self.curr_unit)));
+                }
+                Step::Assert {
+                    text,
+                    value,
+                    passed,
+                } => {
+                    if passed {
+                        run.passed += 1;
+                        run.push_log(RunLog::Pass(format!("PASS {text} ({value})")));
+                    } else {
+                        run.failed += 1;
+                        run.push_log(RunLog::Fail(format!("FAIL {text} ({value})")));
+                    }
+                }
+                Step::Failed(e) => {
+                    run.finish(Outcome::Failed(e));
+                    return;
+                }
+                Step::Done => {
+                    run.finish(Outcome::Done);
+                    self.request_ui_refresh();
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn show_macro_run(&mut self, ctx: &Context) {
+        let Some(run) = &self.macro_run else {
+            return;
+        };
+        let mut open = true;
+        let mut stop = false;
+        Window::new("Macro run")
+            .open(&mut open)
+            .default_size([380.0, 300.0])
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong(&run.name);
+                    match &run.outcome {
+                        None => {
+                            if ui.button("Stop").clicked() {
+                                stop = true;
+                            }
+                        }
+                        Some(Outcome::Done) if run.failed == 0 => {
+                            ui.label(RichText::new("Done").color(Color32::GREEN));
+                        }
+                        Some(Outcome::Done) => {
+                            ui.label(RichText::new("Done with failures").color(Color32::RED));
+                        }
+                        Some(Outcome::Stopped) => {
+                            ui.label(RichText::new("Stopped").color(Color32::YELLOW));
+                        }
+                        Some(Outcome::Failed(_)) => {
+                            ui.label(RichText::new("Failed").color(Color32::RED));
+                        }
+                    }
+                });
+                if let Some(Outcome::Failed(e)) = &run.outcome {
+                    ui.label(RichText::new(e).color(Color32::RED));
+                }
+                if run.is_running() {
+                    ui.add(ProgressBar::new(run.runner.progress()).show_percentage());
+                    if let Some(statement) = run.runner.current() {
+                        ui.monospace(format!("{:>3}: {}", statement.line, statement.text));
+                    }
+                    for state in run.runner.loops() {
+                        ui.label(format!("REPEAT {} of {}", state.iteration, state.count));
+                    }
+                    if let Some(pending) = &run.reply {
+                        ui.label(format!("Waiting for {}", pending.query));
+                    }
+                }
+                if run.passed + run.failed > 0 {
+                    ui.label(format!(
+                        "Assertions: {} passed, {} failed",
+                        run.passed, run.failed
+                    ));
+                }
+                if !run.runner.vars().is_empty() {
+                    ui.collapsing("Variables", |ui| {
+                        for (name, value) in run.runner.vars() {
+                            ui.monospace(format!("{name} = {value}"));
+                        }
+                    });
+                }
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for entry in &run.log {
+                            match entry {
+                                RunLog::Info(text) => ui.monospace(text),
+                                RunLog::Pass(text) => {
+                                    ui.label(RichText::new(text).monospace().color(Color32::GREEN))
+                                }
+                                RunLog::Fail(text) => {
+                                    ui.label(RichText::new(text).monospace().color(Color32::RED))
+                                }
+                            };
+                        }
+                    });
+            });
+        if stop {
+            if let Some(run) = &mut self.macro_run {
+                run.finish(Outcome::Stopped);
+            }
+        }
+        if !open {
+            self.macro_run = None;
+        }
+    }
+}
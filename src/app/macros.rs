@@ -1,6 +1,12 @@
-use crate::scpi_macro::{MacroTarget, ScpiMacro, idn_model};
+use crate::scpi_lint::{Dialect, LintIssue, Severity, lint_macro};
+use crate::scpi_macro::{
+    ImportPolicy, MacroTarget, ScpiMacro, export_macros, idn_model, import_macros, parse_macro_file,
+};
 
-use egui::{Context, FontId, TextEdit, Window};
+use egui::{Color32, Context, FontId, TextEdit, TextFormat, Ui, Window, text::LayoutJob};
+use rfd::FileDialog;
+
+use super::shortcuts::CaptureTarget;
 
 impl super::MyApp {
     pub fn show_macros(&mut self, ctx: &Context) {
@@ -76,7 +82,55 @@ impl super::MyApp {
                         {
                             self.move_selected_macro(1);
                         }
+                        ui.separator();
+                        if ui.button("Import…").clicked() {
+                            self.import_macros_from_dialog();
+                        }
+                        if ui
+                            .add_enabled(can_dup, egui::Button::new("Export selected…"))
+                            .clicked()
+                        {
+                            let selected: Vec<ScpiMacro> = self
+                                .scpi_macros
+                                .iter()
+                                .filter(|m| self.selected_macro_id.as_ref() == Some(&m.id))
+                                .cloned()
+                                .collect();
+                            self.export_macros_to_file(&selected);
+                        }
+                        let any = !self.scpi_macros.is_empty();
+                        if ui
+                            .add_enabled(any, egui::Button::new("Export all…"))
+                            .clicked()
+                        {
+                            self.export_macros_to_file(&self.scpi_macros.clone());
+                        }
+                        egui::ComboBox::from_id_salt("macro_import_policy")
+                            .selected_text(match self.macro_import_policy {
+                                ImportPolicy::KeepBoth => "Same ID: keep both",
+                                ImportPolicy::Replace => "Same ID: replace",
+                            })
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(
+                                    &mut self.macro_import_policy,
+                                    ImportPolicy::KeepBoth,
+                                    "Same ID: keep both",
+                                );
+                                ui.selectable_value(
+                                    &mut self.macro_import_policy,
+                                    ImportPolicy::Replace,
+                                    "Same ID: replace",
+                                );
+                            })
+                            .response
+                            .on_hover_text(
+                                "What an import does with a macro whose ID already exists \
+                                 with different content. Identical macros are skipped.",
+                            );
                     });
+                    if let Some(msg) = &self.macro_io_message {
+                        ui.label(msg);
+                    }
                 });
 
                 egui::Panel::left("scpi_macro_list")
@@ -223,16 +277,45 @@ impl super::MyApp {
                                 );
 
                                 ui.label(
-                                    "SCPI (one command per line; ';' also splits). '#' or '//' comments. Queries are ignored.",
+                                    "SCPI (one command per line; ';' also splits). '#' or '//' comments. \
+                                     WAIT 500ms, REPEAT n { }, v = MEAS?, ASSERT v IN lo .. hi \
+                                     and LOG \"label\" v run as a test procedure.",
                                 );
+                                let dialect = if connected_scpi && m.applies_to.matches(&live_idn) {
+                                    Dialect::from_idn(&live_idn)
+                                } else {
+                                    Dialect::for_target(&m.applies_to)
+                                };
+                                let mut layouter =
+                                    |ui: &Ui, text: &dyn egui::TextBuffer, wrap_width: f32| {
+                                        let issues = lint_macro(text.as_str(), &dialect);
+                                        let mut job = lint_layout_job(ui, text.as_str(), &issues);
+                                        job.wrap.max_width = wrap_width;
+                                        ui.fonts_mut(|f| f.layout_job(job))
+                                    };
                                 ui.add(
                                     TextEdit::multiline(&mut m.body)
                                         .font(FontId::monospace(13.0))
                                         .desired_width(f32::INFINITY)
-                                        .desired_rows(12),
+                                        .desired_rows(12)
+                                        .layouter(&mut layouter),
                                 );
+                                let issues = lint_macro(&m.body, &dialect);
+                                ui.weak(dialect.label());
+                                for issue in &issues {
+                                    let color = issue_color(ui, issue.severity);
+                                    ui.colored_label(
+                                        color,
+                                        format!("Line {}: {}", issue.line, issue.message),
+                                    );
+                                }
                             }
 
+                            ui.horizontal(|ui| {
+                                ui.label("Hotkey:");
+                                self.shortcut_field(ui, CaptureTarget::Macro(sel_id.clone()));
+                            });
+
                             ui.horizontal(|ui| {
                                 if ui.button("Insert current setup").clicked() {
                                     let snippet = self.current_setup_scpi();
@@ -246,21 +329,106 @@ impl super::MyApp {
                                     }
                                 }
                                 if connected_scpi && ui.button("Run now").clicked() {
-                                    if let Some(body) = self
+                                    if let Some((name, body)) = self
                                         .scpi_macros
                                         .iter()
                                         .find(|m| m.id == sel_id)
-                                        .map(|m| m.body.clone())
+                                        .map(|m| (m.name.clone(), m.body.clone()))
                                     {
-                                        self.run_macro_body(&body, false);
+                                        self.run_macro_from_editor(&name, &body);
                                     }
                                 }
+                                ui.checkbox(
+                                    &mut self.macro_check_errors,
+                                    "Check SYST:ERR? after each command",
+                                )
+                                .on_hover_text(
+                                    "Run now asks the meter for errors after every set command \
+                                     and lists them per line in the Macro run window.",
+                                );
                             });
                         });
                 });
             });
     }
 
+    fn export_macros_to_file(&mut self, macros: &[ScpiMacro]) {
+        let file_name = match macros {
+            [m] => format!("{}.json", sanitize_file_name(&m.name, "macro")),
+            _ => "macros.json".to_owned(),
+        };
+        let Some(path) = FileDialog::new()
+            .add_filter("Macro file", &["json"])
+            .set_file_name(file_name)
+            .save_file()
+        else {
+            return;
+        };
+        self.macro_io_message = Some(match std::fs::write(&path, export_macros(macros)) {
+            Ok(()) => format!("Exported {} macro(s) to {}", macros.len(), path.display()),
+            Err(e) => format!("Failed to write macro file: {e}"),
+        });
+    }
+
+    fn import_macros_from_dialog(&mut self) {
+        let Some(paths) = FileDialog::new()
+            .add_filter("Macro file", &["json"])
+            .pick_files()
+        else {
+            return;
+        };
+        let mut messages = Vec::new();
+        for path in paths {
+            let name = path.display().to_string();
+            match std::fs::read_to_string(&path) {
+                Ok(text) => messages.push(self.import_macro_text(&name, &text)),
+                Err(e) => messages.push(format!("{name}: Failed to read file: {e}")),
+            }
+        }
+        self.macro_io_message = Some(messages.join("\n"));
+    }
+
+    /// Merge a macro file into the list, returning a one-line result.
+    fn import_macro_text(&mut self, name: &str, text: &str) -> String {
+        match parse_macro_file(text) {
+            Ok(imported) => {
+                let first = imported.first().map(|m| m.id.clone());
+                let summary =
+                    import_macros(&mut self.scpi_macros, imported, self.macro_import_policy);
+                if self.selected_macro_id.is_none() {
+                    self.selected_macro_id = first;
+                }
+                format!("{name}: {summary}")
+            }
+            Err(e) => format!("{name}: {e}"),
+        }
+    }
+
+    /// Import macro files dropped onto the window and show the result in the
+    /// macros window.
+    pub fn import_dropped_macros(&mut self, ctx: &Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        if dropped.is_empty() {
+            return;
+        }
+        let mut messages = Vec::new();
+        for file in dropped {
+            let name = file.path().display().to_string();
+            let text = match file.bytes() {
+                Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                Err(e) => {
+                    messages.push(format!("{name}: Failed to read file: {e}"));
+                    continue;
+                }
+            };
+            messages.push(self.import_macro_text(&name, &text));
+        }
+        if !messages.is_empty() {
+            self.macro_io_message = Some(messages.join("\n"));
+            self.macros_open = true;
+        }
+    }
+
     fn move_selected_macro(&mut self, delta: isize) {
         let Some(id) = self.selected_macro_id.as_ref() else {
             return;
@@ -275,3 +443,46 @@ impl super::MyApp {
         self.scpi_macros.swap(idx, new_idx as usize);
     }
 }
+
+/// Turn a macro or profile name into something safe to offer as a file name.
+pub(super) fn sanitize_file_name(name: &str, fallback: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "-_ ".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    match cleaned.trim() {
+        "" => fallback.to_owned(),
+        s => s.to_owned(),
+    }
+}
+
+fn issue_color(ui: &Ui, severity: Severity) -> Color32 {
+    match severity {
+        Severity::Error => ui.visuals().error_fg_color,
+        Severity::Warning => ui.visuals().warn_fg_color,
+    }
+}
+
+/// Macro body text with lint issues tinted per line.
+fn lint_layout_job(ui: &Ui, text: &str, issues: &[LintIssue]) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    for (n, line) in text.split_inclusive('\n').enumerate() {
+        let mut format = TextFormat::simple(FontId::monospace(13.0), ui.visuals().text_color());
+        let worst = issues
+            .iter()
+            .filter(|issue| issue.line == n + 1)
+            .map(|issue| issue.severity)
+            .max();
+        if let Some(severity) = worst {
+            format.background = issue_color(ui, severity).gamma_multiply(0.25);
+        }
+        job.append(line, 0.0, format);
+    }
+    job
+}
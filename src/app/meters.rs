@@ -0,0 +1,693 @@
+//! Additional meters next to the main connection.
+//!
+//! The main meter keeps living in the `MyApp` fields. Every extra meter owns
+//! its own task channels ([`super::MeterLink`]) and shared handles, so any mix
+//! of SCPI and Victor connections can run side by side.
+
+use std::collections::VecDeque;
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use egui::{Color32, FontFamily, FontId, RichText, TextEdit};
+use egui_dropdown::DropDownBox;
+use serde::{Deserialize, Serialize};
+
+use crate::expr::TimedValue;
+use crate::helpers::format_measurement;
+use crate::macro_lang::Program;
+use crate::multimeter::MeterMode;
+use crate::scpi_macro::{
+    BootstrapSettings, ScpiMacro, bootstrap_commands, classify_idn, ensure_newline, looks_like_idn,
+    parse_macro_body,
+};
+use crate::serial_line::SerialLine;
+
+use super::connection::RECONNECT_INTERVAL_S;
+use super::{ChannelValue, ConnectionState, ConnectionType, MeterLink, MeterShared};
+
+/// Trace colors handed out to newly added meters.
+const AUX_COLORS: [Color32; 4] = [
+    Color32::from_rgb(255, 170, 0),
+    Color32::from_rgb(230, 80, 230),
+    Color32::from_rgb(120, 220, 80),
+    Color32::from_rgb(255, 90, 90),
+];
+
+/// One row of the combined graph: main meter first, then the additional
+/// meters in list order, then derived channels. Missing readings are NaN.
+pub struct CombinedSample {
+    pub t: f64,
+    pub values: Vec<f64>,
+}
+
+/// Settings an additional meter borrows from the app when connecting.
+pub struct AuxConnectSettings {
+    pub serial_line: SerialLine,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub victor_86bcd_line: SerialLine,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub victor_86e_line: SerialLine,
+    pub rst_on_disconnect: bool,
+    pub auto_reconnect: bool,
+    pub value_debug: Arc<Mutex<bool>>,
+    pub poll_interval: Arc<Mutex<u64>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuxMeter {
+    pub name: String,                    // Persistent, label in graph and recordings
+    pub connection_type: ConnectionType, // Persistent
+    pub serial_port: String,             // Persistent
+    #[cfg(not(target_arch = "wasm32"))]
+    pub hid_device_path: String, // Persistent
+    pub color: Color32,                  // Persistent, combined graph trace color
+    #[serde(skip)]
+    state: ConnectionState,
+    #[serde(skip)]
+    error: Option<String>,
+    #[serde(skip)]
+    link: MeterLink,
+    #[serde(skip)]
+    shared: Option<MeterShared>,
+    #[serde(skip)]
+    applied_idn: Option<String>,
+    #[serde(skip)]
+    retry_at: f64, // UI time of the next reopen attempt while reconnecting
+    #[serde(skip)]
+    pub mode: MeterMode,
+    #[serde(skip)]
+    pub unit: String,
+    #[serde(skip)]
+    pub meas: f64,
+    #[serde(skip)]
+    pub meas_time: f64, // UI time of the last reading
+    #[serde(skip)]
+    pub frame_readings: Vec<TimedValue>, // Readings received this frame
+    #[serde(skip)]
+    lcd_display: String,
+}
+
+impl Default for AuxMeter {
+    fn default() -> Self {
+        Self {
+            name: "Meter 2".to_owned(),
+            connection_type: ConnectionType::default(),
+            serial_port: String::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            hid_device_path: String::new(),
+            color: AUX_COLORS[0],
+            state: ConnectionState::Disconnected,
+            error: None,
+            link: MeterLink::default(),
+            shared: None,
+            applied_idn: None,
+            retry_at: 0.0,
+            mode: MeterMode::Vdc,
+            unit: MeterMode::Vdc.default_unit().to_owned(),
+            meas: f64::NAN,
+            meas_time: 0.0,
+            frame_readings: Vec::new(),
+            lcd_display: String::new(),
+        }
+    }
+}
+
+impl AuxMeter {
+    pub fn new(index: usize) -> Self {
+        Self {
+            name: format!("Meter {}", index + 2),
+            color: AUX_COLORS[index % AUX_COLORS.len()],
+            ..Default::default()
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state == ConnectionState::Connected
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.connection_type != ConnectionType::ScpiSerial
+    }
+
+    pub fn device(&self) -> String {
+        self.shared
+            .as_ref()
+            .map(|s| s.device.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    pub fn connect(&mut self, settings: &AuxConnectSettings) {
+        let shared = MeterShared {
+            device: Arc::new(Mutex::new(String::new())),
+            poll_ready: Arc::new(AtomicBool::new(false)),
+            refresh_requested: Arc::new(AtomicBool::new(false)),
+            value_debug: settings.value_debug.clone(),
+            poll_interval: settings.poll_interval.clone(),
+            timeouts: Arc::new(AtomicU64::new(0)),
+            link_lost: Arc::new(Mutex::new(None)),
+        };
+        match self.open_link(settings, shared.clone()) {
+            Ok(link) => {
+                self.link = link;
+                self.shared = Some(shared);
+                self.state = ConnectionState::Connected;
+                self.error = None;
+            }
+            Err(e) => {
+                self.state = ConnectionState::Disconnected;
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn open_link(
+        &self,
+        settings: &AuxConnectSettings,
+        shared: MeterShared,
+    ) -> Result<MeterLink, String> {
+        match self.connection_type {
+            ConnectionType::ScpiSerial => {
+                let line = &settings.serial_line;
+                let serial = super::open_serial_line(&self.serial_port, line)
+                    .map_err(|e| format!("Failed to connect at {}: {e}", line.label()))?;
+                Ok(super::serial::spawn_scpi_task(
+                    serial,
+                    shared,
+                    settings.rst_on_disconnect,
+                    self.mode,
+                ))
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            ConnectionType::VictorHid => {
+                if self.hid_device_path.is_empty() {
+                    return Err("No Victor HID device selected".to_owned());
+                }
+                Ok(super::hid::spawn_hid_reader(
+                    self.hid_device_path.clone(),
+                    shared,
+                ))
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            ConnectionType::Victor86bcdSerial => {
+                let line = &settings.victor_86bcd_line;
+                let serial = super::open_serial_line(&self.serial_port, line)
+                    .map_err(|e| format!("Failed to connect at {}: {e}", line.label()))?;
+                Ok(super::victor_readonly_serial::spawn_victor_readonly(
+                    serial,
+                    super::victor_readonly_serial::VictorReadonlyProtocol::Dm1107,
+                    shared,
+                    None,
+                ))
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            ConnectionType::Victor86eSerial => {
+                let line = &settings.victor_86e_line;
+                let serial = super::open_serial_line(&self.serial_port, line)
+                    .map_err(|e| format!("Failed to connect at {}: {e}", line.label()))?;
+                Ok(super::victor_readonly_serial::spawn_victor_readonly(
+                    serial,
+                    super::victor_readonly_serial::VictorReadonlyProtocol::Es519xx,
+                    shared,
+                    None,
+                ))
+            }
+        }
+    }
+
+    pub fn disconnect(&mut self) {
+        if let Some(shutdown_tx) = self.link.shutdown_tx.take() {
+            let _ = shutdown_tx.send(());
+        }
+        self.link = MeterLink::default();
+        self.shared = None;
+        self.state = ConnectionState::Disconnected;
+        self.error = None;
+        self.applied_idn = None;
+        self.meas = f64::NAN;
+        self.lcd_display.clear();
+    }
+
+    /// Notice a lost link, and reopen it until the meter is back.
+    pub fn reconnect_tick(&mut self, settings: &AuxConnectSettings, now: f64) {
+        if self.is_connected() {
+            let lost = self
+                .shared
+                .as_ref()
+                .and_then(|s| s.link_lost.lock().unwrap().take());
+            if let Some(reason) = lost {
+                self.disconnect();
+                self.error = Some(reason);
+                if settings.auto_reconnect {
+                    self.state = ConnectionState::Reconnecting;
+                    self.retry_at = now + RECONNECT_INTERVAL_S;
+                }
+            }
+            return;
+        }
+        if self.state != ConnectionState::Reconnecting || now < self.retry_at {
+            return;
+        }
+        self.retry_at = now + RECONNECT_INTERVAL_S;
+        self.connect(settings);
+        if !self.is_connected() {
+            self.state = ConnectionState::Reconnecting;
+        }
+    }
+
+    pub fn queue_scpi(&self, cmd: &str, debug: bool) {
+        let Some(tx) = self.link.cmd_tx.as_ref() else {
+            return;
+        };
+        if let Err(e) = tx.try_send(ensure_newline(cmd)) {
+            if debug {
+                println!("{}: failed to queue command: {}", self.name, e);
+            }
+        }
+    }
+
+    /// Change the measurement function of an SCPI meter.
+    fn set_mode(&mut self, mode: MeterMode, debug: bool) {
+        self.adopt_mode(mode, None);
+        let conf = mode.default_conf();
+        if !conf.is_empty() {
+            self.queue_scpi(conf, debug);
+        }
+    }
+
+    fn adopt_mode(&mut self, mode: MeterMode, unit: Option<&str>) {
+        self.mode = mode;
+        self.unit = unit.unwrap_or(mode.default_unit()).to_owned();
+    }
+
+    /// Drain the task channels. SCPI meters get the dialect bootstrap and
+    /// matching connect macros once their IDN is known.
+    pub fn poll(
+        &mut self,
+        bootstrap: &BootstrapSettings,
+        macros: &[ScpiMacro],
+        debug: bool,
+        now: f64,
+    ) {
+        self.frame_readings.clear();
+        if let Some(rx) = self.link.data_rx.as_mut() {
+            while let Ok(meas) = rx.try_recv() {
+                if let Some((meas, received)) = meas {
+                    self.meas = meas;
+                    self.meas_time = super::reading_time(now, received);
+                    self.frame_readings.push(TimedValue {
+                        value: meas,
+                        time: self.meas_time,
+                    });
+                }
+            }
+        }
+        let mut mode_updates = Vec::new();
+        if let Some(rx) = self.link.mode_rx.as_mut() {
+            while let Ok(update) = rx.try_recv() {
+                mode_updates.push(update);
+            }
+        }
+        for (mode, unit) in mode_updates {
+            self.adopt_mode(mode, Some(&unit));
+        }
+        // Range / rate snapshots only drive the main meter's controls
+        if let Some(rx) = self.link.status_rx.as_mut() {
+            while rx.try_recv().is_ok() {}
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(rx) = self.link.live_rx.as_mut() {
+            while let Ok(update) = rx.try_recv() {
+                self.lcd_display = update.display;
+                if let Some(v) = update.value {
+                    self.meas = v;
+                    self.meas_time = now;
+                    self.frame_readings.push(TimedValue {
+                        value: v,
+                        time: now,
+                    });
+                }
+                self.mode = update.mode;
+                self.unit = update.unit;
+            }
+        }
+
+        if self.connection_type != ConnectionType::ScpiSerial || !self.is_connected() {
+            return;
+        }
+        let Some(shared) = self.shared.clone() else {
+            return;
+        };
+        let idn = shared.device.lock().unwrap().clone();
+        if idn.is_empty()
+            || !looks_like_idn(&idn)
+            || self.applied_idn.as_deref() == Some(idn.as_str())
+        {
+            return;
+        }
+        for cmd in bootstrap_commands(classify_idn(&idn), bootstrap) {
+            self.queue_scpi(&cmd, debug);
+        }
+        // Macro runs drive the main meter only; scripted macros are skipped here
+        for m in macros.iter().filter(|m| {
+            m.run_on_connect
+                && m.applies_to.matches(&idn)
+                && Program::parse(&m.body).is_ok_and(|p| p.is_plain())
+        }) {
+            for cmd in parse_macro_body(&m.body).commands {
+                self.queue_scpi(&cmd, debug);
+            }
+        }
+        shared.refresh_requested.store(true, Ordering::SeqCst);
+        shared.poll_ready.store(true, Ordering::SeqCst);
+        self.applied_idn = Some(idn);
+    }
+
+    /// Latest reading for the recorder, NaN while nothing has arrived.
+    pub fn channel_value(&self) -> ChannelValue {
+        ChannelValue {
+            name: self.name.clone(),
+            unit: self.unit.clone(),
+            value: self.meas,
+        }
+    }
+
+    fn formatted(&self, auto_scale: bool) -> (String, String) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let lcd_override = (self.connection_type == ConnectionType::Victor86bcdSerial
+            && self.meas != crate::helpers::METER_OVERLOAD_VALUE
+            && !self.lcd_display.is_empty())
+        .then_some((self.lcd_display.as_str(), self.unit.as_str()));
+        #[cfg(target_arch = "wasm32")]
+        let lcd_override = None;
+        let auto_scale = auto_scale && self.connection_type != ConnectionType::VictorHid;
+        let (value, mut unit) = format_measurement(
+            self.meas,
+            10,
+            1_000_000.0,
+            0.000001,
+            &self.mode,
+            auto_scale && lcd_override.is_none(),
+            lcd_override,
+        );
+        if self.mode == MeterMode::Temp && !self.unit.is_empty() {
+            unit = self.unit.clone();
+        }
+        (value, unit)
+    }
+}
+
+impl super::MyApp {
+    fn aux_connect_settings(&self) -> AuxConnectSettings {
+        AuxConnectSettings {
+            serial_line: self.serial_line.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            victor_86bcd_line: self.victor_line(ConnectionType::Victor86bcdSerial),
+            #[cfg(not(target_arch = "wasm32"))]
+            victor_86e_line: self.victor_line(ConnectionType::Victor86eSerial),
+            rst_on_disconnect: self.rst_on_disconnect,
+            auto_reconnect: self.auto_reconnect,
+            value_debug: self.value_debug_shared.clone(),
+            poll_interval: self.poll_interval_shared.clone(),
+        }
+    }
+
+    pub fn poll_aux_meters(&mut self, now: f64) {
+        let bootstrap = self.bootstrap_settings();
+        let settings = self.aux_connect_settings();
+        for meter in &mut self.aux_meters {
+            meter.reconnect_tick(&settings, now);
+            meter.poll(&bootstrap, &self.scpi_macros, self.value_debug, now);
+        }
+    }
+
+    pub fn disconnect_aux_meters(&mut self) {
+        for meter in &mut self.aux_meters {
+            meter.disconnect();
+        }
+    }
+
+    /// Latest reading of every connected additional meter, every derived
+    /// channel (NaN while unavailable) and the integrator total.
+    pub fn extra_channel_values(&self) -> Vec<ChannelValue> {
+        let mut channels: Vec<ChannelValue> = self
+            .aux_meters
+            .iter()
+            .filter(|m| m.is_connected())
+            .map(AuxMeter::channel_value)
+            .collect();
+        channels.extend(self.derived_channel_values());
+        channels.extend(self.integrator_channel_value());
+        channels
+    }
+
+    /// True when the main meter or any connected additional meter has a value.
+    pub fn has_any_reading(&self) -> bool {
+        self.curr_meas.is_finite()
+            || self
+                .aux_meters
+                .iter()
+                .any(|m| m.is_connected() && m.meas.is_finite())
+    }
+
+    /// Append one time-aligned row for the combined graph.
+    pub fn sample_combined(&mut self, t: f64) {
+        let mut values = Vec::with_capacity(self.aux_meters.len() + 1);
+        values.push(self.curr_meas);
+        values.extend(self.aux_meters.iter().map(
+            |m| {
+                if m.is_connected() { m.meas } else { f64::NAN }
+            },
+        ));
+        values.extend(self.derived_values.iter().map(|v| v.value()));
+        self.combined_history
+            .push_back(CombinedSample { t, values });
+        while self.combined_history.len() > self.mem_depth {
+            self.combined_history.pop_front();
+        }
+    }
+
+    /// Legend label and color of every combined graph trace, main meter first.
+    pub fn combined_traces(&self) -> Vec<(String, Color32)> {
+        let mut traces = vec![(
+            format!("Meter 1 ({})", self.curr_unit),
+            self.graph_line_color,
+        )];
+        traces.extend(
+            self.aux_meters
+                .iter()
+                .map(|m| (format!("{} ({})", m.name, m.unit), m.color)),
+        );
+        traces.extend(
+            self.derived_channels
+                .iter()
+                .map(|c| (format!("{} ({})", c.name, c.unit), c.color)),
+        );
+        traces
+    }
+
+    pub fn show_aux_meters(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new(format!(
+            "Additional meters ({} connected)",
+            self.aux_meters.iter().filter(|m| m.is_connected()).count()
+        ))
+        .id_salt("aux_meters")
+        .show(ui, |ui| {
+            let settings = self.aux_connect_settings();
+            let debug = self.value_debug;
+            let mut remove = None;
+            for (idx, meter) in self.aux_meters.iter_mut().enumerate() {
+                ui.push_id(idx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(TextEdit::singleline(&mut meter.name).desired_width(90.0));
+                        ui.add_enabled_ui(!meter.is_connected(), |ui| {
+                            show_connection_picker(
+                                ui,
+                                meter,
+                                &self.portlist,
+                                #[cfg(not(target_arch = "wasm32"))]
+                                &self.hid_devicelist,
+                            );
+                        });
+                        if meter.is_connected() {
+                            if ui.button("Disconnect").clicked() {
+                                meter.disconnect();
+                            }
+                        } else if meter.state == ConnectionState::Reconnecting {
+                            if ui.button("Stop retrying").clicked() {
+                                meter.disconnect();
+                            }
+                        } else if ui.button("Connect").clicked() {
+                            meter.connect(&settings);
+                        }
+                        egui::color_picker::color_edit_button_srgba(
+                            ui,
+                            &mut meter.color,
+                            egui::color_picker::Alpha::Opaque,
+                        );
+                        if ui
+                            .add_enabled(!meter.is_connected(), egui::Button::new("Remove"))
+                            .clicked()
+                        {
+                            remove = Some(idx);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let auto_scale = self
+                            .mode_display_settings
+                            .get(&meter.mode)
+                            .is_none_or(|s| s.auto_scale_units);
+                        let frame = egui::Frame {
+                            inner_margin: 8.0.into(),
+                            outer_margin: egui::Margin::symmetric(24, 4),
+                            corner_radius: 5.0.into(),
+                            fill: self.box_background_color,
+                            stroke: egui::Stroke::new(1.0, meter.color),
+                            ..Default::default()
+                        };
+                        frame.show(ui, |ui| {
+                            ui.set_min_width(260.0);
+                            let (value, unit) = meter.formatted(auto_scale);
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    RichText::new(value)
+                                        .color(self.measurement_font_color)
+                                        .font(FontId {
+                                            size: 30.0,
+                                            family: FontFamily::Name("B612Mono-Bold".into()),
+                                        }),
+                                );
+                                ui.label(
+                                    RichText::new(unit).color(self.measurement_font_color).font(
+                                        FontId {
+                                            size: 16.0,
+                                            family: FontFamily::Name("B612Mono-Bold".into()),
+                                        },
+                                    ),
+                                );
+                            });
+                        });
+                        ui.vertical(|ui| {
+                            if meter.state == ConnectionState::Reconnecting {
+                                ui.label(
+                                    RichText::new(format!(
+                                        "Disconnected, retrying: {}",
+                                        meter.error.as_deref().unwrap_or("link lost")
+                                    ))
+                                    .color(Color32::ORANGE),
+                                );
+                            } else if let Some(error) = &meter.error {
+                                ui.label(RichText::new(error).color(Color32::RED));
+                            } else if meter.is_connected() {
+                                let device = meter.device();
+                                ui.label(if device.is_empty() {
+                                    "Connected, awaiting device ID...".to_owned()
+                                } else {
+                                    format!("Connected to: {device}")
+                                });
+                            } else {
+                                ui.label("Not connected.");
+                            }
+                            if meter.is_connected() && !meter.is_read_only() {
+                                let mut mode = meter.mode;
+                                egui::ComboBox::from_id_salt("aux_mode")
+                                    .selected_text(mode.button_label())
+                                    .show_ui(ui, |ui| {
+                                        // Duty cycle is Victor-only
+                                        for m in MeterMode::ALL
+                                            .into_iter()
+                                            .filter(|m| *m != MeterMode::Duty)
+                                        {
+                                            ui.selectable_value(&mut mode, m, m.button_label());
+                                        }
+                                    });
+                                if mode != meter.mode {
+                                    meter.set_mode(mode, debug);
+                                }
+                            }
+                        });
+                    });
+                    ui.separator();
+                });
+            }
+            if let Some(idx) = remove {
+                self.aux_meters.remove(idx);
+                self.combined_history.clear();
+            }
+            if ui.button("Add meter").clicked() {
+                self.aux_meters.push(AuxMeter::new(self.aux_meters.len()));
+                self.combined_history.clear();
+            }
+        });
+    }
+}
+
+fn show_connection_picker(
+    ui: &mut egui::Ui,
+    meter: &mut AuxMeter,
+    portlist: &VecDeque<String>,
+    #[cfg(not(target_arch = "wasm32"))] hid_devicelist: &VecDeque<(String, String)>,
+) {
+    #[cfg(not(target_arch = "wasm32"))]
+    egui::ComboBox::from_id_salt("aux_connection_type")
+        .selected_text(match meter.connection_type {
+            ConnectionType::ScpiSerial => "SCPI Serial (OWON)",
+            ConnectionType::VictorHid => "Victor USB HID (86B/C/D)",
+            ConnectionType::Victor86bcdSerial => "Victor Serial (86B/C/D)",
+            ConnectionType::Victor86eSerial => "Victor Serial (86E)",
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(
+                &mut meter.connection_type,
+                ConnectionType::ScpiSerial,
+                "SCPI Serial (OWON)",
+            );
+            ui.selectable_value(
+                &mut meter.connection_type,
+                ConnectionType::VictorHid,
+                "Victor USB HID (86B/C/D)",
+            );
+            ui.selectable_value(
+                &mut meter.connection_type,
+                ConnectionType::Victor86bcdSerial,
+                "Victor Serial (86B/C/D)",
+            );
+            ui.selectable_value(
+                &mut meter.connection_type,
+                ConnectionType::Victor86eSerial,
+                "Victor Serial (86E)",
+            );
+        });
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if meter.connection_type == ConnectionType::VictorHid {
+        egui::ComboBox::from_id_salt("aux_hid_device")
+            .selected_text(
+                hid_devicelist
+                    .iter()
+                    .find(|(path, _)| *path == meter.hid_device_path)
+                    .map(|(_, label)| label.as_str())
+                    .unwrap_or("No Victor device selected"),
+            )
+            .show_ui(ui, |ui| {
+                for (path, label) in hid_devicelist {
+                    ui.selectable_value(&mut meter.hid_device_path, path.clone(), label);
+                }
+            });
+        return;
+    }
+
+    ui.add(
+        DropDownBox::from_iter(
+            portlist,
+            "aux_portlistbox",
+            &mut meter.serial_port,
+            |ui, text| ui.selectable_label(false, text),
+        )
+        .desired_width(150.0)
+        .select_on_focus(true)
+        .filter_by_input(false),
+    );
+}
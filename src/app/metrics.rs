@@ -0,0 +1,352 @@
+//! Time series export for dashboards: InfluxDB line protocol appended to a
+//! file or pushed to a local write endpoint, and the Prometheus exposition
+//! served on `/metrics` by the HTTP API.
+//!
+//! Influx batches are written on a plain thread so a slow disk or endpoint
+//! never stalls the UI; the UI only formats lines and hands them over.
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    sync::{Arc, Mutex, mpsc},
+    thread,
+};
+
+use egui::{Color32, RichText};
+use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::METER_OVERLOAD_VALUE;
+use crate::metrics::{Exposition, MetricKind, influx_line};
+
+/// Batches waiting for the writer thread before new ones are dropped.
+const INFLUX_QUEUE: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InfluxTarget {
+    File,
+    Http,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InfluxSettings {
+    pub enabled: bool,        // Persistent, off unless turned on in the settings
+    pub target: InfluxTarget, // Persistent
+    pub file_path: String,    // Persistent, lines are appended
+    pub url: String,          // Persistent write endpoint, including db / bucket query
+    pub token: String,        // Persistent, empty for no Authorization header
+    pub measurement: String,  // Persistent measurement name
+    pub interval_ms: u64,     // Persistent minimum time between points, 0 writes each reading
+}
+
+impl Default for InfluxSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: InfluxTarget::File,
+            file_path: String::new(),
+            url: "http://127.0.0.1:8086/api/v2/write?org=lab&bucket=rusty_meter&precision=ns"
+                .to_owned(),
+            token: String::new(),
+            measurement: "rusty_meter".to_owned(),
+            interval_ms: 1000,
+        }
+    }
+}
+
+#[derive(Default)]
+struct WriterStatus {
+    lines: u64,
+    error: Option<String>,
+}
+
+/// A running writer thread. Dropping it closes the queue and ends the thread
+/// once the queued batches are written.
+pub struct InfluxWriter {
+    settings: InfluxSettings,
+    status: Arc<Mutex<WriterStatus>>,
+    batch_tx: mpsc::SyncSender<(usize, String)>,
+    last_sample: f64,                 // UI time of the last batch
+    last_written: Vec<(String, f64)>, // Meter time of the last point per channel
+}
+
+impl InfluxWriter {
+    fn start(settings: &InfluxSettings) -> Self {
+        let status = Arc::new(Mutex::new(WriterStatus::default()));
+        let (batch_tx, batch_rx) = mpsc::sync_channel(INFLUX_QUEUE);
+        let thread_settings = settings.clone();
+        let thread_status = status.clone();
+        thread::spawn(move || write_batches(thread_settings, thread_status, batch_rx));
+        Self {
+            settings: settings.clone(),
+            status,
+            batch_tx,
+            last_sample: f64::NEG_INFINITY,
+            last_written: vec![],
+        }
+    }
+}
+
+fn write_batches(
+    settings: InfluxSettings,
+    status: Arc<Mutex<WriterStatus>>,
+    batch_rx: mpsc::Receiver<(usize, String)>,
+) {
+    let client = reqwest::blocking::Client::new();
+    for (lines, batch) in batch_rx {
+        let result = match settings.target {
+            InfluxTarget::File => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&settings.file_path)
+                .and_then(|mut file| file.write_all(batch.as_bytes()))
+                .map_err(|e| format!("Failed to write {}: {e}", settings.file_path)),
+            InfluxTarget::Http => {
+                let mut request = client
+                    .post(&settings.url)
+                    .header("Content-Type", "text/plain; charset=utf-8")
+                    .body(batch);
+                if !settings.token.is_empty() {
+                    request = request.header("Authorization", format!("Token {}", settings.token));
+                }
+                match request.send() {
+                    Ok(response) if response.status().is_success() => Ok(()),
+                    Ok(response) => Err(format!(
+                        "Failed to push to {}: HTTP {}",
+                        settings.url,
+                        response.status()
+                    )),
+                    Err(e) => Err(format!("Failed to push to {}: {e}", settings.url)),
+                }
+            }
+        };
+        let mut status = status.lock().unwrap();
+        match result {
+            Ok(()) => {
+                status.lines += lines as u64;
+                status.error = None;
+            }
+            Err(e) => status.error = Some(e),
+        }
+    }
+}
+
+impl super::MyApp {
+    /// Start, stop or restart the Influx writer to match the settings and
+    /// queue a point for every channel with a new reading.
+    pub fn influx_tick(&mut self, now: f64) {
+        if !self.influx.enabled {
+            self.influx_writer = None;
+            return;
+        }
+        if self
+            .influx_writer
+            .as_ref()
+            .is_none_or(|w| w.settings != self.influx)
+        {
+            self.influx_writer = Some(InfluxWriter::start(&self.influx));
+        }
+        let Some(writer) = self.influx_writer.as_mut() else {
+            return;
+        };
+        if writer.settings.target == InfluxTarget::File
+            && writer.settings.file_path.trim().is_empty()
+        {
+            return;
+        }
+        if now - writer.last_sample < writer.settings.interval_ms as f64 / 1000.0 {
+            return;
+        }
+        let wall_ns = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        let mut batch = String::new();
+        let mut lines = 0;
+        for channel in &self.live_channels {
+            let Some(reading) = channel.value else {
+                continue;
+            };
+            if reading.value == METER_OVERLOAD_VALUE {
+                continue;
+            }
+            match writer
+                .last_written
+                .iter_mut()
+                .find(|(var, _)| *var == channel.var)
+            {
+                Some((_, time)) if *time == reading.time => continue,
+                Some((_, time)) => *time = reading.time,
+                None => writer
+                    .last_written
+                    .push((channel.var.clone(), reading.time)),
+            }
+            let age_ns = ((now - reading.time).max(0.0) * 1e9) as i64;
+            if let Some(line) = influx_line(
+                &writer.settings.measurement,
+                &[
+                    ("channel", channel.var.as_str()),
+                    ("label", channel.label.as_str()),
+                    ("unit", channel.unit.as_str()),
+                ],
+                &[("value", reading.value)],
+                wall_ns - age_ns,
+            ) {
+                batch.push_str(&line);
+                batch.push('\n');
+                lines += 1;
+            }
+        }
+        if lines == 0 {
+            return;
+        }
+        writer.last_sample = now;
+        // A full queue drops the batch rather than stall the UI
+        let _ = writer.batch_tx.try_send((lines, batch));
+    }
+
+    /// Prometheus exposition of the live channels and the main meter counters.
+    pub fn metrics_exposition(&self) -> String {
+        let mut exp = Exposition::default();
+        exp.family(
+            "rusty_meter_reading",
+            MetricKind::Gauge,
+            "Latest reading per channel, +Inf on overload",
+        );
+        for channel in &self.live_channels {
+            let Some(reading) = channel.value else {
+                continue;
+            };
+            let value = if reading.value == METER_OVERLOAD_VALUE {
+                f64::INFINITY
+            } else {
+                reading.value
+            };
+            exp.sample(
+                "rusty_meter_reading",
+                &[
+                    ("channel", channel.var.as_str()),
+                    ("label", channel.label.as_str()),
+                    ("unit", channel.unit.as_str()),
+                ],
+                value,
+            );
+        }
+        let connected = self.connection_state == super::ConnectionState::Connected;
+        exp.family(
+            "rusty_meter_connected",
+            MetricKind::Gauge,
+            "Whether the main meter is connected",
+        );
+        exp.sample("rusty_meter_connected", &[], f64::from(u8::from(connected)));
+        let counters = [
+            (
+                "rusty_meter_readings_total",
+                "Readings received from the main meter",
+                self.counters.readings,
+            ),
+            (
+                "rusty_meter_timeouts_total",
+                "SCPI replies the main meter did not send in time",
+                self.counters
+                    .timeouts
+                    .load(std::sync::atomic::Ordering::Relaxed),
+            ),
+            (
+                "rusty_meter_reconnects_total",
+                "Connections to the main meter after the first one",
+                self.counters.reconnects(),
+            ),
+        ];
+        for (name, help, value) in counters {
+            exp.family(name, MetricKind::Counter, help);
+            exp.sample(name, &[], value as f64);
+        }
+        exp.finish()
+    }
+
+    pub fn show_influx_settings(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.label("InfluxDB:");
+        ui.checkbox(
+            &mut self.influx.enabled,
+            "Export readings as InfluxDB line protocol",
+        );
+        ui.add_enabled_ui(!self.influx.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.radio_value(
+                    &mut self.influx.target,
+                    InfluxTarget::File,
+                    "Append to file",
+                );
+                ui.radio_value(&mut self.influx.target, InfluxTarget::Http, "Push via HTTP");
+            });
+            egui::Grid::new("influx_settings")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    match self.influx.target {
+                        InfluxTarget::File => {
+                            ui.label("File:");
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut self.influx.file_path)
+                                        .desired_width(240.0),
+                                );
+                                if ui.button("Browse").clicked() {
+                                    if let Some(path) = FileDialog::new()
+                                        .add_filter("Line protocol", &["lp", "txt"])
+                                        .save_file()
+                                    {
+                                        self.influx.file_path = path.display().to_string();
+                                    }
+                                }
+                            });
+                            ui.end_row();
+                        }
+                        InfluxTarget::Http => {
+                            ui.label("Write URL:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.influx.url)
+                                    .desired_width(320.0),
+                            );
+                            ui.end_row();
+                            ui.label("Token:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.influx.token)
+                                    .desired_width(320.0)
+                                    .password(true)
+                                    .hint_text("none"),
+                            );
+                            ui.end_row();
+                        }
+                    }
+                    ui.label("Measurement:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.influx.measurement)
+                            .desired_width(160.0),
+                    );
+                    ui.end_row();
+                    ui.label("Min. interval:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.influx.interval_ms)
+                            .range(0..=3_600_000)
+                            .suffix(" ms"),
+                    )
+                    .on_hover_text("0 writes every reading");
+                    ui.end_row();
+                });
+        });
+        if self.influx.enabled
+            && self.influx.target == InfluxTarget::File
+            && self.influx.file_path.trim().is_empty()
+        {
+            ui.label(RichText::new("No file set").color(Color32::RED));
+        }
+        let Some(writer) = &self.influx_writer else {
+            return;
+        };
+        let status = writer.status.lock().unwrap();
+        match &status.error {
+            Some(e) => ui.label(RichText::new(e).color(Color32::RED)),
+            None => ui.label(format!("{} points written", status.lines)),
+        };
+    }
+}
@@ -4,8 +4,9 @@ use std::{
     collections::{BTreeMap, HashMap, VecDeque},
     sync::{
         Arc, Mutex,
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
     },
+    time::Instant,
 };
 
 use egui::{Color32, FontData, FontDefinitions, FontFamily};
@@ -14,21 +15,49 @@ use mio::{Events, Poll};
 use mio_serial::{SerialPortInfo, SerialStream};
 use tokio::sync::{mpsc, oneshot};
 
+use crate::integrator::Integrator;
 use crate::multimeter::{GenScpi, MeterMode, RangeCmd, RateCmd, ScpiMode};
 use crate::scpi_macro::{
-    BootstrapSettings, MacroTarget, MeterStatus, ScpiMacro, ScpiUiHint, SnapshotRange,
-    bootstrap_commands, classify_idn, ensure_newline, idn_model, is_recordable_scpi,
+    BootstrapSettings, ImportPolicy, MacroTarget, MeterStatus, ScpiMacro, ScpiUiHint,
+    SnapshotRange, bootstrap_commands, classify_idn, ensure_newline, idn_model, is_recordable_scpi,
     looks_like_idn, parse_macro_body, range_table_meter, snapshot_range, ui_hint_from_command,
 };
+use crate::serial_line::{LineParity, SerialLine};
+use crate::shortcuts::ShortcutSettings;
 
 // Submodules for split impl blocks
+mod analysis;
+mod auto_hold;
+mod battery;
+mod binning;
+mod connection;
+mod console;
+mod derived;
+#[cfg(not(target_arch = "wasm32"))]
+mod detect;
 mod graph;
 #[cfg(not(target_arch = "wasm32"))]
 mod hid;
+#[cfg(not(target_arch = "wasm32"))]
+mod http_api;
+mod integrator;
+mod layout;
+mod macro_run;
 mod macros;
+mod meters;
+#[cfg(not(target_arch = "wasm32"))]
+mod metrics;
+#[cfg(not(target_arch = "wasm32"))]
+mod mqtt;
+mod profiles;
 mod recording;
+#[cfg(not(target_arch = "wasm32"))]
+mod scpi_server;
+#[cfg(not(target_arch = "wasm32"))]
+mod scripts;
 mod serial;
 mod settings;
+mod shortcuts;
 mod ui;
 #[cfg(not(target_arch = "wasm32"))]
 mod victor_86bcd_capture_ui;
@@ -56,43 +85,142 @@ pub enum ConnectionType {
     Victor86eSerial,
 }
 
-/// Victor 86D / DM1107: 9600 baud, 8 data bits, no parity, 1 stop (8N1).
+impl ConnectionType {
+    /// Factor from a reading in `unit` to its base unit. The 86E decoder
+    /// already reports SI values next to its range unit, the others report
+    /// what the display shows.
+    pub fn si_factor(self, unit: &str) -> f64 {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self == Self::Victor86eSerial {
+            return 1.0;
+        }
+        crate::multimeter::split_si_prefix(unit).0
+    }
+}
+
+/// Open a serial port with the given line settings.
 /// Line settings must be set on the builder before open — post-open `set_*` is unreliable.
-#[cfg(not(target_arch = "wasm32"))]
-pub(crate) fn open_victor_8n1_serial(
+pub(crate) fn open_serial_line(
     path: &str,
-    baud: u32,
+    line: &SerialLine,
 ) -> Result<SerialStream, mio_serial::Error> {
-    use mio_serial::{DataBits, Parity, SerialPortBuilderExt, StopBits};
+    use mio_serial::{DataBits, FlowControl, Parity, SerialPortBuilderExt, StopBits};
 
-    mio_serial::new(path, baud)
-        .data_bits(DataBits::Eight)
-        .parity(Parity::None)
-        .stop_bits(StopBits::One)
+    line.validate()
+        .map_err(|e| mio_serial::Error::new(mio_serial::ErrorKind::InvalidInput, e))?;
+    let data_bits = match line.data_bits {
+        5 => DataBits::Five,
+        6 => DataBits::Six,
+        7 => DataBits::Seven,
+        _ => DataBits::Eight,
+    };
+    let parity = match line.parity {
+        LineParity::None => Parity::None,
+        LineParity::Odd => Parity::Odd,
+        LineParity::Even => Parity::Even,
+    };
+    let stop_bits = if line.stop_bits == 2 {
+        StopBits::Two
+    } else {
+        StopBits::One
+    };
+    let flow_control = match line.flow_control {
+        crate::serial_line::FlowControl::None => FlowControl::None,
+        crate::serial_line::FlowControl::Software => FlowControl::Software,
+        crate::serial_line::FlowControl::Hardware => FlowControl::Hardware,
+    };
+    mio_serial::new(path, line.baud)
+        .data_bits(data_bits)
+        .parity(parity)
+        .stop_bits(stop_bits)
+        .flow_control(flow_control)
         .open_native_async()
 }
 
+/// Victor 86D / DM1107: 9600 baud, 8 data bits, no parity, 1 stop (8N1).
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) const VICTOR_86BCD_LINE: SerialLine = SerialLine::new(
+    crate::victor_dm1107::VICTOR_86BCD_BAUD,
+    8,
+    LineParity::None,
+    1,
+);
+
 /// Victor 86E / ES51932: 19200 baud, 7 data bits, odd parity, 1 stop (7o1).
 #[cfg(not(target_arch = "wasm32"))]
-pub(crate) fn open_victor_7o1_serial(
-    path: &str,
-    baud: u32,
-) -> Result<SerialStream, mio_serial::Error> {
-    use mio_serial::{DataBits, Parity, SerialPortBuilderExt, StopBits};
+pub(crate) const VICTOR_86E_LINE: SerialLine = SerialLine::new(
+    crate::victor_es519xx::VICTOR_86E_BAUD,
+    7,
+    LineParity::Odd,
+    1,
+);
 
-    mio_serial::new(path, baud)
-        .data_bits(DataBits::Seven)
-        .parity(Parity::Odd)
-        .stop_bits(StopBits::One)
-        .open_native_async()
+/// Shared handles a meter task reports into. One set per meter.
+#[derive(Clone)]
+pub(crate) struct MeterShared {
+    device: Arc<Mutex<String>>,
+    poll_ready: Arc<AtomicBool>,
+    refresh_requested: Arc<AtomicBool>,
+    value_debug: Arc<Mutex<bool>>,
+    poll_interval: Arc<Mutex<u64>>,
+    timeouts: Arc<AtomicU64>, // Replies the meter never sent, counted by the SCPI task
+    link_lost: Arc<Mutex<Option<String>>>, // Set by the task when the port or device goes away
+}
+
+/// Lifetime counters of the main meter connection, exported on `/metrics`.
+#[derive(Default)]
+pub(crate) struct MeterCounters {
+    readings: u64,
+    timeouts: Arc<AtomicU64>,
+    connects: u64,
+}
+
+impl MeterCounters {
+    /// Connections after the first one.
+    fn reconnects(&self) -> u64 {
+        self.connects.saturating_sub(1)
+    }
+}
+
+/// A reading and when the meter task received it, so the UI can place
+/// every sample in time even when several arrive in one frame.
+pub(crate) type Reading = (f64, Instant);
+
+/// UI time of a reading received at `received`.
+fn reading_time(now: f64, received: Instant) -> f64 {
+    now - received.elapsed().as_secs_f64()
+}
+
+/// Channel ends of one running meter task. Channels a task does not use stay `None`.
+#[derive(Default)]
+pub(crate) struct MeterLink {
+    data_rx: Option<mpsc::Receiver<Option<Reading>>>,
+    cmd_tx: Option<mpsc::Sender<String>>,
+    mode_rx: Option<mpsc::Receiver<(MeterMode, String)>>,
+    status_rx: Option<mpsc::Receiver<MeterStatus>>,
+    passthrough_tx: Option<mpsc::Sender<serial::PassthroughCmd>>,
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    live_rx: Option<mpsc::Receiver<crate::victor_dm1107::Dm1107LiveUpdate>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    capture_tx: Option<mpsc::Sender<crate::victor_86bcd_capture::Victor86bcdCaptureJob>>,
 }
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Font of the measurement display, also served to the stream overlay.
+const B612_MONO_BOLD: &[u8] = include_bytes!("../../assets/fonts/B612Mono-Bold.ttf");
+
 const MEM_DEPTH_DEFAULT: usize = 100; // Default slider value
 const MEM_DEPTH_MAX_DEFAULT: usize = 2000; // Default maximum
 const HIST_MEM_DEPTH_DEFAULT: usize = 1000; // Default histogram memory depth
 const HIST_MEM_DEPTH_MAX_DEFAULT: usize = 10000; // Default maximum histogram memory depth
+const POLL_INTERVAL_MS_DEFAULT: u64 = 20; // Default poll interval
+const GRAPH_UPDATE_INTERVAL_MS_DEFAULT: u64 = 20; // Default to 20ms for ~50 FPS
+const RECORDING_INTERVAL_MS_DEFAULT: u64 = 1000; // Default to 1 second
+const BEEPER_ENABLED_DEFAULT: bool = true; // Default to on, per meter spec
+const CONT_THRESHOLD_DEFAULT: u32 = 50; // Default continuity threshold: 50 ohms
+const DIOD_THRESHOLD_DEFAULT: f32 = 2.0; // Default diode threshold: 2.0 volts (mid-range)
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum RecordingFormat {
@@ -120,6 +248,24 @@ pub struct Record {
     pub timestamp: DateTime<chrono::Utc>,
     pub unit: String,
     pub value: f64,
+    #[serde(default)]
+    pub channels: Vec<ChannelValue>, // Additional meters sampled at the same instant
+    #[serde(default)]
+    pub label: String, // Set by a macro LOG statement, empty otherwise
+}
+
+impl Record {
+    fn channel(&self, name: &str) -> Option<&ChannelValue> {
+        self.channels.iter().find(|c| c.name == name)
+    }
+}
+
+/// One additional meter's reading inside a [`Record`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChannelValue {
+    pub name: String,
+    pub unit: String,
+    pub value: f64,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -144,10 +290,15 @@ pub struct MyApp {
     serial_port: String,
     #[cfg(not(target_arch = "wasm32"))]
     hid_device_path: String,
-    baud_rate: u32,
-    bits: u32,
-    stop_bits: u32,
-    parity: bool,
+    // Persistent, SCPI line settings
+    serial_line: SerialLine,
+    // Persistent, use the lines below instead of the fixed Victor settings
+    #[cfg(not(target_arch = "wasm32"))]
+    victor_line_override: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    victor_86bcd_line: SerialLine,
+    #[cfg(not(target_arch = "wasm32"))]
+    victor_86e_line: SerialLine,
     mem_depth: usize,              // Persistent, adjustable via slider
     mem_depth_max: usize,          // Persistent, maximum for slider
     hist_mem_depth: usize,         // Persistent, histogram memory depth
@@ -164,6 +315,7 @@ pub struct MyApp {
     cont_threshold: u32,           // Persistent continuity threshold (0-1000 ohms)
     diod_threshold: f32,           // Persistent diode threshold (0-3.0 volts)
     lock_remote: bool,             // Persistent, whether to lock meter in remote mode
+    auto_reconnect: bool,          // Persistent, reopen the same device after link loss
     curr_rate: usize,              // Persistent, current sampling rate index
     reverse_graph: bool,           // Persistent, whether to reverse graph direction
     graph_line_color: Color32,     // Persistent, color for graph line
@@ -181,6 +333,32 @@ pub struct MyApp {
     mode_display_settings: HashMap<MeterMode, ModeDisplaySettings>,
     #[serde(default)]
     scpi_macros: Vec<ScpiMacro>,
+    #[serde(default)]
+    macro_import_policy: ImportPolicy, // Persistent, how imports treat clashing macro IDs
+    macro_check_errors: bool, // Persistent, editor runs ask SYST:ERR? after each command
+    shortcuts: ShortcutSettings, // Persistent, keyboard shortcuts and foot-pedal key
+    profiles: Vec<profiles::Profile>, // Persistent named bench setups
+    active_profile: String,   // Persistent, name of the last loaded profile
+    #[serde(skip)]
+    profiles_open: bool,
+    #[serde(skip)]
+    profile_name_input: String,
+    #[serde(skip)]
+    profile_io_message: Option<String>, // Do not persist, result of the last import/export
+    #[serde(skip)]
+    profile_setup: Option<(MeterMode, String)>, // Do not persist, mode and range still to send
+    #[serde(skip)]
+    shortcut_capture: Option<shortcuts::CaptureTarget>, // Do not persist, binding waiting for a key
+    #[serde(skip)]
+    display_hold: Option<(MeterMode, f64)>, // Do not persist, reading frozen by Hold
+    #[serde(skip)]
+    rel_reference: Option<(MeterMode, f64)>, // Do not persist, REL zero point
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(default)]
+    scripts: Vec<scripts::Script>, // Persistent Rhai test scripts
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(default)]
+    script_results_dir: String, // Persistent folder for files written by scripts
     #[serde(skip)]
     recording_data: Vec<Record>, // Do not persist recording data
     #[serde(skip)]
@@ -196,6 +374,10 @@ pub struct MyApp {
     #[serde(skip)]
     curr_meas: f64,
     #[serde(skip)]
+    curr_meas_time: f64, // UI time of the last main meter reading
+    #[serde(skip)]
+    frame_readings: Vec<crate::expr::TimedValue>, // Main meter readings received this frame
+    #[serde(skip)]
     curr_unit: String,
     #[serde(skip)]
     issue_new_write: bool,
@@ -211,6 +393,8 @@ pub struct MyApp {
     #[serde(skip)]
     hist_values: VecDeque<f64>, // Buffer for histogram data
     #[serde(skip)]
+    hist_times: VecDeque<f64>, // Unix time of each histogram sample, for the analysis tab
+    #[serde(skip)]
     poll: Poll,
     #[serde(skip)]
     events: Events,
@@ -227,14 +411,29 @@ pub struct MyApp {
     #[serde(skip)]
     macros_open: bool,
     #[serde(skip)]
+    console_open: bool,
+    #[serde(skip)]
+    console: console::ConsoleState,
+    console_history: Vec<String>, // Persistent, commands sent from the SCPI console
+    #[serde(skip)]
     selected_macro_id: Option<String>,
     #[serde(skip)]
+    macro_io_message: Option<String>, // Do not persist, result of the last import/export
+    #[serde(skip)]
     macro_recording: bool,
     #[serde(skip)]
     macro_record_buffer: String,
     #[serde(skip)]
+    macro_run: Option<macro_run::MacroRun>,
+    #[serde(skip)]
+    macro_queue: VecDeque<(String, String)>, // Connect macros waiting for the run before them
+    #[serde(skip)]
     applied_idn: Option<String>,
     #[serde(skip)]
+    link_lost_shared: Arc<Mutex<Option<String>>>, // Reason the main meter task gave up
+    #[serde(skip)]
+    reconnect: connection::ReconnectState,
+    #[serde(skip)]
     poll_ready: Arc<AtomicBool>,
     #[serde(skip)]
     refresh_requested: Arc<AtomicBool>,
@@ -249,7 +448,7 @@ pub struct MyApp {
     #[serde(skip)]
     meter_auto_range: bool,
     #[serde(skip)]
-    serial_rx: Option<mpsc::Receiver<Option<f64>>>, // handle measurements
+    serial_rx: Option<mpsc::Receiver<Option<Reading>>>, // handle measurements
     #[serde(skip)]
     serial_tx: Option<mpsc::Sender<String>>, // channel for sending commands to serial task
     #[serde(skip)]
@@ -258,6 +457,8 @@ pub struct MyApp {
     mode_rx: Option<mpsc::Receiver<(MeterMode, String)>>, // Channel for mode + unit updates
     #[serde(skip)]
     status_rx: Option<mpsc::Receiver<MeterStatus>>,
+    #[serde(skip)]
+    passthrough_tx: Option<mpsc::Sender<serial::PassthroughCmd>>, // SCPI task input for the passthrough port
     #[cfg(not(target_arch = "wasm32"))]
     #[serde(skip)]
     victor_86bcd_rx: Option<mpsc::Receiver<crate::victor_dm1107::Dm1107LiveUpdate>>,
@@ -303,9 +504,62 @@ pub struct MyApp {
     meas_count: u32, // Track measurement cycles for periodic FUNC? polling
     #[serde(skip)]
     last_record_time: f64, // Track last recording time for fixed interval
-    graph_config: graph::GraphConfig, // Graph configuration
     #[serde(skip)]
-    plot_dock_state: DockState<ui::PlotTab>, // Dock state for plot tabs
+    counters: MeterCounters, // Readings, timeouts and connects since start
+    graph_config: graph::GraphConfig,   // Graph configuration
+    plot_export: graph::ExportSettings, // Persistent image export size
+    analysis: analysis::AnalysisState,  // Persistent analysis tab settings
+    aux_meters: Vec<meters::AuxMeter>,  // Persistent additional meter connections
+    derived_channels: Vec<derived::DerivedChannel>, // Persistent virtual channels
+    derived_tolerance_ms: u64,          // Persistent max time skew between derived inputs
+    derived_stale_ms: u64,              // Persistent age after which a derived input is stale
+    #[serde(skip)]
+    derived_values: Vec<derived::DerivedValue>, // Latest result per derived channel
+    #[serde(skip)]
+    live_channels: Vec<derived::LiveChannel>, // Meters and derived channels, refreshed per frame
+    integrator_source: String,          // Persistent channel variable fed to the integrator
+    #[serde(skip)]
+    integrator: Integrator,
+    battery: battery::BatteryTestState, // Persistent discharge test settings
+    #[serde(skip)]
+    battery_open: bool,
+    binning: binning::BinningState, // Persistent component binning settings
+    auto_hold: auto_hold::AutoHold, // Persistent auto-hold settings
+    #[serde(skip)]
+    binning_open: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    http_api: http_api::HttpApiSettings, // Persistent local HTTP API settings
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    http_server: Option<http_api::HttpApiServer>,
+    #[cfg(not(target_arch = "wasm32"))]
+    mqtt: mqtt::MqttSettings, // Persistent MQTT publisher settings
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    mqtt_client: Option<mqtt::MqttClient>,
+    #[cfg(not(target_arch = "wasm32"))]
+    influx: metrics::InfluxSettings, // Persistent InfluxDB line protocol export settings
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    influx_writer: Option<metrics::InfluxWriter>,
+    #[cfg(not(target_arch = "wasm32"))]
+    scpi_server: scpi_server::ScpiServerSettings, // Persistent SCPI passthrough port settings
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    scpi_server_handle: Option<scpi_server::ScpiServer>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    detect: detect::DetectState,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    scripts_open: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    script_state: scripts::ScriptsState,
+    #[serde(skip)]
+    combined_history: VecDeque<meters::CombinedSample>, // Time-aligned samples of all meters
+    plot_dock_state: DockState<ui::PlotTab>, // Persistent dock layout of the plot tabs
+    torn_off_tabs: Vec<ui::PlotTab>,         // Persistent, plot tabs shown in their own window
 }
 
 // Enum to track connection state
@@ -314,6 +568,8 @@ enum ConnectionState {
     Disconnected,
     Connecting,
     Connected,
+    /// The link was lost; the same port or device is reopened periodically.
+    Reconnecting,
 }
 
 impl Default for MyApp {
@@ -323,10 +579,13 @@ impl Default for MyApp {
             serial_port: "".to_owned(),
             #[cfg(not(target_arch = "wasm32"))]
             hid_device_path: "".to_owned(),
-            baud_rate: 115200,
-            bits: 8,
-            stop_bits: 1,
-            parity: false,
+            serial_line: SerialLine::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            victor_line_override: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            victor_86bcd_line: VICTOR_86BCD_LINE,
+            #[cfg(not(target_arch = "wasm32"))]
+            victor_86e_line: VICTOR_86E_LINE,
             mem_depth: MEM_DEPTH_DEFAULT, // Default slider value: 100
             mem_depth_max: MEM_DEPTH_MAX_DEFAULT, // Default max: 2000
             hist_mem_depth: HIST_MEM_DEPTH_DEFAULT, // Default histogram memory depth: 1000
@@ -340,6 +599,8 @@ impl Default for MyApp {
             scpimode: ScpiMode::Idn,
             confstring: "".to_owned(),
             curr_meas: f64::NAN,
+            curr_meas_time: 0.0,
+            frame_readings: Vec::new(),
             curr_unit: "VDC".to_owned(),
             issue_new_write: false,
             readbuf: [0u8; 1024],
@@ -348,6 +609,7 @@ impl Default for MyApp {
             hid_devicelist: VecDeque::with_capacity(4),
             values: VecDeque::with_capacity(MEM_DEPTH_DEFAULT + 1),
             hist_values: VecDeque::with_capacity(MEM_DEPTH_DEFAULT + 1), // Initialize histogram buffer
+            hist_times: VecDeque::with_capacity(MEM_DEPTH_DEFAULT + 1),
             poll: Poll::new().unwrap(),
             events: Events::with_capacity(1),
             serial: None,
@@ -356,13 +618,35 @@ impl Default for MyApp {
             tempdir: tempfile::Builder::new().prefix("rustymeter").tempdir().ok(),
             settings_open: false,
             macros_open: false,
+            console_open: false,
+            console: console::ConsoleState::default(),
+            console_history: vec![],
             selected_macro_id: None,
+            macro_io_message: None,
             macro_recording: false,
             macro_record_buffer: String::new(),
+            macro_run: None,
+            macro_queue: VecDeque::new(),
             applied_idn: None,
             poll_ready: Arc::new(AtomicBool::new(false)),
             refresh_requested: Arc::new(AtomicBool::new(false)),
             scpi_macros: vec![],
+            macro_import_policy: ImportPolicy::default(),
+            macro_check_errors: true,
+            shortcuts: ShortcutSettings::default(),
+            profiles: vec![],
+            active_profile: String::new(),
+            profiles_open: false,
+            profile_name_input: String::new(),
+            profile_io_message: None,
+            profile_setup: None,
+            shortcut_capture: None,
+            display_hold: None,
+            rel_reference: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            scripts: vec![],
+            #[cfg(not(target_arch = "wasm32"))]
+            script_results_dir: String::new(),
             is_init: false,
             ratecmd: RateCmd::default(),
             curr_rate: 0,
@@ -378,7 +662,7 @@ impl Default for MyApp {
             recording_format: RecordingFormat::Csv,
             recording_file_path: "".to_owned(),
             recording_mode: RecordingMode::FixedInterval,
-            recording_interval_ms: 1000, // Default to 1 second
+            recording_interval_ms: RECORDING_INTERVAL_MS_DEFAULT,
             recording_active: false,
             recording_timestamp_format: TimestampFormat::Rfc3339, // Default to RFC3339
             recording_data: vec![],                               // Initialize empty, not persisted
@@ -388,20 +672,24 @@ impl Default for MyApp {
             shutdown_tx: None, // Initially no shutdown signal
             mode_rx: None,     // Initially no mode update channel
             status_rx: None,
+            passthrough_tx: None,
             #[cfg(not(target_arch = "wasm32"))]
             victor_86bcd_rx: None,
             #[cfg(not(target_arch = "wasm32"))]
             victor_lcd_display: String::new(),
-            poll_interval_ms: 20,
-            graph_update_interval_ms: 20, // Default to 20ms for ~50 FPS
+            poll_interval_ms: POLL_INTERVAL_MS_DEFAULT,
+            graph_update_interval_ms: GRAPH_UPDATE_INTERVAL_MS_DEFAULT,
             graph_update_interval_max: 1000, // Default maximum of 1000ms
-            beeper_enabled: true,         // Default to on, per meter spec
-            rst_on_disconnect: true,      // Default to on, can be disabled in settings
-            cont_threshold: 50,           // Default continuity threshold: 50 ohms
-            diod_threshold: 2.0,          // Default diode threshold: 2.0 volts (mid-range)
-            lock_remote: true,            // Default to locking remote mode
+            beeper_enabled: BEEPER_ENABLED_DEFAULT,
+            rst_on_disconnect: true, // Default to on, can be disabled in settings
+            cont_threshold: CONT_THRESHOLD_DEFAULT,
+            diod_threshold: DIOD_THRESHOLD_DEFAULT,
+            lock_remote: true, // Default to locking remote mode
+            auto_reconnect: true,
+            link_lost_shared: Arc::new(Mutex::new(None)),
+            reconnect: connection::ReconnectState::default(),
             value_debug_shared: Arc::new(Mutex::new(false)),
-            poll_interval_shared: Arc::new(Mutex::new(20)),
+            poll_interval_shared: Arc::new(Mutex::new(POLL_INTERVAL_MS_DEFAULT)),
             #[cfg(not(target_arch = "wasm32"))]
             victor_86bcd_capture_function:
                 crate::victor_86bcd_capture::Victor86bcdCaptureFunction::default(),
@@ -432,7 +720,47 @@ impl Default for MyApp {
             meas_count: 0,                                   // Initialize measurement counter
             last_record_time: 0.0,                           // Initialize last recording time
             graph_config: graph::GraphConfig::default(),     // Default graph config
-            plot_dock_state: DockState::new(vec![]), // Initialize empty, populated in update
+            plot_export: graph::ExportSettings::default(),
+            analysis: analysis::AnalysisState::default(),
+            aux_meters: vec![],
+            derived_channels: vec![],
+            derived_tolerance_ms: 500,
+            derived_stale_ms: 3000,
+            derived_values: vec![],
+            live_channels: vec![],
+            integrator_source: "m1".to_owned(),
+            integrator: Integrator::default(),
+            battery: battery::BatteryTestState::default(),
+            battery_open: false,
+            binning: binning::BinningState::default(),
+            auto_hold: auto_hold::AutoHold::default(),
+            binning_open: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            http_api: http_api::HttpApiSettings::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            http_server: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            mqtt: mqtt::MqttSettings::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            mqtt_client: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            influx: metrics::InfluxSettings::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            influx_writer: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            scpi_server: scpi_server::ScpiServerSettings::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            scpi_server_handle: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            detect: detect::DetectState::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            scripts_open: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            script_state: scripts::ScriptsState::default(),
+            counters: MeterCounters::default(),
+            combined_history: VecDeque::with_capacity(MEM_DEPTH_DEFAULT + 1),
+            plot_dock_state: DockState::new(ui::PlotTab::ALL.to_vec()),
+            torn_off_tabs: vec![],
             mode_display_settings: HashMap::default(),
         }
     }
@@ -447,9 +775,7 @@ impl MyApp {
 
         fonts.font_data.insert(
             "B612Mono-Bold".to_owned(),
-            Arc::new(FontData::from_static(include_bytes!(
-                "../../assets/fonts/B612Mono-Bold.ttf"
-            ))),
+            Arc::new(FontData::from_static(B612_MONO_BOLD)),
         );
 
         let mut newfam = BTreeMap::new();
@@ -478,6 +804,34 @@ impl MyApp {
         app
     }
 
+    fn meter_shared(&self) -> MeterShared {
+        MeterShared {
+            device: self.device.clone(),
+            poll_ready: self.poll_ready.clone(),
+            refresh_requested: self.refresh_requested.clone(),
+            value_debug: self.value_debug_shared.clone(),
+            poll_interval: self.poll_interval_shared.clone(),
+            timeouts: self.counters.timeouts.clone(),
+            link_lost: self.link_lost_shared.clone(),
+        }
+    }
+
+    /// Take over the channels of a freshly spawned task for the main meter.
+    fn adopt_link(&mut self, link: MeterLink) {
+        self.counters.connects += 1;
+        self.serial_rx = link.data_rx;
+        self.serial_tx = link.cmd_tx;
+        self.mode_rx = link.mode_rx;
+        self.status_rx = link.status_rx;
+        self.passthrough_tx = link.passthrough_tx;
+        self.shutdown_tx = link.shutdown_tx;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.victor_86bcd_rx = link.live_rx;
+            self.victor_86bcd_capture_tx = link.capture_tx;
+        }
+    }
+
     fn queue_scpi(&mut self, cmd: impl Into<String>, record: bool) {
         let cmd = ensure_newline(&cmd.into());
         if cmd.trim().is_empty() {
@@ -515,6 +869,13 @@ impl MyApp {
         }
     }
 
+    /// Sound the meter's beeper. Read-only meters cannot be commanded.
+    fn beep_meter(&mut self) {
+        if !self.is_read_only() {
+            self.queue_scpi("SYST:BEEP:IMM", false);
+        }
+    }
+
     fn run_macro_body(&mut self, body: &str, record: bool) {
         let parsed = parse_macro_body(body);
         if self.value_debug && !parsed.skipped_queries.is_empty() {
@@ -629,19 +990,14 @@ impl MyApp {
         for cmd in bootstrap {
             self.queue_scpi(cmd, false);
         }
-        let matching: Vec<String> = self
+        self.apply_profile_setup();
+        // Started one after another by `macro_run_tick`, in list order
+        self.macro_queue = self
             .scpi_macros
             .iter()
             .filter(|m| m.run_on_connect && m.applies_to.matches(idn))
-            .map(|m| m.body.clone())
+            .map(|m| (m.name.clone(), m.body.clone()))
             .collect();
-        for body in matching {
-            let parsed = parse_macro_body(&body);
-            for cmd in &parsed.commands {
-                self.queue_scpi(cmd.clone(), false);
-            }
-            self.apply_scpi_hints(&parsed.commands);
-        }
         self.request_ui_refresh();
         self.poll_ready.store(true, Ordering::SeqCst);
     }
@@ -719,6 +1075,7 @@ impl MyApp {
         self.curr_unit = unit.unwrap_or(mode.default_unit()).to_owned();
         self.values = VecDeque::with_capacity(self.mem_depth);
         self.hist_values = VecDeque::with_capacity(self.hist_mem_depth);
+        self.hist_times = VecDeque::with_capacity(self.hist_mem_depth);
         self.rangecmd = if self.is_read_only() {
             None
         } else {
@@ -755,32 +1112,17 @@ impl MyApp {
 
     // Method to handle disconnection
     fn disconnect(&mut self) {
-        if let Some(shutdown_tx) = self.shutdown_tx.take() {
-            let _ = shutdown_tx.send(()); // Signal the serial task to shut down
-        }
-        self.serial_tx = None; // Drop sender to stop sending commands
-        self.serial_rx = None; // Drop receiver to stop receiving measurements
-        self.mode_rx = None; // Drop mode receiver
-        self.status_rx = None;
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            self.victor_86bcd_rx = None;
-            self.victor_lcd_display.clear();
-            self.victor_86bcd_capture_tx = None;
-        }
-        self.serial = None; // Clear serial port
+        self.drop_main_link();
         self.connection_state = ConnectionState::Disconnected;
         self.connection_error = None; // Clear any previous error
-        let mut device = self.device.lock().unwrap();
-        *device = "".to_owned(); // Clear device string
-        drop(device);
-        self.applied_idn = None;
-        self.poll_ready.store(false, Ordering::SeqCst);
-        self.refresh_requested.store(false, Ordering::SeqCst);
+        self.reconnect = connection::ReconnectState::default();
         self.macro_recording = false;
+        self.display_hold = None;
+        self.rel_reference = None;
         self.curr_meas = f64::NAN; // Reset measurement
         self.values.clear(); // Clear graph data
         self.hist_values.clear(); // Clear histogram data
+        self.hist_times.clear();
         self.meas_count = 0; // Reset measurement counter
     }
 
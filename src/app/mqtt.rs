@@ -0,0 +1,459 @@
+//! Optional MQTT publisher: readings as JSON, retained connection status and
+//! IDN, and an optional command topic for mode / range / macro control.
+//!
+//! Like the HTTP API, the client task only exchanges messages with the UI:
+//! `mqtt_tick` hands it readings to publish and applies the commands it
+//! receives through the same path as the HTTP endpoints.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use egui::{Color32, RichText};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::mpsc,
+};
+
+use crate::http_api::ApiCommand;
+use crate::mqtt::{self, ConnectOptions, Packet, Will};
+
+const KEEP_ALIVE_S: u16 = 30;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Retained on the status topic by us on shutdown, or by the broker as our
+/// last will when the connection drops.
+const OFFLINE_STATUS: &str = r#"{"online":false,"connected":false}"#;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttSettings {
+    pub enabled: bool,     // Persistent, off unless turned on in the settings
+    pub host: String,      // Persistent broker host
+    pub port: u16,         // Persistent broker port
+    pub client_id: String, // Persistent
+    pub username: String,  // Persistent, empty for anonymous
+    #[serde(skip)]
+    pub password: String, // Not saved with the settings, entered each session
+    pub base_topic: String, // Persistent prefix of all topics
+    pub interval_ms: u64,  // Persistent minimum time between readings, 0 publishes each one
+    pub commands: bool,    // Persistent, subscribe to <base>/command
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".to_owned(),
+            port: 1883,
+            client_id: "rusty_meter".to_owned(),
+            username: String::new(),
+            password: String::new(),
+            base_topic: "rusty_meter".to_owned(),
+            interval_ms: 0,
+            commands: false,
+        }
+    }
+}
+
+impl MqttSettings {
+    fn topic(&self, leaf: &str) -> String {
+        format!("{}/{leaf}", self.base_topic.trim_end_matches('/'))
+    }
+}
+
+#[derive(Clone)]
+struct Outgoing {
+    topic: String,
+    payload: String,
+    retain: bool,
+}
+
+#[derive(Clone)]
+enum ClientStatus {
+    Connecting,
+    Connected,
+    Failed(String),
+}
+
+/// A running client task. Dropping it publishes the offline status and
+/// disconnects.
+pub struct MqttClient {
+    settings: MqttSettings,
+    status: Arc<Mutex<ClientStatus>>,
+    out_tx: mpsc::Sender<Outgoing>,
+    cmd_rx: mpsc::Receiver<Result<ApiCommand, String>>,
+    last_status: Option<String>,
+    last_idn: Option<String>,
+    last_publish: f64, // Reading time of the last published reading
+}
+
+impl MqttClient {
+    fn start(settings: &MqttSettings, ctx: &egui::Context) -> Self {
+        let status = Arc::new(Mutex::new(ClientStatus::Connecting));
+        let (out_tx, out_rx) = mpsc::channel(256);
+        let (cmd_tx, cmd_rx) = mpsc::channel(16);
+        tokio::spawn(run_client(
+            settings.clone(),
+            status.clone(),
+            out_rx,
+            cmd_tx,
+            ctx.clone(),
+        ));
+        Self {
+            settings: settings.clone(),
+            status,
+            out_tx,
+            cmd_rx,
+            last_status: None,
+            last_idn: None,
+            last_publish: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Queue a message. Returns false when the queue is full, in which case
+    /// readings are simply dropped (decimated) and retained state retried.
+    fn send(&self, topic: String, payload: String, retain: bool) -> bool {
+        self.out_tx
+            .try_send(Outgoing {
+                topic,
+                payload,
+                retain,
+            })
+            .is_ok()
+    }
+}
+
+/// Keep the latest retained message per topic for replay after reconnects.
+fn remember_retained(retained: &mut Vec<Outgoing>, msg: &Outgoing) {
+    if msg.retain {
+        retained.retain(|m| m.topic != msg.topic);
+        retained.push(msg.clone());
+    }
+}
+
+async fn run_client(
+    settings: MqttSettings,
+    status: Arc<Mutex<ClientStatus>>,
+    mut out_rx: mpsc::Receiver<Outgoing>,
+    cmd_tx: mpsc::Sender<Result<ApiCommand, String>>,
+    ctx: egui::Context,
+) {
+    let mut retained = Vec::new();
+    loop {
+        *status.lock().unwrap() = ClientStatus::Connecting;
+        match session(
+            &settings,
+            &mut out_rx,
+            &cmd_tx,
+            &mut retained,
+            &status,
+            &ctx,
+        )
+        .await
+        {
+            Ok(()) => return,
+            Err(e) => {
+                *status.lock().unwrap() = ClientStatus::Failed(e);
+                ctx.request_repaint();
+            }
+        }
+        // Wait before reconnecting, keeping retained state current; stop
+        // if the UI drops the client meanwhile
+        let delay = tokio::time::sleep(RECONNECT_DELAY);
+        tokio::pin!(delay);
+        loop {
+            tokio::select! {
+                _ = &mut delay => break,
+                msg = out_rx.recv() => match msg {
+                    Some(msg) => remember_retained(&mut retained, &msg),
+                    None => return,
+                },
+            }
+        }
+    }
+}
+
+async fn read_packet(stream: &mut TcpStream, buf: &mut Vec<u8>) -> Result<Packet, String> {
+    let mut chunk = [0u8; 1024];
+    loop {
+        if let Some((packet, used)) = mqtt::decode(buf)? {
+            buf.drain(..used);
+            return Ok(packet);
+        }
+        match stream.read(&mut chunk).await {
+            Ok(0) => return Err("Broker closed the connection".to_owned()),
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            Err(e) => return Err(format!("Failed to read from broker: {e}")),
+        }
+    }
+}
+
+/// One broker connection. `Ok` when the UI dropped the client, `Err` when
+/// the connection failed and should be retried.
+async fn session(
+    settings: &MqttSettings,
+    out_rx: &mut mpsc::Receiver<Outgoing>,
+    cmd_tx: &mpsc::Sender<Result<ApiCommand, String>>,
+    retained: &mut Vec<Outgoing>,
+    status: &Mutex<ClientStatus>,
+    ctx: &egui::Context,
+) -> Result<(), String> {
+    let address = format!("{}:{}", settings.host, settings.port);
+    let mut stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&address))
+        .await
+        .map_err(|_| format!("Failed to connect to {address}: timed out"))?
+        .map_err(|e| format!("Failed to connect to {address}: {e}"))?;
+    let write_err = |e: std::io::Error| format!("Failed to write to broker: {e}");
+    let status_topic = settings.topic("status");
+    let command_topic = settings.topic("command");
+    let connect = mqtt::connect(&ConnectOptions {
+        client_id: &settings.client_id,
+        keep_alive_s: KEEP_ALIVE_S,
+        username: Some(settings.username.as_str()).filter(|u| !u.is_empty()),
+        password: Some(settings.password.as_str()).filter(|p| !p.is_empty()),
+        will: Some(Will {
+            topic: &status_topic,
+            payload: OFFLINE_STATUS.as_bytes(),
+            retain: true,
+        }),
+    });
+    stream.write_all(&connect).await.map_err(write_err)?;
+    let mut buf = Vec::new();
+    match tokio::time::timeout(CONNECT_TIMEOUT, read_packet(&mut stream, &mut buf))
+        .await
+        .map_err(|_| "Broker did not answer CONNECT".to_owned())??
+    {
+        Packet::ConnAck { return_code: 0 } => {}
+        Packet::ConnAck { return_code } => {
+            return Err(format!(
+                "Broker refused the connection: {}",
+                mqtt::connack_error(return_code)
+            ));
+        }
+        _ => return Err("Unexpected reply to CONNECT".to_owned()),
+    }
+    if settings.commands {
+        stream
+            .write_all(&mqtt::subscribe(1, &command_topic))
+            .await
+            .map_err(write_err)?;
+    }
+    for msg in retained.iter() {
+        stream
+            .write_all(&mqtt::publish(&msg.topic, msg.payload.as_bytes(), true))
+            .await
+            .map_err(write_err)?;
+    }
+    *status.lock().unwrap() = ClientStatus::Connected;
+    ctx.request_repaint();
+
+    let period = Duration::from_secs(KEEP_ALIVE_S as u64 / 2);
+    let mut ping = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    let mut chunk = [0u8; 1024];
+    loop {
+        tokio::select! {
+            msg = out_rx.recv() => {
+                let Some(msg) = msg else {
+                    // A clean DISCONNECT discards the will, so go offline first
+                    let offline = mqtt::publish(&status_topic, OFFLINE_STATUS.as_bytes(), true);
+                    let _ = stream.write_all(&offline).await;
+                    let _ = stream.write_all(&mqtt::DISCONNECT).await;
+                    return Ok(());
+                };
+                remember_retained(retained, &msg);
+                stream
+                    .write_all(&mqtt::publish(&msg.topic, msg.payload.as_bytes(), msg.retain))
+                    .await
+                    .map_err(write_err)?;
+            }
+            read = stream.read(&mut chunk) => {
+                match read {
+                    Ok(0) => return Err("Broker closed the connection".to_owned()),
+                    Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                    Err(e) => return Err(format!("Failed to read from broker: {e}")),
+                }
+                while let Some((packet, used)) = mqtt::decode(&buf)? {
+                    buf.drain(..used);
+                    match packet {
+                        Packet::Publish { topic, payload } if topic == command_topic => {
+                            let _ = cmd_tx.try_send(mqtt::parse_command(&payload));
+                            ctx.request_repaint();
+                        }
+                        Packet::SubAck { granted: false } => {
+                            return Err(format!(
+                                "Broker refused the subscription to {command_topic}"
+                            ));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ = ping.tick() => {
+                stream.write_all(&mqtt::PINGREQ).await.map_err(write_err)?;
+            }
+        }
+    }
+}
+
+impl super::MyApp {
+    /// Start, stop or restart the client to match the settings, publish new
+    /// readings and status changes, and apply received commands.
+    pub fn mqtt_tick(&mut self, ctx: &egui::Context, now: f64) {
+        if !self.mqtt.enabled {
+            self.mqtt_client = None;
+            return;
+        }
+        if self
+            .mqtt_client
+            .as_ref()
+            .is_none_or(|c| c.settings != self.mqtt)
+        {
+            self.mqtt_client = Some(MqttClient::start(&self.mqtt, ctx));
+        }
+        let connected = self.connection_state == super::ConnectionState::Connected;
+        let device = self.device.lock().unwrap().clone();
+        let status = json!({
+            "online": true,
+            "connected": connected,
+            "connection": format!("{:?}", self.connection_type),
+        })
+        .to_string();
+        // Every reading the meter task delivered this frame, stamped with
+        // the time it arrived rather than the frame time
+        let wall_now = chrono::Utc::now();
+        let readings: Vec<(f64, String)> = self
+            .frame_readings
+            .iter()
+            .map(|r| {
+                let overload = r.value == crate::helpers::METER_OVERLOAD_VALUE;
+                let age_ms = ((now - r.time) * 1000.0).max(0.0) as i64;
+                let reading = json!({
+                    "value": (r.value.is_finite() && !overload).then_some(r.value),
+                    "overload": overload,
+                    "unit": self.curr_unit,
+                    "mode": self.metermode.button_label(),
+                    "timestamp": (wall_now - chrono::Duration::milliseconds(age_ms)).to_rfc3339(),
+                });
+                (r.time, reading.to_string())
+            })
+            .collect();
+        let Some(client) = self.mqtt_client.as_mut() else {
+            return;
+        };
+        let settings = &client.settings;
+        if client.last_status.as_ref() != Some(&status)
+            && client.send(settings.topic("status"), status.clone(), true)
+        {
+            client.last_status = Some(status);
+        }
+        if client.last_idn.as_ref() != Some(&device)
+            && client.send(settings.topic("idn"), device.clone(), true)
+        {
+            client.last_idn = Some(device);
+        }
+        let interval = client.settings.interval_ms as f64 / 1000.0;
+        if connected {
+            for (time, reading) in readings {
+                if time - client.last_publish >= interval {
+                    // A full queue drops the reading, the next one goes out instead
+                    client.send(settings.topic("reading"), reading, false);
+                    client.last_publish = time;
+                }
+            }
+        }
+
+        let mut commands = Vec::new();
+        while let Ok(command) = client.cmd_rx.try_recv() {
+            commands.push(command);
+        }
+        let result_topic = client.settings.topic("command/result");
+        for command in commands {
+            let result = command.and_then(|command| self.apply_api_command(command));
+            let payload = match result {
+                Ok(message) => json!({ "ok": true, "message": message }),
+                Err(e) => json!({ "ok": false, "error": e }),
+            };
+            if let Some(client) = &self.mqtt_client {
+                client.send(result_topic.clone(), payload.to_string(), false);
+            }
+        }
+    }
+
+    pub fn show_mqtt_settings(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.label("MQTT:");
+        ui.checkbox(&mut self.mqtt.enabled, "Publish readings to an MQTT broker");
+        ui.add_enabled_ui(!self.mqtt.enabled, |ui| {
+            egui::Grid::new("mqtt_settings")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Broker:");
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.mqtt.host)
+                                .desired_width(160.0)
+                                .hint_text("localhost"),
+                        );
+                        ui.add(egui::DragValue::new(&mut self.mqtt.port).range(1..=65535));
+                    });
+                    ui.end_row();
+                    ui.label("Client ID:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.mqtt.client_id).desired_width(160.0),
+                    );
+                    ui.end_row();
+                    ui.label("User name:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.mqtt.username)
+                            .desired_width(160.0)
+                            .hint_text("anonymous"),
+                    );
+                    ui.end_row();
+                    ui.label("Password:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.mqtt.password)
+                            .desired_width(160.0)
+                            .password(true),
+                    )
+                    .on_hover_text("Not saved, enter it again after a restart");
+                    ui.end_row();
+                    ui.label("Base topic:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.mqtt.base_topic).desired_width(160.0),
+                    );
+                    ui.end_row();
+                    ui.label("Min. interval:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.mqtt.interval_ms)
+                            .range(0..=3_600_000)
+                            .suffix(" ms"),
+                    )
+                    .on_hover_text("0 publishes every reading");
+                    ui.end_row();
+                });
+            let command_topic = self.mqtt.topic("command");
+            ui.checkbox(
+                &mut self.mqtt.commands,
+                format!("Accept commands on {command_topic} (SCPI meters)"),
+            );
+        });
+        let Some(client) = &self.mqtt_client else {
+            return;
+        };
+        match &*client.status.lock().unwrap() {
+            ClientStatus::Connecting => ui.label("Connecting..."),
+            ClientStatus::Connected => ui.label(format!(
+                "Connected, publishing to {}/#",
+                client.settings.base_topic.trim_end_matches('/')
+            )),
+            ClientStatus::Failed(e) => {
+                ui.label(RichText::new(format!("{e}, retrying")).color(Color32::RED))
+            }
+        };
+    }
+}
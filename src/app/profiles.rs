@@ -0,0 +1,451 @@
+//! Named bench profiles: connection, line settings, meter setup, graph and
+//! recorder settings and the connect macros, switched from the top bar.
+//!
+//! Mode and range need a connected SCPI meter. When a profile is loaded
+//! while disconnected they are kept and sent after the connect bootstrap.
+
+use egui::{ComboBox, Context, RichText, TextEdit, Ui, Window};
+use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
+
+use crate::multimeter::{GenScpi, MeterMode};
+use crate::profile::{export_profiles, parse_profile_file, unique_name};
+use crate::serial_line::SerialLine;
+
+use super::graph::GraphConfig;
+use super::macros::sanitize_file_name;
+use super::{ConnectionState, ConnectionType, RecordingFormat, RecordingMode, TimestampFormat};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    pub connection_type: ConnectionType,
+    pub serial_port: String,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub hid_device_path: String,
+    pub serial_line: SerialLine,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub victor_line_override: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub victor_86bcd_line: SerialLine,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub victor_86e_line: SerialLine,
+    pub mode: MeterMode,
+    /// Range as labelled in the range selector, e.g. `5V`; empty for auto.
+    pub range: String,
+    /// Rate as labelled in the rate selector, e.g. `Fast`.
+    pub rate: String,
+    pub beeper_enabled: bool,
+    pub cont_threshold: u32,
+    pub diod_threshold: f32,
+    pub poll_interval_ms: u64,
+    pub mem_depth: usize,
+    pub hist_mem_depth: usize,
+    pub graph_update_interval_ms: u64,
+    pub reverse_graph: bool,
+    pub graph_config: GraphConfig,
+    pub recording_format: RecordingFormat,
+    pub recording_file_path: String,
+    pub recording_mode: RecordingMode,
+    pub recording_interval_ms: u64,
+    pub recording_timestamp_format: TimestampFormat,
+    /// IDs of the macros that run on connect.
+    pub connect_macros: Vec<String>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            connection_type: ConnectionType::default(),
+            serial_port: String::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            hid_device_path: String::new(),
+            serial_line: SerialLine::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            victor_line_override: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            victor_86bcd_line: super::VICTOR_86BCD_LINE,
+            #[cfg(not(target_arch = "wasm32"))]
+            victor_86e_line: super::VICTOR_86E_LINE,
+            mode: MeterMode::Vdc,
+            range: String::new(),
+            rate: String::new(),
+            beeper_enabled: super::BEEPER_ENABLED_DEFAULT,
+            cont_threshold: super::CONT_THRESHOLD_DEFAULT,
+            diod_threshold: super::DIOD_THRESHOLD_DEFAULT,
+            poll_interval_ms: super::POLL_INTERVAL_MS_DEFAULT,
+            mem_depth: super::MEM_DEPTH_DEFAULT,
+            hist_mem_depth: super::HIST_MEM_DEPTH_DEFAULT,
+            graph_update_interval_ms: super::GRAPH_UPDATE_INTERVAL_MS_DEFAULT,
+            reverse_graph: false,
+            graph_config: GraphConfig::default(),
+            recording_format: RecordingFormat::Csv,
+            recording_file_path: String::new(),
+            recording_mode: RecordingMode::FixedInterval,
+            recording_interval_ms: super::RECORDING_INTERVAL_MS_DEFAULT,
+            recording_timestamp_format: TimestampFormat::Rfc3339,
+            connect_macros: vec![],
+        }
+    }
+}
+
+impl Profile {
+    /// Whether loading `other` means opening a different port or device.
+    fn same_link(&self, other: &Self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.hid_device_path != other.hid_device_path
+            || self.victor_line_override != other.victor_line_override
+            || self.victor_86bcd_line != other.victor_86bcd_line
+            || self.victor_86e_line != other.victor_86e_line
+        {
+            return false;
+        }
+        self.connection_type == other.connection_type
+            && self.serial_port == other.serial_port
+            && self.serial_line == other.serial_line
+    }
+}
+
+impl super::MyApp {
+    fn capture_profile(&self, name: &str) -> Profile {
+        Profile {
+            name: name.to_owned(),
+            connection_type: self.connection_type,
+            serial_port: self.serial_port.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            hid_device_path: self.hid_device_path.clone(),
+            serial_line: self.serial_line.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            victor_line_override: self.victor_line_override,
+            #[cfg(not(target_arch = "wasm32"))]
+            victor_86bcd_line: self.victor_86bcd_line.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            victor_86e_line: self.victor_86e_line.clone(),
+            mode: self.metermode,
+            range: match &self.rangecmd {
+                Some(rangecmd) if !self.meter_auto_range => {
+                    rangecmd.get_opt(self.curr_range).0.to_owned()
+                }
+                _ => String::new(),
+            },
+            rate: self.ratecmd.get_opt(self.curr_rate).0.to_owned(),
+            beeper_enabled: self.beeper_enabled,
+            cont_threshold: self.cont_threshold,
+            diod_threshold: self.diod_threshold,
+            poll_interval_ms: self.poll_interval_ms,
+            mem_depth: self.mem_depth,
+            hist_mem_depth: self.hist_mem_depth,
+            graph_update_interval_ms: self.graph_update_interval_ms,
+            reverse_graph: self.reverse_graph,
+            graph_config: self.graph_config,
+            recording_format: self.recording_format.clone(),
+            recording_file_path: self.recording_file_path.clone(),
+            recording_mode: self.recording_mode.clone(),
+            recording_interval_ms: self.recording_interval_ms,
+            recording_timestamp_format: self.recording_timestamp_format.clone(),
+            connect_macros: self
+                .scpi_macros
+                .iter()
+                .filter(|m| m.run_on_connect)
+                .map(|m| m.id.clone())
+                .collect(),
+        }
+    }
+
+    /// Switch to a profile. A connected meter on another port is
+    /// disconnected and the profile's connection opened instead.
+    fn apply_profile(&mut self, profile: &Profile) {
+        let was_connected = self.connection_state != ConnectionState::Disconnected;
+        let relink = was_connected && !self.capture_profile("").same_link(profile);
+        if relink {
+            self.disconnect();
+        }
+
+        self.connection_type = profile.connection_type;
+        self.serial_port = profile.serial_port.clone();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.hid_device_path = profile.hid_device_path.clone();
+            self.victor_line_override = profile.victor_line_override;
+            self.victor_86bcd_line = profile.victor_86bcd_line.clone();
+            self.victor_86e_line = profile.victor_86e_line.clone();
+        }
+        self.serial_line = profile.serial_line.clone();
+        let beeper_changed = self.beeper_enabled != profile.beeper_enabled
+            || self.cont_threshold != profile.cont_threshold
+            || self.diod_threshold != profile.diod_threshold;
+        self.beeper_enabled = profile.beeper_enabled;
+        self.cont_threshold = profile.cont_threshold;
+        self.diod_threshold = profile.diod_threshold;
+        let rate = (0..self.ratecmd.len()).find(|&i| self.ratecmd.get_opt(i).0 == profile.rate);
+        let rate_changed = rate.is_some_and(|idx| idx != self.curr_rate);
+        if let Some(idx) = rate {
+            self.curr_rate = idx;
+        }
+
+        self.poll_interval_ms = profile.poll_interval_ms.max(1);
+        *self.poll_interval_shared.lock().unwrap() = self.poll_interval_ms;
+        self.mem_depth = profile.mem_depth.clamp(10, self.mem_depth_max);
+        while self.values.len() > self.mem_depth {
+            self.values.pop_front();
+        }
+        self.hist_mem_depth = profile.hist_mem_depth.clamp(100, self.hist_mem_depth_max);
+        self.trim_histogram();
+        self.graph_update_interval_ms = profile
+            .graph_update_interval_ms
+            .clamp(10, self.graph_update_interval_max);
+        self.reverse_graph = profile.reverse_graph;
+        self.graph_config = profile.graph_config;
+        self.graph_config.num_bins = self.graph_config.num_bins.min(self.graph_config.max_bins);
+
+        // A running recording keeps its file and format
+        if !self.recording_active {
+            self.recording_format = profile.recording_format.clone();
+            self.recording_file_path = profile.recording_file_path.clone();
+            self.recording_mode = profile.recording_mode.clone();
+            self.recording_interval_ms = profile.recording_interval_ms;
+            self.recording_timestamp_format = profile.recording_timestamp_format.clone();
+        }
+        for m in &mut self.scpi_macros {
+            m.run_on_connect = profile.connect_macros.contains(&m.id);
+        }
+
+        self.active_profile = profile.name.clone();
+        self.profile_setup = Some((profile.mode, profile.range.clone()));
+        if relink {
+            self.connect_main();
+        } else if self.scpi_macros_on_main() && self.applied_idn.is_some() {
+            if rate_changed {
+                self.confstring = self
+                    .ratecmd
+                    .gen_scpi(self.ratecmd.get_opt(self.curr_rate).0);
+                self.queue_scpi(self.confstring.clone(), false);
+            }
+            if beeper_changed {
+                let family =
+                    crate::scpi_macro::classify_idn(self.applied_idn.as_deref().unwrap_or(""));
+                let settings = self.bootstrap_settings();
+                for cmd in crate::scpi_macro::beeper_threshold_commands(family, &settings) {
+                    self.queue_scpi(cmd, false);
+                }
+            }
+            self.apply_profile_setup();
+        }
+    }
+
+    /// Send the mode and range of the loaded profile, once the meter is
+    /// identified. Read-only meters keep whatever the dial says.
+    pub(super) fn apply_profile_setup(&mut self) {
+        let Some((mode, range)) = self.profile_setup.take() else {
+            return;
+        };
+        if self.is_read_only() {
+            return;
+        }
+        self.set_mode(mode);
+        let Some(rangecmd) = &self.rangecmd else {
+            return;
+        };
+        let Some(idx) = (0..rangecmd.len()).find(|&i| rangecmd.get_opt(i).0 == range) else {
+            return;
+        };
+        self.curr_range = idx;
+        self.meter_auto_range = idx == 0;
+        self.confstring = rangecmd.gen_scpi(rangecmd.get_opt(idx).0);
+        self.queue_scpi(self.confstring.clone(), false);
+    }
+
+    /// Top bar profile picker.
+    pub fn show_profile_switcher(&mut self, ui: &mut Ui) {
+        let selected = if self.active_profile.is_empty() {
+            "No profile"
+        } else {
+            self.active_profile.as_str()
+        };
+        let mut chosen = None;
+        ComboBox::from_id_salt("profile_switcher")
+            .selected_text(format!("Profile: {selected}"))
+            .show_ui(ui, |ui| {
+                for (idx, profile) in self.profiles.iter().enumerate() {
+                    let active = profile.name == self.active_profile;
+                    if ui.selectable_label(active, &profile.name).clicked() {
+                        chosen = Some(idx);
+                    }
+                }
+                if !self.profiles.is_empty() {
+                    ui.separator();
+                }
+                if ui.button("Manage profiles…").clicked() {
+                    self.profiles_open = true;
+                }
+            });
+        if let Some(idx) = chosen {
+            let profile = self.profiles[idx].clone();
+            self.apply_profile(&profile);
+        }
+    }
+
+    pub fn show_profiles(&mut self, ctx: &Context) {
+        if !self.profiles_open {
+            return;
+        }
+        let mut open = true;
+        let mut load = None;
+        let mut update = None;
+        let mut export = None;
+        let mut delete = None;
+        Window::new("Profiles")
+            .open(&mut open)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.label(
+                    "A profile keeps the connection, line settings, mode, range, rate, \
+                     thresholds, graph and recorder settings and the connect macros.",
+                );
+                ui.horizontal(|ui| {
+                    ui.add(
+                        TextEdit::singleline(&mut self.profile_name_input)
+                            .desired_width(200.0)
+                            .hint_text("Profile name"),
+                    );
+                    if ui.button("Save current").clicked() {
+                        let name = unique_name(
+                            self.profiles.iter().map(|p| p.name.as_str()),
+                            &self.profile_name_input,
+                        );
+                        self.profiles.push(self.capture_profile(&name));
+                        self.active_profile = name;
+                        self.profile_name_input.clear();
+                    }
+                });
+                if !self.profiles.is_empty() {
+                    ui.separator();
+                    egui::Grid::new("profile_list")
+                        .num_columns(5)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (idx, profile) in self.profiles.iter().enumerate() {
+                                let name = RichText::new(&profile.name);
+                                if profile.name == self.active_profile {
+                                    ui.label(name.strong());
+                                } else {
+                                    ui.label(name);
+                                }
+                                if ui.button("Load").clicked() {
+                                    load = Some(idx);
+                                }
+                                if ui
+                                    .button("Update")
+                                    .on_hover_text("Overwrite with the current settings")
+                                    .clicked()
+                                {
+                                    update = Some(idx);
+                                }
+                                if ui.button("Export…").clicked() {
+                                    export = Some(idx);
+                                }
+                                if ui.button("Delete").clicked() {
+                                    delete = Some(idx);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Import…").clicked() {
+                        self.import_profiles_from_dialog();
+                    }
+                    if ui
+                        .add_enabled(!self.profiles.is_empty(), egui::Button::new("Export all…"))
+                        .clicked()
+                    {
+                        let profiles = self.profiles.clone();
+                        self.export_profiles_to_file(&profiles);
+                    }
+                });
+                if let Some(msg) = &self.profile_io_message {
+                    ui.label(msg);
+                }
+            });
+        if let Some(idx) = load {
+            let profile = self.profiles[idx].clone();
+            self.apply_profile(&profile);
+        }
+        if let Some(idx) = update {
+            let name = self.profiles[idx].name.clone();
+            self.profiles[idx] = self.capture_profile(&name);
+            self.active_profile = name;
+        }
+        if let Some(idx) = export {
+            let profile = self.profiles[idx].clone();
+            self.export_profiles_to_file(&[profile]);
+        }
+        if let Some(idx) = delete {
+            let removed = self.profiles.remove(idx);
+            if removed.name == self.active_profile {
+                self.active_profile.clear();
+            }
+        }
+        if !open {
+            self.profiles_open = false;
+            self.profile_io_message = None;
+        }
+    }
+
+    fn export_profiles_to_file(&mut self, profiles: &[Profile]) {
+        let file_name = match profiles {
+            [p] => format!("{}.json", sanitize_file_name(&p.name, "profile")),
+            _ => "profiles.json".to_owned(),
+        };
+        let Some(path) = FileDialog::new()
+            .add_filter("Profile file", &["json"])
+            .set_file_name(file_name)
+            .save_file()
+        else {
+            return;
+        };
+        self.profile_io_message = Some(match std::fs::write(&path, export_profiles(profiles)) {
+            Ok(()) => format!(
+                "Exported {} profile(s) to {}",
+                profiles.len(),
+                path.display()
+            ),
+            Err(e) => format!("Failed to write profile file: {e}"),
+        });
+    }
+
+    /// Imported profiles are added; clashing names get a number appended.
+    fn import_profiles_from_dialog(&mut self) {
+        let Some(paths) = FileDialog::new()
+            .add_filter("Profile file", &["json"])
+            .pick_files()
+        else {
+            return;
+        };
+        let mut messages = Vec::new();
+        for path in paths {
+            let name = path.display();
+            let imported = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read file: {e}"))
+                .and_then(|text| parse_profile_file::<Profile>(&text));
+            match imported {
+                Ok(imported) => {
+                    let count = imported.len();
+                    for mut profile in imported {
+                        profile.name = unique_name(
+                            self.profiles.iter().map(|p| p.name.as_str()),
+                            &profile.name,
+                        );
+                        self.profiles.push(profile);
+                    }
+                    messages.push(format!("{name}: imported {count} profile(s)"));
+                }
+                Err(e) => messages.push(format!("{name}: {e}")),
+            }
+        }
+        self.profile_io_message = Some(messages.join("\n"));
+    }
+}
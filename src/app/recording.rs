@@ -206,6 +206,8 @@ impl super::MyApp {
                                 .column(Column::initial(200.0).at_least(100.0))
                                 .column(Column::initial(100.0).at_least(50.0))
                                 .column(Column::initial(100.0).at_least(50.0))
+                                .column(Column::initial(100.0).at_least(50.0))
+                                .column(Column::remainder().at_least(100.0))
                                 .header(20.0, |mut header| {
                                     header.col(|ui| {
                                         ui.label(
@@ -228,6 +230,17 @@ impl super::MyApp {
                                             RichText::new("Value").font(FontId::proportional(16.0)),
                                         );
                                     });
+                                    header.col(|ui| {
+                                        ui.label(
+                                            RichText::new("Label").font(FontId::proportional(16.0)),
+                                        );
+                                    });
+                                    header.col(|ui| {
+                                        ui.label(
+                                            RichText::new("Other meters")
+                                                .font(FontId::proportional(16.0)),
+                                        );
+                                    });
                                 })
                                 .body(|mut body| {
                                     for record in self.recording_data.iter() {
@@ -252,6 +265,22 @@ impl super::MyApp {
                                             row.col(|ui| {
                                                 ui.label(format!("{:.4}", record.value));
                                             });
+                                            row.col(|ui| {
+                                                ui.label(&record.label);
+                                            });
+                                            row.col(|ui| {
+                                                let others: Vec<String> = record
+                                                    .channels
+                                                    .iter()
+                                                    .map(|c| {
+                                                        format!(
+                                                            "{}: {:.4} {}",
+                                                            c.name, c.value, c.unit
+                                                        )
+                                                    })
+                                                    .collect();
+                                                ui.label(others.join(", "));
+                                            });
                                         });
                                     }
                                 });
@@ -275,42 +304,97 @@ impl super::MyApp {
     }
 
     pub fn record_measurement(&mut self) {
-        if !self.curr_meas.is_nan() {
-            let index = self.recording_data.len(); // Assign index based on current length
-            self.recording_data.push(super::Record {
-                index,
-                timestamp: chrono::Utc::now(),
-                unit: self.curr_unit.clone(),
-                value: self.curr_meas,
-            });
+        if self.has_any_reading() {
+            self.record_value(self.curr_meas);
         }
     }
 
+    /// Append one entry with `value` as the main meter reading, e.g. a
+    /// latched auto-hold value.
+    pub fn record_value(&mut self, value: f64) {
+        let index = self.recording_data.len(); // Assign index based on current length
+        self.recording_data.push(super::Record {
+            index,
+            timestamp: chrono::Utc::now(),
+            unit: self.curr_unit.clone(),
+            value,
+            channels: self.extra_channel_values(),
+            label: String::new(),
+        });
+    }
+
+    /// Record a value logged by a macro, whether or not recording is active.
+    pub fn record_labelled(&mut self, label: &str, value: f64) {
+        self.record_value(value);
+        if let Some(record) = self.recording_data.last_mut() {
+            record.label = label.to_owned();
+        }
+    }
+
+    /// Names of all additional and derived channels appearing in the recording, in order of
+    /// first appearance. Each becomes a Unit/Value column pair on export.
+    fn recorded_channel_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for channel in self.recording_data.iter().flat_map(|r| &r.channels) {
+            if !names.contains(&channel.name) {
+                names.push(channel.name.clone());
+            }
+        }
+        names
+    }
+
     pub fn save_recording_data(&self) {
         if self.recording_data.is_empty() || self.recording_file_path.is_empty() {
             return;
         }
 
+        let channel_names = self.recorded_channel_names();
+        // Macro LOG statements label their records; plain recordings keep the old layout
+        let labelled = self.recording_data.iter().any(|r| !r.label.is_empty());
         match self.recording_format {
             super::RecordingFormat::Csv => {
                 let file =
                     File::create(&self.recording_file_path).expect("Failed to create CSV file");
                 let mut writer = WriterBuilder::new().from_writer(file);
+                let mut header: Vec<String> = ["Index", "Timestamp", "Unit", "Value"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect();
+                if labelled {
+                    header.push("Label".to_owned());
+                }
+                for name in &channel_names {
+                    header.push(format!("{name} Unit"));
+                    header.push(format!("{name} Value"));
+                }
                 writer
-                    .write_record(["Index", "Timestamp", "Unit", "Value"])
+                    .write_record(&header)
                     .expect("Failed to write CSV header");
                 for record in &self.recording_data {
                     let timestamp_str = match self.recording_timestamp_format {
                         super::TimestampFormat::Rfc3339 => record.timestamp.to_rfc3339(),
                         super::TimestampFormat::Unix => record.timestamp.timestamp().to_string(),
                     };
+                    let mut row = vec![
+                        record.index.to_string(),
+                        timestamp_str,
+                        record.unit.clone(),
+                        record.value.to_string(),
+                    ];
+                    if labelled {
+                        row.push(record.label.clone());
+                    }
+                    for name in &channel_names {
+                        match record.channel(name) {
+                            Some(channel) => {
+                                row.push(channel.unit.clone());
+                                row.push(channel.value.to_string());
+                            }
+                            None => row.extend([String::new(), String::new()]),
+                        }
+                    }
                     writer
-                        .write_record(&[
-                            record.index.to_string(),
-                            timestamp_str,
-                            record.unit.clone(),
-                            record.value.to_string(),
-                        ])
+                        .write_record(&row)
                         .expect("Failed to write CSV record");
                 }
                 writer.flush().expect("Failed to flush CSV writer");
@@ -330,12 +414,17 @@ impl super::MyApp {
                                 serde_json::Number::from(record.timestamp.timestamp()),
                             ),
                         };
-                        serde_json::json!({
+                        let mut value = serde_json::json!({
                             "index": record.index,
                             "timestamp": timestamp_value,
                             "unit": record.unit,
                             "value": record.value,
-                        })
+                            "channels": record.channels,
+                        });
+                        if !record.label.is_empty() {
+                            value["label"] = serde_json::Value::String(record.label.clone());
+                        }
+                        value
                     })
                     .collect();
                 serde_json::to_writer(file, &records).expect("Failed to write JSON data");
@@ -358,6 +447,21 @@ impl super::MyApp {
                 sheet
                     .write_string(0, 3, "Value", None)
                     .expect("Failed to write XLSX header");
+                let first_channel_col: u16 = if labelled { 5 } else { 4 };
+                if labelled {
+                    sheet
+                        .write_string(0, 4, "Label", None)
+                        .expect("Failed to write XLSX header");
+                }
+                for (n, name) in channel_names.iter().enumerate() {
+                    let col = first_channel_col + 2 * n as u16;
+                    sheet
+                        .write_string(0, col, &format!("{name} Unit"), None)
+                        .expect("Failed to write XLSX header");
+                    sheet
+                        .write_string(0, col + 1, &format!("{name} Value"), None)
+                        .expect("Failed to write XLSX header");
+                }
                 for (i, record) in self.recording_data.iter().enumerate() {
                     sheet
                         .write_number((i + 1) as u32, 0, record.index as f64, None)
@@ -372,9 +476,29 @@ impl super::MyApp {
                     sheet
                         .write_string((i + 1) as u32, 2, &record.unit, None)
                         .expect("Failed to write XLSX record");
-                    sheet
-                        .write_number((i + 1) as u32, 3, record.value, None)
-                        .expect("Failed to write XLSX record");
+                    // Gap markers carry no value; leave the cell empty
+                    if record.value.is_finite() {
+                        sheet
+                            .write_number((i + 1) as u32, 3, record.value, None)
+                            .expect("Failed to write XLSX record");
+                    }
+                    if labelled {
+                        sheet
+                            .write_string((i + 1) as u32, 4, &record.label, None)
+                            .expect("Failed to write XLSX record");
+                    }
+                    for (n, name) in channel_names.iter().enumerate() {
+                        let Some(channel) = record.channel(name) else {
+                            continue;
+                        };
+                        let col = first_channel_col + 2 * n as u16;
+                        sheet
+                            .write_string((i + 1) as u32, col, &channel.unit, None)
+                            .expect("Failed to write XLSX record");
+                        sheet
+                            .write_number((i + 1) as u32, col + 1, channel.value, None)
+                            .expect("Failed to write XLSX record");
+                    }
                 }
                 workbook.close().expect("Failed to close XLSX workbook");
             }
@@ -0,0 +1,248 @@
+//! Optional SCPI passthrough port, so other programs (test scripts, VISA
+//! clients with a raw socket resource) can use the meter while RustyMeter
+//! holds the serial port.
+//!
+//! Every client line goes straight into the SCPI task of the main meter: sets
+//! are queued with the UI commands, and a line with a query gets the next
+//! reply line once the task has the wire to itself. `MEAS?` polling carries on
+//! in between.
+
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+use std::time::Duration;
+
+use egui::{Color32, RichText};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, oneshot},
+};
+
+use super::http_api::ServerStatus;
+use super::serial::PassthroughCmd;
+use crate::scpi_macro::{ensure_newline, expects_reply};
+
+/// Longest accepted command line; longer lines close the connection.
+const MAX_LINE_BYTES: usize = 4096;
+/// How long a query waits for its turn on the wire plus the reply.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScpiServerSettings {
+    pub enabled: bool,        // Persistent, off unless turned on in the settings
+    pub bind_address: String, // Persistent, localhost unless configured otherwise
+    pub port: u16,            // Persistent
+}
+
+impl Default for ScpiServerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "127.0.0.1".to_owned(),
+            port: 5025,
+        }
+    }
+}
+
+type MeterInput = Arc<Mutex<Option<mpsc::Sender<PassthroughCmd>>>>;
+
+/// A running server. Dropping `shutdown_tx` stops the accept loop.
+pub struct ScpiServer {
+    settings: ScpiServerSettings,
+    status: Arc<Mutex<ServerStatus>>,
+    meter: MeterInput,
+    clients: Arc<AtomicUsize>,
+    _shutdown_tx: oneshot::Sender<()>,
+}
+
+impl ScpiServer {
+    fn start(settings: &ScpiServerSettings, ctx: &egui::Context) -> Self {
+        let status = Arc::new(Mutex::new(ServerStatus::Starting));
+        let meter = MeterInput::default();
+        let clients = Arc::new(AtomicUsize::new(0));
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::spawn(serve(
+            settings.clone(),
+            status.clone(),
+            meter.clone(),
+            clients.clone(),
+            ctx.clone(),
+            shutdown_rx,
+        ));
+        Self {
+            settings: settings.clone(),
+            status,
+            meter,
+            clients,
+            _shutdown_tx: shutdown_tx,
+        }
+    }
+}
+
+async fn serve(
+    settings: ScpiServerSettings,
+    status: Arc<Mutex<ServerStatus>>,
+    meter: MeterInput,
+    clients: Arc<AtomicUsize>,
+    ctx: egui::Context,
+    mut shutdown_rx: oneshot::Receiver<()>,
+) {
+    let address = settings.bind_address.as_str();
+    let listener = match TcpListener::bind((address, settings.port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            *status.lock().unwrap() =
+                ServerStatus::Failed(format!("Failed to bind {address}:{}: {e}", settings.port));
+            ctx.request_repaint();
+            return;
+        }
+    };
+    if let Ok(addr) = listener.local_addr() {
+        *status.lock().unwrap() = ServerStatus::Listening(addr);
+        ctx.request_repaint();
+    }
+    loop {
+        tokio::select! {
+            _ = &mut shutdown_rx => break,
+            accepted = listener.accept() => {
+                if let Ok((stream, _)) = accepted {
+                    let meter = meter.clone();
+                    let clients = clients.clone();
+                    let ctx = ctx.clone();
+                    tokio::spawn(async move {
+                        clients.fetch_add(1, Ordering::Relaxed);
+                        ctx.request_repaint();
+                        handle_client(stream, meter).await;
+                        clients.fetch_sub(1, Ordering::Relaxed);
+                        ctx.request_repaint();
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Serve one client, one line at a time. A query is answered with the
+/// meter's reply line, or not at all if the meter stays silent. The
+/// connection closes when no SCPI meter is connected.
+async fn handle_client(stream: TcpStream, meter: MeterInput) {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match (&mut reader)
+            .take(MAX_LINE_BYTES as u64)
+            .read_until(b'\n', &mut buf)
+            .await
+        {
+            Ok(0) | Err(_) => break,
+            Ok(n) if n == MAX_LINE_BYTES && !buf.ends_with(b"\n") => break,
+            Ok(_) => {}
+        }
+        let line = String::from_utf8_lossy(&buf);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some(tx) = meter.lock().unwrap().clone() else {
+            break;
+        };
+        let cmd = ensure_newline(&line);
+        if !expects_reply(&cmd) {
+            if tx.send(PassthroughCmd { cmd, reply: None }).await.is_err() {
+                break;
+            }
+            continue;
+        }
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let request = PassthroughCmd {
+            cmd,
+            reply: Some(reply_tx),
+        };
+        if tx.send(request).await.is_err() {
+            break;
+        }
+        if let Ok(Ok(reply)) = tokio::time::timeout(REPLY_TIMEOUT, reply_rx).await {
+            if write
+                .write_all(format!("{reply}\n").as_bytes())
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    }
+}
+
+impl super::MyApp {
+    /// Start, stop or restart the passthrough port to match the settings and
+    /// point it at the SCPI task of the current connection.
+    pub fn scpi_server_tick(&mut self, ctx: &egui::Context) {
+        if !self.scpi_server.enabled {
+            self.scpi_server_handle = None;
+            return;
+        }
+        if self
+            .scpi_server_handle
+            .as_ref()
+            .is_none_or(|s| s.settings != self.scpi_server)
+        {
+            self.scpi_server_handle = Some(ScpiServer::start(&self.scpi_server, ctx));
+        }
+        if let Some(server) = &self.scpi_server_handle {
+            *server.meter.lock().unwrap() = self.passthrough_tx.clone();
+        }
+    }
+
+    pub fn show_scpi_server_settings(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.label("SCPI passthrough:");
+        ui.checkbox(
+            &mut self.scpi_server.enabled,
+            "Share the meter on a SCPI TCP port",
+        )
+        .on_hover_text(
+            "Other programs send SCPI lines over TCP while RustyMeter keeps polling. \
+             A line with a query is answered with one reply line.",
+        );
+        ui.add_enabled_ui(!self.scpi_server.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Bind address:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.scpi_server.bind_address)
+                        .desired_width(140.0)
+                        .hint_text("127.0.0.1"),
+                );
+                ui.label("Port:");
+                ui.add(egui::DragValue::new(&mut self.scpi_server.port).range(1..=65535));
+            });
+        });
+        if !matches!(
+            self.scpi_server.bind_address.trim(),
+            "127.0.0.1" | "localhost" | "::1"
+        ) {
+            ui.label(
+                RichText::new("Other hosts on the network can control the meter.")
+                    .color(Color32::YELLOW),
+            );
+        }
+        let Some(server) = &self.scpi_server_handle else {
+            return;
+        };
+        match &*server.status.lock().unwrap() {
+            ServerStatus::Starting => ui.label("Starting..."),
+            ServerStatus::Listening(addr) => ui.label(format!(
+                "Listening on {addr}, {} client(s)",
+                server.clients.load(Ordering::Relaxed)
+            )),
+            ServerStatus::Failed(e) => ui.label(RichText::new(e).color(Color32::RED)),
+        };
+        if self.passthrough_tx.is_none() {
+            ui.label("Clients are disconnected until a SCPI meter is connected.");
+        }
+    }
+}
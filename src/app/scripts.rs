@@ -0,0 +1,510 @@
+//! Rhai test scripts: stored next to the SCPI macros, edited in the Scripts
+//! window and launchable from main-window buttons.
+//!
+//! A script runs on its own thread (`crate::scripting`). Its requests arrive
+//! over a channel that `script_tick` drains every frame, so meter commands go
+//! through the same paths as the buttons and the HTTP API, and dialogs are
+//! ordinary egui windows.
+
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use egui::{Color32, Context, FontId, RichText, TextEdit, Window};
+use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use super::serial::PassthroughCmd;
+use crate::helpers::METER_OVERLOAD_VALUE;
+use crate::scpi_macro::{ensure_newline, new_macro_id};
+use crate::scripting::{
+    ScriptCall, ScriptHost, ScriptReply, ScriptRequest, ScriptValue, run_script,
+};
+
+/// How long `query()` waits for its turn on the wire plus the reply.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Lines kept in the script output.
+const SCRIPT_LOG_LINES: usize = 500;
+
+const NEW_SCRIPT: &str = r#"// set_mode("VDC"); set_range("5V"); set_rate("Slow");
+// let v = wait_stable(0.1, 5, 10000);   // tolerance %, samples, timeout ms
+// if v < 4.95 || v > 5.05 { alarm(`5V rail out of limits: ${v}`); }
+// log("5V rail", v);
+"#;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Script {
+    pub id: String,
+    pub name: String,         // Persistent
+    pub source: String,       // Persistent
+    pub show_as_button: bool, // Persistent, button under the mode grid
+}
+
+impl Default for Script {
+    fn default() -> Self {
+        Self {
+            id: new_macro_id(),
+            name: "New script".to_owned(),
+            source: NEW_SCRIPT.to_owned(),
+            show_as_button: true,
+        }
+    }
+}
+
+enum Dialog {
+    Message(String),
+    Confirm(String),
+    Input { prompt: String, text: String },
+}
+
+struct ScriptRun {
+    name: String,
+    calls: mpsc::Receiver<ScriptCall>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Result<(), String>>>,
+    outcome: Option<Result<(), String>>,
+    dialog: Option<(Dialog, mpsc::Sender<ScriptReply>)>,
+    alarm: Option<String>,
+    log: Vec<String>,
+}
+
+impl ScriptRun {
+    fn is_running(&self) -> bool {
+        self.outcome.is_none()
+    }
+
+    fn push_log(&mut self, line: String) {
+        self.log.push(line);
+        if self.log.len() > SCRIPT_LOG_LINES {
+            self.log.remove(0);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ScriptsState {
+    selected: Option<String>,
+    /// Script to start on the next tick, which has the context to wake the UI.
+    start: Option<String>,
+    run: Option<ScriptRun>,
+}
+
+impl super::MyApp {
+    /// Ask for the script with `id` to start unless one is running.
+    pub fn request_script(&mut self, id: &str) {
+        if !self
+            .script_state
+            .run
+            .as_ref()
+            .is_some_and(ScriptRun::is_running)
+        {
+            self.script_state.start = Some(id.to_owned());
+        }
+    }
+
+    fn start_script(&mut self, ctx: &Context, id: &str) {
+        let Some(script) = self.scripts.iter().find(|s| s.id == id) else {
+            return;
+        };
+        let (calls_tx, calls) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let wake_ctx = ctx.clone();
+        let host = ScriptHost {
+            calls: calls_tx,
+            stop: stop.clone(),
+            wake: Arc::new(move || wake_ctx.request_repaint()),
+            results_dir: PathBuf::from(self.script_results_dir.trim()),
+        };
+        let source = script.source.clone();
+        let handle = thread::spawn(move || run_script(&source, &host));
+        self.script_state.run = Some(ScriptRun {
+            name: script.name.clone(),
+            calls,
+            stop,
+            handle: Some(handle),
+            outcome: None,
+            dialog: None,
+            alarm: None,
+            log: Vec::new(),
+        });
+    }
+
+    /// Start a requested script and answer the running one.
+    pub fn script_tick(&mut self, ctx: &Context) {
+        if let Some(id) = self.script_state.start.take() {
+            self.start_script(ctx, &id);
+        }
+        let Some(mut run) = self.script_state.run.take() else {
+            return;
+        };
+        if run.is_running() {
+            while let Ok(call) = run.calls.try_recv() {
+                self.answer_script(&mut run, call);
+            }
+            if run.handle.as_ref().is_some_and(JoinHandle::is_finished) {
+                let result = run
+                    .handle
+                    .take()
+                    .and_then(|h| h.join().ok())
+                    .unwrap_or_else(|| Err("The script thread panicked".to_owned()));
+                run.dialog = None;
+                run.outcome = Some(result);
+            }
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+        self.script_state.run = Some(run);
+    }
+
+    fn answer_script(&mut self, run: &mut ScriptRun, call: ScriptCall) {
+        let reply = match call.request {
+            ScriptRequest::Command(command) => self.apply_api_command(command).map(|message| {
+                run.push_log(message);
+                ScriptValue::None
+            }),
+            ScriptRequest::Reading(channel) => Ok(self
+                .live_channels
+                .iter()
+                .find(|c| c.var == channel)
+                .and_then(|c| c.value)
+                .map_or(ScriptValue::None, |v| ScriptValue::Reading {
+                    value: if v.value == METER_OVERLOAD_VALUE {
+                        f64::INFINITY
+                    } else {
+                        v.value
+                    },
+                    time: v.time,
+                })),
+            ScriptRequest::Unit(channel) => self
+                .live_channels
+                .iter()
+                .find(|c| c.var == channel)
+                .map(|c| ScriptValue::Text(c.unit.clone()))
+                .ok_or_else(|| format!("No channel named '{channel}'")),
+            ScriptRequest::Scpi { channel, cmd } => self.script_scpi(&channel, &cmd),
+            ScriptRequest::Query(cmd) => {
+                // Answered from a task once the meter replies
+                self.script_query(&cmd, call.reply);
+                return;
+            }
+            ScriptRequest::Record => {
+                self.record_measurement();
+                Ok(ScriptValue::None)
+            }
+            ScriptRequest::Log { label, value } => {
+                self.record_labelled(&label, value);
+                run.push_log(format!("{label}: {value} {}", self.curr_unit));
+                Ok(ScriptValue::None)
+            }
+            ScriptRequest::Print(text) => {
+                run.push_log(text);
+                Ok(ScriptValue::None)
+            }
+            ScriptRequest::Beep => {
+                self.beep_meter();
+                Ok(ScriptValue::None)
+            }
+            ScriptRequest::Alarm(text) => {
+                self.beep_meter();
+                run.push_log(format!("ALARM: {text}"));
+                run.alarm = Some(text);
+                Ok(ScriptValue::None)
+            }
+            ScriptRequest::Message(text) => {
+                run.dialog = Some((Dialog::Message(text), call.reply));
+                return;
+            }
+            ScriptRequest::Confirm(text) => {
+                run.dialog = Some((Dialog::Confirm(text), call.reply));
+                return;
+            }
+            ScriptRequest::Input(prompt) => {
+                let dialog = Dialog::Input {
+                    prompt,
+                    text: String::new(),
+                };
+                run.dialog = Some((dialog, call.reply));
+                return;
+            }
+        };
+        let _ = call.reply.send(reply);
+    }
+
+    /// Set command for the main meter (`m1`) or an additional meter (`m2`...).
+    fn script_scpi(&mut self, channel: &str, cmd: &str) -> ScriptReply {
+        if channel == "m1" {
+            let Some(tx) = &self.passthrough_tx else {
+                return Err("No SCPI meter connected".to_owned());
+            };
+            let cmd = ensure_newline(cmd);
+            tx.try_send(PassthroughCmd {
+                cmd: cmd.clone(),
+                reply: None,
+            })
+            .map_err(|e| format!("Failed to send command: {e}"))?;
+            self.apply_scpi_hints(&[cmd]);
+            return Ok(ScriptValue::None);
+        }
+        let meter = channel
+            .strip_prefix('m')
+            .and_then(|n| n.parse::<usize>().ok())
+            .and_then(|n| n.checked_sub(2))
+            .and_then(|idx| self.aux_meters.get(idx))
+            .ok_or_else(|| format!("No meter named '{channel}'"))?;
+        if !meter.is_connected() {
+            return Err(format!("{channel} is not connected"));
+        }
+        meter.queue_scpi(cmd, self.value_debug);
+        Ok(ScriptValue::None)
+    }
+
+    fn script_query(&self, cmd: &str, reply: mpsc::Sender<ScriptReply>) {
+        let Some(tx) = self.passthrough_tx.clone() else {
+            let _ = reply.send(Err("No SCPI meter connected".to_owned()));
+            return;
+        };
+        let cmd = ensure_newline(cmd);
+        let (reply_tx, reply_rx) = oneshot::channel();
+        if let Err(e) = tx.try_send(PassthroughCmd {
+            cmd: cmd.clone(),
+            reply: Some(reply_tx),
+        }) {
+            let _ = reply.send(Err(format!("Failed to send query: {e}")));
+            return;
+        }
+        tokio::spawn(async move {
+            let result = match tokio::time::timeout(QUERY_TIMEOUT, reply_rx).await {
+                Ok(Ok(line)) => Ok(ScriptValue::Text(line.trim().to_owned())),
+                _ => Err(format!("No reply to {}", cmd.trim_end())),
+            };
+            let _ = reply.send(result);
+        });
+    }
+
+    fn stop_script(&mut self) {
+        if let Some(run) = &mut self.script_state.run {
+            run.stop.store(true, Ordering::Relaxed);
+            // Unblock a waiting dialog; the thread then sees the stop flag
+            run.dialog = None;
+        }
+    }
+
+    /// Dialogs and alarms of the running script, shown whether or not the
+    /// Scripts window is open.
+    pub fn show_script_dialog(&mut self, ctx: &Context) {
+        let Some(run) = &mut self.script_state.run else {
+            return;
+        };
+        if let Some(alarm) = run.alarm.clone() {
+            let mut dismissed = false;
+            Window::new(format!("Alarm: {}", run.name))
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(RichText::new(alarm).color(Color32::RED).strong());
+                    dismissed = ui.button("Dismiss").clicked();
+                });
+            if dismissed {
+                run.alarm = None;
+            }
+        }
+        let Some((dialog, _)) = &mut run.dialog else {
+            return;
+        };
+        let mut answer: Option<ScriptReply> = None;
+        Window::new(run.name.as_str())
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| match dialog {
+                Dialog::Message(text) => {
+                    ui.label(text.as_str());
+                    if ui.button("OK").clicked() {
+                        answer = Some(Ok(ScriptValue::None));
+                    }
+                }
+                Dialog::Confirm(text) => {
+                    ui.label(text.as_str());
+                    ui.horizontal(|ui| {
+                        if ui.button("Yes").clicked() {
+                            answer = Some(Ok(ScriptValue::Bool(true)));
+                        }
+                        if ui.button("No").clicked() {
+                            answer = Some(Ok(ScriptValue::Bool(false)));
+                        }
+                    });
+                }
+                Dialog::Input { prompt, text } => {
+                    ui.label(prompt.as_str());
+                    let response = ui.add(TextEdit::singleline(text).desired_width(260.0));
+                    let enter =
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    ui.horizontal(|ui| {
+                        if ui.button("OK").clicked() || enter {
+                            answer = Some(Ok(ScriptValue::Text(text.clone())));
+                        }
+                        if ui.button("Cancel").clicked() {
+                            answer = Some(Err("Cancelled by the operator".to_owned()));
+                        }
+                    });
+                }
+            });
+        if let Some(answer) = answer {
+            if let Some((_, reply)) = run.dialog.take() {
+                let _ = reply.send(answer);
+            }
+        }
+    }
+
+    pub fn show_scripts(&mut self, ctx: &Context) {
+        if !self.scripts_open {
+            return;
+        }
+        let running = self
+            .script_state
+            .run
+            .as_ref()
+            .is_some_and(ScriptRun::is_running);
+        let mut open = true;
+        Window::new("Scripts")
+            .open(&mut open)
+            .default_size([780.0, 520.0])
+            .min_size([560.0, 320.0])
+            .resizable(true)
+            .vscroll(false)
+            .show(ctx, |ui| {
+                egui::Panel::top("scripts_toolbar").show(ui, |ui| {
+                    ui.label(
+                        "Test procedures in Rhai. Scripts can drive the meter, read channels, \
+                         wait for stable readings, log to the recorder, ask the operator and \
+                         write results files. See the README for the function list.",
+                    );
+                    ui.horizontal(|ui| {
+                        if ui.button("Add").clicked() {
+                            let script = Script::default();
+                            self.script_state.selected = Some(script.id.clone());
+                            self.scripts.push(script);
+                        }
+                        let selected = self.script_state.selected.clone();
+                        if ui
+                            .add_enabled(selected.is_some(), egui::Button::new("Delete"))
+                            .clicked()
+                            && let Some(id) = selected
+                        {
+                            self.scripts.retain(|s| s.id != id);
+                            self.script_state.selected = self.scripts.first().map(|s| s.id.clone());
+                        }
+                        ui.separator();
+                        ui.label("Results folder:");
+                        ui.add(
+                            TextEdit::singleline(&mut self.script_results_dir)
+                                .desired_width(220.0)
+                                .hint_text("write_file() is refused"),
+                        );
+                        if ui.button("Browse").clicked() {
+                            if let Some(dir) = FileDialog::new().pick_folder() {
+                                self.script_results_dir = dir.display().to_string();
+                            }
+                        }
+                    });
+                });
+
+                egui::Panel::bottom("scripts_output")
+                    .exact_size(160.0)
+                    .show(ui, |ui| self.show_script_output(ui));
+
+                egui::Panel::left("scripts_list")
+                    .exact_size(200.0)
+                    .resizable(false)
+                    .show(ui, |ui| {
+                        let mut pick = None;
+                        for script in &self.scripts {
+                            let mut label = script.name.clone();
+                            if script.show_as_button {
+                                label.push_str("  [button]");
+                            }
+                            let selected =
+                                self.script_state.selected.as_deref() == Some(script.id.as_str());
+                            if ui.selectable_label(selected, label).clicked() {
+                                pick = Some(script.id.clone());
+                            }
+                        }
+                        if let Some(id) = pick {
+                            self.script_state.selected = Some(id);
+                        }
+                    });
+
+                egui::CentralPanel::default().show(ui, |ui| {
+                    let Some(id) = self.script_state.selected.clone() else {
+                        ui.label("Add a script, or select one from the list.");
+                        return;
+                    };
+                    let Some(script) = self.scripts.iter_mut().find(|s| s.id == id) else {
+                        return;
+                    };
+                    ui.horizontal(|ui| {
+                        ui.label("Name:");
+                        ui.add(TextEdit::singleline(&mut script.name).desired_width(240.0));
+                        ui.checkbox(&mut script.show_as_button, "Show as button on main window");
+                    });
+                    ui.horizontal(|ui| {
+                        if running {
+                            if ui.button("Stop").clicked() {
+                                self.stop_script();
+                            }
+                        } else if ui.button("Run").clicked() {
+                            self.request_script(&id);
+                        }
+                    });
+                    egui::ScrollArea::vertical()
+                        .id_salt("script_editor")
+                        .auto_shrink([false, false])
+                        .show(ui, |ui| {
+                            if let Some(script) = self.scripts.iter_mut().find(|s| s.id == id) {
+                                ui.add(
+                                    TextEdit::multiline(&mut script.source)
+                                        .code_editor()
+                                        .font(FontId::monospace(13.0))
+                                        .desired_width(f32::INFINITY)
+                                        .desired_rows(16),
+                                );
+                            }
+                        });
+                });
+            });
+        if !open {
+            self.scripts_open = false;
+        }
+    }
+
+    fn show_script_output(&mut self, ui: &mut egui::Ui) {
+        let Some(run) = &self.script_state.run else {
+            ui.label("No script has run yet.");
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.strong(&run.name);
+            match &run.outcome {
+                None => ui.label("running..."),
+                Some(Ok(())) => ui.label(RichText::new("finished").color(Color32::GREEN)),
+                Some(Err(e)) => ui.label(RichText::new(e).color(Color32::RED)),
+            };
+        });
+        egui::ScrollArea::vertical()
+            .id_salt("script_output")
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for line in &run.log {
+                    ui.monospace(line);
+                }
+            });
+    }
+}
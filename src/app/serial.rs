@@ -1,7 +1,7 @@
 use std::{
     collections::VecDeque,
     io::{self, Read, Write},
-    sync::atomic::Ordering,
+    sync::atomic::{AtomicU64, Ordering},
     time::{Duration, Instant},
 };
 
@@ -21,6 +21,9 @@ const UI_SYNC_INTERVAL: Duration = Duration::from_millis(500);
 /// step. MEAS? is independent and must not stall.
 const STATUS_TIMEOUT: Duration = Duration::from_millis(1000);
 const MEAS_TIMEOUT: Duration = Duration::from_secs(2);
+/// `MEAS?` timeouts in a row after which the link counts as lost. A USB
+/// adapter can stay enumerated while the meter behind it is gone.
+const MEAS_TIMEOUTS_LOST: u8 = 5;
 
 /// One GUI status query. Replies never look like `MEAS?` (no scientific
 /// notation), so they can share the wire with measurements.
@@ -45,6 +48,13 @@ impl StatusStep {
     }
 }
 
+/// A line from a client of the SCPI passthrough port. Lines that expect a
+/// reply carry the channel the next reply line goes back on.
+pub(super) struct PassthroughCmd {
+    pub cmd: String,
+    pub reply: Option<oneshot::Sender<String>>,
+}
+
 struct Session {
     scpimode: ScpiMode,
     awaiting_idn: bool,
@@ -53,6 +63,7 @@ struct Session {
     idn_tries_left: u8,
     awaiting_meas: bool,
     meas_since: Option<Instant>,
+    missed_meas: u8,
     /// `Some` = a GUI sync is in progress (status queries only).
     in_status_cycle: bool,
     status: Option<StatusStep>,
@@ -66,6 +77,9 @@ struct Session {
     last_status_done: Instant,
     last_mode: MeterMode,
     swap_diod_cont: bool,
+    /// Passthrough query on the wire; the next reply line is its answer.
+    passthrough: Option<oneshot::Sender<String>>,
+    passthrough_since: Option<Instant>,
 }
 
 impl Session {
@@ -78,6 +92,7 @@ impl Session {
             idn_tries_left: 5,
             awaiting_meas: false,
             meas_since: None,
+            missed_meas: 0,
             in_status_cycle: false,
             status: None,
             status_since: None,
@@ -90,9 +105,35 @@ impl Session {
             last_status_done: Instant::now(),
             last_mode: mode,
             swap_diod_cont: false,
+            passthrough: None,
+            passthrough_since: None,
         }
     }
 
+    /// No reply outstanding. Replies are matched by content, which cannot
+    /// tell a passthrough reply apart, so those queries wait for a quiet line.
+    fn line_idle(&self) -> bool {
+        !self.awaiting_idn
+            && !self.awaiting_meas
+            && self.status_since.is_none()
+            && self.passthrough.is_none()
+    }
+
+    fn meas_received(&mut self) {
+        self.awaiting_meas = false;
+        self.meas_since = None;
+        self.missed_meas = 0;
+    }
+
+    /// Give up on the outstanding `MEAS?`; true once too many in a row went
+    /// unanswered.
+    fn meas_timed_out(&mut self) -> bool {
+        self.awaiting_meas = false;
+        self.meas_since = None;
+        self.missed_meas = self.missed_meas.saturating_add(1);
+        self.missed_meas >= MEAS_TIMEOUTS_LOST
+    }
+
     fn ask_status(&mut self, step: StatusStep) {
         self.next_status = Some(step);
         self.status = Some(step);
@@ -244,231 +285,308 @@ fn discard_pending_input(serial: &mut SerialStream) {
 
 impl super::MyApp {
     pub fn spawn_serial_task(&mut self) {
-        if self.serial.is_none() {
+        let Some(serial) = self.serial.take() else {
             return;
-        }
+        };
+        let link = spawn_scpi_task(
+            serial,
+            self.meter_shared(),
+            self.rst_on_disconnect,
+            self.metermode,
+        );
+        self.adopt_link(link);
+    }
+}
 
-        let (tx_data, rx_data) = mpsc::channel::<Option<f64>>(100); // Channel for measurements
-        let (tx_cmd, mut rx_cmd) = mpsc::channel::<String>(100); // Channel for commands
-        let (tx_mode, rx_mode) = mpsc::channel::<(MeterMode, String)>(10);
-        let (tx_status, rx_status) = mpsc::channel::<MeterStatus>(16);
-        let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>(); // Shutdown signal
-        self.serial_rx = Some(rx_data);
-        self.serial_tx = Some(tx_cmd.clone());
-        self.mode_rx = Some(rx_mode);
-        self.status_rx = Some(rx_status);
-        self.shutdown_tx = Some(shutdown_tx);
-
-        let mut serial = self.serial.take().unwrap();
-        let value_debug_shared = self.value_debug_shared.clone();
-        let poll_interval_shared = self.poll_interval_shared.clone();
-        let device_shared = self.device.clone();
-        let poll_ready = self.poll_ready.clone();
-        let refresh_requested = self.refresh_requested.clone();
-        let rst_on_disconnect = self.rst_on_disconnect;
-        let curr_mode = self.metermode;
-
-        tokio::spawn(async move {
-            let mut poll = Poll::new().unwrap();
-            let mut events = Events::with_capacity(1);
-            let mut readbuf = [0u8; 1024];
-            let mut line_buf = String::new();
-            let mut command_queue: VecDeque<String> = VecDeque::new();
-            let mut shutting_down = false;
-            let mut session = Session::new(curr_mode);
-
-            // Register serial port for readable and writable events
-            poll.registry()
-                .register(
-                    &mut serial,
-                    SERIAL_TOKEN,
-                    Interest::READABLE | Interest::WRITABLE,
-                )
-                .unwrap();
-            if *value_debug_shared.lock().unwrap() {
-                println!("Serial port registered for READABLE and WRITABLE events");
-            }
+/// Start the SCPI poll task on an opened port and hand back its channels.
+pub(super) fn spawn_scpi_task(
+    mut serial: SerialStream,
+    shared: super::MeterShared,
+    rst_on_disconnect: bool,
+    curr_mode: MeterMode,
+) -> super::MeterLink {
+    let (tx_data, rx_data) = mpsc::channel::<Option<super::Reading>>(100); // Channel for measurements
+    let (tx_cmd, mut rx_cmd) = mpsc::channel::<String>(100); // Channel for commands
+    let (tx_mode, rx_mode) = mpsc::channel::<(MeterMode, String)>(10);
+    let (tx_status, rx_status) = mpsc::channel::<MeterStatus>(16);
+    let (tx_passthrough, mut rx_passthrough) = mpsc::channel::<PassthroughCmd>(32);
+    let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>(); // Shutdown signal
+    let link = super::MeterLink {
+        data_rx: Some(rx_data),
+        cmd_tx: Some(tx_cmd),
+        mode_rx: Some(rx_mode),
+        status_rx: Some(rx_status),
+        passthrough_tx: Some(tx_passthrough),
+        shutdown_tx: Some(shutdown_tx),
+        ..Default::default()
+    };
 
-            // Drop leftover MEAS? replies from a previous session sitting in the UART.
-            discard_pending_input(&mut serial);
+    let super::MeterShared {
+        device: device_shared,
+        poll_ready,
+        refresh_requested,
+        value_debug: value_debug_shared,
+        poll_interval: poll_interval_shared,
+        timeouts,
+        link_lost,
+    } = shared;
+
+    tokio::spawn(async move {
+        let mut poll = Poll::new().unwrap();
+        let mut events = Events::with_capacity(1);
+        let mut readbuf = [0u8; 1024];
+        let mut line_buf = String::new();
+        let mut command_queue: VecDeque<String> = VecDeque::new();
+        let mut passthrough_queue: VecDeque<PassthroughCmd> = VecDeque::new();
+        let mut shutting_down = false;
+        let mut lost: Option<String> = None;
+        let mut session = Session::new(curr_mode);
+
+        // Register serial port for readable and writable events
+        poll.registry()
+            .register(
+                &mut serial,
+                SERIAL_TOKEN,
+                Interest::READABLE | Interest::WRITABLE,
+            )
+            .unwrap();
+        if *value_debug_shared.lock().unwrap() {
+            println!("Serial port registered for READABLE and WRITABLE events");
+        }
+
+        // Drop leftover MEAS? replies from a previous session sitting in the UART.
+        discard_pending_input(&mut serial);
+
+        // Identify first. Dialect bootstrap + user connect macros are queued
+        // from the UI after the IDN reply is parsed.
+
+        loop {
+            tokio::select! {
+                _ = &mut shutdown_rx, if !shutting_down => {
+                    if *value_debug_shared.lock().unwrap() {
+                        println!("Shutdown signal received, processing remaining queue: {:?}", command_queue);
+                    }
+                    shutting_down = true;
+                    session.awaiting_meas = false;
+                    session.in_status_cycle = false;
+                    session.status = None;
+                    session.next_status = None;
+                    session.retry_idn = false;
+                    session.passthrough = None;
+                    passthrough_queue.clear();
+                    command_queue.push_back("SYST:LOC\n".to_string());
+                    if rst_on_disconnect {
+                        command_queue.push_back("*RST\n".to_string());
+                    }
+                    if *value_debug_shared.lock().unwrap() {
+                        println!("Queued SYST:LOC and *RST (if set) for shutdown, queue: {:?}", command_queue);
+                    }
+                }
+                _ = async {
+                    let debug = *value_debug_shared.lock().unwrap();
+                    let interval = *poll_interval_shared.lock().unwrap();
 
-            // Identify first. Dialect bootstrap + user connect macros are queued
-            // from the UI after the IDN reply is parsed.
+                    if debug {
+                        println!("Starting poll loop, queue: {:?}", command_queue);
+                    }
 
-            loop {
-                tokio::select! {
-                    _ = &mut shutdown_rx, if !shutting_down => {
-                        if *value_debug_shared.lock().unwrap() {
-                            println!("Shutdown signal received, processing remaining queue: {:?}", command_queue);
-                        }
-                        shutting_down = true;
-                        session.awaiting_meas = false;
-                        session.in_status_cycle = false;
-                        session.status = None;
-                        session.next_status = None;
-                        session.retry_idn = false;
-                        command_queue.push_back("SYST:LOC\n".to_string());
-                        if rst_on_disconnect {
-                            command_queue.push_back("*RST\n".to_string());
-                        }
-                        if *value_debug_shared.lock().unwrap() {
-                            println!("Queued SYST:LOC and *RST (if set) for shutdown, queue: {:?}", command_queue);
+                    while let Ok(cmd) = rx_cmd.try_recv() {
+                        if debug {
+                            println!("Queuing command from UI: {:?}", cmd);
                         }
+                        command_queue.push_back(cmd);
                     }
-                    _ = async {
-                        let debug = *value_debug_shared.lock().unwrap();
-                        let interval = *poll_interval_shared.lock().unwrap();
-
+                    while let Ok(request) = rx_passthrough.try_recv() {
                         if debug {
-                            println!("Starting poll loop, queue: {:?}", command_queue);
+                            println!("Passthrough command: {:?}", request.cmd);
                         }
+                        // Sets join the UI commands; queries wait for a quiet line
+                        if request.reply.is_some() {
+                            passthrough_queue.push_back(request);
+                        } else {
+                            command_queue.push_back(request.cmd);
+                        }
+                    }
 
-                        while let Ok(cmd) = rx_cmd.try_recv() {
+                    match poll.poll(&mut events, Some(Duration::from_millis(interval))) {
+                        Ok(()) => {
                             if debug {
-                                println!("Queuing command from UI: {:?}", cmd);
+                                println!(
+                                    "Poll returned events: {:?}",
+                                    events.iter().collect::<Vec<_>>()
+                                );
                             }
-                            command_queue.push_back(cmd);
-                        }
-
-                        match poll.poll(&mut events, Some(Duration::from_millis(interval))) {
-                            Ok(()) => {
-                                if debug {
-                                    println!(
-                                        "Poll returned events: {:?}",
-                                        events.iter().collect::<Vec<_>>()
-                                    );
-                                }
 
-                                for event in events.iter() {
-                                    if event.is_readable() {
-                                        if debug {
-                                            println!("Readable event detected");
-                                        }
-                                        loop {
-                                            match serial.read(&mut readbuf) {
-                                                Ok(count) => {
-                                                    let chunk = String::from_utf8_lossy(
-                                                        &readbuf[..count],
-                                                    );
-                                                    if debug {
-                                                        println!("Received: {:?}", chunk);
-                                                    }
-                                                    line_buf.push_str(&chunk);
-                                                    while let Some(line) = take_scpi_line(&mut line_buf) {
-                                                        let trimmed = line.trim();
-                                                        if trimmed.is_empty() {
-                                                            continue;
-                                                        }
-                                                        handle_line(
-                                                            &mut session,
-                                                            trimmed,
-                                                            &device_shared,
-                                                            &tx_mode,
-                                                            &tx_status,
-                                                            &tx_data,
-                                                            debug,
-                                                        ).await;
-                                                    }
+                            for event in events.iter() {
+                                if event.is_readable() {
+                                    if debug {
+                                        println!("Readable event detected");
+                                    }
+                                    loop {
+                                        match serial.read(&mut readbuf) {
+                                            Ok(0) => {
+                                                // End of file: the port was hung up
+                                                if event.is_read_closed() || event.is_error() {
+                                                    lost = Some("Serial port closed".to_owned());
                                                 }
-                                                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
-                                                    if debug {
-                                                        println!("Read would block, exiting read loop");
-                                                    }
-                                                    break;
+                                                break;
+                                            }
+                                            Ok(count) => {
+                                                let chunk = String::from_utf8_lossy(
+                                                    &readbuf[..count],
+                                                );
+                                                if debug {
+                                                    println!("Received: {:?}", chunk);
                                                 }
-                                                Err(e) => {
-                                                    if debug {
-                                                        println!("Serial read error: {}", e);
+                                                line_buf.push_str(&chunk);
+                                                while let Some(line) = take_scpi_line(&mut line_buf) {
+                                                    let trimmed = line.trim();
+                                                    if trimmed.is_empty() {
+                                                        continue;
                                                     }
-                                                    break;
+                                                    handle_line(
+                                                        &mut session,
+                                                        trimmed,
+                                                        &device_shared,
+                                                        &tx_mode,
+                                                        &tx_status,
+                                                        &tx_data,
+                                                        debug,
+                                                    ).await;
                                                 }
                                             }
+                                            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                                                if debug {
+                                                    println!("Read would block, exiting read loop");
+                                                }
+                                                break;
+                                            }
+                                            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                                            Err(e) => {
+                                                if debug {
+                                                    println!("Serial read error: {}", e);
+                                                }
+                                                lost = Some(format!("Serial read error: {e}"));
+                                                break;
+                                            }
                                         }
                                     }
                                 }
                             }
-                            Err(e) => {
-                                if debug {
-                                    println!("Poll error: {}", e);
-                                }
+                        }
+                        Err(e) => {
+                            if debug {
+                                println!("Poll error: {}", e);
                             }
                         }
+                    }
 
-                        on_timeouts(&mut session, &tx_status, debug).await;
+                    if lost.is_some() {
+                        return;
+                    }
 
-                        drain_sets(&mut serial, &mut command_queue, debug);
-                        if !shutting_down {
-                            coalesce_ui_queries(
-                                &mut command_queue,
-                                &refresh_requested,
-                                debug,
-                            );
-                        }
+                    lost = on_timeouts(&mut session, &tx_status, &timeouts, debug).await;
+                    if lost.is_some() {
+                        return;
+                    }
 
-                        if !shutting_down && (session.scpimode == ScpiMode::Idn
-                            || session.awaiting_idn
-                            || session.retry_idn)
+                    drain_sets(&mut serial, &mut command_queue, debug);
+                    if !shutting_down {
+                        coalesce_ui_queries(
+                            &mut command_queue,
+                            &refresh_requested,
+                            debug,
+                        );
+                    }
+
+                    if !shutting_down && (session.scpimode == ScpiMode::Idn
+                        || session.awaiting_idn
+                        || session.retry_idn)
+                    {
+                        if session.retry_idn
+                            && !session.awaiting_idn
+                            && write_cmd(&mut serial, "*IDN?\n", debug)
                         {
-                            if session.retry_idn
-                                && !session.awaiting_idn
-                                && write_cmd(&mut serial, "*IDN?\n", debug)
-                            {
-                                session.retry_idn = false;
-                                session.awaiting_idn = true;
-                                session.idn_since = Some(Instant::now());
+                            session.retry_idn = false;
+                            session.awaiting_idn = true;
+                            session.idn_since = Some(Instant::now());
+                        }
+                    } else if !shutting_down
+                        && command_queue.is_empty()
+                        && poll_ready.load(Ordering::SeqCst)
+                        && (!passthrough_queue.is_empty() || session.passthrough.is_some())
+                    {
+                        // No MEAS? or status query goes out until the
+                        // passthrough queries are answered.
+                        if session.line_idle() {
+                            if let Some(request) = passthrough_queue.pop_front() {
+                                if write_cmd(&mut serial, &request.cmd, debug) {
+                                    session.passthrough = request.reply;
+                                    session.passthrough_since = Some(Instant::now());
+                                } else {
+                                    passthrough_queue.push_front(request);
+                                }
                             }
-                        } else if !shutting_down
-                            && command_queue.is_empty()
-                            && poll_ready.load(Ordering::SeqCst)
+                        }
+                    } else if !shutting_down
+                        && command_queue.is_empty()
+                        && poll_ready.load(Ordering::SeqCst)
+                    {
+                        // MEAS? first, always, on this loop's roster. Status never
+                        // occupies the measurement slot.
+                        if !session.awaiting_meas && write_cmd(&mut serial, "MEAS?\n", debug)
                         {
-                            // MEAS? first, always, on this loop's roster. Status never
-                            // occupies the measurement slot.
-                            if !session.awaiting_meas && write_cmd(&mut serial, "MEAS?\n", debug)
-                            {
-                                session.awaiting_meas = true;
-                                session.meas_since = Some(Instant::now());
-                            }
+                            session.awaiting_meas = true;
+                            session.meas_since = Some(Instant::now());
+                        }
 
-                            let want_cycle = refresh_requested.load(Ordering::SeqCst)
-                                || session.last_status_done.elapsed() >= UI_SYNC_INTERVAL;
-                            if !session.in_status_cycle && want_cycle {
-                                refresh_requested.store(false, Ordering::SeqCst);
-                                session.start_status_cycle();
-                            }
+                        let want_cycle = refresh_requested.load(Ordering::SeqCst)
+                            || session.last_status_done.elapsed() >= UI_SYNC_INTERVAL;
+                        if !session.in_status_cycle && want_cycle {
+                            refresh_requested.store(false, Ordering::SeqCst);
+                            session.start_status_cycle();
+                        }
 
-                            if let Some(step) = session.next_status {
-                                if write_cmd(&mut serial, step.cmd(), debug) {
-                                    session.next_status = None;
-                                    session.status = Some(step);
-                                    session.status_since = Some(Instant::now());
-                                }
+                        if let Some(step) = session.next_status {
+                            if write_cmd(&mut serial, step.cmd(), debug) {
+                                session.next_status = None;
+                                session.status = Some(step);
+                                session.status_since = Some(Instant::now());
                             }
                         }
+                    }
 
-                        tokio::time::sleep(Duration::from_millis(interval)).await;
-                    } => {}
-                }
+                    tokio::time::sleep(Duration::from_millis(interval)).await;
+                } => {}
+            }
 
-                if shutting_down {
-                    let debug = *value_debug_shared.lock().unwrap();
-                    while let Ok(cmd) = rx_cmd.try_recv() {
-                        command_queue.push_back(cmd);
-                    }
-                    drain_sets(&mut serial, &mut command_queue, debug);
-                    if debug {
-                        println!("Shutdown flush done, leftover queue: {:?}", command_queue);
-                    }
-                    break;
-                }
+            if let Some(reason) = lost.take() {
+                // Nothing to flush into a dead port; let the UI reopen it
+                *link_lost.lock().unwrap() = Some(reason);
+                break;
             }
 
-            if *value_debug_shared.lock().unwrap() {
-                println!("Cleaning up serial task");
+            if shutting_down {
+                let debug = *value_debug_shared.lock().unwrap();
+                while let Ok(cmd) = rx_cmd.try_recv() {
+                    command_queue.push_back(cmd);
+                }
+                drain_sets(&mut serial, &mut command_queue, debug);
+                if debug {
+                    println!("Shutdown flush done, leftover queue: {:?}", command_queue);
+                }
+                break;
             }
-            let _ = poll.registry().deregister(&mut serial);
-            drop(serial);
-        });
-    }
+        }
+
+        if *value_debug_shared.lock().unwrap() {
+            println!("Cleaning up serial task");
+        }
+        let _ = poll.registry().deregister(&mut serial);
+        drop(serial);
+    });
+
+    link
 }
 
 async fn handle_line(
@@ -477,9 +595,18 @@ async fn handle_line(
     device_shared: &std::sync::Arc<std::sync::Mutex<String>>,
     tx_mode: &mpsc::Sender<(MeterMode, String)>,
     tx_status: &mpsc::Sender<MeterStatus>,
-    tx_data: &mpsc::Sender<Option<f64>>,
+    tx_data: &mpsc::Sender<Option<super::Reading>>,
     debug: bool,
 ) {
+    if let Some(reply) = session.passthrough.take() {
+        session.passthrough_since = None;
+        if debug {
+            println!("Passthrough reply: {trimmed:?}");
+        }
+        let _ = reply.send(trimmed.to_owned());
+        return;
+    }
+
     let unquoted = trimmed.trim_matches('"');
     let class = scpi_macro::classify_reply(unquoted);
 
@@ -532,9 +659,8 @@ async fn handle_line(
     match class {
         ReplyClass::Meas => {
             if let Ok(meas) = trimmed.parse::<f64>() {
-                let _ = tx_data.send(Some(meas)).await;
-                session.awaiting_meas = false;
-                session.meas_since = None;
+                let _ = tx_data.send(Some((meas, Instant::now()))).await;
+                session.meas_received();
                 if debug {
                     println!("Sent measurement: {}", meas);
                 }
@@ -631,7 +757,15 @@ async fn maybe_flush_status(
     session.end_status_cycle();
 }
 
-async fn on_timeouts(session: &mut Session, tx_status: &mpsc::Sender<MeterStatus>, debug: bool) {
+/// Give up on replies that did not arrive in time, counting each in `timeouts`.
+/// Returns why the link counts as lost once `MEAS?` keeps timing out.
+async fn on_timeouts(
+    session: &mut Session,
+    tx_status: &mpsc::Sender<MeterStatus>,
+    timeouts: &AtomicU64,
+    debug: bool,
+) -> Option<String> {
+    let mut lost = None;
     if session.awaiting_idn
         && session
             .idn_since
@@ -640,6 +774,7 @@ async fn on_timeouts(session: &mut Session, tx_status: &mpsc::Sender<MeterStatus
         if debug {
             println!("SCPI timeout waiting for Idn");
         }
+        timeouts.fetch_add(1, Ordering::Relaxed);
         if session.idn_tries_left > 0 {
             session.idn_tries_left -= 1;
             session.retry_idn = true;
@@ -656,8 +791,26 @@ async fn on_timeouts(session: &mut Session, tx_status: &mpsc::Sender<MeterStatus
         if debug {
             println!("SCPI timeout waiting for Meas");
         }
-        session.awaiting_meas = false;
-        session.meas_since = None;
+        timeouts.fetch_add(1, Ordering::Relaxed);
+        if session.meas_timed_out() {
+            lost = Some(format!(
+                "No reply to {MEAS_TIMEOUTS_LOST} MEAS? queries in a row"
+            ));
+        }
+    }
+
+    if session.passthrough.is_some()
+        && session
+            .passthrough_since
+            .is_some_and(|t| t.elapsed() >= MEAS_TIMEOUT)
+    {
+        if debug {
+            println!("SCPI timeout waiting for passthrough reply");
+        }
+        timeouts.fetch_add(1, Ordering::Relaxed);
+        // Dropping the sender tells the client there is no answer
+        session.passthrough = None;
+        session.passthrough_since = None;
     }
 
     if let Some(step) = session.status {
@@ -668,6 +821,7 @@ async fn on_timeouts(session: &mut Session, tx_status: &mpsc::Sender<MeterStatus
             if debug {
                 println!("SCPI timeout waiting for {step:?}");
             }
+            timeouts.fetch_add(1, Ordering::Relaxed);
             match step {
                 StatusStep::Func => session.ask_status(StatusStep::Rate),
                 StatusStep::Rate => {
@@ -690,11 +844,13 @@ async fn on_timeouts(session: &mut Session, tx_status: &mpsc::Sender<MeterStatus
             maybe_flush_status(session, tx_status, debug).await;
         }
     }
+    lost
 }
 
 #[cfg(test)]
 mod tests {
-    use super::take_scpi_line;
+    use super::{MEAS_TIMEOUTS_LOST, Session, take_scpi_line};
+    use crate::multimeter::MeterMode;
 
     #[test]
     fn splits_batched_status_replies() {
@@ -716,4 +872,18 @@ mod tests {
         assert_eq!(take_scpi_line(&mut buf).as_deref(), Some("F"));
         assert!(buf.is_empty());
     }
+
+    #[test]
+    fn consecutive_meas_timeouts_lose_the_link() {
+        let mut session = Session::new(MeterMode::Vdc);
+        for _ in 1..MEAS_TIMEOUTS_LOST {
+            assert!(!session.meas_timed_out());
+        }
+        // A late reply means the meter is still there
+        session.meas_received();
+        for _ in 1..MEAS_TIMEOUTS_LOST {
+            assert!(!session.meas_timed_out());
+        }
+        assert!(session.meas_timed_out());
+    }
 }
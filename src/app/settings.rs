@@ -1,6 +1,72 @@
-use egui::{Context, TextEdit, Window, color_picker::color_picker_color32};
+use egui::{ComboBox, Context, TextEdit, Ui, Window, color_picker::color_picker_color32};
+
+use crate::serial_line::{COMMON_BAUD_RATES, FlowControl, LineParity, SerialLine};
+
+/// Baud rate, data bits, parity, stop bits and flow control in one row.
+fn serial_line_ui(ui: &mut Ui, id_salt: &str, line: &mut SerialLine) {
+    ui.horizontal(|ui| {
+        ComboBox::from_id_salt((id_salt, "baud"))
+            .selected_text(line.baud.to_string())
+            .show_ui(ui, |ui| {
+                for baud in COMMON_BAUD_RATES {
+                    ui.selectable_value(&mut line.baud, baud, baud.to_string());
+                }
+            });
+        ui.add(egui::DragValue::new(&mut line.baud).range(50..=4_000_000))
+            .on_hover_text("Custom baud rate");
+        ComboBox::from_id_salt((id_salt, "data_bits"))
+            .selected_text(format!("{} data bits", line.data_bits))
+            .show_ui(ui, |ui| {
+                for bits in 5..=8 {
+                    ui.selectable_value(&mut line.data_bits, bits, bits.to_string());
+                }
+            });
+        ComboBox::from_id_salt((id_salt, "parity"))
+            .selected_text(format!("Parity {}", line.parity.label()))
+            .show_ui(ui, |ui| {
+                for parity in LineParity::ALL {
+                    ui.selectable_value(&mut line.parity, parity, parity.label());
+                }
+            });
+        ComboBox::from_id_salt((id_salt, "stop_bits"))
+            .selected_text(format!("{} stop", line.stop_bits))
+            .show_ui(ui, |ui| {
+                for bits in 1..=2 {
+                    ui.selectable_value(&mut line.stop_bits, bits, bits.to_string());
+                }
+            });
+        ComboBox::from_id_salt((id_salt, "flow"))
+            .selected_text(format!("Flow {}", line.flow_control.label()))
+            .show_ui(ui, |ui| {
+                for flow in FlowControl::ALL {
+                    ui.selectable_value(&mut line.flow_control, flow, flow.label());
+                }
+            });
+    });
+}
 
 impl super::MyApp {
+    /// Advanced: replace the fixed Victor line settings, e.g. for a clone
+    /// meter or an adapter that needs flow control.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_victor_line_settings(&mut self, ui: &mut Ui) {
+        ui.checkbox(
+            &mut self.victor_line_override,
+            "Override Victor serial line settings (advanced)",
+        );
+        if !self.victor_line_override {
+            return;
+        }
+        ui.label("Victor 86B/C/D (DM1107):");
+        serial_line_ui(ui, "victor_86bcd_line", &mut self.victor_86bcd_line);
+        ui.label("Victor 86E (ES51932):");
+        serial_line_ui(ui, "victor_86e_line", &mut self.victor_86e_line);
+        if ui.button("Reset to meter defaults").clicked() {
+            self.victor_86bcd_line = super::VICTOR_86BCD_LINE;
+            self.victor_86e_line = super::VICTOR_86E_LINE;
+        }
+    }
+
     pub fn show_settings(&mut self, ctx: &Context) {
         if self.settings_open {
             Window::new("Settings")
@@ -16,8 +82,8 @@ impl super::MyApp {
                             "Send RST via SCPI on disconnect",
                         );
                         ui.checkbox(
-                            &mut self.parity,
-                            "Use parity bit (ignored right now, always None)",
+                            &mut self.auto_reconnect,
+                            "Reconnect automatically when the cable is unplugged",
                         );
                         let mut value_debug = *self.value_debug_shared.lock().unwrap();
                         if ui
@@ -28,20 +94,10 @@ impl super::MyApp {
                             *self.value_debug_shared.lock().unwrap() = value_debug;
                         }
 
-                        ui.label("Baud rate:");
-                        ui.add(
-                            TextEdit::singleline(&mut self.baud_rate.to_string())
-                                .desired_width(800.0),
-                        );
-                        ui.label("Data Bits (ignored right now, always 8):");
-                        ui.add(
-                            TextEdit::singleline(&mut self.bits.to_string()).desired_width(800.0),
-                        );
-                        ui.label("Stop bits (ignored right now, always 1):");
-                        ui.add(
-                            TextEdit::singleline(&mut self.stop_bits.to_string())
-                                .desired_width(800.0),
-                        );
+                        ui.label("Serial line (applied on connect):");
+                        serial_line_ui(ui, "scpi_line", &mut self.serial_line);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        self.show_victor_line_settings(ui);
                         ui.label("Serial poll interval (ms):");
                         let mut interval_str = self.poll_interval_ms.to_string();
                         if ui
@@ -100,9 +156,7 @@ impl super::MyApp {
                                     // Clamp hist_mem_depth to new max if necessary
                                     if self.hist_mem_depth > self.hist_mem_depth_max {
                                         self.hist_mem_depth = self.hist_mem_depth_max;
-                                        while self.hist_values.len() > self.hist_mem_depth {
-                                            self.hist_values.pop_front();
-                                        }
+                                        self.trim_histogram();
                                     }
                                 }
                             }
@@ -151,6 +205,37 @@ impl super::MyApp {
                                 }
                             }
                         }
+                        ui.label("Auto-hold:");
+                        ui.horizontal(|ui| {
+                            ui.label("settle within");
+                            ui.add(
+                                egui::DragValue::new(&mut self.auto_hold.tolerance_pct)
+                                    .range(0.001..=10.0)
+                                    .speed(0.01)
+                                    .suffix(" %"),
+                            );
+                            ui.label("for");
+                            ui.add(
+                                egui::DragValue::new(&mut self.auto_hold.samples)
+                                    .range(2..=100)
+                                    .suffix(" readings"),
+                            );
+                            ui.checkbox(&mut self.auto_hold.beep, "Beep (SCPI)");
+                            ui.checkbox(&mut self.auto_hold.record, "Record latched values");
+                        });
+                        ui.label("Image export size (px):");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut self.plot_export.width)
+                                    .range(320..=8192)
+                                    .prefix("W "),
+                            );
+                            ui.add(
+                                egui::DragValue::new(&mut self.plot_export.height)
+                                    .range(240..=8192)
+                                    .prefix("H "),
+                            );
+                        });
                         ui.horizontal(|ui| {
                             ui.vertical(|ui| {
                                 ui.label("Graph line color:");
@@ -185,6 +270,15 @@ impl super::MyApp {
                                 );
                             });
                         });
+                        self.show_shortcut_settings(ui);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        self.show_http_api_settings(ui);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        self.show_mqtt_settings(ui);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        self.show_influx_settings(ui);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        self.show_scpi_server_settings(ui);
                         if ui.button("Close").clicked() {
                             self.settings_open = false;
                         }
@@ -0,0 +1,311 @@
+//! Keyboard shortcuts, per-macro hotkeys and the foot-pedal key.
+//!
+//! Shortcuts fire while the window has focus and no text field is being
+//! typed into. The pedal key fires even then, since a pedal is pressed with
+//! the hands on the probes.
+
+use egui::{Color32, Context, Event, Key, Modifiers, RichText, Ui};
+
+use crate::multimeter::GenScpi;
+use crate::shortcuts::{Action, Chord, duplicate_chords, normalize_chord};
+
+/// Binding that the next key press is recorded into.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CaptureTarget {
+    Action(Action),
+    Pedal,
+    /// Hotkey of the SCPI macro with this id.
+    Macro(String),
+}
+
+enum Trigger {
+    Action(Action),
+    Macro(String),
+}
+
+fn key_by_name(name: &str) -> Option<Key> {
+    Key::ALL
+        .iter()
+        .copied()
+        .find(|key| key.name().eq_ignore_ascii_case(name))
+}
+
+fn chord_of(key: Key, modifiers: Modifiers) -> Chord {
+    Chord {
+        ctrl: modifiers.command,
+        alt: modifiers.alt,
+        shift: modifiers.shift,
+        key: key.name().to_owned(),
+    }
+}
+
+fn chord_matches(spec: &str, pressed: &Chord) -> bool {
+    Chord::parse(spec)
+        .ok()
+        .flatten()
+        .is_some_and(|c| c.matches(pressed.ctrl, pressed.alt, pressed.shift, &pressed.key))
+}
+
+impl super::MyApp {
+    /// Handle this frame's key presses. Runs before any widget reads input,
+    /// so a matched key is consumed and does not also reach the UI.
+    pub fn shortcuts_tick(&mut self, ctx: &Context) {
+        let presses: Vec<(Key, Modifiers)> = ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    Event::Key {
+                        key,
+                        pressed: true,
+                        repeat: false,
+                        modifiers,
+                        ..
+                    } => Some((*key, *modifiers)),
+                    _ => None,
+                })
+                .collect()
+        });
+        let Some(&(first_key, first_modifiers)) = presses.first() else {
+            return;
+        };
+        if let Some(target) = self.shortcut_capture.take() {
+            match first_key {
+                Key::Escape => {}
+                Key::Backspace | Key::Delete => self.bind_shortcut(&target, ""),
+                key => self.bind_shortcut(&target, &chord_of(key, first_modifiers).to_string()),
+            }
+            ctx.input_mut(|i| i.consume_key(first_modifiers, first_key));
+            return;
+        }
+        let typing = ctx.egui_wants_keyboard_input();
+        for (key, modifiers) in presses {
+            let pressed = chord_of(key, modifiers);
+            let trigger = if chord_matches(&self.shortcuts.pedal_key, &pressed) {
+                Some(Trigger::Action(self.shortcuts.pedal_action))
+            } else if self.shortcuts.enabled && !typing {
+                self.shortcut_trigger(&pressed)
+            } else {
+                None
+            };
+            let Some(trigger) = trigger else {
+                continue;
+            };
+            ctx.input_mut(|i| i.consume_key(modifiers, key));
+            match trigger {
+                Trigger::Action(action) => self.run_shortcut(action),
+                Trigger::Macro(id) => self.run_macro_hotkey(&id),
+            }
+        }
+    }
+
+    fn shortcut_trigger(&self, pressed: &Chord) -> Option<Trigger> {
+        if let Some(action) =
+            self.shortcuts
+                .action_for(pressed.ctrl, pressed.alt, pressed.shift, &pressed.key)
+        {
+            return Some(Trigger::Action(action));
+        }
+        self.scpi_macros
+            .iter()
+            .find(|m| chord_matches(&m.hotkey, pressed))
+            .map(|m| Trigger::Macro(m.id.clone()))
+    }
+
+    fn run_shortcut(&mut self, action: Action) {
+        let controllable = self.scpi_macros_on_main();
+        match action {
+            Action::Mode(mode) if controllable && self.mode_visible_in_ui(mode) => {
+                self.set_mode(mode);
+            }
+            Action::RangeUp | Action::RangeDown if controllable => {
+                let Some(rangecmd) = &self.rangecmd else {
+                    return;
+                };
+                // Table order: auto first, then smallest to largest
+                let idx = if action == Action::RangeUp {
+                    (self.curr_range + 1).min(rangecmd.len() - 1)
+                } else {
+                    self.curr_range.saturating_sub(1)
+                };
+                if idx != self.curr_range {
+                    self.curr_range = idx;
+                    self.meter_auto_range = idx == 0;
+                    self.confstring = rangecmd.gen_scpi(rangecmd.get_opt(idx).0);
+                    self.queue_scpi(self.confstring.clone(), true);
+                }
+            }
+            Action::NextRate if controllable => {
+                self.curr_rate = (self.curr_rate + 1) % self.ratecmd.len();
+                self.confstring = self
+                    .ratecmd
+                    .gen_scpi(self.ratecmd.get_opt(self.curr_rate).0);
+                self.queue_scpi(self.confstring.clone(), true);
+            }
+            Action::Hold => self.toggle_hold(),
+            Action::Rel => self.toggle_rel(),
+            Action::ToggleRecording => {
+                if self.recording_active {
+                    self.recording_active = false;
+                    self.save_recording_data();
+                } else if self.recording_file_path.is_empty() {
+                    // Needs a target file first
+                    self.recording_open = true;
+                } else {
+                    self.recording_active = true;
+                }
+            }
+            Action::RecordPoint if self.recording_active => self.record_measurement(),
+            _ => {}
+        }
+    }
+
+    fn run_macro_hotkey(&mut self, id: &str) {
+        let idn = self.device.lock().unwrap().clone();
+        if !self.scpi_macros_on_main() {
+            return;
+        }
+        if let Some((name, body)) = self
+            .scpi_macros
+            .iter()
+            .find(|m| m.id == id && m.applies_to.matches(&idn))
+            .map(|m| (m.name.clone(), m.body.clone()))
+        {
+            self.start_macro_from_ui(&name, &body);
+        }
+    }
+
+    fn bind_shortcut(&mut self, target: &CaptureTarget, spec: &str) {
+        match target {
+            CaptureTarget::Action(action) => self.shortcuts.set_key(*action, spec),
+            CaptureTarget::Pedal => self.shortcuts.pedal_key = normalize_chord(spec),
+            CaptureTarget::Macro(id) => {
+                if let Some(m) = self.scpi_macros.iter_mut().find(|m| m.id == *id) {
+                    m.hotkey = normalize_chord(spec);
+                }
+            }
+        }
+    }
+
+    fn shortcut_text(&self, target: &CaptureTarget) -> &str {
+        match target {
+            CaptureTarget::Action(action) => self.shortcuts.key(*action),
+            CaptureTarget::Pedal => &self.shortcuts.pedal_key,
+            CaptureTarget::Macro(id) => self
+                .scpi_macros
+                .iter()
+                .find(|m| m.id == *id)
+                .map_or("", |m| m.hotkey.as_str()),
+        }
+    }
+
+    /// Current chord plus a button that records the next key press.
+    pub fn shortcut_field(&mut self, ui: &mut Ui, target: CaptureTarget) {
+        if self.shortcut_capture.as_ref() == Some(&target) {
+            ui.label(RichText::new("Press a key… (Esc cancels, Backspace clears)").italics());
+            if ui.button("Cancel").clicked() {
+                self.shortcut_capture = None;
+            }
+            return;
+        }
+        let text = self.shortcut_text(&target).to_owned();
+        match Chord::parse(&text) {
+            Ok(None) => {
+                ui.weak("—");
+            }
+            Ok(Some(chord)) if key_by_name(&chord.key).is_some() => {
+                ui.monospace(&text);
+            }
+            Ok(Some(_)) => {
+                ui.label(RichText::new(format!("{text} (unknown key)")).color(Color32::RED));
+            }
+            Err(e) => {
+                ui.label(RichText::new(e).color(Color32::RED));
+            }
+        }
+        if ui.button("Set…").clicked() {
+            self.shortcut_capture = Some(target.clone());
+        }
+        if !text.is_empty() && ui.small_button("✕").on_hover_text("Clear").clicked() {
+            self.bind_shortcut(&target, "");
+        }
+    }
+
+    pub fn show_shortcut_settings(&mut self, ui: &mut Ui) {
+        ui.separator();
+        ui.label("Keyboard shortcuts:");
+        ui.checkbox(
+            &mut self.shortcuts.enabled,
+            "Enable shortcuts while the window has focus (not while typing in a field)",
+        );
+        egui::CollapsingHeader::new("Shortcut overview")
+            .id_salt("shortcut_overview")
+            .show(ui, |ui| {
+                egui::Grid::new("shortcut_grid")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for action in Action::all() {
+                            ui.label(action.label());
+                            ui.horizontal(|ui| {
+                                self.shortcut_field(ui, CaptureTarget::Action(action));
+                            });
+                            ui.end_row();
+                        }
+                        let hotkeys: Vec<(String, String)> = self
+                            .scpi_macros
+                            .iter()
+                            .filter(|m| !m.hotkey.is_empty())
+                            .map(|m| (m.id.clone(), m.name.clone()))
+                            .collect();
+                        for (id, name) in hotkeys {
+                            ui.label(format!("Macro '{name}'"));
+                            ui.horizontal(|ui| {
+                                self.shortcut_field(ui, CaptureTarget::Macro(id));
+                            });
+                            ui.end_row();
+                        }
+                        ui.label("Foot pedal key");
+                        ui.horizontal(|ui| {
+                            self.shortcut_field(ui, CaptureTarget::Pedal);
+                        });
+                        ui.end_row();
+                        ui.label("Foot pedal does");
+                        egui::ComboBox::from_id_salt("pedal_action")
+                            .selected_text(self.shortcuts.pedal_action.label())
+                            .show_ui(ui, |ui| {
+                                for action in Action::all() {
+                                    ui.selectable_value(
+                                        &mut self.shortcuts.pedal_action,
+                                        action,
+                                        action.label(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+                    });
+                ui.weak(
+                    "Macro hotkeys are set in the macro editor. The pedal key also works \
+                     while typing, so pick one nothing else uses (e.g. F13).",
+                );
+                let bindings: Vec<(String, String)> = Action::all()
+                    .into_iter()
+                    .map(|a| (a.label(), self.shortcuts.key(a).to_owned()))
+                    .chain(
+                        self.scpi_macros
+                            .iter()
+                            .map(|m| (format!("Macro '{}'", m.name), m.hotkey.clone())),
+                    )
+                    .chain([("Foot pedal".to_owned(), self.shortcuts.pedal_key.clone())])
+                    .collect();
+                for (chord, labels) in duplicate_chords(&bindings) {
+                    ui.label(
+                        RichText::new(format!(
+                            "{chord} is bound to {}; only one of them runs",
+                            labels.join(", ")
+                        ))
+                        .color(Color32::YELLOW),
+                    );
+                }
+            });
+    }
+}
@@ -1,9 +1,11 @@
-use egui::{AtomExt, FontFamily, FontId, SliderClamping, TextWrapMode, Vec2};
-use egui_dock::{DockArea, DockState, Style, TabViewer};
+use egui::{
+    AtomExt, FontFamily, FontId, SliderClamping, TextWrapMode, Vec2, ViewportBuilder, ViewportId,
+};
+use egui_dock::{DockArea, Style, TabViewer};
 use egui_dropdown::DropDownBox;
-use mio_serial::{DataBits, SerialPort, SerialPortBuilderExt};
 use std::collections::VecDeque;
 
+use crate::expr::TimedValue;
 use crate::helpers::{format_measurement, powered_by};
 use crate::multimeter::{GenScpi, MeterMode};
 
@@ -26,16 +28,33 @@ fn macro_name_fits_one_cell(ui: &egui::Ui, name: &str) -> bool {
 }
 
 // Enum to represent tab types
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum PlotTab {
     Graph,
     Histogram,
+    Analysis,
+    Combined,
+}
+
+impl PlotTab {
+    /// Default dock order.
+    pub const ALL: [Self; 4] = [Self::Graph, Self::Combined, Self::Histogram, Self::Analysis];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Graph => "Graph",
+            Self::Histogram => "Histogram",
+            Self::Analysis => "Analysis",
+            Self::Combined => "All meters",
+        }
+    }
 }
 
 // Tab viewer implementation for PlotTab
 struct PlotTabViewer<'a> {
     values: &'a VecDeque<f64>,
     hist_values: &'a mut VecDeque<f64>,
+    hist_times: &'a mut VecDeque<f64>,
     reverse_graph: &'a mut bool,
     graph_line_color: egui::Color32,
     hist_bar_color: egui::Color32,
@@ -51,20 +70,22 @@ struct PlotTabViewer<'a> {
     graph_update_interval_max: u64,
     hist_mem_depth_max: usize,
     curr_unit: &'a str,
+    plot_export: &'a mut super::graph::ExportSettings,
+    export_title: String,
+    analysis: &'a mut super::analysis::AnalysisState,
+    combined_history: &'a VecDeque<super::meters::CombinedSample>,
+    combined_traces: Vec<(String, egui::Color32)>,
 }
 
 impl TabViewer for PlotTabViewer<'_> {
     type Tab = PlotTab;
 
     fn id(&mut self, tab: &mut Self::Tab) -> egui::Id {
-        egui::Id::new(&*tab)
+        egui::Id::new(*tab)
     }
 
     fn title(&mut self, tab: &mut Self::Tab) -> egui::WidgetText {
-        match tab {
-            PlotTab::Graph => "Graph".into(),
-            PlotTab::Histogram => "Histogram".into(),
-        }
+        tab.title().into()
     }
 
     fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Self::Tab) {
@@ -80,10 +101,13 @@ impl TabViewer for PlotTabViewer<'_> {
                 self.mem_depth_max,
                 self.graph_update_interval_max,
                 self.curr_unit,
+                self.plot_export,
+                &self.export_title,
             ),
             PlotTab::Histogram => super::graph::show_histogram(
                 ui,
                 self.hist_values,
+                self.hist_times,
                 self.curr_meas,
                 self.metermode,
                 self.graph_config,
@@ -92,6 +116,24 @@ impl TabViewer for PlotTabViewer<'_> {
                 self.hist_collect_interval_ms,
                 self.hist_mem_depth,
                 self.hist_mem_depth_max,
+                self.plot_export,
+                &self.export_title,
+            ),
+            PlotTab::Analysis => super::analysis::show_analysis(
+                ui,
+                self.analysis,
+                self.hist_values,
+                self.hist_times,
+                self.curr_unit,
+            ),
+            PlotTab::Combined => super::graph::show_combined_graph(
+                ui,
+                self.combined_history,
+                &self.combined_traces,
+                self.mem_depth,
+                self.graph_update_interval_ms,
+                self.mem_depth_max,
+                self.graph_update_interval_max,
             ),
         }
     }
@@ -123,7 +165,83 @@ impl super::MyApp {
         }
     }
 
-    fn scpi_macros_on_main(&self) -> bool {
+    /// Value and unit text of the main display: per-mode unit scaling,
+    /// Victor LCD text and decoder units.
+    pub fn main_display(&self) -> (String, String) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // 86B/C/D only: glass text from segment decode.
+            let lcd_override = if self.connection_type == super::ConnectionType::Victor86bcdSerial
+                && self.curr_meas != crate::helpers::METER_OVERLOAD_VALUE
+                && !self.victor_lcd_display.is_empty()
+            {
+                Some((self.victor_lcd_display.as_str(), self.curr_unit.as_str()))
+            } else {
+                None
+            };
+            // 86B/C/D: lcd_override. HID: no auto-scale.
+            // SCPI: format_measurement(auto_scale).
+            // 86E: ON → SI + magnitude auto; OFF → decoder unit (meter range).
+            let auto_scale = match self.connection_type {
+                super::ConnectionType::Victor86bcdSerial | super::ConnectionType::VictorHid => {
+                    false
+                }
+                _ => self.auto_scale_units(&self.metermode),
+            };
+            let (formatted_value, mut display_unit) = {
+                let use_meter_unit = self.connection_type == super::ConnectionType::Victor86eSerial
+                    && !auto_scale
+                    && !self.curr_unit.is_empty()
+                    && self.curr_meas.is_finite()
+                    && self.curr_meas != crate::helpers::METER_OVERLOAD_VALUE;
+
+                if use_meter_unit {
+                    // What the meter “sends” as unit for this range.
+                    let scaled =
+                        crate::victor_es519xx::si_to_meter_unit(self.curr_meas, &self.curr_unit);
+                    let (num, _) = format_measurement(
+                        scaled,
+                        10,
+                        1_000_000.0,
+                        0.000001,
+                        &self.metermode,
+                        false,
+                        None,
+                    );
+                    (num, self.curr_unit.clone())
+                } else {
+                    format_measurement(
+                        self.curr_meas,
+                        10,
+                        1_000_000.0,
+                        0.000001,
+                        &self.metermode,
+                        auto_scale,
+                        lcd_override,
+                    )
+                }
+            };
+            // Temp unit from decoder (°C / °F); formatter defaults to °C.
+            if self.metermode == MeterMode::Temp && !self.curr_unit.is_empty() {
+                display_unit = self.curr_unit.clone();
+            }
+            (formatted_value, display_unit)
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            format_measurement(
+                self.curr_meas,
+                10,
+                1_000_000.0,
+                0.000001,
+                &self.metermode,
+                self.auto_scale_units(&self.metermode),
+                None,
+            )
+        }
+    }
+
+    pub fn scpi_macros_on_main(&self) -> bool {
         self.connection_type == super::ConnectionType::ScpiSerial
             && self.connection_state == super::ConnectionState::Connected
             && !self.is_read_only()
@@ -152,16 +270,44 @@ impl super::MyApp {
         }
     }
 
+    /// Button macros for the connected SCPI meter, then button scripts for
+    /// any connected meter.
     fn matching_button_macros(&self) -> Vec<(String, String)> {
+        let mut buttons = Vec::new();
         let idn = self.device.lock().unwrap().clone();
-        if idn.is_empty() {
-            return Vec::new();
+        if self.scpi_macros_on_main() && !idn.is_empty() {
+            buttons.extend(
+                self.scpi_macros
+                    .iter()
+                    .filter(|m| m.show_as_button && m.applies_to.matches(&idn))
+                    .map(|m| (m.id.clone(), m.name.clone())),
+            );
         }
-        self.scpi_macros
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.connection_state == super::ConnectionState::Connected {
+            buttons.extend(
+                self.scripts
+                    .iter()
+                    .filter(|s| s.show_as_button)
+                    .map(|s| (s.id.clone(), s.name.clone())),
+            );
+        }
+        buttons
+    }
+
+    /// Start the macro or script behind a main-window button.
+    fn run_button(&mut self, id: &str) {
+        if let Some((name, body)) = self
+            .scpi_macros
             .iter()
-            .filter(|m| m.show_as_button && m.applies_to.matches(&idn))
-            .map(|m| (m.id.clone(), m.name.clone()))
-            .collect()
+            .find(|m| m.id == id)
+            .map(|m| (m.name.clone(), m.body.clone()))
+        {
+            self.start_macro_from_ui(&name, &body);
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.request_script(id);
     }
 
     fn show_macro_buttons(&mut self, ui: &mut egui::Ui) {
@@ -217,14 +363,8 @@ impl super::MyApp {
                                 ui.add(btn).clicked()
                             })
                             .inner;
-                        if clicked
-                            && let Some(body) = self
-                                .scpi_macros
-                                .iter()
-                                .find(|m| m.id == id)
-                                .map(|m| m.body.clone())
-                        {
-                            self.run_macro_body(&body, false);
+                        if clicked {
+                            self.run_button(&id);
                         }
                     }
                 });
@@ -270,15 +410,17 @@ impl super::MyApp {
                     }
                 });
             }
-            // Initialize dock state
-            let tabs = vec![PlotTab::Graph, PlotTab::Histogram];
-            self.plot_dock_state = DockState::new(tabs);
+            // Saved dock layout, plus any tab it is missing
+            self.restore_plot_layout();
             if self.connect_on_startup {
                 connect_now = true;
             }
             self.is_init = true;
         }
 
+        let now = ui.ctx().input(|i| i.time);
+        self.frame_readings.clear();
+
         // Victor 86B/C/D serial: LCD text + mode from DM1107 frames (read-only, no SCPI ranges)
         #[cfg(not(target_arch = "wasm32"))]
         if self.connection_type == super::ConnectionType::Victor86bcdSerial {
@@ -287,12 +429,19 @@ impl super::MyApp {
                     self.victor_lcd_display = update.display;
                     if let Some(v) = update.value {
                         self.curr_meas = v;
+                        self.curr_meas_time = now;
+                        self.counters.readings += 1;
+                        self.frame_readings.push(TimedValue {
+                            value: v,
+                            time: now,
+                        });
                     }
                     if update.mode != self.metermode {
                         self.metermode = update.mode;
                         self.curr_unit = update.unit;
                         self.values = VecDeque::with_capacity(self.mem_depth);
                         self.hist_values = VecDeque::with_capacity(self.hist_mem_depth);
+                        self.hist_times = VecDeque::with_capacity(self.hist_mem_depth);
                         self.rangecmd = None;
                         self.curr_range = 0;
                         if self.value_debug {
@@ -314,12 +463,18 @@ impl super::MyApp {
         if poll_serial_rx {
             if let Some(ref mut rx) = self.serial_rx {
                 while let Ok(meas_opt) = rx.try_recv() {
-                    if let Some(meas) = meas_opt {
+                    if let Some((meas, received)) = meas_opt {
+                        let time = super::reading_time(now, received);
                         self.curr_meas = meas;
+                        self.curr_meas_time = time;
+                        self.counters.readings += 1;
+                        self.frame_readings.push(TimedValue { value: meas, time });
                     }
                 }
             }
         }
+        self.reconnect_tick(now);
+        self.poll_aux_meters(now);
 
         // After *IDN? is stored on `device`, play dialect bootstrap then user connect macros.
         if self.connection_type == super::ConnectionType::ScpiSerial
@@ -361,10 +516,27 @@ impl super::MyApp {
             self.apply_meter_status(status);
         }
 
+        self.update_derived(now);
+        self.feed_integrator();
+        self.binning_tick();
+        self.auto_hold_tick();
+        self.shortcuts_tick(ui.ctx());
+        self.import_dropped_macros(ui.ctx());
+        self.macro_run_tick(ui.ctx(), now);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.script_tick(ui.ctx());
+        #[cfg(not(target_arch = "wasm32"))]
+        self.http_api_tick(ui.ctx(), now);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.mqtt_tick(ui.ctx(), now);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.influx_tick(now);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.scpi_server_tick(ui.ctx());
+
         // Handle graph and histogram updates and recording based on the configured interval
-        let current_time = ui.ctx().input(|i| i.time); // Get current time in seconds
         let graph_interval = self.graph_update_interval_ms as f64 / 1000.0; // Convert ms to seconds
-        if current_time - self.last_graph_update >= graph_interval {
+        if now - self.last_graph_update >= graph_interval {
             // Skip non-finite samples — histogram binning panics on Inf/NaN.
             if self.curr_meas.is_finite() {
                 self.values.push_back(self.curr_meas);
@@ -372,17 +544,19 @@ impl super::MyApp {
                 while self.values.len() > self.mem_depth {
                     self.values.pop_front();
                 }
-                // Record measurement for fixed interval mode
-                if self.recording_active
-                    && matches!(self.recording_mode, super::RecordingMode::FixedInterval)
-                    && current_time - self.last_record_time
-                        >= self.recording_interval_ms as f64 / 1000.0
-                {
-                    self.record_measurement();
-                    self.last_record_time = current_time;
-                }
             }
-            self.last_graph_update = current_time;
+            self.sample_combined(now);
+            self.battery_tick();
+            // Record measurement for fixed interval mode
+            if self.has_any_reading()
+                && self.recording_active
+                && matches!(self.recording_mode, super::RecordingMode::FixedInterval)
+                && now - self.last_record_time >= self.recording_interval_ms as f64 / 1000.0
+            {
+                self.record_measurement();
+                self.last_record_time = now;
+            }
+            self.last_graph_update = now;
         }
 
         egui::Panel::top("top_panel").show(ui, |ui| {
@@ -394,13 +568,33 @@ impl super::MyApp {
                     if ui.button("SCPI macros").clicked() {
                         self.macros_open = true;
                     }
+                    if ui.button("Profiles").clicked() {
+                        self.profiles_open = true;
+                    }
+                    if ui.button("SCPI console").clicked() {
+                        self.console_open = true;
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button("Scripts").clicked() {
+                        self.scripts_open = true;
+                    }
+                    if ui.button("Battery test").clicked() {
+                        self.battery_open = true;
+                    }
+                    if ui.button("Component binning").clicked() {
+                        self.binning_open = true;
+                    }
                     if !is_web && ui.button("Quit").clicked() {
                         self.disconnect(); // Use disconnect method instead of partial cleanup
+                        self.disconnect_aux_meters();
                         ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
                     }
                 });
+                self.show_layout_menu(ui);
                 ui.add_space(16.0);
                 egui::widgets::global_theme_preference_buttons(ui);
+                ui.add_space(16.0);
+                self.show_profile_switcher(ui);
             });
         });
 
@@ -534,97 +728,20 @@ impl super::MyApp {
                         super::ConnectionState::Disconnected => {
                             if ui.button("Connect").clicked() || connect_now {
                                 connect_now = false;
-                                self.connection_state = super::ConnectionState::Connecting;
-                                self.connection_error = None;
-
-                                match self.connection_type {
-                                    super::ConnectionType::ScpiSerial => {
-                                        match mio_serial::new(&self.serial_port, self.baud_rate)
-                                            .open_native_async()
-                                        {
-                                            Ok(serial) => {
-                                                self.serial = Some(serial);
-                                                if let Some(ref mut serial) = self.serial {
-                                                    let _ = serial.set_data_bits(DataBits::Eight);
-                                                    let _ = serial
-                                                        .set_stop_bits(mio_serial::StopBits::One);
-                                                    let _ =
-                                                        serial.set_parity(mio_serial::Parity::None);
-                                                    self.connection_state =
-                                                        super::ConnectionState::Connected;
-                                                    self.curr_meter = "OWON XDM1041".to_owned();
-                                                    self.spawn_serial_task();
-                                                }
-                                            }
-                                            Err(e) => {
-                                                self.connection_state =
-                                                    super::ConnectionState::Disconnected;
-                                                self.connection_error =
-                                                    Some(format!("Failed to connect: {}", e));
-                                            }
-                                        }
-                                    }
-                                    #[cfg(not(target_arch = "wasm32"))]
-                                    super::ConnectionType::VictorHid => {
-                                        if self.hid_device_path.is_empty() {
-                                            self.connection_state =
-                                                super::ConnectionState::Disconnected;
-                                            self.connection_error =
-                                                Some("No Victor HID device selected".to_owned());
-                                        } else {
-                                            self.connection_state =
-                                                super::ConnectionState::Connected;
-                                            self.curr_meter = "Victor 86B/C/D".to_owned();
-                                            self.rangecmd = None;
-                                            self.spawn_hid_task();
-                                        }
-                                    }
-                                    #[cfg(not(target_arch = "wasm32"))]
-                                    super::ConnectionType::Victor86bcdSerial => {
-                                        match super::open_victor_8n1_serial(
-                                            &self.serial_port,
-                                            crate::victor_dm1107::VICTOR_86BCD_BAUD,
-                                        ) {
-                                            Ok(serial) => {
-                                                self.serial = Some(serial);
-                                                self.connection_state =
-                                                    super::ConnectionState::Connected;
-                                                self.curr_meter =
-                                                    "Victor 86B/C/D (DM1107)".to_owned();
-                                                self.rangecmd = None;
-                                                self.spawn_victor_86bcd_serial_task();
-                                            }
-                                            Err(e) => {
-                                                self.connection_state =
-                                                    super::ConnectionState::Disconnected;
-                                                self.connection_error =
-                                                    Some(format!("Failed to connect: {}", e));
-                                            }
-                                        }
-                                    }
-                                    #[cfg(not(target_arch = "wasm32"))]
-                                    super::ConnectionType::Victor86eSerial => {
-                                        match super::open_victor_7o1_serial(
-                                            &self.serial_port,
-                                            crate::victor_es519xx::VICTOR_86E_BAUD,
-                                        ) {
-                                            Ok(serial) => {
-                                                self.serial = Some(serial);
-                                                self.connection_state =
-                                                    super::ConnectionState::Connected;
-                                                self.curr_meter = "Victor 86E".to_owned();
-                                                self.rangecmd = None;
-                                                self.spawn_victor_86e_serial_task();
-                                            }
-                                            Err(e) => {
-                                                self.connection_state =
-                                                    super::ConnectionState::Disconnected;
-                                                self.connection_error =
-                                                    Some(format!("Failed to connect: {}", e));
-                                            }
-                                        }
-                                    }
-                                }
+                                self.connect_main();
+                            }
+                            #[cfg(not(target_arch = "wasm32"))]
+                            if ui
+                                .add_enabled(
+                                    !self.detect.is_running(),
+                                    egui::Button::new("Auto-detect"),
+                                )
+                                .on_hover_text(
+                                    "Scan serial ports and Victor HID devices for a meter",
+                                )
+                                .clicked()
+                            {
+                                self.start_detection();
                             }
                         }
                         super::ConnectionState::Connecting => {
@@ -635,12 +752,39 @@ impl super::MyApp {
                                 self.disconnect();
                             }
                         }
+                        super::ConnectionState::Reconnecting => {
+                            if ui.button("Stop retrying").clicked() {
+                                self.disconnect();
+                            }
+                        }
                     }
 
                     // Recording button
                     if ui.button("Start Recording").clicked() {
                         self.recording_open = true;
                     }
+                    ui.toggle_value(&mut self.auto_hold.enabled, "Auto-hold")
+                        .on_hover_text(
+                            "Latch each settled reading, and record it while recording in \
+                             Manual mode. Lift the probes to arm for the next one. \
+                             Settle band and beep are in Settings.",
+                        );
+                    let mut hold = self.display_hold.is_some();
+                    if ui
+                        .toggle_value(&mut hold, "Hold")
+                        .on_hover_text("Keep the current reading on screen")
+                        .changed()
+                    {
+                        self.toggle_hold();
+                    }
+                    let mut rel = self.rel_reference.is_some();
+                    if ui
+                        .toggle_value(&mut rel, "REL")
+                        .on_hover_text("Show the difference to the current reading")
+                        .changed()
+                    {
+                        self.toggle_rel();
+                    }
                     self.show_record_macro_button(ui);
                 });
 
@@ -665,8 +809,22 @@ impl super::MyApp {
                                 ui.label("Connected, awaiting device ID...");
                             }
                         }
+                        super::ConnectionState::Reconnecting => {
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "Disconnected, retrying (attempt {}): {}",
+                                    self.reconnect.attempts,
+                                    self.connection_error.as_deref().unwrap_or("link lost"),
+                                ))
+                                .color(egui::Color32::ORANGE),
+                            );
+                        }
                     }
                 });
+
+                self.show_aux_meters(ui);
+                self.show_derived_channels(ui);
+                self.show_integrator(ui);
             });
 
             ui.separator();
@@ -731,81 +889,7 @@ impl super::MyApp {
                         Vec2 { x: 400.0, y: 300.0 },
                         egui::Layout::top_down(egui::Align::RIGHT).with_cross_justify(false),
                         |ui| {
-                            #[cfg(not(target_arch = "wasm32"))]
-                            let (formatted_value, display_unit) = {
-                                // 86B/C/D only: glass text from segment decode.
-                                let lcd_override = if self.connection_type
-                                    == super::ConnectionType::Victor86bcdSerial
-                                    && self.curr_meas != crate::helpers::METER_OVERLOAD_VALUE
-                                    && !self.victor_lcd_display.is_empty()
-                                {
-                                    Some((
-                                        self.victor_lcd_display.as_str(),
-                                        self.curr_unit.as_str(),
-                                    ))
-                                } else {
-                                    None
-                                };
-                                // 86B/C/D: lcd_override. HID: no auto-scale.
-                                // SCPI: format_measurement(auto_scale).
-                                // 86E: ON → SI + magnitude auto; OFF → decoder unit (meter range).
-                                let auto_scale = match self.connection_type {
-                                    super::ConnectionType::Victor86bcdSerial
-                                    | super::ConnectionType::VictorHid => false,
-                                    _ => self.auto_scale_units(&self.metermode),
-                                };
-                                let (formatted_value, mut display_unit) = {
-                                    let use_meter_unit = self.connection_type
-                                        == super::ConnectionType::Victor86eSerial
-                                        && !auto_scale
-                                        && !self.curr_unit.is_empty()
-                                        && self.curr_meas.is_finite()
-                                        && self.curr_meas != crate::helpers::METER_OVERLOAD_VALUE;
-
-                                    if use_meter_unit {
-                                        // What the meter “sends” as unit for this range.
-                                        let scaled = crate::victor_es519xx::si_to_meter_unit(
-                                            self.curr_meas,
-                                            &self.curr_unit,
-                                        );
-                                        let (num, _) = format_measurement(
-                                            scaled,
-                                            10,
-                                            1_000_000.0,
-                                            0.000001,
-                                            &self.metermode,
-                                            false,
-                                            None,
-                                        );
-                                        (num, self.curr_unit.clone())
-                                    } else {
-                                        format_measurement(
-                                            self.curr_meas,
-                                            10,
-                                            1_000_000.0,
-                                            0.000001,
-                                            &self.metermode,
-                                            auto_scale,
-                                            lcd_override,
-                                        )
-                                    }
-                                };
-                                // Temp unit from decoder (°C / °F); formatter defaults to °C.
-                                if self.metermode == MeterMode::Temp && !self.curr_unit.is_empty() {
-                                    display_unit = self.curr_unit.clone();
-                                }
-                                (formatted_value, display_unit)
-                            };
-                            #[cfg(target_arch = "wasm32")]
-                            let (formatted_value, display_unit) = format_measurement(
-                                self.curr_meas,
-                                10,
-                                1_000_000.0,
-                                0.000001,
-                                &self.metermode,
-                                self.auto_scale_units(&self.metermode),
-                                None,
-                            );
+                            let (formatted_value, display_unit) = self.main_display();
                             ui.label(
                                 egui::RichText::new(formatted_value)
                                     .color(self.measurement_font_color)
@@ -822,6 +906,46 @@ impl super::MyApp {
                                         family: FontFamily::Name("B612Mono-Bold".into()),
                                     }),
                             );
+                            if self.auto_hold.enabled {
+                                let text = match self.auto_hold.held {
+                                    Some(held) => {
+                                        let (value, unit) = format_measurement(
+                                            held,
+                                            10,
+                                            1_000_000.0,
+                                            0.000001,
+                                            &self.metermode,
+                                            self.auto_scale_units(&self.metermode),
+                                            None,
+                                        );
+                                        format!(
+                                            "{} HOLD {} {}",
+                                            if self.auto_hold.armed { "○" } else { "●" },
+                                            value.trim(),
+                                            unit
+                                        )
+                                    }
+                                    None => "○ HOLD armed".to_owned(),
+                                };
+                                ui.label(
+                                    egui::RichText::new(text)
+                                        .color(self.measurement_font_color)
+                                        .font(FontId {
+                                            size: 24.0,
+                                            family: FontFamily::Name("B612Mono-Bold".into()),
+                                        }),
+                                );
+                            }
+                            for text in self.hold_rel_lines() {
+                                ui.label(
+                                    egui::RichText::new(text)
+                                        .color(self.measurement_font_color)
+                                        .font(FontId {
+                                            size: 24.0,
+                                            family: FontFamily::Name("B612Mono-Bold".into()),
+                                        }),
+                                );
+                            }
                         },
                     );
                 });
@@ -881,9 +1005,7 @@ impl super::MyApp {
                                 });
                             }
                         }); // add_enabled_ui
-                        if self.scpi_macros_on_main() {
-                            self.show_macro_buttons(ui);
-                        }
+                        self.show_macro_buttons(ui);
                     });
                 });
 
@@ -987,10 +1109,17 @@ impl super::MyApp {
             // Dock area for graph and histogram
             {
                 // Scope to limit the mutable borrow of plot_dock_state
+                let combined_traces = self.combined_traces();
                 let dock_state = &mut self.plot_dock_state;
+                let export_title = match crate::scpi_macro::idn_model(&self.device.lock().unwrap())
+                {
+                    model if model.is_empty() => "RustyMeter".to_owned(),
+                    model => model,
+                };
                 let mut viewer = PlotTabViewer {
                     values: &self.values,
                     hist_values: &mut self.hist_values,
+                    hist_times: &mut self.hist_times,
                     reverse_graph: &mut self.reverse_graph,
                     graph_line_color: self.graph_line_color,
                     hist_bar_color: self.hist_bar_color,
@@ -1006,16 +1135,56 @@ impl super::MyApp {
                     graph_update_interval_max: self.graph_update_interval_max,
                     hist_mem_depth_max: self.hist_mem_depth_max,
                     curr_unit: &self.curr_unit,
+                    plot_export: &mut self.plot_export,
+                    export_title,
+                    analysis: &mut self.analysis,
+                    combined_history: &self.combined_history,
+                    combined_traces,
                 };
                 DockArea::new(dock_state)
                     .style(Style::from_egui(ui.style()))
                     .show_close_buttons(false)
                     .show_inside(ui, &mut viewer);
+
+                // Torn-off tabs, each in its own OS window. Closing one docks it again.
+                let mut dock_back = Vec::new();
+                for &tab in &self.torn_off_tabs {
+                    ui.ctx().show_viewport_immediate(
+                        ViewportId::from_hash_of(("plot_tab_viewport", tab)),
+                        ViewportBuilder::default()
+                            .with_title(format!("RustyMeter - {}", tab.title()))
+                            .with_inner_size([700.0, 450.0])
+                            .with_resizable(true),
+                        |ui, _class| {
+                            let mut shown = tab;
+                            egui::CentralPanel::default().show(ui, |ui| {
+                                viewer.ui(ui, &mut shown);
+                            });
+                            if ui.ctx().input(|i| i.viewport().close_requested()) {
+                                dock_back.push(tab);
+                            }
+                        },
+                    );
+                }
+                for tab in dock_back {
+                    self.dock_plot_tab(tab);
+                }
             }
 
             // Show settings and recording windows
             self.show_settings(ui.ctx());
             self.show_macros(ui.ctx());
+            self.show_profiles(ui.ctx());
+            self.show_console(ui.ctx());
+            self.show_macro_run(ui.ctx());
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                self.show_scripts(ui.ctx());
+                self.show_script_dialog(ui.ctx());
+                self.show_detect(ui.ctx());
+            }
+            self.show_battery_test(ui.ctx());
+            self.show_binning(ui.ctx());
             self.show_recording_window(ui);
 
             // ensure repaint based on update intervals
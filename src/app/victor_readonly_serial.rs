@@ -2,7 +2,8 @@
 //!
 //! Both meters stream measurement frames over CP2102 USB-UART; neither accepts
 //! remote commands. Serial line settings are applied before spawn (see
-//! [`super::open_victor_8n1_serial`] / [`super::open_victor_7o1_serial`]).
+//! [`super::open_serial_line`], [`super::VICTOR_86BCD_LINE`] and
+//! [`super::VICTOR_86E_LINE`]).
 //!
 //! I/O uses [`mio::Poll`] on a blocking thread. The poll wakes when the port is
 //! readable; the idle timeout exists only so shutdown can be observed.
@@ -68,7 +69,7 @@ struct CaptureSide {
 
 enum TaskDispatch {
     Es519xx {
-        tx_value: mpsc::Sender<Option<f64>>,
+        tx_value: mpsc::Sender<Option<super::Reading>>,
         tx_mode: mpsc::Sender<(MeterMode, String)>,
     },
     Dm1107 {
@@ -144,7 +145,9 @@ impl Decoder {
                             reading.value, reading.mode, reading.unit
                         );
                     }
-                    let _ = tx_value.send(Some(reading.value)).await;
+                    let _ = tx_value
+                        .send(Some((reading.value, std::time::Instant::now())))
+                        .await;
                     // Always push mode/unit so range-unit changes (Ω↔kΩ) update the UI.
                     let mode_changed = *last_mode != Some(reading.mode);
                     let unit_changed = last_unit.as_deref() != Some(reading.unit.as_str());
@@ -314,6 +317,7 @@ impl SerialReader {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_serial_loop(
     mut serial: mio_serial::SerialStream,
     protocol: VictorReadonlyProtocol,
@@ -322,6 +326,7 @@ async fn run_serial_loop(
     mut capture: Option<CaptureSide>,
     value_debug_shared: Arc<std::sync::Mutex<bool>>,
     device_shared: Arc<std::sync::Mutex<String>>,
+    link_lost: Arc<std::sync::Mutex<Option<String>>>,
 ) {
     let _ = serial.clear(ClearBuffer::Input);
     // Harmless on 86E if ignored; required on some 86B/C/D CP2102 links.
@@ -388,7 +393,11 @@ async fn run_serial_loop(
                 }
             }
             chunk = reader.next_chunk(), if !shutting_down => {
-                let Some(chunk) = chunk else { break; };
+                let Some(chunk) = chunk else {
+                    // The reader only stops on its own when the port fails
+                    *link_lost.lock().unwrap() = Some("Victor serial port closed".to_owned());
+                    break;
+                };
                 let debug = *value_debug_shared.lock().unwrap();
                 decoder
                     .feed_and_dispatch(protocol, &chunk, &mut active_capture, &dispatch, debug)
@@ -410,56 +419,16 @@ async fn run_serial_loop(
 
 impl super::MyApp {
     pub fn spawn_victor_readonly_serial_task(&mut self, protocol: VictorReadonlyProtocol) {
-        if self.serial.is_none() {
+        let Some(serial) = self.serial.take() else {
             return;
-        }
-
-        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
-        self.shutdown_tx = Some(shutdown_tx);
-        self.serial_tx = None;
-
-        let serial = self.serial.take().unwrap();
-        let value_debug_shared = self.value_debug_shared.clone();
-        let device_shared = self.device.clone();
-
-        let (dispatch, capture) = match protocol {
-            VictorReadonlyProtocol::Es519xx => {
-                let (tx_value, rx_value) = mpsc::channel::<Option<f64>>(100);
-                let (tx_mode, rx_mode) = mpsc::channel::<(MeterMode, String)>(10);
-                self.serial_rx = Some(rx_value);
-                self.mode_rx = Some(rx_mode);
-                self.victor_86bcd_rx = None;
-                (TaskDispatch::Es519xx { tx_value, tx_mode }, None)
-            }
-            VictorReadonlyProtocol::Dm1107 => {
-                let (capture_tx, capture_rx) = mpsc::channel::<Victor86bcdCaptureJob>(8);
-                let (tx_live, rx_live) = mpsc::channel::<Dm1107LiveUpdate>(100);
-                self.victor_86bcd_capture_tx = Some(capture_tx);
-                self.victor_86bcd_rx = Some(rx_live);
-                self.serial_rx = None;
-                self.mode_rx = None;
-                (
-                    TaskDispatch::Dm1107 { tx_live },
-                    Some(CaptureSide {
-                        rx: capture_rx,
-                        status: self.victor_86bcd_capture_status_shared.clone(),
-                    }),
-                )
-            }
         };
-
-        tokio::spawn(async move {
-            run_serial_loop(
-                serial,
-                protocol,
-                shutdown_rx,
-                dispatch,
-                capture,
-                value_debug_shared,
-                device_shared,
-            )
-            .await;
-        });
+        let link = spawn_victor_readonly(
+            serial,
+            protocol,
+            self.meter_shared(),
+            Some(self.victor_86bcd_capture_status_shared.clone()),
+        );
+        self.adopt_link(link);
     }
 
     pub fn spawn_victor_86e_serial_task(&mut self) {
@@ -471,6 +440,60 @@ impl super::MyApp {
     }
 }
 
+/// Start the read-only Victor task on an opened port and hand back its channels.
+/// DM1107 links only accept capture jobs when `capture_status` is given.
+pub(super) fn spawn_victor_readonly(
+    serial: mio_serial::SerialStream,
+    protocol: VictorReadonlyProtocol,
+    shared: super::MeterShared,
+    capture_status: Option<Arc<std::sync::Mutex<victor_86bcd_capture::Victor86bcdCaptureStatus>>>,
+) -> super::MeterLink {
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let mut link = super::MeterLink {
+        shutdown_tx: Some(shutdown_tx),
+        ..Default::default()
+    };
+
+    let (dispatch, capture) = match protocol {
+        VictorReadonlyProtocol::Es519xx => {
+            let (tx_value, rx_value) = mpsc::channel::<Option<super::Reading>>(100);
+            let (tx_mode, rx_mode) = mpsc::channel::<(MeterMode, String)>(10);
+            link.data_rx = Some(rx_value);
+            link.mode_rx = Some(rx_mode);
+            (TaskDispatch::Es519xx { tx_value, tx_mode }, None)
+        }
+        VictorReadonlyProtocol::Dm1107 => {
+            let (tx_live, rx_live) = mpsc::channel::<Dm1107LiveUpdate>(100);
+            link.live_rx = Some(rx_live);
+            let capture = capture_status.map(|status| {
+                let (capture_tx, capture_rx) = mpsc::channel::<Victor86bcdCaptureJob>(8);
+                link.capture_tx = Some(capture_tx);
+                CaptureSide {
+                    rx: capture_rx,
+                    status,
+                }
+            });
+            (TaskDispatch::Dm1107 { tx_live }, capture)
+        }
+    };
+
+    tokio::spawn(async move {
+        run_serial_loop(
+            serial,
+            protocol,
+            shutdown_rx,
+            dispatch,
+            capture,
+            shared.value_debug,
+            shared.device,
+            shared.link_lost,
+        )
+        .await;
+    });
+
+    link
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,223 @@
+//! Battery discharge test: log voltage and current under load until the
+//! cell reaches a cutoff voltage, then report capacity, energy and an
+//! internal resistance estimate.
+
+use crate::integrator::Integrator;
+
+/// One logged point of the discharge curve. `t` is seconds since start,
+/// `ah` / `wh` are the totals up to this point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DischargeSample {
+    pub t: f64,
+    pub voltage: f64,
+    pub current: f64,
+    pub ah: f64,
+    pub wh: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DischargeReport {
+    pub duration_s: f64,
+    pub capacity_ah: f64,
+    pub energy_wh: f64,
+    pub start_v: f64,
+    pub end_v: f64,
+    pub avg_current: f64,
+    /// (open circuit V − first loaded V) / first current, when the open
+    /// circuit voltage was captured before the load was applied.
+    pub internal_resistance: Option<f64>,
+}
+
+/// Current through a fixed load resistance.
+pub fn current_from_load(voltage: f64, load_ohms: f64) -> f64 {
+    if load_ohms > 0.0 {
+        voltage / load_ohms
+    } else {
+        f64::NAN
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DischargeRun {
+    pub samples: Vec<DischargeSample>,
+    pub open_circuit_v: Option<f64>,
+    start_time: Option<f64>,
+    cutoff_v: f64,
+    confirm_samples: usize,
+    below_cutoff: usize,
+    charge: Integrator,
+    energy: Integrator,
+    finished: bool,
+}
+
+impl DischargeRun {
+    /// `confirm_samples` consecutive readings at or below `cutoff_v` end the
+    /// run, so a single noisy reading does not stop it early.
+    pub fn new(cutoff_v: f64, confirm_samples: usize, open_circuit_v: Option<f64>) -> Self {
+        let mut charge = Integrator::default();
+        let mut energy = Integrator::default();
+        charge.start();
+        energy.start();
+        Self {
+            samples: Vec::new(),
+            open_circuit_v,
+            start_time: None,
+            cutoff_v,
+            confirm_samples: confirm_samples.max(1),
+            below_cutoff: 0,
+            charge,
+            energy,
+            finished: false,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// End the run early, keeping the samples logged so far.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Time of the last logged sample, in the caller's clock.
+    pub fn last_time(&self) -> Option<f64> {
+        Some(self.start_time? + self.samples.last()?.t)
+    }
+
+    /// Log a sample taken at `t`. Returns true once the cutoff is confirmed;
+    /// later samples are ignored.
+    pub fn add(&mut self, t: f64, voltage: f64, current: f64) -> bool {
+        if self.finished || !voltage.is_finite() || !current.is_finite() {
+            return self.finished;
+        }
+        if self.last_time().is_some_and(|last| t <= last) {
+            return false;
+        }
+        let start = *self.start_time.get_or_insert(t);
+        // Magnitude, so either probe polarity counts as discharge
+        let current = current.abs();
+        self.charge.add(t, current);
+        self.energy.add(t, current * voltage);
+        self.samples.push(DischargeSample {
+            t: t - start,
+            voltage,
+            current,
+            ah: self.charge.hours(),
+            wh: self.energy.hours(),
+        });
+        if voltage <= self.cutoff_v {
+            self.below_cutoff += 1;
+        } else {
+            self.below_cutoff = 0;
+        }
+        self.finished = self.below_cutoff >= self.confirm_samples;
+        self.finished
+    }
+
+    pub fn report(&self) -> Option<DischargeReport> {
+        let first = self.samples.first()?;
+        let last = self.samples.last()?;
+        let avg_current = if last.t > 0.0 {
+            last.ah * 3600.0 / last.t
+        } else {
+            first.current
+        };
+        let internal_resistance = self
+            .open_circuit_v
+            .filter(|_| first.current > 0.0)
+            .map(|ocv| (ocv - first.voltage) / first.current);
+        Some(DischargeReport {
+            duration_s: last.t,
+            capacity_ah: last.ah,
+            energy_wh: last.wh,
+            start_v: first.voltage,
+            end_v: last.voltage,
+            avg_current,
+            internal_resistance,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_current_discharge() {
+        let mut run = DischargeRun::new(3.0, 2, Some(4.2));
+        // 0.5 A for one hour, voltage falling linearly 4.0 -> 3.0
+        let mut stopped_at = None;
+        for i in 0..=3600 {
+            let t = 100.0 + i as f64;
+            let v = 4.0 - i as f64 / 3600.0;
+            if run.add(t, v, -0.5) {
+                stopped_at = Some(i);
+                break;
+            }
+        }
+        // Cutoff reached at i = 3600 needs a second confirming sample, so
+        // the loop runs out first
+        assert_eq!(stopped_at, None);
+        assert!(!run.is_finished());
+        assert!(run.add(3701.0, 2.99, 0.5));
+        let report = run.report().unwrap();
+        assert!((report.capacity_ah - 0.5 * 3601.0 / 3600.0).abs() < 1e-9);
+        assert!(report.energy_wh > 1.7 && report.energy_wh < 1.76);
+        assert_eq!(report.start_v, 4.0);
+        assert_eq!(report.end_v, 2.99);
+        assert!((report.avg_current - 0.5).abs() < 1e-9);
+        assert!((report.internal_resistance.unwrap() - 0.4).abs() < 1e-9);
+        assert_eq!(run.samples[0].t, 0.0);
+        // Finished runs ignore further samples
+        assert!(run.add(4000.0, 2.5, 0.5));
+        assert_eq!(run.samples.len(), 3602);
+    }
+
+    #[test]
+    fn single_dip_does_not_stop() {
+        let mut run = DischargeRun::new(3.0, 3, None);
+        assert!(!run.add(0.0, 3.5, 1.0));
+        assert!(!run.add(1.0, 2.9, 1.0));
+        assert!(!run.add(2.0, 3.4, 1.0));
+        assert!(!run.add(3.0, 2.9, 1.0));
+        assert!(!run.add(4.0, 2.9, 1.0));
+        assert!(run.add(5.0, 2.8, 1.0));
+        assert_eq!(run.report().unwrap().internal_resistance, None);
+        let mut run = DischargeRun::new(3.0, 3, None);
+        run.add(0.0, 3.5, 1.0);
+        run.finish();
+        assert!(run.is_finished());
+        assert!(run.add(1.0, 2.0, 1.0));
+        assert_eq!(run.samples.len(), 1);
+    }
+
+    #[test]
+    fn skips_repeated_and_invalid_samples() {
+        let mut run = DischargeRun::new(3.0, 1, None);
+        assert!(run.report().is_none());
+        run.add(1.0, 3.7, f64::NAN);
+        run.add(1.0, 3.7, 1.0);
+        run.add(1.0, 3.6, 1.0);
+        run.add(0.5, 3.6, 1.0);
+        assert_eq!(run.samples.len(), 1);
+        assert_eq!(run.last_time(), Some(1.0));
+        assert_eq!(current_from_load(3.6, 1.8), 2.0);
+        assert!(current_from_load(3.6, 0.0).is_nan());
+    }
+
+    #[test]
+    fn millivolt_and_milliamp_readings_in_base_units() {
+        use crate::multimeter::split_si_prefix;
+        let (mv, _) = split_si_prefix("mVDC");
+        let (ma, _) = split_si_prefix("mADC");
+        let mut run = DischargeRun::new(3.0, 1, Some(4.1));
+        assert!(!run.add(0.0, 4000.0 * mv, 500.0 * ma));
+        assert!(!run.add(3600.0, 3500.0 * mv, 500.0 * ma));
+        assert!(run.add(3601.0, 2990.0 * mv, 500.0 * ma));
+        let report = run.report().unwrap();
+        assert!((report.capacity_ah - 0.5 * 3601.0 / 3600.0).abs() < 1e-9);
+        assert!((report.avg_current - 0.5).abs() < 1e-9);
+        assert!((report.internal_resistance.unwrap() - 0.2).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,192 @@
+//! Sorting resistors and capacitors into tolerance bins around a nominal
+//! value, with IEC 60063 E-series lookup.
+
+use serde::{Deserialize, Serialize};
+
+const E6: [f64; 6] = [1.0, 1.5, 2.2, 3.3, 4.7, 6.8];
+const E12: [f64; 12] = [1.0, 1.2, 1.5, 1.8, 2.2, 2.7, 3.3, 3.9, 4.7, 5.6, 6.8, 8.2];
+const E24: [f64; 24] = [
+    1.0, 1.1, 1.2, 1.3, 1.5, 1.6, 1.8, 2.0, 2.2, 2.4, 2.7, 3.0, 3.3, 3.6, 3.9, 4.3, 4.7, 5.1, 5.6,
+    6.2, 6.8, 7.5, 8.2, 9.1,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ESeries {
+    E6,
+    E12,
+    E24,
+    E48,
+    E96,
+    E192,
+}
+
+impl ESeries {
+    pub const ALL: [ESeries; 6] = [
+        ESeries::E6,
+        ESeries::E12,
+        ESeries::E24,
+        ESeries::E48,
+        ESeries::E96,
+        ESeries::E192,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ESeries::E6 => "E6",
+            ESeries::E12 => "E12",
+            ESeries::E24 => "E24",
+            ESeries::E48 => "E48",
+            ESeries::E96 => "E96",
+            ESeries::E192 => "E192",
+        }
+    }
+
+    /// Tolerance the series is specified for, in percent.
+    pub fn tolerance_pct(self) -> f64 {
+        match self {
+            ESeries::E6 => 20.0,
+            ESeries::E12 => 10.0,
+            ESeries::E24 => 5.0,
+            ESeries::E48 => 2.0,
+            ESeries::E96 => 1.0,
+            ESeries::E192 => 0.5,
+        }
+    }
+
+    /// Mantissas of one decade, 1.0 up to below 10.
+    pub fn values(self) -> Vec<f64> {
+        let n = match self {
+            ESeries::E6 => return E6.to_vec(),
+            ESeries::E12 => return E12.to_vec(),
+            ESeries::E24 => return E24.to_vec(),
+            ESeries::E48 => 48,
+            ESeries::E96 => 96,
+            ESeries::E192 => 192,
+        };
+        // E48 and up follow the formula rounded to three digits, except
+        // the one historical E192 value 9.20 (formula gives 9.19)
+        (0..n)
+            .map(|i| {
+                let v = (10f64.powf(i as f64 / n as f64) * 100.0).round() / 100.0;
+                if n == 192 && v == 9.19 { 9.2 } else { v }
+            })
+            .collect()
+    }
+
+    /// Closest series value to `value` (on a log scale). `None` for zero,
+    /// negative or non-finite input.
+    pub fn nearest(self, value: f64) -> Option<f64> {
+        if !(value.is_finite() && value > 0.0) {
+            return None;
+        }
+        let decade = 10f64.powf(value.log10().floor());
+        let mantissa = value / decade;
+        let mut candidates = self.values();
+        candidates.push(10.0);
+        let best = candidates.into_iter().min_by(|a, b| {
+            (mantissa / a)
+                .ln()
+                .abs()
+                .total_cmp(&(mantissa / b).ln().abs())
+        })?;
+        Some(best * decade)
+    }
+}
+
+/// Where a part falls relative to its nominal value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bin {
+    /// Within the n-th (ascending) tolerance band.
+    Band(usize),
+    Low,
+    High,
+}
+
+/// Deviation of `value` from `nominal` in percent.
+pub fn deviation_pct(value: f64, nominal: f64) -> f64 {
+    (value - nominal) / nominal * 100.0
+}
+
+/// First band (sorted ascending, in percent) that contains `value`.
+pub fn classify(value: f64, nominal: f64, bands: &[f64]) -> Bin {
+    let dev = deviation_pct(value, nominal);
+    match bands.iter().position(|band| dev.abs() <= *band) {
+        Some(i) => Bin::Band(i),
+        None if dev < 0.0 => Bin::Low,
+        None => Bin::High,
+    }
+}
+
+pub fn bin_label(bin: Bin, bands: &[f64]) -> String {
+    match bin {
+        Bin::Band(i) => format!("±{}%", bands[i]),
+        Bin::Low => "LOW".to_owned(),
+        Bin::High => "HIGH".to_owned(),
+    }
+}
+
+/// Parse a band list like `"1, 2, 5"` into sorted, positive percentages.
+pub fn parse_bands(text: &str) -> Result<Vec<f64>, String> {
+    let mut bands = text
+        .split([',', ';', ' '])
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| {
+            let s = s.trim().trim_end_matches('%');
+            match s.parse::<f64>() {
+                Ok(v) if v > 0.0 && v.is_finite() => Ok(v),
+                _ => Err(format!("Invalid tolerance band '{s}'")),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if bands.is_empty() {
+        return Err("Enter at least one tolerance band".to_owned());
+    }
+    bands.sort_by(f64::total_cmp);
+    bands.dedup();
+    Ok(bands)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn series_values() {
+        assert_eq!(ESeries::E12.values().len(), 12);
+        let e96 = ESeries::E96.values();
+        assert_eq!(e96.len(), 96);
+        assert_eq!(e96[1], 1.02);
+        assert_eq!(e96[95], 9.76);
+        let e48 = ESeries::E48.values();
+        assert_eq!(e48[47], 9.53);
+        let e192 = ESeries::E192.values();
+        assert!(e192.contains(&9.2) && !e192.contains(&9.19));
+    }
+
+    #[test]
+    fn nearest_value() {
+        assert_eq!(ESeries::E12.nearest(4650.0), Some(4700.0));
+        let v = ESeries::E24.nearest(0.000_009_8).unwrap();
+        assert!((v / 1e-5 - 1.0).abs() < 1e-12);
+        assert_eq!(ESeries::E6.nearest(1.0), Some(1.0));
+        let v = ESeries::E96.nearest(10_050.0).unwrap();
+        assert!((v - 10_000.0).abs() < 1e-9);
+        assert_eq!(ESeries::E12.nearest(0.0), None);
+        assert_eq!(ESeries::E12.nearest(f64::NAN), None);
+    }
+
+    #[test]
+    fn classify_into_bands() {
+        let bands = parse_bands("5, 1 2%").unwrap();
+        assert_eq!(bands, vec![1.0, 2.0, 5.0]);
+        assert_eq!(classify(1005.0, 1000.0, &bands), Bin::Band(0));
+        assert_eq!(classify(985.0, 1000.0, &bands), Bin::Band(1));
+        assert_eq!(classify(1049.0, 1000.0, &bands), Bin::Band(2));
+        assert_eq!(classify(1060.0, 1000.0, &bands), Bin::High);
+        assert_eq!(classify(900.0, 1000.0, &bands), Bin::Low);
+        assert_eq!(bin_label(Bin::Band(1), &bands), "±2%");
+        assert!(parse_bands("").is_err());
+        assert!(parse_bands("1, x").is_err());
+        assert!(parse_bands("-1").is_err());
+    }
+}
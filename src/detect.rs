@@ -0,0 +1,214 @@
+//! Meter auto-detection: decide from what a port sent back which protocol is
+//! behind it, and how sure that guess is.
+//!
+//! SCPI meters answer `*IDN?`. Victor meters are read-only and only stream,
+//! so they are recognised by listening: DM1107 frames start with `a5 12`,
+//! ES51932 frames are 14 bytes ending in CR LF, and the HID cable sends
+//! obfuscated FS9922 packets.
+
+use crate::scpi_macro::{ScpiFamily, classify_idn, looks_like_idn};
+use crate::{victor_dm1107, victor_es519xx, victor_fs9922};
+
+/// Baud rates `*IDN?` is tried at, most common first.
+pub const SCPI_BAUD_RATES: [u32; 4] = [115_200, 9_600, 19_200, 57_600];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    /// OWON XDM and other SCPI meters over serial.
+    Scpi { baud: u32 },
+    /// Victor 86B/C/D serial, 9600 8N1.
+    Dm1107,
+    /// Victor 86E serial, 19200 7o1.
+    Es519xx,
+    /// Legacy Victor 86B/C/D USB HID cable.
+    Fs9922Hid,
+}
+
+impl Protocol {
+    pub fn label(self) -> String {
+        match self {
+            Self::Scpi { baud } => format!("SCPI serial, {baud} baud"),
+            Self::Dm1107 => "Victor 86B/C/D serial (DM1107)".to_owned(),
+            Self::Es519xx => "Victor 86E serial (ES51932)".to_owned(),
+            Self::Fs9922Hid => "Victor 86B/C/D HID (FS9922)".to_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+impl Confidence {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Low => "possible",
+            Self::Medium => "likely",
+            Self::High => "certain",
+        }
+    }
+
+    /// Frames seen while listening: one could be chance, three are not.
+    fn from_frames(count: usize) -> Option<Self> {
+        match count {
+            0 => None,
+            1 => Some(Self::Low),
+            2 => Some(Self::Medium),
+            _ => Some(Self::High),
+        }
+    }
+}
+
+/// A proposed connection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    /// Serial port name, or HID device path.
+    pub port: String,
+    pub protocol: Protocol,
+    pub confidence: Confidence,
+    /// What was seen, e.g. the IDN reply.
+    pub detail: String,
+}
+
+/// Judge the reply to `*IDN?`. Garbage from a wrong baud rate is rejected.
+pub fn idn_candidate(port: &str, baud: u32, reply: &[u8]) -> Option<Candidate> {
+    let text = String::from_utf8_lossy(reply);
+    let line = text.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let printable = line.chars().all(|c| c.is_ascii_graphic() || c == ' ');
+    if !printable || !looks_like_idn(line) || !line.contains(',') {
+        return None;
+    }
+    let confidence = if classify_idn(line) != ScpiFamily::Unknown {
+        Confidence::High
+    } else if line.split(',').count() >= 4 {
+        Confidence::Medium
+    } else {
+        Confidence::Low
+    };
+    Some(Candidate {
+        port: port.to_owned(),
+        protocol: Protocol::Scpi { baud },
+        confidence,
+        detail: line.to_owned(),
+    })
+}
+
+/// Judge bytes heard while listening with the line settings of `protocol`.
+pub fn stream_candidate(port: &str, protocol: Protocol, bytes: &[u8]) -> Option<Candidate> {
+    let frames = match protocol {
+        Protocol::Dm1107 => (0..bytes.len())
+            .filter(|&i| victor_dm1107::parse_frame_at(bytes, i).is_some())
+            .count(),
+        Protocol::Es519xx => victor_es519xx::feed_bytes(&mut Vec::new(), bytes).len(),
+        Protocol::Scpi { .. } | Protocol::Fs9922Hid => 0,
+    };
+    Some(Candidate {
+        port: port.to_owned(),
+        protocol,
+        confidence: Confidence::from_frames(frames)?,
+        detail: format!("{frames} frames in {} bytes", bytes.len()),
+    })
+}
+
+/// Judge a Victor HID device from the reports read from it. The USB IDs
+/// already match, so it is at least likely, even with the meter switched off.
+pub fn hid_candidate(path: &str, label: &str, reports: &[Vec<u8>]) -> Candidate {
+    let readings = reports
+        .iter()
+        .filter(|r| victor_fs9922::parse_hid_buffer(r).is_some())
+        .count();
+    let (confidence, detail) = if readings > 0 {
+        (Confidence::High, format!("{label}, {readings} readings"))
+    } else {
+        (
+            Confidence::Medium,
+            format!("{label}, no readings (meter off?)"),
+        )
+    };
+    Candidate {
+        port: path.to_owned(),
+        protocol: Protocol::Fs9922Hid,
+        confidence,
+        detail,
+    }
+}
+
+/// Most confident first; on a port, keep only the best guess.
+pub fn rank(candidates: &mut Vec<Candidate>) {
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.confidence));
+    let mut seen: Vec<String> = Vec::new();
+    candidates.retain(|c| {
+        if seen.contains(&c.port) {
+            return false;
+        }
+        seen.push(c.port.clone());
+        true
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DM1107_FRAME: [u8; 20] = [
+        0xa5, 0x12, 0x04, 0x00, 0x40, 0x5f, 0x5f, 0xdf, 0x5f, 0x42, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x0c, 0x04, 0x49,
+    ];
+
+    #[test]
+    fn judges_idn_replies() {
+        let owon = idn_candidate("COM3", 115_200, b"OWON,XDM1041,2209185,V3.7.2,2\r\n").unwrap();
+        assert_eq!(owon.confidence, Confidence::High);
+        assert_eq!(owon.protocol, Protocol::Scpi { baud: 115_200 });
+        assert_eq!(owon.detail, "OWON,XDM1041,2209185,V3.7.2,2");
+
+        let other = idn_candidate("COM3", 9_600, b"\nKeysight,34461A,MY1,A.02\n").unwrap();
+        assert_eq!(other.confidence, Confidence::Medium);
+
+        assert!(idn_candidate("COM3", 9_600, b"").is_none());
+        assert!(idn_candidate("COM3", 9_600, b"1.234E+00\n").is_none());
+        assert!(idn_candidate("COM3", 9_600, &[0xf8, 0x2c, 0x80, 0x0a]).is_none());
+    }
+
+    #[test]
+    fn counts_streamed_frames() {
+        let mut bytes = vec![0x00, 0x13];
+        bytes.extend_from_slice(&DM1107_FRAME);
+        let one = stream_candidate("/dev/ttyUSB0", Protocol::Dm1107, &bytes).unwrap();
+        assert_eq!(one.confidence, Confidence::Low);
+        bytes.extend_from_slice(&DM1107_FRAME);
+        bytes.extend_from_slice(&DM1107_FRAME);
+        let three = stream_candidate("/dev/ttyUSB0", Protocol::Dm1107, &bytes).unwrap();
+        assert_eq!(three.confidence, Confidence::High);
+        assert!(stream_candidate("/dev/ttyUSB0", Protocol::Es519xx, &bytes).is_none());
+
+        let es = b"103560;000:3\r\n103560;000:3\r\n".repeat(2);
+        let es = stream_candidate("/dev/ttyUSB1", Protocol::Es519xx, &es).unwrap();
+        assert_eq!(es.confidence, Confidence::High);
+        assert!(stream_candidate("/dev/ttyUSB1", Protocol::Dm1107, b"noise").is_none());
+    }
+
+    #[test]
+    fn ranks_best_guess_per_port() {
+        let hid = hid_candidate("hid-1", "Victor Multimeter", &[vec![0; 14]]);
+        assert_eq!(hid.confidence, Confidence::Medium);
+        let candidate = |port: &str, protocol, confidence| Candidate {
+            port: port.to_owned(),
+            protocol,
+            confidence,
+            detail: String::new(),
+        };
+        let mut all = vec![
+            candidate("A", Protocol::Dm1107, Confidence::Low),
+            hid,
+            candidate("A", Protocol::Scpi { baud: 9_600 }, Confidence::High),
+        ];
+        rank(&mut all);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].protocol, Protocol::Scpi { baud: 9_600 });
+        assert_eq!(all[1].port, "hid-1");
+    }
+}
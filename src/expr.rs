@@ -0,0 +1,487 @@
+//! Arithmetic expressions over named channels, used for derived channels
+//! such as `P = m1 * m2`.
+//!
+//! Grammar: numbers (`1.5`, `2e-3`), identifiers, `+ - * / ^`, unary minus,
+//! parentheses and the functions `abs sqrt ln log10 exp min max`.
+//! `^` binds tightest and is right associative.
+
+use std::fmt;
+
+use crate::helpers::METER_OVERLOAD_VALUE;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Num(f64),
+    Var(String),
+    Neg(Box<Expr>),
+    Bin(Op, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+/// Parse or evaluation failure, with a message suitable for the UI.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExprError(pub String);
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, ExprError> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = src.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '0'..='9' | '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                // Exponent, only when followed by digits so `2e` stays an error
+                if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                    let mut j = i + 1;
+                    if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                        j += 1;
+                    }
+                    if j < chars.len() && chars[j].is_ascii_digit() {
+                        i = j;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+                let text: String = chars[start..i].iter().collect();
+                let value = text
+                    .parse()
+                    .map_err(|_| ExprError(format!("Invalid number '{text}'")))?;
+                tokens.push(Token::Num(value));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            '+' | '-' | '*' | '/' | '^' => {
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            _ => return Err(ExprError(format!("Unexpected character '{c}'"))),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token, what: &str) -> Result<(), ExprError> {
+        if self.next() == Some(token) {
+            Ok(())
+        } else {
+            Err(ExprError(format!("Expected {what}")))
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.product()?;
+        while let Some(Token::Op(c @ ('+' | '-'))) = self.peek() {
+            let op = if *c == '+' { Op::Add } else { Op::Sub };
+            self.pos += 1;
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(self.product()?));
+        }
+        Ok(lhs)
+    }
+
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Op(c @ ('*' | '/'))) = self.peek() {
+            let op = if *c == '*' { Op::Mul } else { Op::Div };
+            self.pos += 1;
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        if self.peek() == Some(&Token::Op('-')) {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::Op('+')) {
+            self.pos += 1;
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr, ExprError> {
+        let base = self.atom()?;
+        if self.peek() == Some(&Token::Op('^')) {
+            self.pos += 1;
+            // Right associative, and -x^2 style exponents are allowed
+            return Ok(Expr::Bin(Op::Pow, Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Expr, ExprError> {
+        match self.next() {
+            Some(Token::Num(v)) => Ok(Expr::Num(v)),
+            Some(Token::Ident(name)) => {
+                if self.peek() != Some(&Token::LParen) {
+                    return Ok(Expr::Var(name));
+                }
+                self.pos += 1;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    loop {
+                        args.push(self.sum()?);
+                        if self.peek() == Some(&Token::Comma) {
+                            self.pos += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                self.expect(Token::RParen, "')' after function arguments")?;
+                check_call(&name, args.len())?;
+                Ok(Expr::Call(name, args))
+            }
+            Some(Token::LParen) => {
+                let inner = self.sum()?;
+                self.expect(Token::RParen, "')'")?;
+                Ok(inner)
+            }
+            Some(Token::Op(c)) => Err(ExprError(format!("Unexpected '{c}'"))),
+            Some(Token::RParen) => Err(ExprError("Unexpected ')'".to_owned())),
+            Some(Token::Comma) => Err(ExprError("Unexpected ','".to_owned())),
+            None => Err(ExprError("Unexpected end of expression".to_owned())),
+        }
+    }
+}
+
+fn check_call(name: &str, argc: usize) -> Result<(), ExprError> {
+    let expected = match name {
+        "abs" | "sqrt" | "ln" | "log10" | "exp" => 1,
+        "min" | "max" => 2,
+        _ => return Err(ExprError(format!("Unknown function '{name}'"))),
+    };
+    if argc == expected {
+        Ok(())
+    } else {
+        Err(ExprError(format!(
+            "{name}() takes {expected} argument(s), got {argc}"
+        )))
+    }
+}
+
+/// True for names usable as a variable: a letter or `_` followed by
+/// letters, digits or `_`.
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Parse an expression. Empty input is an error.
+pub fn parse(src: &str) -> Result<Expr, ExprError> {
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+    };
+    let expr = parser.sum()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(_) => Err(ExprError("Unexpected input after expression".to_owned())),
+    }
+}
+
+impl Expr {
+    /// Evaluate with `lookup` resolving variable names.
+    pub fn eval(&self, lookup: &dyn Fn(&str) -> Option<f64>) -> Result<f64, ExprError> {
+        Ok(match self {
+            Expr::Num(v) => *v,
+            Expr::Var(name) => {
+                lookup(name).ok_or_else(|| ExprError(format!("Unknown channel '{name}'")))?
+            }
+            Expr::Neg(inner) => -inner.eval(lookup)?,
+            Expr::Bin(op, lhs, rhs) => {
+                let (a, b) = (lhs.eval(lookup)?, rhs.eval(lookup)?);
+                match op {
+                    Op::Add => a + b,
+                    Op::Sub => a - b,
+                    Op::Mul => a * b,
+                    Op::Div => a / b,
+                    Op::Pow => a.powf(b),
+                }
+            }
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|a| a.eval(lookup))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match name.as_str() {
+                    "abs" => args[0].abs(),
+                    "sqrt" => args[0].sqrt(),
+                    "ln" => args[0].ln(),
+                    "log10" => args[0].log10(),
+                    "exp" => args[0].exp(),
+                    "min" => args[0].min(args[1]),
+                    "max" => args[0].max(args[1]),
+                    _ => unreachable!("checked while parsing"),
+                }
+            }
+        })
+    }
+
+    /// Variable names used, in first-use order without duplicates.
+    pub fn variables(&self) -> Vec<String> {
+        fn walk(expr: &Expr, out: &mut Vec<String>) {
+            match expr {
+                Expr::Num(_) => {}
+                Expr::Var(name) => {
+                    if !out.contains(name) {
+                        out.push(name.clone());
+                    }
+                }
+                Expr::Neg(inner) => walk(inner, out),
+                Expr::Bin(_, lhs, rhs) => {
+                    walk(lhs, out);
+                    walk(rhs, out);
+                }
+                Expr::Call(_, args) => args.iter().for_each(|a| walk(a, out)),
+            }
+        }
+        let mut out = Vec::new();
+        walk(self, &mut out);
+        out
+    }
+}
+
+/// A channel reading and the time (seconds) it last updated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimedValue {
+    pub value: f64,
+    pub time: f64,
+}
+
+/// Why a derived channel has no value right now.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Unavailable {
+    /// Input has no reading yet (or is disconnected).
+    Missing(String),
+    /// Input has not updated within the stale timeout.
+    Stale(String),
+    /// Input reads overload (`OL`).
+    Overload(String),
+    /// Inputs updated further apart than the alignment tolerance.
+    Skewed,
+    Error(ExprError),
+}
+
+impl fmt::Display for Unavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unavailable::Missing(name) => write!(f, "waiting for {name}"),
+            Unavailable::Stale(name) => write!(f, "stale: {name} stopped updating"),
+            Unavailable::Overload(name) => write!(f, "{name} is in overload"),
+            Unavailable::Skewed => f.write_str("inputs out of alignment"),
+            Unavailable::Error(e) => e.fmt(f),
+        }
+    }
+}
+
+/// Evaluate `expr` when every input is present, fresher than `stale_after`
+/// and all inputs updated within `tolerance` of each other. The result
+/// carries the oldest input time so staleness propagates through chains of
+/// derived channels.
+pub fn eval_aligned(
+    expr: &Expr,
+    lookup: &dyn Fn(&str) -> Option<TimedValue>,
+    now: f64,
+    tolerance: f64,
+    stale_after: f64,
+) -> Result<TimedValue, Unavailable> {
+    let mut oldest = f64::INFINITY;
+    let mut newest = f64::NEG_INFINITY;
+    for name in expr.variables() {
+        let input = lookup(&name).ok_or_else(|| Unavailable::Missing(name.clone()))?;
+        if !input.value.is_finite() {
+            return Err(Unavailable::Missing(name));
+        }
+        if input.value == METER_OVERLOAD_VALUE {
+            return Err(Unavailable::Overload(name));
+        }
+        if now - input.time > stale_after {
+            return Err(Unavailable::Stale(name));
+        }
+        oldest = oldest.min(input.time);
+        newest = newest.max(input.time);
+    }
+    if newest - oldest > tolerance {
+        return Err(Unavailable::Skewed);
+    }
+    let value = expr
+        .eval(&|name| lookup(name).map(|v| v.value))
+        .map_err(Unavailable::Error)?;
+    Ok(TimedValue {
+        value,
+        // Constant expressions are always fresh
+        time: if oldest.is_finite() { oldest } else { now },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval_with(src: &str, vars: &[(&str, f64)]) -> Result<f64, ExprError> {
+        parse(src)?.eval(&|name| vars.iter().find(|(n, _)| *n == name).map(|(_, v)| *v))
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(eval_with("1 + 2 * 3", &[]), Ok(7.0));
+        assert_eq!(eval_with("(1 + 2) * 3", &[]), Ok(9.0));
+        assert_eq!(eval_with("8 / 4 / 2", &[]), Ok(1.0));
+        assert_eq!(eval_with("2 ^ 3 ^ 2", &[]), Ok(512.0));
+        assert_eq!(eval_with("-2 ^ 2", &[]), Ok(-4.0));
+        assert_eq!(eval_with("2 ^ -1", &[]), Ok(0.5));
+        assert_eq!(eval_with("1.5e3 - 2E-1", &[]), Ok(1499.8));
+    }
+
+    #[test]
+    fn variables_and_functions() {
+        let vars = [("m1", 12.0), ("m2", 0.5), ("Pin", 10.0), ("Pout", 8.5)];
+        assert_eq!(eval_with("m1 * m2", &vars), Ok(6.0));
+        assert_eq!(eval_with("Pout / Pin", &vars), Ok(0.85));
+        assert_eq!(eval_with("abs(m2 - m1)", &vars), Ok(11.5));
+        assert_eq!(eval_with("max(m1, Pin) + sqrt(4)", &vars), Ok(14.0));
+        assert_eq!(
+            eval_with("m3 + 1", &vars),
+            Err(ExprError("Unknown channel 'm3'".to_owned()))
+        );
+        assert_eq!(
+            parse("m1 * m2 + m1").unwrap().variables(),
+            vec!["m1".to_owned(), "m2".to_owned()]
+        );
+    }
+
+    #[test]
+    fn identifiers() {
+        assert!(is_identifier("Pout"));
+        assert!(is_identifier("_t2"));
+        assert!(!is_identifier(""));
+        assert!(!is_identifier("2x"));
+        assert!(!is_identifier("P out"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("").is_err());
+        assert!(parse("1 +").is_err());
+        assert!(parse("(1 + 2").is_err());
+        assert!(parse("1 2").is_err());
+        assert!(parse("m1 $ 2").is_err());
+        assert!(parse("foo(1)").is_err());
+        assert!(parse("min(1)").is_err());
+    }
+
+    #[test]
+    fn aligned_evaluation() {
+        let expr = parse("v * i").unwrap();
+        let inputs = |v_time: f64, i_time: f64| {
+            move |name: &str| match name {
+                "v" => Some(TimedValue {
+                    value: 5.0,
+                    time: v_time,
+                }),
+                "i" => Some(TimedValue {
+                    value: 2.0,
+                    time: i_time,
+                }),
+                _ => None,
+            }
+        };
+        let ok = eval_aligned(&expr, &inputs(9.9, 9.8), 10.0, 0.5, 2.0).unwrap();
+        assert_eq!(ok.value, 10.0);
+        assert_eq!(ok.time, 9.8);
+        assert_eq!(
+            eval_aligned(&expr, &inputs(9.9, 8.0), 10.0, 0.5, 5.0),
+            Err(Unavailable::Skewed)
+        );
+        assert_eq!(
+            eval_aligned(&expr, &inputs(9.9, 7.0), 10.0, 5.0, 2.0),
+            Err(Unavailable::Stale("i".to_owned()))
+        );
+        let missing = parse("v * r").unwrap();
+        assert_eq!(
+            eval_aligned(&missing, &inputs(9.9, 9.9), 10.0, 0.5, 2.0),
+            Err(Unavailable::Missing("r".to_owned()))
+        );
+        let overload = |name: &str| {
+            Some(TimedValue {
+                value: if name == "i" {
+                    METER_OVERLOAD_VALUE
+                } else {
+                    5.0
+                },
+                time: 9.9,
+            })
+        };
+        assert_eq!(
+            eval_aligned(&expr, &overload, 10.0, 0.5, 2.0),
+            Err(Unavailable::Overload("i".to_owned()))
+        );
+    }
+}
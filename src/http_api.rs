@@ -0,0 +1,515 @@
+//! Minimal HTTP/1.1 handling for the local JSON API: request head parsing,
+//! routing to API calls and response formatting. The socket side and the
+//! state it serves live in `app::http_api`.
+//!
+//! Only what a `curl` call, a small script or a browser overlay needs is
+//! supported: one request per connection, `Content-Length` bodies, JSON in
+//! and out, a Server-Sent Events stream and a Prometheus scrape endpoint.
+
+use std::net::{IpAddr, SocketAddr};
+
+use serde_json::{Value, json};
+
+use crate::multimeter::MeterMode;
+
+/// Upper bound for the request line plus headers.
+pub const MAX_HEAD_BYTES: usize = 16 * 1024;
+/// Upper bound for a request body.
+pub const MAX_BODY_BYTES: usize = 64 * 1024;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RequestHead {
+    pub method: String,
+    pub path: String,
+    pub content_length: usize,
+    pub host: Option<String>,
+    pub origin: Option<String>,
+    pub content_type: Option<String>,
+}
+
+/// Offset just past the blank line that ends the header block.
+pub fn head_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4)
+}
+
+/// Parse the request line and headers. Only `Content-Length`, `Host`,
+/// `Origin` and `Content-Type` are kept.
+pub fn parse_head(head: &str) -> Result<RequestHead, String> {
+    let mut lines = head.split("\r\n");
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(format!("Malformed request line '{request_line}'"));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(format!("Unsupported protocol '{version}'"));
+    }
+    // Query strings are not used by any endpoint
+    let path = target.split_once('?').map_or(target, |(path, _)| path);
+    let mut content_length = 0;
+    let (mut host, mut origin, mut content_type) = (None, None, None);
+    for line in lines.filter(|l| !l.is_empty()) {
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(format!("Malformed header '{line}'"));
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => {
+                content_length = value
+                    .parse()
+                    .map_err(|_| format!("Invalid Content-Length '{value}'"))?;
+            }
+            "host" => host = Some(value.to_owned()),
+            "origin" => origin = Some(value.to_owned()),
+            "content-type" => content_type = Some(value.to_owned()),
+            _ => {}
+        }
+    }
+    Ok(RequestHead {
+        method: method.to_ascii_uppercase(),
+        path: percent_decode(path),
+        content_length,
+        host,
+        origin,
+        content_type,
+    })
+}
+
+/// Split `host[:port]`, with IPv6 literals in brackets (`[::1]:8780`).
+fn split_authority(authority: &str) -> Option<(&str, Option<u16>)> {
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(rest) => {
+            let (host, rest) = rest.split_once(']')?;
+            (host, rest.strip_prefix(':'))
+        }
+        None => match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    let port = match port {
+        Some(port) => Some(port.parse().ok()?),
+        None => None,
+    };
+    Some((host, port))
+}
+
+/// `localhost`, a loopback address or the address the client connected to.
+fn is_local_host(host: &str, local: SocketAddr) -> bool {
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback() || ip == local.ip())
+}
+
+/// Guard against browsers being used to reach the API: a web page can send
+/// requests to `localhost`, and DNS rebinding can make another name point at
+/// it. The `Host` must name this server, an `Origin` must be this server,
+/// and commands must be JSON, which a page cannot send cross-origin without
+/// a preflight. `local` is the address the connection was accepted on.
+pub fn check_request(head: &RequestHead, local: SocketAddr) -> Result<(), Response> {
+    let host = head
+        .host
+        .as_deref()
+        .ok_or_else(|| Response::error(400, "Missing Host header"))?;
+    match split_authority(host) {
+        Some((name, port))
+            if is_local_host(name, local) && port.is_none_or(|p| p == local.port()) => {}
+        _ => {
+            return Err(Response::error(
+                403,
+                format!("Host '{host}' is not allowed"),
+            ));
+        }
+    }
+    if let Some(origin) = &head.origin {
+        let own = origin
+            .strip_prefix("http://")
+            .and_then(split_authority)
+            .is_some_and(|(name, port)| {
+                is_local_host(name, local) && port.unwrap_or(80) == local.port()
+            });
+        if !own {
+            return Err(Response::error(
+                403,
+                format!("Origin '{origin}' is not allowed"),
+            ));
+        }
+    }
+    if head.method == "POST" {
+        let json = head.content_type.as_deref().is_some_and(|t| {
+            let mime = t.split(';').next().unwrap_or_default().trim();
+            mime.eq_ignore_ascii_case("application/json")
+        });
+        if !json {
+            return Err(Response::error(415, "Use Content-Type: application/json"));
+        }
+    }
+    Ok(())
+}
+
+/// Decode `%XX` escapes and `+` in a URL path. Invalid escapes are kept
+/// as they are.
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = |b: u8| (b as char).to_digit(16);
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(hi), Some(lo)) => {
+                    out.push((hi * 16 + lo) as u8);
+                    i += 3;
+                    continue;
+                }
+                _ => out.push(b'%'),
+            },
+            b'+' => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json(status: u16, value: &Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    pub fn html(body: &str) -> Self {
+        Self {
+            status: 200,
+            content_type: "text/html; charset=utf-8",
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, &json!({ "error": message.into() }))
+    }
+
+    /// Serialized response including headers. Every response closes the
+    /// connection.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+             Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason(self.status),
+            self.content_type,
+            self.body.len()
+        )
+        .into_bytes();
+        out.extend_from_slice(&self.body);
+        out
+    }
+}
+
+/// Response head that opens a Server-Sent Events stream. Events follow
+/// until either side closes the connection.
+pub const EVENT_STREAM_HEAD: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+    Cache-Control: no-store\r\nConnection: keep-alive\r\n\r\n";
+
+/// Keep-alive comment, stops proxies and browsers from timing out an idle
+/// stream.
+pub const EVENT_STREAM_PING: &[u8] = b": ping\n\n";
+
+/// One Server-Sent Event carrying `data`. Each line of `data` gets its own
+/// `data:` field so the client sees the original line breaks.
+pub fn sse_event(data: &str) -> String {
+    let mut out = String::with_capacity(data.len() + 8);
+    for line in data.lines() {
+        out.push_str("data: ");
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push('\n');
+    out
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+/// A change requested through the API, applied on the UI thread.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApiCommand {
+    SetMode(MeterMode),
+    /// Range label (`"5V"`), SCPI parameter (`"5"`) or `"auto"`.
+    SetRange(String),
+    /// Rate label (`"Fast"`) or SCPI code (`"F"`).
+    SetRate(String),
+    /// Name of a SCPI macro.
+    RunMacro(String),
+    Recording(bool),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Route {
+    /// HTML page for use as a browser source in streaming software.
+    Overlay,
+    OverlayFont,
+    /// Server-Sent Events with the overlay state.
+    Stream,
+    Reading,
+    Status,
+    Stats,
+    Macros,
+    /// Prometheus scrape endpoint.
+    Metrics,
+    Command(ApiCommand),
+}
+
+/// Match a mode by name (`"Vdc"`) or button label (`"VDC"`, `"Ohm"`),
+/// ignoring case.
+pub fn parse_mode(s: &str) -> Option<MeterMode> {
+    let s = s.trim();
+    MeterMode::ALL.into_iter().find(|m| {
+        format!("{m:?}").eq_ignore_ascii_case(s) || m.button_label().eq_ignore_ascii_case(s)
+    })
+}
+
+fn body_field(body: &[u8], key: &str) -> Result<Value, Response> {
+    let value: Value = serde_json::from_slice(body)
+        .map_err(|e| Response::error(400, format!("Invalid JSON body: {e}")))?;
+    value
+        .get(key)
+        .cloned()
+        .ok_or_else(|| Response::error(400, format!("Missing field '{key}'")))
+}
+
+fn body_string(body: &[u8], key: &str) -> Result<String, Response> {
+    match body_field(body, key)? {
+        Value::String(s) => Ok(s),
+        _ => Err(Response::error(
+            400,
+            format!("Field '{key}' must be a string"),
+        )),
+    }
+}
+
+/// Map a request to an API call. Errors come back as ready-made responses.
+pub fn route(method: &str, path: &str, body: &[u8]) -> Result<Route, Response> {
+    let path = path.trim_end_matches('/');
+    let get = |route| match method {
+        "GET" => Ok(route),
+        _ => Err(Response::error(405, "Use GET")),
+    };
+    let post = |build: &dyn Fn() -> Result<ApiCommand, Response>| match method {
+        "POST" => build().map(Route::Command),
+        _ => Err(Response::error(405, "Use POST")),
+    };
+    match path {
+        "/overlay" => get(Route::Overlay),
+        "/overlay/font.ttf" => get(Route::OverlayFont),
+        "/api/stream" => get(Route::Stream),
+        "/api/reading" => get(Route::Reading),
+        "/api/status" => get(Route::Status),
+        "/api/stats" => get(Route::Stats),
+        "/api/macros" => get(Route::Macros),
+        "/metrics" => get(Route::Metrics),
+        "/api/mode" => post(&|| {
+            let mode = body_string(body, "mode")?;
+            parse_mode(&mode)
+                .map(ApiCommand::SetMode)
+                .ok_or_else(|| Response::error(400, format!("Unknown mode '{mode}'")))
+        }),
+        "/api/range" => post(&|| body_string(body, "range").map(ApiCommand::SetRange)),
+        "/api/rate" => post(&|| body_string(body, "rate").map(ApiCommand::SetRate)),
+        "/api/macro" => post(&|| body_string(body, "name").map(ApiCommand::RunMacro)),
+        "/api/recording" => post(&|| match body_field(body, "active")? {
+            Value::Bool(active) => Ok(ApiCommand::Recording(active)),
+            _ => Err(Response::error(400, "Field 'active' must be true or false")),
+        }),
+        _ => Err(Response::error(404, format!("No endpoint '{path}'"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_request_head() {
+        let raw = b"POST /api/m%6Fde?x=1 HTTP/1.1\r\nHost: localhost\r\n\
+                    content-length: 15\r\n\r\n{\"mode\":\"vac\"}";
+        let end = head_end(raw).unwrap();
+        assert_eq!(&raw[end..], b"{\"mode\":\"vac\"}");
+        let head = parse_head(std::str::from_utf8(&raw[..end]).unwrap()).unwrap();
+        assert_eq!(head.method, "POST");
+        assert_eq!(head.path, "/api/mode");
+        assert_eq!(head.content_length, 15);
+        assert_eq!(head.host.as_deref(), Some("localhost"));
+        assert_eq!(head.origin, None);
+        assert!(head_end(b"GET / HTTP/1.1\r\nHost: x\r\n").is_none());
+        assert!(parse_head("GET /\r\n\r\n").is_err());
+        assert!(parse_head("GET / SPDY/3\r\n\r\n").is_err());
+        assert!(parse_head("GET / HTTP/1.1\r\nContent-Length: x\r\n\r\n").is_err());
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz+%41"), "%zz A");
+    }
+
+    fn local() -> SocketAddr {
+        "127.0.0.1:8780".parse().unwrap()
+    }
+
+    fn head(method: &str, headers: &[(&str, &str)]) -> RequestHead {
+        let mut raw = format!("{method} /api/mode HTTP/1.1\r\n");
+        for (name, value) in headers {
+            raw.push_str(&format!("{name}: {value}\r\n"));
+        }
+        raw.push_str("\r\n");
+        parse_head(&raw).unwrap()
+    }
+
+    #[test]
+    fn accepts_local_json_requests() {
+        let json = ("Content-Type", "application/json; charset=utf-8");
+        for host in ["localhost:8780", "127.0.0.1", "[::1]:8780", "LOCALHOST"] {
+            assert_eq!(
+                check_request(&head("POST", &[("Host", host), json]), local()),
+                Ok(())
+            );
+        }
+        let same_origin = [
+            ("Host", "localhost:8780"),
+            ("Origin", "http://localhost:8780"),
+        ];
+        assert_eq!(check_request(&head("GET", &same_origin), local()), Ok(()));
+        // A LAN client uses the address the server was reached on.
+        let lan: SocketAddr = "192.168.1.5:8780".parse().unwrap();
+        assert_eq!(
+            check_request(&head("GET", &[("Host", "192.168.1.5:8780")]), lan),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_post_without_json_content_type() {
+        let host = ("Host", "localhost:8780");
+        let form = ("Content-Type", "application/x-www-form-urlencoded");
+        let text = ("Content-Type", "text/plain");
+        for headers in [vec![host], vec![host, form], vec![host, text]] {
+            let err = check_request(&head("POST", &headers), local()).unwrap_err();
+            assert_eq!(err.status, 415);
+        }
+    }
+
+    #[test]
+    fn rejects_foreign_origin() {
+        for origin in [
+            "http://evil.example",
+            "http://localhost:9999",
+            "https://localhost:8780",
+            "null",
+        ] {
+            let headers = [("Host", "localhost:8780"), ("Origin", origin)];
+            let err = check_request(&head("GET", &headers), local()).unwrap_err();
+            assert_eq!(err.status, 403, "{origin}");
+        }
+    }
+
+    #[test]
+    fn rejects_foreign_host() {
+        for host in [
+            "evil.example",
+            "evil.example:8780",
+            "localhost:80",
+            "10.0.0.1:8780",
+        ] {
+            let err = check_request(&head("GET", &[("Host", host)]), local()).unwrap_err();
+            assert_eq!(err.status, 403, "{host}");
+        }
+        assert_eq!(
+            check_request(&head("GET", &[]), local())
+                .unwrap_err()
+                .status,
+            400
+        );
+    }
+
+    #[test]
+    fn routes_requests() {
+        assert_eq!(route("GET", "/api/reading/", b""), Ok(Route::Reading));
+        assert_eq!(route("GET", "/overlay", b""), Ok(Route::Overlay));
+        assert_eq!(route("GET", "/api/stream", b""), Ok(Route::Stream));
+        assert_eq!(route("GET", "/metrics", b""), Ok(Route::Metrics));
+        assert_eq!(route("POST", "/api/reading", b"").unwrap_err().status, 405);
+        assert_eq!(route("GET", "/api/nope", b"").unwrap_err().status, 404);
+        assert_eq!(
+            route("POST", "/api/mode", br#"{"mode":"ohm"}"#),
+            Ok(Route::Command(ApiCommand::SetMode(MeterMode::Res)))
+        );
+        assert_eq!(
+            route("POST", "/api/mode", br#"{"mode":"Vdc"}"#),
+            Ok(Route::Command(ApiCommand::SetMode(MeterMode::Vdc)))
+        );
+        assert_eq!(
+            route("POST", "/api/mode", br#"{"mode":"volts"}"#)
+                .unwrap_err()
+                .status,
+            400
+        );
+        assert_eq!(
+            route("POST", "/api/range", br#"{"range":"5V"}"#),
+            Ok(Route::Command(ApiCommand::SetRange("5V".to_owned())))
+        );
+        assert_eq!(
+            route("POST", "/api/recording", br#"{"active":false}"#),
+            Ok(Route::Command(ApiCommand::Recording(false)))
+        );
+        assert_eq!(
+            route("POST", "/api/recording", br#"{"active":"yes"}"#)
+                .unwrap_err()
+                .status,
+            400
+        );
+        assert_eq!(route("POST", "/api/macro", b"{").unwrap_err().status, 400);
+        assert_eq!(route("POST", "/api/rate", b"{}").unwrap_err().status, 400);
+    }
+
+    #[test]
+    fn formats_response() {
+        let bytes = Response::error(404, "gone").to_bytes();
+        let text = String::from_utf8(bytes).unwrap();
+        assert!(text.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(text.contains("Content-Length: 16\r\n"));
+        assert!(text.ends_with("\r\n\r\n{\"error\":\"gone\"}"));
+        let head = String::from_utf8(EVENT_STREAM_HEAD.to_vec()).unwrap();
+        assert!(head.contains("\r\nContent-Type: text/event-stream\r\n"));
+        assert!(head.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn formats_sse_events() {
+        assert_eq!(sse_event("{\"v\":1}"), "data: {\"v\":1}\n\n");
+        assert_eq!(sse_event("a\nb"), "data: a\ndata: b\n\n");
+    }
+}
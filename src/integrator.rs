@@ -0,0 +1,214 @@
+//! Running integral of a channel over its sample timestamps: charge from a
+//! current, energy from a power.
+//!
+//! Uses the trapezoid rule between consecutive samples, so irregular
+//! spacing from polling jitter or a missed reading only widens one step
+//! instead of skewing the total.
+
+use crate::multimeter::split_si_prefix;
+
+/// What the integral of a unit means, picked from the base of the source
+/// unit, so `mADC` is a charge too. Samples are fed in base units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegralKind {
+    /// Amperes integrate to charge (Ah, C).
+    Charge,
+    /// Watts integrate to energy (Wh, J).
+    Energy,
+    /// Anything else integrates to `<unit>·s`.
+    Other,
+}
+
+impl IntegralKind {
+    pub fn from_unit(unit: &str) -> Self {
+        match split_si_prefix(unit).1 {
+            "A" | "ADC" | "AAC" => Self::Charge,
+            "W" => Self::Energy,
+            _ => Self::Other,
+        }
+    }
+
+    /// Unit of [`Self::hours`] values, used for recording.
+    pub fn hour_unit(self, source_unit: &str) -> String {
+        match self {
+            Self::Charge => "Ah".to_owned(),
+            Self::Energy => "Wh".to_owned(),
+            Self::Other => format!("{}·h", split_si_prefix(source_unit).1),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Integrator {
+    /// Accumulated integral in source unit × seconds.
+    pub total: f64,
+    /// Time covered by integrated samples, in seconds.
+    pub elapsed: f64,
+    running: bool,
+    last: Option<(f64, f64)>,
+}
+
+impl Integrator {
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Start or resume. The first sample after this only sets the baseline.
+    pub fn start(&mut self) {
+        self.running = true;
+        self.last = None;
+    }
+
+    pub fn stop(&mut self) {
+        self.running = false;
+        self.last = None;
+    }
+
+    pub fn reset(&mut self) {
+        self.total = 0.0;
+        self.elapsed = 0.0;
+        self.last = None;
+    }
+
+    /// Feed one sample taken at `t` seconds. Non-finite values are skipped,
+    /// the next good sample then spans the gap. Samples that do not move
+    /// time forward only update the baseline value.
+    pub fn add(&mut self, t: f64, value: f64) {
+        if !self.running || !value.is_finite() {
+            return;
+        }
+        if let Some((t0, v0)) = self.last {
+            let dt = t - t0;
+            if dt < 0.0 {
+                return;
+            }
+            self.total += 0.5 * (v0 + value) * dt;
+            self.elapsed += dt;
+        }
+        self.last = Some((t, value));
+    }
+
+    /// Total in hour based units (Ah, Wh, `<unit>·h`).
+    pub fn hours(&self) -> f64 {
+        self.total / 3600.0
+    }
+
+    /// Human readable total, e.g. `12.345 mAh (44.44 C)`.
+    pub fn format_total(&self, kind: IntegralKind, source_unit: &str) -> String {
+        let hours = self.hours();
+        match kind {
+            IntegralKind::Charge if hours.abs() < 1.0 => {
+                format!("{:.4} mAh ({:.4} C)", hours * 1e3, self.total)
+            }
+            IntegralKind::Charge => format!("{:.6} Ah ({:.2} C)", hours, self.total),
+            IntegralKind::Energy if hours.abs() < 1.0 => {
+                format!("{:.4} mWh ({:.4} J)", hours * 1e3, self.total)
+            }
+            IntegralKind::Energy => format!("{:.6} Wh ({:.2} J)", hours, self.total),
+            IntegralKind::Other => {
+                format!("{:.6} {}·s", self.total, split_si_prefix(source_unit).1)
+            }
+        }
+    }
+}
+
+/// `h:mm:ss` for an elapsed time in seconds.
+pub fn format_elapsed(seconds: f64) -> String {
+    let secs = seconds.max(0.0) as u64;
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_current_over_irregular_samples() {
+        let mut integ = Integrator::default();
+        integ.start();
+        // 2 A with jittery spacing over 3600 s
+        for &t in &[0.0, 0.9, 2.3, 2.4, 1800.0, 3599.5, 3600.0] {
+            integ.add(t, 2.0);
+        }
+        assert!((integ.hours() - 2.0).abs() < 1e-12);
+        assert_eq!(integ.elapsed, 3600.0);
+    }
+
+    #[test]
+    fn trapezoid_on_ramp_is_exact() {
+        let mut integ = Integrator::default();
+        integ.start();
+        for &t in &[0.0, 1.0, 4.0, 10.0] {
+            integ.add(t, t);
+        }
+        assert!((integ.total - 50.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn skips_nan_and_backwards_time_and_respects_stop() {
+        let mut integ = Integrator::default();
+        integ.add(0.0, 1.0); // not running yet
+        integ.start();
+        integ.add(0.0, 1.0);
+        integ.add(1.0, f64::NAN);
+        integ.add(0.5, 1.0); // baseline is still t = 0
+        integ.add(2.0, 1.0);
+        assert_eq!(integ.total, 2.0);
+        integ.stop();
+        integ.add(3.0, 1.0);
+        assert_eq!(integ.total, 2.0);
+        // Resuming does not integrate over the paused time
+        integ.start();
+        integ.add(10.0, 1.0);
+        integ.add(11.0, 1.0);
+        assert_eq!(integ.total, 3.0);
+        assert_eq!(integ.elapsed, 3.0);
+        integ.reset();
+        assert_eq!((integ.total, integ.elapsed), (0.0, 0.0));
+        assert!(integ.is_running());
+    }
+
+    #[test]
+    fn kinds_and_formatting() {
+        assert_eq!(IntegralKind::from_unit("ADC"), IntegralKind::Charge);
+        assert_eq!(IntegralKind::from_unit("W"), IntegralKind::Energy);
+        assert_eq!(IntegralKind::from_unit("VDC"), IntegralKind::Other);
+        let integ = Integrator {
+            total: 36.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            integ.format_total(IntegralKind::Charge, "ADC"),
+            "10.0000 mAh (36.0000 C)"
+        );
+        assert_eq!(IntegralKind::Energy.hour_unit("W"), "Wh");
+        assert_eq!(format_elapsed(3725.9), "1:02:05");
+    }
+
+    #[test]
+    fn prefixed_units_integrate_in_base_units() {
+        for unit in ["mADC", "mAAC", "µADC", "uADC"] {
+            assert_eq!(
+                IntegralKind::from_unit(unit),
+                IntegralKind::Charge,
+                "{unit}"
+            );
+        }
+        assert_eq!(IntegralKind::from_unit("mW"), IntegralKind::Energy);
+        assert_eq!(IntegralKind::from_unit("mVDC"), IntegralKind::Other);
+        assert_eq!(IntegralKind::Other.hour_unit("kΩ"), "Ω·h");
+
+        // 500 mA for an hour is 0.5 Ah once scaled by the prefix
+        let (factor, _) = split_si_prefix("mADC");
+        let mut integ = Integrator::default();
+        integ.start();
+        for &t in &[0.0, 1800.0, 3600.0] {
+            integ.add(t, 500.0 * factor);
+        }
+        assert!((integ.hours() - 0.5).abs() < 1e-12);
+        assert_eq!(
+            integ.format_total(IntegralKind::from_unit("mADC"), "mADC"),
+            "500.0000 mAh (1800.0000 C)"
+        );
+    }
+}
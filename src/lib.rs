@@ -1,12 +1,35 @@
 #![warn(clippy::all, rust_2018_idioms)]
 #![allow(clippy::collapsible_if)]
 
+mod analysis;
 mod app;
 pub use app::MyApp;
+mod battery;
+mod binning;
+#[cfg(not(target_arch = "wasm32"))]
+mod detect;
+mod expr;
 mod helpers;
+#[cfg(not(target_arch = "wasm32"))]
+mod http_api;
+mod integrator;
+mod macro_lang;
+#[cfg(not(target_arch = "wasm32"))]
+mod metrics;
+#[cfg(not(target_arch = "wasm32"))]
+mod mqtt;
 mod multimeter;
+mod plot_export;
+mod profile;
+mod scpi_lint;
 mod scpi_macro;
 #[cfg(not(target_arch = "wasm32"))]
+mod scripting;
+mod serial_line;
+mod shortcuts;
+mod stable;
+mod stats;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod victor_86bcd_capture;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod victor_dm1107;
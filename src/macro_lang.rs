@@ -0,0 +1,617 @@
+//! Statements a SCPI macro body may mix with plain set commands, turning a
+//! macro into a small test procedure:
+//!
+//! ```text
+//! CONF:VOLT:DC AUTO
+//! WAIT 500ms
+//! sum = 0
+//! REPEAT 10 {
+//!     v = MEAS?
+//!     sum = sum + v
+//! }
+//! avg = sum / 10
+//! ASSERT avg IN 4.95 .. 5.05
+//! LOG "5V rail" avg
+//! ```
+//!
+//! Keywords are case-insensitive. Right-hand sides, limits and logged values
+//! are [`crate::expr`] expressions over the macro's variables and the live
+//! channels (`m1`, derived channels). A query not assigned to a variable is
+//! skipped, as in plain bodies. `app::macro_run` drives a [`Runner`].
+
+use crate::expr::{self, Expr};
+use crate::scpi_macro::{ensure_newline, expects_reply, is_query, strip_comment};
+
+/// Statements executed without yielding before `Runner::next` takes a zero
+/// wait, so a long loop of assignments cannot freeze the UI.
+const MAX_STEPS_PER_CALL: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CmpOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl CmpOp {
+    /// Longer operators first so `<=` is not read as `<`.
+    const ALL: [(&'static str, CmpOp); 6] = [
+        ("<=", CmpOp::Le),
+        (">=", CmpOp::Ge),
+        ("==", CmpOp::Eq),
+        ("!=", CmpOp::Ne),
+        ("<", CmpOp::Lt),
+        (">", CmpOp::Gt),
+    ];
+
+    fn holds(self, a: f64, b: f64) -> bool {
+        match self {
+            CmpOp::Lt => a < b,
+            CmpOp::Le => a <= b,
+            CmpOp::Gt => a > b,
+            CmpOp::Ge => a >= b,
+            CmpOp::Eq => a == b,
+            CmpOp::Ne => a != b,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Check {
+    /// Inclusive limits.
+    Within(Expr, Expr),
+    Compare(CmpOp, Expr),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Instr {
+    /// A set command, newline terminated.
+    Scpi(String),
+    /// Seconds.
+    Wait(f64),
+    Query {
+        var: String,
+        query: String,
+    },
+    Let {
+        var: String,
+        expr: Expr,
+    },
+    Assert {
+        expr: Expr,
+        check: Check,
+    },
+    Log {
+        label: String,
+        expr: Expr,
+    },
+    /// `end` is the index of the matching `EndRepeat`.
+    Repeat {
+        count: u32,
+        end: usize,
+    },
+    /// `start` is the index of the matching `Repeat`.
+    EndRepeat {
+        start: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statement {
+    /// 1-based line in the macro body.
+    pub line: usize,
+    /// Source text, for progress and assertion messages.
+    pub text: String,
+    pub instr: Instr,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Program {
+    pub statements: Vec<Statement>,
+}
+
+impl Program {
+    /// Parse a macro body. Errors name the offending line.
+    pub fn parse(body: &str) -> Result<Program, String> {
+        let mut statements: Vec<Statement> = Vec::new();
+        let mut open: Vec<usize> = Vec::new();
+        for (n, raw_line) in body.lines().enumerate() {
+            let line = n + 1;
+            for part in split_statements(strip_comment(raw_line)) {
+                let text = part.trim();
+                if text.is_empty() {
+                    continue;
+                }
+                let instr = match parse_statement(text) {
+                    Ok(Some(instr)) => instr,
+                    Ok(None) => continue,
+                    Err(e) => return Err(format!("Line {line}: {e}")),
+                };
+                let index = statements.len();
+                let instr = match instr {
+                    Instr::Repeat { count, .. } => {
+                        open.push(index);
+                        Instr::Repeat { count, end: 0 }
+                    }
+                    Instr::EndRepeat { .. } => {
+                        let Some(start) = open.pop() else {
+                            return Err(format!("Line {line}: '}}' without REPEAT"));
+                        };
+                        if let Instr::Repeat { end, .. } = &mut statements[start].instr {
+                            *end = index;
+                        }
+                        Instr::EndRepeat { start }
+                    }
+                    other => other,
+                };
+                statements.push(Statement {
+                    line,
+                    text: text.to_owned(),
+                    instr,
+                });
+            }
+        }
+        if let Some(&start) = open.last() {
+            return Err(format!(
+                "Line {}: REPEAT is missing its '}}'",
+                statements[start].line
+            ));
+        }
+        Ok(Program { statements })
+    }
+
+    /// Only set commands: the body can be queued in one go.
+    pub fn is_plain(&self) -> bool {
+        self.statements
+            .iter()
+            .all(|s| matches!(s.instr, Instr::Scpi(_)))
+    }
+}
+
+/// Split on `;` outside double quotes, so a `LOG` label may contain one.
+fn split_statements(line: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&line[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&line[start..]);
+    parts
+}
+
+fn parse_expr(src: &str) -> Result<Expr, String> {
+    expr::parse(src).map_err(|e| format!("{e} in '{}'", src.trim()))
+}
+
+/// One statement; `None` for a query that is skipped.
+fn parse_statement(text: &str) -> Result<Option<Instr>, String> {
+    if text == "}" {
+        return Ok(Some(Instr::EndRepeat { start: 0 }));
+    }
+    let (keyword, rest) = match text.split_once(char::is_whitespace) {
+        Some((keyword, rest)) => (keyword, rest.trim()),
+        None => (text, ""),
+    };
+    match keyword.to_ascii_uppercase().as_str() {
+        "WAIT" => return parse_duration(rest).map(|s| Some(Instr::Wait(s))),
+        "REPEAT" => {
+            let Some(count) = rest.strip_suffix('{') else {
+                return Err("REPEAT needs a '{' at the end of the line".to_owned());
+            };
+            let count = count
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| format!("REPEAT count '{}' is not a number", count.trim()))?;
+            return Ok(Some(Instr::Repeat { count, end: 0 }));
+        }
+        "ASSERT" => return parse_assert(rest).map(Some),
+        "LOG" => return parse_log(rest).map(Some),
+        _ => {}
+    }
+    if let Some((var, rhs)) = split_assignment(text) {
+        let rhs = rhs.trim();
+        if expects_reply(rhs) {
+            return Ok(Some(Instr::Query {
+                var: var.to_owned(),
+                query: ensure_newline(rhs),
+            }));
+        }
+        return Ok(Some(Instr::Let {
+            var: var.to_owned(),
+            expr: parse_expr(rhs)?,
+        }));
+    }
+    if is_query(text) {
+        return Ok(None);
+    }
+    Ok(Some(Instr::Scpi(ensure_newline(text))))
+}
+
+/// `name = rhs`, but not `name == rhs`.
+fn split_assignment(text: &str) -> Option<(&str, &str)> {
+    let (lhs, rhs) = text.split_once('=')?;
+    let var = lhs.trim();
+    (expr::is_identifier(var) && !rhs.starts_with('=')).then_some((var, rhs))
+}
+
+/// `500ms`, `2 s`, `1.5min`.
+fn parse_duration(src: &str) -> Result<f64, String> {
+    let src = src.trim();
+    let split = src
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(src.len());
+    let (number, unit) = src.split_at(split);
+    let invalid = || format!("WAIT needs a duration like 500ms or 2s, not '{src}'");
+    let scale = match unit.trim().to_ascii_lowercase().as_str() {
+        "ms" => 0.001,
+        "s" => 1.0,
+        "min" => 60.0,
+        _ => return Err(invalid()),
+    };
+    number
+        .parse::<f64>()
+        .map(|v| v * scale)
+        .map_err(|_| invalid())
+}
+
+/// `ASSERT expr IN lo .. hi` or `ASSERT expr <op> expr`.
+fn parse_assert(src: &str) -> Result<Instr, String> {
+    let upper = src.to_ascii_uppercase();
+    if let Some(at) = upper.find(" IN ") {
+        let (lo, hi) = src[at + 4..]
+            .split_once("..")
+            .ok_or_else(|| "ASSERT ... IN needs limits like 4.9 .. 5.1".to_owned())?;
+        return Ok(Instr::Assert {
+            expr: parse_expr(&src[..at])?,
+            check: Check::Within(parse_expr(lo)?, parse_expr(hi)?),
+        });
+    }
+    for (token, op) in CmpOp::ALL {
+        if let Some((lhs, rhs)) = src.split_once(token) {
+            return Ok(Instr::Assert {
+                expr: parse_expr(lhs)?,
+                check: Check::Compare(op, parse_expr(rhs)?),
+            });
+        }
+    }
+    Err("ASSERT needs 'IN lo .. hi' or a comparison such as '< 0.5'".to_owned())
+}
+
+/// `LOG "label" expr`, or `LOG expr` labelled with the expression text.
+fn parse_log(src: &str) -> Result<Instr, String> {
+    if let Some(quoted) = src.strip_prefix('"') {
+        let (label, rest) = quoted
+            .split_once('"')
+            .ok_or_else(|| "LOG label is missing its closing quote".to_owned())?;
+        return Ok(Instr::Log {
+            label: label.to_owned(),
+            expr: parse_expr(rest)?,
+        });
+    }
+    Ok(Instr::Log {
+        label: src.to_owned(),
+        expr: parse_expr(src)?,
+    })
+}
+
+/// A number from a query reply such as `+4.9921E+00` or `"1.5"`.
+pub fn reply_value(reply: &str) -> Option<f64> {
+    reply.trim().trim_matches('"').trim().parse().ok()
+}
+
+/// What the runner needs from the app next.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    Send(String),
+    /// Send the query, then call [`Runner::set`] with the reply value.
+    Query {
+        var: String,
+        query: String,
+    },
+    /// Seconds before the next call.
+    Wait(f64),
+    Log {
+        label: String,
+        value: f64,
+    },
+    Assert {
+        text: String,
+        value: f64,
+        passed: bool,
+    },
+    Failed(String),
+    Done,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoopState {
+    pub iteration: u32,
+    pub count: u32,
+}
+
+/// Steps through a [`Program`]. Set commands, queries, waits, logs and
+/// assertions come back as [`Step`]s; the rest runs inside `next`.
+pub struct Runner {
+    program: Program,
+    pc: usize,
+    loops: Vec<LoopState>,
+    vars: Vec<(String, f64)>,
+    /// Line of the statement behind the last returned step.
+    line: usize,
+}
+
+impl Runner {
+    pub fn new(program: Program) -> Self {
+        Self {
+            program,
+            pc: 0,
+            loops: Vec::new(),
+            vars: Vec::new(),
+            line: 0,
+        }
+    }
+
+    pub fn set(&mut self, var: &str, value: f64) {
+        match self.vars.iter_mut().find(|(name, _)| name == var) {
+            Some((_, v)) => *v = value,
+            None => self.vars.push((var.to_owned(), value)),
+        }
+    }
+
+    pub fn var(&self, name: &str) -> Option<f64> {
+        self.vars.iter().find(|(n, _)| n == name).map(|&(_, v)| v)
+    }
+
+    /// Variables in order of first assignment.
+    pub fn vars(&self) -> &[(String, f64)] {
+        &self.vars
+    }
+
+    /// Enclosing loops, outermost first.
+    pub fn loops(&self) -> &[LoopState] {
+        &self.loops
+    }
+
+    /// The statement about to run, `None` once finished.
+    pub fn current(&self) -> Option<&Statement> {
+        self.program.statements.get(self.pc)
+    }
+
+    /// 1-based line of the statement the last [`Runner::next`] stopped at.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Fraction of statements passed, ignoring loop repetitions.
+    pub fn progress(&self) -> f32 {
+        let len = self.program.statements.len().max(1);
+        self.pc.min(len) as f32 / len as f32
+    }
+
+    /// Run up to the next step the app must carry out. `channels` resolves
+    /// names that are not macro variables.
+    pub fn next(&mut self, channels: &dyn Fn(&str) -> Option<f64>) -> Step {
+        for _ in 0..MAX_STEPS_PER_CALL {
+            let Some(statement) = self.program.statements.get(self.pc) else {
+                return Step::Done;
+            };
+            let line = statement.line;
+            let eval = |expr: &Expr| {
+                expr.eval(&|name| self.var(name).or_else(|| channels(name)))
+                    .map_err(|e| format!("Line {line}: {e}"))
+            };
+            let step = match &statement.instr {
+                Instr::Scpi(cmd) => Some(Step::Send(cmd.clone())),
+                Instr::Wait(seconds) => Some(Step::Wait(*seconds)),
+                Instr::Query { var, query } => Some(Step::Query {
+                    var: var.clone(),
+                    query: query.clone(),
+                }),
+                Instr::Let { var, expr } => match eval(expr) {
+                    Ok(value) => {
+                        let var = var.clone();
+                        self.set(&var, value);
+                        None
+                    }
+                    Err(e) => return Step::Failed(e),
+                },
+                Instr::Assert { expr, check } => {
+                    let checked = eval(expr).and_then(|value| {
+                        let passed = match check {
+                            Check::Within(lo, hi) => value >= eval(lo)? && value <= eval(hi)?,
+                            Check::Compare(op, rhs) => op.holds(value, eval(rhs)?),
+                        };
+                        Ok((value, passed))
+                    });
+                    match checked {
+                        Ok((value, passed)) => Some(Step::Assert {
+                            text: statement.text.clone(),
+                            value,
+                            passed,
+                        }),
+                        Err(e) => return Step::Failed(e),
+                    }
+                }
+                Instr::Log { label, expr } => match eval(expr) {
+                    Ok(value) => Some(Step::Log {
+                        label: label.clone(),
+                        value,
+                    }),
+                    Err(e) => return Step::Failed(e),
+                },
+                &Instr::Repeat { count, end } => {
+                    if count == 0 {
+                        self.pc = end + 1;
+                        continue;
+                    }
+                    self.loops.push(LoopState {
+                        iteration: 1,
+                        count,
+                    });
+                    None
+                }
+                &Instr::EndRepeat { start } => {
+                    if let Some(top) = self.loops.last_mut() {
+                        if top.iteration < top.count {
+                            top.iteration += 1;
+                            self.pc = start + 1;
+                            continue;
+                        }
+                    }
+                    self.loops.pop();
+                    None
+                }
+            };
+            self.pc += 1;
+            if let Some(step) = step {
+                self.line = line;
+                return step;
+            }
+        }
+        Step::Wait(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_channels(_: &str) -> Option<f64> {
+        None
+    }
+
+    #[test]
+    fn plain_bodies_stay_plain() {
+        let program = Program::parse("CONF:VOLT:DC AUTO\nRATE F; MEAS? # poll\n").unwrap();
+        assert!(program.is_plain());
+        assert_eq!(program.statements.len(), 2);
+        assert_eq!(
+            program.statements[1].instr,
+            Instr::Scpi("RATE F\n".to_owned())
+        );
+        assert!(!Program::parse("WAIT 1s").unwrap().is_plain());
+    }
+
+    #[test]
+    fn parses_statements() {
+        let program = Program::parse(
+            "wait 500ms\nv = MEAS?\nx = v * 2\nASSERT x in 1 .. 2\nASSERT v >= 0.5\n\
+             LOG \"rail; 5V\" x",
+        )
+        .unwrap();
+        let instrs: Vec<&Instr> = program.statements.iter().map(|s| &s.instr).collect();
+        assert_eq!(instrs[0], &Instr::Wait(0.5));
+        assert_eq!(
+            instrs[1],
+            &Instr::Query {
+                var: "v".to_owned(),
+                query: "MEAS?\n".to_owned()
+            }
+        );
+        assert!(matches!(instrs[2], Instr::Let { var, .. } if var == "x"));
+        assert!(matches!(
+            instrs[3],
+            Instr::Assert {
+                check: Check::Within(..),
+                ..
+            }
+        ));
+        assert!(matches!(
+            instrs[4],
+            Instr::Assert {
+                check: Check::Compare(CmpOp::Ge, _),
+                ..
+            }
+        ));
+        assert!(matches!(instrs[5], Instr::Log { label, .. } if label == "rail; 5V"));
+    }
+
+    #[test]
+    fn reports_errors_with_line() {
+        assert_eq!(
+            Program::parse("RATE F\nWAIT soon").unwrap_err(),
+            "Line 2: WAIT needs a duration like 500ms or 2s, not 'soon'"
+        );
+        assert!(
+            Program::parse("REPEAT 3 {\nRATE F")
+                .unwrap_err()
+                .starts_with("Line 1:")
+        );
+        assert!(Program::parse("}").unwrap_err().starts_with("Line 1:"));
+        assert!(Program::parse("REPEAT x {\n}").is_err());
+        assert!(Program::parse("ASSERT v").is_err());
+    }
+
+    #[test]
+    fn runs_loops_queries_and_asserts() {
+        let program = Program::parse(
+            "sum = 0\nREPEAT 3 {\n  v = MEAS?\n  sum = sum + v\n}\navg = sum / 3\n\
+             ASSERT avg IN 1.9 .. 2.1\nLOG \"avg\" avg\nREPEAT 0 {\nRATE F\n}",
+        )
+        .unwrap();
+        let mut runner = Runner::new(program);
+        let mut queries = 0;
+        let mut steps = Vec::new();
+        loop {
+            match runner.next(&no_channels) {
+                Step::Query { var, .. } => {
+                    queries += 1;
+                    assert_eq!(runner.loops()[0].iteration, queries);
+                    assert_eq!(runner.line(), 3);
+                    runner.set(&var, queries as f64);
+                }
+                Step::Done => break,
+                step => steps.push(step),
+            }
+        }
+        assert_eq!(queries, 3);
+        assert_eq!(
+            steps,
+            vec![
+                Step::Assert {
+                    text: "ASSERT avg IN 1.9 .. 2.1".to_owned(),
+                    value: 2.0,
+                    passed: true
+                },
+                Step::Log {
+                    label: "avg".to_owned(),
+                    value: 2.0
+                },
+            ]
+        );
+        assert_eq!(runner.progress(), 1.0);
+    }
+
+    #[test]
+    fn unknown_names_fail_and_channels_resolve() {
+        let mut runner = Runner::new(Program::parse("ASSERT m1 < 1").unwrap());
+        assert!(matches!(runner.next(&no_channels), Step::Failed(e) if e.starts_with("Line 1:")));
+        let mut runner = Runner::new(Program::parse("ASSERT m1 < 1").unwrap());
+        let step = runner.next(&|name| (name == "m1").then_some(0.5));
+        assert!(matches!(step, Step::Assert { passed: true, .. }));
+    }
+
+    #[test]
+    fn long_loops_yield() {
+        let mut runner = Runner::new(Program::parse("REPEAT 100000 {\nx = 1\n}").unwrap());
+        assert_eq!(runner.next(&no_channels), Step::Wait(0.0));
+    }
+
+    #[test]
+    fn reply_values() {
+        assert_eq!(reply_value(" +4.9921E+00\r"), Some(4.9921));
+        assert_eq!(reply_value("\"1.5\""), Some(1.5));
+        assert_eq!(reply_value("VOLT"), None);
+    }
+}
@@ -0,0 +1,168 @@
+//! Text formats for exporting readings to time series databases: InfluxDB
+//! line protocol and the Prometheus text exposition format. Where the lines
+//! go is up to `app::metrics` and `app::http_api`.
+
+use std::fmt::Write as _;
+
+/// Escape a measurement name: commas and spaces.
+fn escape_measurement(s: &str) -> String {
+    escape(s, &[',', ' '])
+}
+
+/// Escape a tag key, tag value or field key: commas, equals signs and spaces.
+fn escape_key(s: &str) -> String {
+    escape(s, &[',', '=', ' '])
+}
+
+fn escape(s: &str, special: &[char]) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            // Line breaks end a line; nothing in a key can stand for them
+            '\n' | '\r' => out.push(' '),
+            c if special.contains(&c) => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// One line of InfluxDB line protocol with float fields and a nanosecond
+/// timestamp. Tags with an empty value and non-finite fields are left out,
+/// since Influx rejects both; `None` when no field is left.
+pub fn influx_line(
+    measurement: &str,
+    tags: &[(&str, &str)],
+    fields: &[(&str, f64)],
+    timestamp_ns: i64,
+) -> Option<String> {
+    let fields: Vec<String> = fields
+        .iter()
+        .filter(|(_, v)| v.is_finite())
+        .map(|(k, v)| format!("{}={v:?}", escape_key(k)))
+        .collect();
+    if fields.is_empty() {
+        return None;
+    }
+    let mut line = escape_measurement(measurement);
+    for (key, value) in tags.iter().filter(|(_, v)| !v.is_empty()) {
+        let _ = write!(line, ",{}={}", escape_key(key), escape_key(value));
+    }
+    let _ = write!(line, " {} {timestamp_ns}", fields.join(","));
+    Some(line)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetricKind {
+    Gauge,
+    Counter,
+}
+
+/// Builder for a Prometheus text exposition (format 0.0.4).
+#[derive(Default)]
+pub struct Exposition {
+    out: String,
+}
+
+pub const EXPOSITION_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+impl Exposition {
+    /// Start a metric family. Its samples follow with [`Self::sample`].
+    pub fn family(&mut self, name: &str, kind: MetricKind, help: &str) {
+        let help = help.replace('\\', "\\\\").replace('\n', "\\n");
+        let kind = match kind {
+            MetricKind::Gauge => "gauge",
+            MetricKind::Counter => "counter",
+        };
+        let _ = writeln!(self.out, "# HELP {name} {help}");
+        let _ = writeln!(self.out, "# TYPE {name} {kind}");
+    }
+
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(k, v)| {
+                    let v = v
+                        .replace('\\', "\\\\")
+                        .replace('"', "\\\"")
+                        .replace('\n', "\\n");
+                    format!("{k}=\"{v}\"")
+                })
+                .collect();
+            let _ = write!(self.out, "{{{}}}", labels.join(","));
+        }
+        let value = if value.is_nan() {
+            "NaN".to_owned()
+        } else if value.is_infinite() {
+            if value > 0.0 { "+Inf" } else { "-Inf" }.to_owned()
+        } else {
+            value.to_string()
+        };
+        let _ = writeln!(self.out, " {value}");
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_influx_lines() {
+        assert_eq!(
+            influx_line(
+                "rusty meter",
+                &[("channel", "m1"), ("label", "Meter 1"), ("unit", "")],
+                &[("value", 1.5), ("overload", f64::NAN)],
+                1_700_000_000_000_000_000,
+            )
+            .as_deref(),
+            Some(r"rusty\ meter,channel=m1,label=Meter\ 1 value=1.5 1700000000000000000")
+        );
+        assert_eq!(
+            influx_line("m", &[("k", "a=b,c")], &[("v", 2.0)], 0).as_deref(),
+            Some(r"m,k=a\=b\,c v=2.0 0")
+        );
+        assert_eq!(
+            influx_line("m", &[], &[("v", 1e-7)], 5).as_deref(),
+            Some("m v=1e-7 5")
+        );
+        assert_eq!(influx_line("m", &[], &[("v", f64::INFINITY)], 0), None);
+    }
+
+    #[test]
+    fn formats_exposition() {
+        let mut exp = Exposition::default();
+        exp.family("rm_reading", MetricKind::Gauge, "Latest reading");
+        exp.sample(
+            "rm_reading",
+            &[("channel", "m1"), ("label", "say \"hi\"")],
+            0.25,
+        );
+        exp.sample("rm_reading", &[("channel", "m2")], f64::NEG_INFINITY);
+        exp.family(
+            "rm_readings_total",
+            MetricKind::Counter,
+            "Readings\nreceived",
+        );
+        exp.sample("rm_readings_total", &[], 42.0);
+        assert_eq!(
+            exp.finish(),
+            "# HELP rm_reading Latest reading\n\
+             # TYPE rm_reading gauge\n\
+             rm_reading{channel=\"m1\",label=\"say \\\"hi\\\"\"} 0.25\n\
+             rm_reading{channel=\"m2\"} -Inf\n\
+             # HELP rm_readings_total Readings\\nreceived\n\
+             # TYPE rm_readings_total counter\n\
+             rm_readings_total 42\n"
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! Minimal MQTT 3.1.1 packet encoding and decoding for the reading
+//! publisher: QoS 0 publish and subscribe, keep-alive and a last will.
+//! The connection itself lives in `app::mqtt`.
+
+use serde_json::Value;
+
+use crate::http_api::{ApiCommand, parse_mode};
+
+pub const PINGREQ: [u8; 2] = [0xC0, 0x00];
+pub const DISCONNECT: [u8; 2] = [0xE0, 0x00];
+
+/// Incoming packets larger than this are treated as a protocol error.
+const MAX_PACKET_BYTES: usize = 1024 * 1024;
+
+fn put_remaining_length(out: &mut Vec<u8>, mut len: usize) {
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+}
+
+/// Length-prefixed string or binary field.
+fn put_field(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + 5);
+    out.push(header);
+    put_remaining_length(&mut out, body.len());
+    out.extend_from_slice(body);
+    out
+}
+
+/// Message the broker publishes for us if the connection drops.
+pub struct Will<'a> {
+    pub topic: &'a str,
+    pub payload: &'a [u8],
+    pub retain: bool,
+}
+
+pub struct ConnectOptions<'a> {
+    pub client_id: &'a str,
+    pub keep_alive_s: u16,
+    pub username: Option<&'a str>,
+    pub password: Option<&'a str>,
+    pub will: Option<Will<'a>>,
+}
+
+/// CONNECT with a clean session.
+pub fn connect(opts: &ConnectOptions<'_>) -> Vec<u8> {
+    let mut flags = 0x02;
+    let mut body = Vec::new();
+    put_field(&mut body, b"MQTT");
+    body.push(4); // protocol level 3.1.1
+    let flags_at = body.len();
+    body.push(0);
+    body.extend_from_slice(&opts.keep_alive_s.to_be_bytes());
+    put_field(&mut body, opts.client_id.as_bytes());
+    if let Some(will) = &opts.will {
+        flags |= 0x04;
+        if will.retain {
+            flags |= 0x20;
+        }
+        put_field(&mut body, will.topic.as_bytes());
+        put_field(&mut body, will.payload);
+    }
+    if let Some(username) = opts.username {
+        flags |= 0x80;
+        put_field(&mut body, username.as_bytes());
+        // A password is only allowed together with a user name
+        if let Some(password) = opts.password {
+            flags |= 0x40;
+            put_field(&mut body, password.as_bytes());
+        }
+    }
+    body[flags_at] = flags;
+    packet(0x10, &body)
+}
+
+/// QoS 0 PUBLISH.
+pub fn publish(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::with_capacity(topic.len() + payload.len() + 2);
+    put_field(&mut body, topic.as_bytes());
+    body.extend_from_slice(payload);
+    packet(if retain { 0x31 } else { 0x30 }, &body)
+}
+
+/// SUBSCRIBE to one topic filter at QoS 0.
+pub fn subscribe(packet_id: u16, filter: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&packet_id.to_be_bytes());
+    put_field(&mut body, filter.as_bytes());
+    body.push(0);
+    packet(0x82, &body)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Packet {
+    ConnAck {
+        return_code: u8,
+    },
+    Publish {
+        topic: String,
+        payload: Vec<u8>,
+    },
+    SubAck {
+        granted: bool,
+    },
+    PingResp,
+    /// Any other packet type, by its fixed header type nibble.
+    Other(u8),
+}
+
+/// Decode the first complete packet in `buf` and the number of bytes it
+/// used. `Ok(None)` when more bytes are needed.
+pub fn decode(buf: &[u8]) -> Result<Option<(Packet, usize)>, String> {
+    let Some(&header) = buf.first() else {
+        return Ok(None);
+    };
+    let mut len = 0usize;
+    let mut pos = 1;
+    loop {
+        let Some(&byte) = buf.get(pos) else {
+            return Ok(None);
+        };
+        len |= ((byte & 0x7F) as usize) << (7 * (pos - 1));
+        pos += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if pos > 4 {
+            return Err("Malformed remaining length".to_owned());
+        }
+    }
+    if len > MAX_PACKET_BYTES {
+        return Err(format!("Packet of {len} bytes is too large"));
+    }
+    let Some(body) = buf.get(pos..pos + len) else {
+        return Ok(None);
+    };
+    let packet = match header >> 4 {
+        2 if len >= 2 => Packet::ConnAck {
+            return_code: body[1],
+        },
+        3 => {
+            let topic_len = body
+                .get(..2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+                .ok_or("Truncated PUBLISH")?;
+            let topic = body.get(2..2 + topic_len).ok_or("Truncated PUBLISH")?;
+            // QoS 1 and 2 carry a packet identifier before the payload
+            let payload_at = 2 + topic_len + if header & 0x06 != 0 { 2 } else { 0 };
+            Packet::Publish {
+                topic: String::from_utf8_lossy(topic).into_owned(),
+                payload: body.get(payload_at..).unwrap_or_default().to_vec(),
+            }
+        }
+        9 => Packet::SubAck {
+            granted: body.get(2).is_some_and(|&code| code & 0x80 == 0),
+        },
+        13 => Packet::PingResp,
+        kind => Packet::Other(kind),
+    };
+    Ok(Some((packet, pos + len)))
+}
+
+/// Reason a broker refused the connection, from the CONNACK return code.
+pub fn connack_error(code: u8) -> &'static str {
+    match code {
+        1 => "unacceptable protocol version",
+        2 => "client identifier rejected",
+        3 => "server unavailable",
+        4 => "bad user name or password",
+        5 => "not authorized",
+        _ => "refused",
+    }
+}
+
+/// Parse a command topic payload: a JSON object with one of `mode`,
+/// `range`, `rate`, `macro` (strings) or `recording` (bool).
+pub fn parse_command(payload: &[u8]) -> Result<ApiCommand, String> {
+    let value: Value =
+        serde_json::from_slice(payload).map_err(|e| format!("Invalid JSON command: {e}"))?;
+    let Some(object) = value.as_object().filter(|o| o.len() == 1) else {
+        return Err("A command is an object with exactly one field".to_owned());
+    };
+    let (key, value) = object.iter().next().unwrap();
+    let text = || {
+        value
+            .as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| format!("Field '{key}' must be a string"))
+    };
+    match key.as_str() {
+        "mode" => {
+            let mode = text()?;
+            parse_mode(&mode)
+                .map(ApiCommand::SetMode)
+                .ok_or_else(|| format!("Unknown mode '{mode}'"))
+        }
+        "range" => text().map(ApiCommand::SetRange),
+        "rate" => text().map(ApiCommand::SetRate),
+        "macro" => text().map(ApiCommand::RunMacro),
+        "recording" => value
+            .as_bool()
+            .map(ApiCommand::Recording)
+            .ok_or_else(|| "Field 'recording' must be true or false".to_owned()),
+        _ => Err(format!("Unknown command '{key}'")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multimeter::MeterMode;
+
+    #[test]
+    fn encodes_packets() {
+        let will = Will {
+            topic: "m/status",
+            payload: b"off",
+            retain: true,
+        };
+        let bytes = connect(&ConnectOptions {
+            client_id: "rm",
+            keep_alive_s: 30,
+            username: Some("u"),
+            password: Some("p"),
+            will: Some(will),
+        });
+        let mut expected = vec![0x10, 35, 0, 4, b'M', b'Q', b'T', b'T', 4, 0xE6, 0, 30];
+        expected.extend_from_slice(&[0, 2, b'r', b'm']);
+        expected.extend_from_slice(&[0, 8, b'm', b'/', b's', b't', b'a', b't', b'u', b's']);
+        expected.extend_from_slice(&[0, 3, b'o', b'f', b'f', 0, 1, b'u', 0, 1, b'p']);
+        assert_eq!(bytes, expected);
+        assert_eq!(
+            publish("a/b", b"1", true),
+            vec![0x31, 6, 0, 3, b'a', b'/', b'b', b'1']
+        );
+        assert_eq!(subscribe(7, "c"), vec![0x82, 6, 0, 7, 0, 1, b'c', 0]);
+        // Remaining lengths above 127 take more than one byte
+        let long = publish("t", &[0u8; 200], false);
+        assert_eq!(&long[..3], &[0x30, 0xCB, 0x01]);
+    }
+
+    #[test]
+    fn decodes_packets() {
+        assert_eq!(decode(&[]), Ok(None));
+        assert_eq!(
+            decode(&[0x20, 2, 0, 5]),
+            Ok(Some((Packet::ConnAck { return_code: 5 }, 4)))
+        );
+        let mut buf = publish("cmd", b"{}", false);
+        buf.extend_from_slice(&[0xD0, 0]);
+        let (first, used) = decode(&buf).unwrap().unwrap();
+        assert_eq!(
+            first,
+            Packet::Publish {
+                topic: "cmd".to_owned(),
+                payload: b"{}".to_vec()
+            }
+        );
+        assert_eq!(decode(&buf[used..]), Ok(Some((Packet::PingResp, 2))));
+        assert_eq!(decode(&buf[..4]), Ok(None));
+        // QoS 1 publish skips the packet identifier
+        let qos1 = [0x32, 7, 0, 1, b'x', 0, 9, b'h', b'i'];
+        assert_eq!(
+            decode(&qos1).unwrap().unwrap().0,
+            Packet::Publish {
+                topic: "x".to_owned(),
+                payload: b"hi".to_vec()
+            }
+        );
+        assert_eq!(
+            decode(&[0x90, 3, 0, 1, 0x80]).unwrap().unwrap().0,
+            Packet::SubAck { granted: false }
+        );
+        assert!(decode(&[0x30, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]).is_err());
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            parse_command(br#"{"mode":"vac"}"#),
+            Ok(ApiCommand::SetMode(MeterMode::Vac))
+        );
+        assert_eq!(
+            parse_command(br#"{"macro":"Zero"}"#),
+            Ok(ApiCommand::RunMacro("Zero".to_owned()))
+        );
+        assert_eq!(
+            parse_command(br#"{"recording":true}"#),
+            Ok(ApiCommand::Recording(true))
+        );
+        assert!(parse_command(br#"{"mode":"VDC","range":"5V"}"#).is_err());
+        assert!(parse_command(br#"{"range":5}"#).is_err());
+        assert!(parse_command(br#"{"reboot":true}"#).is_err());
+        assert!(parse_command(b"VDC").is_err());
+    }
+}
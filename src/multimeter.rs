@@ -369,3 +369,25 @@ fn parse_eng(raw: &str) -> Option<f64> {
     }
     t.parse::<f64>().ok()
 }
+
+/// Split an SI prefix off a display unit, e.g. `mADC` → `(1e-3, "ADC")` and
+/// `kΩ` → `(1e3, "Ω")`. A prefix only counts when a base unit follows, so
+/// `mil` or a bare `m` stay as they are.
+pub fn split_si_prefix(unit: &str) -> (f64, &str) {
+    let unit = unit.trim();
+    let mut chars = unit.chars();
+    let factor = match chars.next() {
+        Some('p') => 1e-12,
+        Some('n') => 1e-9,
+        Some('u' | 'µ' | 'μ') => 1e-6,
+        Some('m') => 1e-3,
+        Some('k') => 1e3,
+        Some('M') => 1e6,
+        _ => return (1.0, unit),
+    };
+    let base = chars.as_str();
+    match base.chars().next() {
+        Some(c) if c.is_ascii_uppercase() || c == 'Ω' => (factor, base),
+        _ => (1.0, unit),
+    }
+}
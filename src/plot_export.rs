@@ -0,0 +1,1065 @@
+//! Offscreen PNG/SVG rendering of the graph and histogram tabs.
+//!
+//! Plots are drawn from the underlying samples, not from a screenshot, so the
+//! output resolution is independent of the window. SVG is plain text; PNG is
+//! rasterised into an [`image::RgbaImage`] with a built-in 5x7 bitmap font so
+//! no font files or extra dependencies are needed.
+
+use std::borrow::Cow;
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+
+/// Output file type, picked from the file extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Png,
+    Svg,
+}
+
+impl ExportFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "png" => Some(Self::Png),
+            "svg" => Some(Self::Svg),
+            _ => None,
+        }
+    }
+}
+
+/// One histogram bar in data units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExportBar {
+    pub center: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Stroke pattern of vertical marker lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dash {
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl Dash {
+    /// (on, off) lengths in pixels at text scale 1, `None` for solid.
+    fn pattern(self) -> Option<(f64, f64)> {
+        match self {
+            Self::Solid => None,
+            Self::Dashed => Some((8.0, 5.0)),
+            Self::Dotted => Some((2.0, 4.0)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SeriesKind {
+    Line(Vec<[f64; 2]>),
+    Bars(Vec<ExportBar>),
+    /// Full-height markers at these x positions, e.g. the mean and ±σ.
+    VLines {
+        xs: Vec<f64>,
+        dash: Dash,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportSeries {
+    pub name: String,
+    pub color: [u8; 3],
+    pub kind: SeriesKind,
+}
+
+/// Everything needed to draw one plot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlotExport {
+    pub title: String,
+    pub x_label: String,
+    pub y_label: String,
+    /// Shown under the title, e.g. when the data was captured.
+    pub timestamp: String,
+    pub series: Vec<ExportSeries>,
+    /// Logarithmic y axis. Drawn like the histogram tab: `log10(y) + 1`, so a
+    /// count of one still shows above the baseline; values below 0.1 are dropped.
+    pub log_y: bool,
+}
+
+fn log_height(y: f64) -> f64 {
+    y.log10() + 1.0
+}
+
+impl PlotExport {
+    /// The plot with log y values mapped to drawing space.
+    fn drawn(&self) -> Cow<'_, Self> {
+        if !self.log_y {
+            return Cow::Borrowed(self);
+        }
+        let series = self
+            .series
+            .iter()
+            .map(|s| ExportSeries {
+                name: s.name.clone(),
+                color: s.color,
+                kind: match &s.kind {
+                    SeriesKind::Line(points) => SeriesKind::Line(
+                        points
+                            .iter()
+                            .filter(|p| log_height(p[1]) >= 0.0)
+                            .map(|p| [p[0], log_height(p[1])])
+                            .collect(),
+                    ),
+                    SeriesKind::Bars(bars) => SeriesKind::Bars(
+                        bars.iter()
+                            .filter(|bar| bar.height > 0.0)
+                            .map(|bar| ExportBar {
+                                height: log_height(bar.height),
+                                ..*bar
+                            })
+                            .collect(),
+                    ),
+                    kind @ SeriesKind::VLines { .. } => kind.clone(),
+                },
+            })
+            .collect();
+        Cow::Owned(Self {
+            series,
+            ..self.clone()
+        })
+    }
+}
+
+/// Data bounds of a plot, padded so lines do not sit on the frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Bounds {
+    x_min: f64,
+    x_max: f64,
+    y_min: f64,
+    y_max: f64,
+}
+
+impl Bounds {
+    fn of(plot: &PlotExport) -> Self {
+        let mut b = Self {
+            x_min: f64::INFINITY,
+            x_max: f64::NEG_INFINITY,
+            y_min: f64::INFINITY,
+            y_max: f64::NEG_INFINITY,
+        };
+        let mut has_bars = false;
+        for s in &plot.series {
+            match &s.kind {
+                SeriesKind::Line(points) => {
+                    for p in points
+                        .iter()
+                        .filter(|p| p[0].is_finite() && p[1].is_finite())
+                    {
+                        b.include(p[0], p[1]);
+                    }
+                }
+                SeriesKind::Bars(bars) => {
+                    has_bars = true;
+                    for bar in bars {
+                        b.include(bar.center - bar.width / 2.0, bar.height);
+                        b.include(bar.center + bar.width / 2.0, 0.0);
+                    }
+                }
+                SeriesKind::VLines { xs, .. } => {
+                    for &x in xs.iter().filter(|x| x.is_finite()) {
+                        b.x_min = b.x_min.min(x);
+                        b.x_max = b.x_max.max(x);
+                    }
+                }
+            }
+        }
+        if !b.x_min.is_finite() {
+            return Self {
+                x_min: 0.0,
+                x_max: 1.0,
+                y_min: 0.0,
+                y_max: 1.0,
+            };
+        }
+        // Markers alone give no y range.
+        if !b.y_min.is_finite() {
+            b.y_min = 0.0;
+            b.y_max = 1.0;
+        }
+        if b.x_min == b.x_max {
+            b.x_min -= 0.5;
+            b.x_max += 0.5;
+        }
+        if b.y_min == b.y_max {
+            let pad = if b.y_min == 0.0 {
+                1.0
+            } else {
+                b.y_min.abs() * 0.1
+            };
+            b.y_min -= pad;
+            b.y_max += pad;
+        }
+        let pad = (b.y_max - b.y_min) * 0.05;
+        b.y_max += pad;
+        // Bars grow from zero, keep the baseline on the frame.
+        if !has_bars || b.y_min < 0.0 {
+            b.y_min -= pad;
+        }
+        b
+    }
+
+    fn include(&mut self, x: f64, y: f64) {
+        self.x_min = self.x_min.min(x);
+        self.x_max = self.x_max.max(x);
+        self.y_min = self.y_min.min(y);
+        self.y_max = self.y_max.max(y);
+    }
+}
+
+/// Pixel layout shared by the SVG and PNG renderers.
+#[derive(Clone, Copy, Debug)]
+struct Frame {
+    left: f64,
+    top: f64,
+    right: f64,
+    bottom: f64,
+    bounds: Bounds,
+}
+
+impl Frame {
+    fn new(width: u32, height: u32, font: f64, bounds: Bounds, y_label_chars: usize) -> Self {
+        Self {
+            // Widest y tick label plus room for the rotated axis label.
+            left: (y_label_chars as f64 * 0.62 + 3.0) * font,
+            top: 4.5 * font,
+            right: width as f64 - 2.0 * font,
+            bottom: height as f64 - 4.2 * font,
+            bounds,
+        }
+    }
+
+    fn px(&self, x: f64) -> f64 {
+        let b = &self.bounds;
+        self.left + (x - b.x_min) / (b.x_max - b.x_min) * (self.right - self.left)
+    }
+
+    fn py(&self, y: f64) -> f64 {
+        let b = &self.bounds;
+        self.bottom - (y - b.y_min) / (b.y_max - b.y_min) * (self.bottom - self.top)
+    }
+}
+
+/// Text scale for a given output size. 1 at roughly 800x450.
+fn text_scale(width: u32, height: u32) -> f64 {
+    (width.min(height * 16 / 9) as f64 / 800.0).max(0.5)
+}
+
+/// Round tick positions covering `[min, max]`, about `target` of them.
+pub fn nice_ticks(min: f64, max: f64, target: usize) -> Vec<f64> {
+    if !min.is_finite() || !max.is_finite() || max <= min || target == 0 {
+        return Vec::new();
+    }
+    let raw = (max - min) / target as f64;
+    let mag = 10f64.powf(raw.log10().floor());
+    let norm = raw / mag;
+    let step = if norm < 1.5 {
+        1.0
+    } else if norm < 3.0 {
+        2.0
+    } else if norm < 7.0 {
+        5.0
+    } else {
+        10.0
+    } * mag;
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
+
+/// Tick label with just enough decimals for the tick spacing.
+pub fn format_tick(value: f64, step: f64) -> String {
+    // Ticks are multiples of `step`; anything much smaller is rounding noise at zero.
+    let value = if value.abs() < step.abs() * 1e-6 {
+        0.0
+    } else {
+        value
+    };
+    let abs = value.abs();
+    if abs != 0.0 && !(1e-3..1e6).contains(&abs) {
+        return format!("{value:.2e}");
+    }
+    let decimals = if step > 0.0 {
+        (-step.log10().floor()).max(0.0) as usize
+    } else {
+        0
+    };
+    let s = format!("{value:.decimals$}");
+    // Avoid "-0" for ticks that round to zero.
+    if s.trim_start_matches('-')
+        .chars()
+        .all(|c| c == '0' || c == '.')
+    {
+        s.trim_start_matches('-').to_owned()
+    } else {
+        s
+    }
+}
+
+fn tick_step(ticks: &[f64]) -> f64 {
+    if ticks.len() >= 2 {
+        ticks[1] - ticks[0]
+    } else {
+        1.0
+    }
+}
+
+/// Frame, ticks and font size shared by both renderers.
+struct Layout {
+    frame: Frame,
+    font: f64,
+    scale: f64,
+    x_ticks: Vec<f64>,
+    y_ticks: Vec<f64>,
+    x_step: f64,
+    y_labels: Vec<String>,
+}
+
+impl Layout {
+    fn new(plot: &PlotExport, width: u32, height: u32) -> Self {
+        let scale = text_scale(width, height);
+        let font = 13.0 * scale;
+        let b = Bounds::of(plot);
+        let x_ticks = nice_ticks(b.x_min, b.x_max, 8);
+        let x_step = tick_step(&x_ticks);
+        let (y_ticks, y_labels): (Vec<f64>, Vec<String>) = if plot.log_y {
+            // One tick per decade, labelled with the count it stands for.
+            (b.y_min.ceil() as i64..=b.y_max.floor() as i64)
+                .map(|decade| {
+                    let value = 10f64.powi(decade as i32 - 1);
+                    (decade as f64, format_tick(value, value.min(1.0)))
+                })
+                .unzip()
+        } else {
+            let ticks = nice_ticks(b.y_min, b.y_max, 6);
+            let step = tick_step(&ticks);
+            let labels = ticks.iter().map(|&t| format_tick(t, step)).collect();
+            (ticks, labels)
+        };
+        let widest = y_labels
+            .iter()
+            .map(|l| l.chars().count())
+            .max()
+            .unwrap_or(1);
+        Self {
+            frame: Frame::new(width, height, font, b, widest),
+            font,
+            scale,
+            x_ticks,
+            y_ticks,
+            x_step,
+            y_labels,
+        }
+    }
+}
+
+fn svg_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn svg_color(c: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", c[0], c[1], c[2])
+}
+
+pub fn render_svg(plot: &PlotExport, width: u32, height: u32) -> String {
+    use std::fmt::Write;
+
+    let plot = &*plot.drawn();
+    let Layout {
+        frame,
+        font,
+        scale,
+        x_ticks,
+        y_ticks,
+        x_step,
+        y_labels,
+    } = Layout::new(plot, width, height);
+    let mut out = String::new();
+
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="monospace">"#
+    );
+    let _ = writeln!(
+        out,
+        r#"<rect x="0" y="0" width="{width}" height="{height}" fill="white"/>"#
+    );
+
+    // Grid and tick labels
+    for &t in &x_ticks {
+        let x = frame.px(t);
+        let _ = writeln!(
+            out,
+            r##"<line x1="{x:.1}" y1="{:.1}" x2="{x:.1}" y2="{:.1}" stroke="#dddddd"/>"##,
+            frame.top, frame.bottom
+        );
+        let _ = writeln!(
+            out,
+            r#"<text x="{x:.1}" y="{:.1}" font-size="{font:.1}" text-anchor="middle">{}</text>"#,
+            frame.bottom + font * 1.4,
+            svg_escape(&format_tick(t, x_step))
+        );
+    }
+    for (&t, label) in y_ticks.iter().zip(&y_labels) {
+        let y = frame.py(t);
+        let _ = writeln!(
+            out,
+            r##"<line x1="{:.1}" y1="{y:.1}" x2="{:.1}" y2="{y:.1}" stroke="#dddddd"/>"##,
+            frame.left, frame.right
+        );
+        let _ = writeln!(
+            out,
+            r#"<text x="{:.1}" y="{:.1}" font-size="{font:.1}" text-anchor="end">{}</text>"#,
+            frame.left - font * 0.5,
+            y + font * 0.35,
+            svg_escape(label)
+        );
+    }
+
+    // Series, clipped to the plot area
+    let _ = writeln!(
+        out,
+        r#"<clipPath id="plot-area"><rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}"/></clipPath>"#,
+        frame.left,
+        frame.top,
+        frame.right - frame.left,
+        frame.bottom - frame.top
+    );
+    let _ = writeln!(out, r#"<g clip-path="url(#plot-area)">"#);
+    for s in &plot.series {
+        let color = svg_color(s.color);
+        match &s.kind {
+            SeriesKind::Line(points) => {
+                let pts: Vec<String> = points
+                    .iter()
+                    .filter(|p| p[0].is_finite() && p[1].is_finite())
+                    .map(|p| format!("{:.2},{:.2}", frame.px(p[0]), frame.py(p[1])))
+                    .collect();
+                let _ = writeln!(
+                    out,
+                    r#"<polyline fill="none" stroke="{color}" stroke-width="{:.1}" points="{}"/>"#,
+                    2.0 * scale,
+                    pts.join(" ")
+                );
+            }
+            SeriesKind::Bars(bars) => {
+                for bar in bars {
+                    let x0 = frame.px(bar.center - bar.width * 0.475);
+                    let x1 = frame.px(bar.center + bar.width * 0.475);
+                    let y0 = frame.py(bar.height.max(0.0));
+                    let y1 = frame.py(0.0);
+                    let _ = writeln!(
+                        out,
+                        r#"<rect x="{x0:.2}" y="{y0:.2}" width="{:.2}" height="{:.2}" fill="{color}" stroke="black" stroke-width="0.5"/>"#,
+                        (x1 - x0).max(0.0),
+                        (y1 - y0).max(0.0)
+                    );
+                }
+            }
+            SeriesKind::VLines { xs, dash } => {
+                let dasharray = dash
+                    .pattern()
+                    .map(|(on, off)| {
+                        format!(
+                            r#" stroke-dasharray="{:.1},{:.1}""#,
+                            on * scale,
+                            off * scale
+                        )
+                    })
+                    .unwrap_or_default();
+                for x in xs.iter().map(|&x| frame.px(x)) {
+                    let _ = writeln!(
+                        out,
+                        r#"<line x1="{x:.2}" y1="{:.1}" x2="{x:.2}" y2="{:.1}" stroke="{color}" stroke-width="{:.1}"{dasharray}/>"#,
+                        frame.top,
+                        frame.bottom,
+                        1.5 * scale
+                    );
+                }
+            }
+        }
+    }
+    let _ = writeln!(out, "</g>");
+
+    // Frame, title, labels
+    let _ = writeln!(
+        out,
+        r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="none" stroke="black"/>"#,
+        frame.left,
+        frame.top,
+        frame.right - frame.left,
+        frame.bottom - frame.top
+    );
+    let _ = writeln!(
+        out,
+        r#"<text x="{:.1}" y="{:.1}" font-size="{:.1}" font-weight="bold" text-anchor="middle">{}</text>"#,
+        width as f64 / 2.0,
+        font * 1.8,
+        font * 1.4,
+        svg_escape(&plot.title)
+    );
+    let _ = writeln!(
+        out,
+        r##"<text x="{:.1}" y="{:.1}" font-size="{font:.1}" text-anchor="middle" fill="#555555">{}</text>"##,
+        width as f64 / 2.0,
+        font * 3.3,
+        svg_escape(&plot.timestamp)
+    );
+    let _ = writeln!(
+        out,
+        r#"<text x="{:.1}" y="{:.1}" font-size="{font:.1}" text-anchor="middle">{}</text>"#,
+        (frame.left + frame.right) / 2.0,
+        height as f64 - font * 0.8,
+        svg_escape(&plot.x_label)
+    );
+    let _ = writeln!(
+        out,
+        r#"<text transform="translate({:.1},{:.1}) rotate(-90)" font-size="{font:.1}" text-anchor="middle">{}</text>"#,
+        font * 1.3,
+        (frame.top + frame.bottom) / 2.0,
+        svg_escape(&plot.y_label)
+    );
+    if plot.series.len() > 1 {
+        for (i, s) in plot.series.iter().enumerate() {
+            let y = frame.top + font * (1.4 + 1.4 * i as f64);
+            let x = frame.right - font * 12.0;
+            let _ = writeln!(
+                out,
+                r#"<rect x="{x:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"/>"#,
+                y - font * 0.8,
+                font * 0.8,
+                font * 0.8,
+                svg_color(s.color)
+            );
+            let _ = writeln!(
+                out,
+                r#"<text x="{:.1}" y="{y:.1}" font-size="{font:.1}">{}</text>"#,
+                x + font * 1.2,
+                svg_escape(&s.name)
+            );
+        }
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// 5x7 glyph rows, bit 4 is the leftmost column. Lowercase has its own
+/// glyphs: `mV` must not read as `MV`.
+fn glyph(c: char) -> [u8; 7] {
+    match c {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        'a' => [0x00, 0x00, 0x0E, 0x01, 0x0F, 0x11, 0x0F],
+        'b' => [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1E],
+        'c' => [0x00, 0x00, 0x0E, 0x10, 0x10, 0x11, 0x0E],
+        'd' => [0x01, 0x01, 0x0D, 0x13, 0x11, 0x11, 0x0F],
+        'e' => [0x00, 0x00, 0x0E, 0x11, 0x1F, 0x10, 0x0E],
+        'f' => [0x06, 0x09, 0x08, 0x1C, 0x08, 0x08, 0x08],
+        'g' => [0x00, 0x0F, 0x11, 0x11, 0x0F, 0x01, 0x0E],
+        'h' => [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11],
+        'i' => [0x04, 0x00, 0x0C, 0x04, 0x04, 0x04, 0x0E],
+        'j' => [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0C],
+        'k' => [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12],
+        'l' => [0x0C, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'm' => [0x00, 0x00, 0x1A, 0x15, 0x15, 0x11, 0x11],
+        'n' => [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11],
+        'o' => [0x00, 0x00, 0x0E, 0x11, 0x11, 0x11, 0x0E],
+        'p' => [0x00, 0x00, 0x1E, 0x11, 0x1E, 0x10, 0x10],
+        'q' => [0x00, 0x00, 0x0D, 0x13, 0x0F, 0x01, 0x01],
+        'r' => [0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10],
+        's' => [0x00, 0x00, 0x0E, 0x10, 0x0E, 0x01, 0x1E],
+        't' => [0x08, 0x08, 0x1C, 0x08, 0x08, 0x09, 0x06],
+        'u' => [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0D],
+        'v' => [0x00, 0x00, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'w' => [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0A],
+        'x' => [0x00, 0x00, 0x11, 0x0A, 0x04, 0x0A, 0x11],
+        'y' => [0x00, 0x00, 0x11, 0x11, 0x0F, 0x01, 0x0E],
+        'z' => [0x00, 0x00, 0x1F, 0x02, 0x04, 0x08, 0x1F],
+        ' ' => [0; 7],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        '-' | '—' | '–' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '[' => [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E],
+        ']' => [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E],
+        '<' => [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02],
+        '>' => [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '=' => [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00],
+        '*' => [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '°' => [0x0C, 0x12, 0x12, 0x0C, 0x00, 0x00, 0x00],
+        'Σ' => [0x1F, 0x10, 0x08, 0x04, 0x08, 0x10, 0x1F],
+        'σ' => [0x00, 0x00, 0x0F, 0x12, 0x11, 0x11, 0x0E],
+        'µ' | 'μ' => [0x00, 0x11, 0x11, 0x11, 0x13, 0x1D, 0x10],
+        'Ω' => [0x0E, 0x11, 0x11, 0x11, 0x0A, 0x0A, 0x1B],
+        '±' => [0x04, 0x04, 0x1F, 0x04, 0x04, 0x00, 0x1F],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+/// Minimal raster canvas on top of [`RgbaImage`].
+struct Canvas {
+    img: RgbaImage,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            img: RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255])),
+        }
+    }
+
+    fn put(&mut self, x: i64, y: i64, c: Rgba<u8>) {
+        if x >= 0 && y >= 0 && (x as u32) < self.img.width() && (y as u32) < self.img.height() {
+            self.img.put_pixel(x as u32, y as u32, c);
+        }
+    }
+
+    fn fill_rect(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, c: Rgba<u8>) {
+        let (xa, xb) = (x0.min(x1).round() as i64, x0.max(x1).round() as i64);
+        let (ya, yb) = (y0.min(y1).round() as i64, y0.max(y1).round() as i64);
+        for y in ya..yb.max(ya + 1) {
+            for x in xa..xb.max(xa + 1) {
+                self.put(x, y, c);
+            }
+        }
+    }
+
+    fn stroke_rect(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, w: f64, c: Rgba<u8>) {
+        self.line(x0, y0, x1, y0, w, c);
+        self.line(x1, y0, x1, y1, w, c);
+        self.line(x1, y1, x0, y1, w, c);
+        self.line(x0, y1, x0, y0, w, c);
+    }
+
+    /// Thick line by stamping squares along the segment.
+    fn line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, w: f64, c: Rgba<u8>) {
+        let len = ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt();
+        let steps = (len * 2.0).ceil().max(1.0) as usize;
+        let half = (w / 2.0).max(0.5);
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            let x = x0 + (x1 - x0) * t;
+            let y = y0 + (y1 - y0) * t;
+            self.fill_rect(x - half, y - half, x + half, y + half, c);
+        }
+    }
+
+    /// Draw `text` with its anchor at (x, y). `align` is 0 left, 0.5 centre, 1 right.
+    /// `vertical` rotates the text 90° counter-clockwise around the anchor.
+    fn text(&mut self, text: &str, x: f64, y: f64, px: f64, align: f64, vertical: bool) {
+        // 7 glyph rows plus spacing fill roughly `px` pixels.
+        let cell = (px / 9.0).max(1.0).round() as i64;
+        let advance = 6 * cell;
+        let total = text.chars().count() as i64 * advance - cell;
+        let start = -(total as f64 * align).round() as i64;
+        let black = Rgba([0, 0, 0, 255]);
+        for (i, ch) in text.chars().enumerate() {
+            let rows = glyph(ch);
+            for (row, bits) in rows.iter().enumerate() {
+                for col in 0..5 {
+                    if bits & (0x10 >> col) == 0 {
+                        continue;
+                    }
+                    // Glyph-local offsets along (u) and across (v) the text direction.
+                    let u = start + i as i64 * advance + col as i64 * cell;
+                    let v = row as i64 * cell - 7 * cell / 2;
+                    for du in 0..cell {
+                        for dv in 0..cell {
+                            let (dx, dy) = if vertical {
+                                (v + dv, -(u + du))
+                            } else {
+                                (u + du, v + dv)
+                            };
+                            self.put(x.round() as i64 + dx, y.round() as i64 + dy, black);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn render_png(plot: &PlotExport, width: u32, height: u32) -> RgbaImage {
+    let plot = &*plot.drawn();
+    let width = width.max(64);
+    let height = height.max(64);
+    let Layout {
+        frame,
+        font,
+        scale,
+        x_ticks,
+        y_ticks,
+        x_step,
+        y_labels,
+    } = Layout::new(plot, width, height);
+    let mut canvas = Canvas::new(width, height);
+    let grid = Rgba([221, 221, 221, 255]);
+    let black = Rgba([0, 0, 0, 255]);
+
+    for &t in &x_ticks {
+        let x = frame.px(t);
+        canvas.line(x, frame.top, x, frame.bottom, 1.0, grid);
+    }
+    for &t in &y_ticks {
+        let y = frame.py(t);
+        canvas.line(frame.left, y, frame.right, y, 1.0, grid);
+    }
+
+    for s in &plot.series {
+        let color = Rgba([s.color[0], s.color[1], s.color[2], 255]);
+        match &s.kind {
+            SeriesKind::Line(points) => {
+                let pts: Vec<(f64, f64)> = points
+                    .iter()
+                    .filter(|p| p[0].is_finite() && p[1].is_finite())
+                    .map(|p| (frame.px(p[0]), frame.py(p[1])))
+                    .collect();
+                for w in pts.windows(2) {
+                    canvas.line(w[0].0, w[0].1, w[1].0, w[1].1, 2.0 * scale, color);
+                }
+                if let [only] = pts.as_slice() {
+                    canvas.fill_rect(
+                        only.0 - 2.0 * scale,
+                        only.1 - 2.0 * scale,
+                        only.0 + 2.0 * scale,
+                        only.1 + 2.0 * scale,
+                        color,
+                    );
+                }
+            }
+            SeriesKind::Bars(bars) => {
+                for bar in bars {
+                    let x0 = frame.px(bar.center - bar.width * 0.475);
+                    let x1 = frame.px(bar.center + bar.width * 0.475);
+                    let y0 = frame.py(bar.height.max(0.0));
+                    let y1 = frame.py(0.0);
+                    canvas.fill_rect(x0, y0, x1, y1, color);
+                    canvas.stroke_rect(x0, y0, x1, y1, 1.0, black);
+                }
+            }
+            SeriesKind::VLines { xs, dash } => {
+                let (on, off) = dash
+                    .pattern()
+                    .map_or((frame.bottom - frame.top, 0.0), |(on, off)| {
+                        (on * scale, off * scale)
+                    });
+                for x in xs.iter().map(|&x| frame.px(x)) {
+                    let mut y = frame.top;
+                    while y < frame.bottom {
+                        let end = (y + on).min(frame.bottom);
+                        canvas.line(x, y, x, end, 1.5 * scale, color);
+                        y = end + off;
+                    }
+                }
+            }
+        }
+    }
+
+    // Wipe anything drawn outside the plot area (thick strokes at the edges),
+    // then put the tick labels on top.
+    let white = Rgba([255, 255, 255, 255]);
+    canvas.fill_rect(0.0, 0.0, width as f64, frame.top - 1.0, white);
+    canvas.fill_rect(0.0, frame.bottom + 1.0, width as f64, height as f64, white);
+    canvas.fill_rect(0.0, frame.top, frame.left - 1.0, frame.bottom, white);
+    canvas.fill_rect(
+        frame.right + 1.0,
+        frame.top,
+        width as f64,
+        frame.bottom,
+        white,
+    );
+    for &t in &x_ticks {
+        canvas.text(
+            &format_tick(t, x_step),
+            frame.px(t),
+            frame.bottom + font * 1.0,
+            font,
+            0.5,
+            false,
+        );
+    }
+    for (&t, label) in y_ticks.iter().zip(&y_labels) {
+        canvas.text(
+            label,
+            frame.left - font * 0.5,
+            frame.py(t),
+            font,
+            1.0,
+            false,
+        );
+    }
+
+    canvas.stroke_rect(frame.left, frame.top, frame.right, frame.bottom, 1.0, black);
+    canvas.text(
+        &plot.title,
+        width as f64 / 2.0,
+        font * 1.3,
+        font * 1.4,
+        0.5,
+        false,
+    );
+    canvas.text(
+        &plot.timestamp,
+        width as f64 / 2.0,
+        font * 2.9,
+        font,
+        0.5,
+        false,
+    );
+    canvas.text(
+        &plot.x_label,
+        (frame.left + frame.right) / 2.0,
+        height as f64 - font * 1.2,
+        font,
+        0.5,
+        false,
+    );
+    canvas.text(
+        &plot.y_label,
+        font * 1.2,
+        (frame.top + frame.bottom) / 2.0,
+        font,
+        0.5,
+        true,
+    );
+    if plot.series.len() > 1 {
+        for (i, s) in plot.series.iter().enumerate() {
+            let y = frame.top + font * (1.2 + 1.4 * i as f64);
+            let x = frame.right - font * 12.0;
+            let color = Rgba([s.color[0], s.color[1], s.color[2], 255]);
+            canvas.fill_rect(x, y - font * 0.4, x + font * 0.8, y + font * 0.4, color);
+            canvas.text(&s.name, x + font * 1.2, y, font, 0.0, false);
+        }
+    }
+    canvas.img
+}
+
+/// Render and write `plot` to `path`. The format follows the file extension.
+pub fn save(plot: &PlotExport, path: &Path, width: u32, height: u32) -> Result<(), String> {
+    match ExportFormat::from_path(path) {
+        Some(ExportFormat::Svg) => std::fs::write(path, render_svg(plot, width, height))
+            .map_err(|e| format!("Failed to write {}: {e}", path.display())),
+        Some(ExportFormat::Png) => render_png(plot, width, height)
+            .save(path)
+            .map_err(|e| format!("Failed to write {}: {e}", path.display())),
+        None => Err(format!(
+            "Unknown image type for {} (use .png or .svg)",
+            path.display()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_plot() -> PlotExport {
+        PlotExport {
+            title: "Graph <VDC>".into(),
+            x_label: "Samples".into(),
+            y_label: "VDC".into(),
+            timestamp: "2026-01-01 12:00:00".into(),
+            series: vec![ExportSeries {
+                name: "VDC".into(),
+                color: [0, 255, 255],
+                kind: SeriesKind::Line(vec![[0.0, 1.0], [1.0, 2.0], [2.0, 1.5]]),
+            }],
+            log_y: false,
+        }
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(
+            ExportFormat::from_path(Path::new("a/b.PNG")),
+            Some(ExportFormat::Png)
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("x.svg")),
+            Some(ExportFormat::Svg)
+        );
+        assert_eq!(ExportFormat::from_path(Path::new("x.jpg")), None);
+        assert_eq!(ExportFormat::from_path(Path::new("noext")), None);
+    }
+
+    #[test]
+    fn nice_ticks_are_round() {
+        assert_eq!(
+            nice_ticks(0.0, 10.0, 5),
+            vec![0.0, 2.0, 4.0, 6.0, 8.0, 10.0]
+        );
+        let t = nice_ticks(0.12, 0.31, 4);
+        assert_eq!(t.len(), 4);
+        assert!((t[0] - 0.15).abs() < 1e-12);
+        assert!(nice_ticks(1.0, 1.0, 5).is_empty());
+    }
+
+    #[test]
+    fn tick_labels_follow_step() {
+        assert_eq!(format_tick(0.15, 0.05), "0.15");
+        assert_eq!(format_tick(200.0, 50.0), "200");
+        assert_eq!(format_tick(-1e-17, 0.1), "0.0");
+        assert_eq!(format_tick(-0.04, 0.1), "0.0");
+        assert_eq!(format_tick(5e-6, 1e-6), "5.00e-6");
+    }
+
+    #[test]
+    fn svg_has_labels_and_escapes() {
+        let svg = render_svg(&sample_plot(), 800, 450);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("Graph &lt;VDC&gt;"));
+        assert!(svg.contains("2026-01-01 12:00:00"));
+        assert!(svg.contains(">Samples<"));
+        assert!(svg.contains("<polyline"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn png_matches_requested_size_and_draws() {
+        let img = render_png(&sample_plot(), 640, 360);
+        assert_eq!(img.dimensions(), (640, 360));
+        assert!(img.pixels().any(|p| *p == Rgba([0, 255, 255, 255])));
+        assert!(img.pixels().any(|p| *p == Rgba([0, 0, 0, 255])));
+    }
+
+    #[test]
+    fn lowercase_prefixes_keep_their_glyphs() {
+        assert_ne!(glyph('m'), glyph('M'));
+        assert_ne!(glyph('k'), glyph('K'));
+        assert_eq!(glyph('V'), [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]);
+        let fallback = glyph('\u{2603}');
+        assert!(('a'..='z').all(|c| glyph(c) != fallback));
+    }
+
+    #[test]
+    fn bars_start_at_zero() {
+        let plot = PlotExport {
+            series: vec![ExportSeries {
+                name: "Counts".into(),
+                color: [255, 0, 0],
+                kind: SeriesKind::Bars(vec![
+                    ExportBar {
+                        center: 1.0,
+                        width: 0.5,
+                        height: 3.0,
+                    },
+                    ExportBar {
+                        center: 1.5,
+                        width: 0.5,
+                        height: 5.0,
+                    },
+                ]),
+            }],
+            ..sample_plot()
+        };
+        let b = Bounds::of(&plot);
+        assert_eq!(b.y_min, 0.0);
+        assert!(b.y_max > 5.0);
+        assert_eq!(b.x_min, 0.75);
+        assert_eq!(b.x_max, 1.75);
+    }
+
+    #[test]
+    fn log_y_draws_decades_and_markers() {
+        let plot = PlotExport {
+            series: vec![
+                ExportSeries {
+                    name: "Count".into(),
+                    color: [255, 0, 0],
+                    kind: SeriesKind::Bars(
+                        [0.0, 1.0, 1000.0]
+                            .iter()
+                            .enumerate()
+                            .map(|(i, &height)| ExportBar {
+                                center: i as f64,
+                                width: 1.0,
+                                height,
+                            })
+                            .collect(),
+                    ),
+                },
+                ExportSeries {
+                    name: "Mean".into(),
+                    color: [0, 0, 255],
+                    kind: SeriesKind::VLines {
+                        xs: vec![5.0],
+                        dash: Dash::Dashed,
+                    },
+                },
+            ],
+            log_y: true,
+            ..sample_plot()
+        };
+        let drawn = plot.drawn();
+        let SeriesKind::Bars(bars) = &drawn.series[0].kind else {
+            panic!("bars stay bars");
+        };
+        // The empty bin is left out; one count is one decade above the baseline.
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0].height, 1.0);
+        assert_eq!(bars[1].height, 4.0);
+        let b = Bounds::of(&drawn);
+        assert_eq!(b.y_min, 0.0);
+        assert_eq!(b.x_max, 5.0);
+
+        let svg = render_svg(&plot, 800, 450);
+        assert!(svg.contains(">1000<"));
+        assert!(svg.contains(">0.1<"));
+        assert!(svg.contains("stroke-dasharray"));
+        let img = render_png(&plot, 640, 360);
+        assert!(img.pixels().any(|p| *p == Rgba([0, 0, 255, 255])));
+    }
+}
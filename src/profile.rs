@@ -0,0 +1,110 @@
+//! Profile files: named bench setups shared as pretty-printed JSON, in the
+//! same versioned wrapper style as macro files.
+//!
+//! The profile contents are defined by the app; this only handles the file
+//! wrapper and naming.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+const PROFILE_FILE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct ProfileFile<T> {
+    rusty_meter_profiles: u32,
+    profiles: Vec<T>,
+}
+
+pub fn export_profiles<T: Serialize + Clone>(profiles: &[T]) -> String {
+    let file = ProfileFile {
+        rusty_meter_profiles: PROFILE_FILE_VERSION,
+        profiles: profiles.to_vec(),
+    };
+    let mut text = serde_json::to_string_pretty(&file).expect("profiles serialize to JSON");
+    text.push('\n');
+    text
+}
+
+/// Read a profile file. A single bare profile is accepted too.
+pub fn parse_profile_file<T: DeserializeOwned>(text: &str) -> Result<Vec<T>, String> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Content<T> {
+        File(ProfileFile<T>),
+        Single(T),
+    }
+    match serde_json::from_str(text) {
+        Ok(Content::File(file)) if file.rusty_meter_profiles > PROFILE_FILE_VERSION => {
+            Err(format!(
+                "Profile file version {} is newer than this RustyMeter supports",
+                file.rusty_meter_profiles
+            ))
+        }
+        Ok(Content::File(file)) => Ok(file.profiles),
+        Ok(Content::Single(profile)) => Ok(vec![profile]),
+        Err(_) => Err("Not a RustyMeter profile file".to_owned()),
+    }
+}
+
+/// `wanted`, or `wanted (2)`, `wanted (3)`, … if that name is taken.
+pub fn unique_name<'a>(taken: impl Iterator<Item = &'a str> + Clone, wanted: &str) -> String {
+    let wanted = match wanted.trim() {
+        "" => "Profile",
+        name => name,
+    };
+    (1..)
+        .map(|n| match n {
+            1 => wanted.to_owned(),
+            n => format!("{wanted} ({n})"),
+        })
+        .find(|name| !taken.clone().any(|t| t == name))
+        .expect("a free name exists")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Bench {
+        name: String,
+        #[serde(default)]
+        baud: u32,
+    }
+
+    #[test]
+    fn round_trips_profile_files() {
+        let benches = vec![
+            Bench {
+                name: "Power bench".to_owned(),
+                baud: 115_200,
+            },
+            Bench {
+                name: "Temperature log".to_owned(),
+                baud: 9_600,
+            },
+        ];
+        let text = export_profiles(&benches);
+        assert!(text.contains("\"rusty_meter_profiles\": 1"));
+        assert_eq!(parse_profile_file::<Bench>(&text).unwrap(), benches);
+
+        let single = parse_profile_file::<Bench>(r#"{"name": "Hand-written"}"#).unwrap();
+        assert_eq!(single[0].baud, 0);
+
+        let newer = r#"{"rusty_meter_profiles": 9, "profiles": []}"#;
+        assert!(
+            parse_profile_file::<Bench>(newer)
+                .unwrap_err()
+                .contains("newer")
+        );
+        assert!(parse_profile_file::<Bench>("[1, 2]").is_err());
+    }
+
+    #[test]
+    fn numbers_clashing_names() {
+        let taken = ["Bench", "Bench (2)"];
+        assert_eq!(unique_name(taken.iter().copied(), "Bench"), "Bench (3)");
+        assert_eq!(unique_name(taken.iter().copied(), " Logger "), "Logger");
+        assert_eq!(unique_name([].iter().copied(), ""), "Profile");
+    }
+}
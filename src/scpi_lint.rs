@@ -0,0 +1,349 @@
+//! Macro linting against the meter's SCPI dialect.
+//!
+//! Compact Owons ignore commands they do not understand, so a typo in a macro
+//! otherwise goes unnoticed. Headers match SCPI style: each node in its short
+//! (upper case) or long form, `[NODE]` optional, case-insensitive. The
+//! command table is [`OWON_MEAS_HEADERS`], shared with the SCPI console;
+//! `CONFigure` is checked separately by [`check_conf`].
+
+use crate::macro_lang::{Instr, Program};
+use crate::multimeter::{MeterMode, RangeCmd, RateCmd};
+use crate::scpi_macro::{
+    MacroTarget, OWON_MEAS_HEADERS, Param, ScpiFamily, classify_idn, range_table_meter,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Not in our command table; the meter may still know it.
+    Warning,
+    /// Bad syntax or a parameter the meter rejects.
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintIssue {
+    /// 1-based line in the macro body.
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// The command set a macro is checked against.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Dialect {
+    /// Compact Owon; `range_meter` selects the [`RangeCmd`] tables.
+    OwonMeas { range_meter: String },
+    /// No command table: only the macro syntax is checked.
+    Unchecked,
+}
+
+impl Dialect {
+    pub fn from_idn(idn: &str) -> Self {
+        match classify_idn(idn) {
+            ScpiFamily::OwonMeas => Self::OwonMeas {
+                range_meter: range_table_meter(idn),
+            },
+            ScpiFamily::OwonXdm6000 | ScpiFamily::Unknown => Self::Unchecked,
+        }
+    }
+
+    /// Dialect implied by a macro's target when no matching meter is connected.
+    pub fn for_target(target: &MacroTarget) -> Self {
+        match target {
+            MacroTarget::OwonMeas => Self::from_idn("OWON,XDM1041"),
+            MacroTarget::Model(model) => Self::from_idn(&format!("OWON,{model}")),
+            MacroTarget::AllScpi | MacroTarget::OwonXdm6000 | MacroTarget::IdnContains(_) => {
+                Self::Unchecked
+            }
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::OwonMeas { range_meter } => format!("Checked against {range_meter} commands"),
+            Self::Unchecked => "Syntax check only: no command table for this target".to_owned(),
+        }
+    }
+}
+
+/// Check a macro body. Syntax errors stop the check at the first one.
+pub fn lint_macro(body: &str, dialect: &Dialect) -> Vec<LintIssue> {
+    let program = match Program::parse(body) {
+        Ok(program) => program,
+        Err(e) => return vec![syntax_issue(&e)],
+    };
+    let Dialect::OwonMeas { range_meter } = dialect else {
+        return Vec::new();
+    };
+    program
+        .statements
+        .iter()
+        .filter_map(|statement| {
+            let cmd = match &statement.instr {
+                Instr::Scpi(cmd) => cmd,
+                Instr::Query { query, .. } => query,
+                _ => return None,
+            };
+            let (severity, message) = check_command(cmd, range_meter).err()?;
+            Some(LintIssue {
+                line: statement.line,
+                severity,
+                message,
+            })
+        })
+        .collect()
+}
+
+/// The error in a `SYST:ERR?` reply such as `-113,"Undefined header"`;
+/// `None` for `0,"No error"`.
+pub fn scpi_error(reply: &str) -> Option<String> {
+    let reply = reply.trim();
+    let code = reply.split(',').next().unwrap_or("").trim();
+    match code.parse::<i32>() {
+        Ok(0) => None,
+        _ => Some(reply.to_owned()),
+    }
+}
+
+/// [`Program::parse`] errors read `Line N: message`.
+fn syntax_issue(e: &str) -> LintIssue {
+    let (line, message) = e
+        .strip_prefix("Line ")
+        .and_then(|rest| rest.split_once(": "))
+        .and_then(|(n, message)| Some((n.parse().ok()?, message)))
+        .unwrap_or((1, e));
+    LintIssue {
+        line,
+        severity: Severity::Error,
+        message: message.to_owned(),
+    }
+}
+
+fn check_command(cmd: &str, range_meter: &str) -> Result<(), (Severity, String)> {
+    let cmd = cmd.trim();
+    let (header, param) = match cmd.split_once(char::is_whitespace) {
+        Some((header, param)) => (header, param.trim()),
+        None => (cmd, ""),
+    };
+    if let Some(function) = conf_function(header) {
+        return check_conf(function, param, range_meter).map_err(|e| (Severity::Error, e));
+    }
+    let Some(&(_, kind)) = OWON_MEAS_HEADERS
+        .iter()
+        .find(|(pattern, _)| header_matches(pattern, header))
+    else {
+        return Err((
+            Severity::Warning,
+            format!("'{header}' is not a known command for this meter"),
+        ));
+    };
+    check_param(kind, header, param).map_err(|e| (Severity::Error, e))
+}
+
+/// What follows `CONF:` / `CONFigure:SCALar:` in a set command.
+fn conf_function(header: &str) -> Option<&str> {
+    let header = header.strip_prefix(':').unwrap_or(header);
+    let (first, rest) = header.split_once(':')?;
+    if !node_matches("CONFigure", first) {
+        return None;
+    }
+    match rest.split_once(':') {
+        Some((node, function)) if node_matches("SCALar", node) => Some(function),
+        _ => Some(rest),
+    }
+}
+
+fn check_conf(function: &str, param: &str, range_meter: &str) -> Result<(), String> {
+    let Some(mode) = MeterMode::ALL.into_iter().find(|mode| {
+        mode.conf_prefixes()
+            .iter()
+            .any(|prefix| prefix.eq_ignore_ascii_case(function))
+    }) else {
+        return Err(format!(
+            "'CONF:{function}' is not a function; use VOLT:DC, VOLT:AC, CURR:DC, CURR:AC, \
+             RES, CAP, FREQ, PER, DIOD, CONT or TEMP:RTD"
+        ));
+    };
+    if param.is_empty() {
+        return Ok(());
+    }
+    match RangeCmd::new(range_meter, mode) {
+        Some(table) if table.index_of_param(param).is_none() => {
+            let ranges: Vec<&str> = (0..table.len()).map(|i| table.get_opt(i).0).collect();
+            Err(format!(
+                "'{param}' is not a {} range; use {}",
+                mode.button_label(),
+                ranges.join(", ")
+            ))
+        }
+        None if !mode.has_manual_range() => {
+            Err(format!("CONF:{function} takes no range parameter"))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_param(kind: Param, header: &str, param: &str) -> Result<(), String> {
+    match kind {
+        Param::None if !param.is_empty() => Err(format!("'{header}' takes no parameter")),
+        Param::Number if param.parse::<f64>().is_err() => {
+            Err(format!("'{header}' needs a number, not '{param}'"))
+        }
+        Param::OnOff
+            if !["ON", "OFF", "1", "0"]
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(param)) =>
+        {
+            Err(format!("'{header}' needs ON or OFF, not '{param}'"))
+        }
+        Param::Rate if RateCmd::default().index_of_scpi(param).is_none() => {
+            let rate = RateCmd::default();
+            let tokens: Vec<&str> = (0..rate.len()).map(|i| rate.get_opt(i).1).collect();
+            Err(format!(
+                "RATE needs one of {}, not '{param}'",
+                tokens.join(", ")
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn header_matches(pattern: &str, header: &str) -> bool {
+    let header = header.strip_prefix(':').unwrap_or(header);
+    let (pattern, pattern_query) = match pattern.strip_suffix('?') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let (header, header_query) = match header.strip_suffix('?') {
+        Some(header) => (header, true),
+        None => (header, false),
+    };
+    let pattern: Vec<&str> = pattern.split(':').collect();
+    let nodes: Vec<&str> = header.split(':').collect();
+    pattern_query == header_query && nodes_match(&pattern, &nodes)
+}
+
+fn nodes_match(pattern: &[&str], nodes: &[&str]) -> bool {
+    let Some((first, rest)) = pattern.split_first() else {
+        return nodes.is_empty();
+    };
+    let matches_first = |node: &str| match first.strip_prefix('[') {
+        Some(optional) => node_matches(optional.trim_end_matches(']'), node),
+        None => node_matches(first, node),
+    };
+    (first.starts_with('[') && nodes_match(rest, nodes))
+        || nodes
+            .split_first()
+            .is_some_and(|(node, others)| matches_first(node) && nodes_match(rest, others))
+}
+
+/// `node` is the short or long form of a mnemonic such as `THREshold`.
+fn node_matches(mnemonic: &str, node: &str) -> bool {
+    let short: String = mnemonic
+        .chars()
+        .filter(|c| !c.is_ascii_lowercase())
+        .collect();
+    node.eq_ignore_ascii_case(&short) || node.eq_ignore_ascii_case(mnemonic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owon() -> Dialect {
+        Dialect::from_idn("OWON,XDM1041,2204123,V4.2.0")
+    }
+
+    fn messages(body: &str) -> Vec<(usize, Severity)> {
+        lint_macro(body, &owon())
+            .into_iter()
+            .map(|issue| (issue.line, issue.severity))
+            .collect()
+    }
+
+    #[test]
+    fn headers_match_short_and_long_forms() {
+        assert!(header_matches("SYSTem:BEEPer:[STATe]", "SYST:BEEP:STATe"));
+        assert!(header_matches("SYSTem:BEEPer:[STATe]", "system:beeper"));
+        assert!(header_matches("SYSTem:BEEPer:[STATe]?", ":SYST:BEEP?"));
+        assert!(!header_matches("SYSTem:BEEPer:[STATe]", "SYST:BEEP?"));
+        assert!(!header_matches("SYSTem:BEEPer:[STATe]", "SYST:BEE"));
+        assert!(header_matches("CONTinuity:THREshold", "CONT:THRE"));
+        assert!(header_matches("*IDN?", "*idn?"));
+        assert!(header_matches("MEASure?", "MEAS?"));
+    }
+
+    #[test]
+    fn lints_compact_owon_commands() {
+        let body = "CONF:VOLT:DC 5\n\
+                    CONF:VOLT:DC 7   # no such range\n\
+                    CONFigure:SCALar:CURRent:AC 500mA\n\
+                    CONF:VOLT:DX AUTO\n\
+                    RATE Q\n\
+                    RATE f\n\
+                    SYST:BEP:STAT ON\n\
+                    SYST:BEEP:STAT MAYBE\n\
+                    CONF:FREQ 5\n\
+                    v = MEAS?\n\
+                    w = MEAZ?\n\
+                    CONT:THRE 50; DIOD:THRE low";
+        assert_eq!(
+            messages(body),
+            vec![
+                (2, Severity::Error),
+                (4, Severity::Error),
+                (5, Severity::Error),
+                (7, Severity::Warning),
+                (8, Severity::Error),
+                (9, Severity::Error),
+                (11, Severity::Warning),
+                (12, Severity::Error),
+            ]
+        );
+        let issues = lint_macro("CONF:VOLT:DC 7", &owon());
+        assert_eq!(
+            issues[0].message,
+            "'7' is not a VDC range; use auto, 50mV, 500mV, 5V, 50V, 500V, 1000V"
+        );
+    }
+
+    #[test]
+    fn syntax_errors_and_unchecked_dialects() {
+        let issues = lint_macro("RATE F\nWAIT soon", &Dialect::Unchecked);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, 2);
+        assert!(issues[0].message.starts_with("WAIT needs a duration"));
+        assert!(lint_macro("FOO:BAR 1", &Dialect::Unchecked).is_empty());
+
+        assert_eq!(
+            Dialect::for_target(&MacroTarget::OwonMeas),
+            Dialect::OwonMeas {
+                range_meter: "OWON XDM1041".to_owned()
+            }
+        );
+        assert_eq!(
+            Dialect::for_target(&MacroTarget::OwonXdm6000),
+            Dialect::Unchecked
+        );
+    }
+
+    #[test]
+    fn reads_error_queue_replies() {
+        assert_eq!(scpi_error("0,\"No error\"\n"), None);
+        assert_eq!(scpi_error("+0,\"No error\""), None);
+        assert_eq!(
+            scpi_error("-113,\"Undefined header\""),
+            Some("-113,\"Undefined header\"".to_owned())
+        );
+        assert!(scpi_error("garbage").is_some());
+    }
+
+    #[test]
+    fn console_completions_lint_clean() {
+        for cmd in crate::scpi_macro::scpi_completions("") {
+            if !cmd.ends_with(' ') {
+                assert_eq!(lint_macro(&cmd, &owon()), [], "{cmd}");
+            }
+        }
+    }
+}
@@ -5,7 +5,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::multimeter::MeterMode;
+use crate::multimeter::{MeterMode, RangeCmd, RateCmd};
 
 /// SCPI dialect family inferred from `*IDN?`.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -65,6 +65,7 @@ impl MacroTarget {
 /// A named, user-editable SCPI sequence.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ScpiMacro {
+    #[serde(default = "new_macro_id")]
     pub id: String,
     pub name: String,
     pub body: String,
@@ -74,6 +75,9 @@ pub struct ScpiMacro {
     pub run_on_connect: bool,
     #[serde(default)]
     pub show_as_button: bool,
+    /// Keyboard chord such as `Ctrl+1`; empty for none.
+    #[serde(default)]
+    pub hotkey: String,
 }
 
 impl ScpiMacro {
@@ -85,17 +89,132 @@ impl ScpiMacro {
             applies_to: MacroTarget::default(),
             run_on_connect: false,
             show_as_button: true,
+            hotkey: String::new(),
         }
     }
 }
 
+/// Time-based ID with a per-process counter so IDs minted within the same
+/// clock tick (e.g. every ID-less entry of one macro file) stay distinct.
 pub fn new_macro_id() -> String {
+    use std::sync::atomic::{AtomicU64, Ordering};
     use std::time::{SystemTime, UNIX_EPOCH};
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
     let t = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_nanos())
         .unwrap_or(0);
-    format!("{t:x}")
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{t:x}-{n:x}")
+}
+
+/// Version written to macro library files; newer files are refused.
+const MACRO_FILE_VERSION: u32 = 1;
+
+/// A shareable macro library: pretty-printed JSON so it diffs well in
+/// version control. `id` may be left out of hand-written entries.
+#[derive(Serialize, Deserialize)]
+struct MacroFile {
+    rusty_meter_macros: u32,
+    macros: Vec<ScpiMacro>,
+}
+
+pub fn export_macros(macros: &[ScpiMacro]) -> String {
+    let file = MacroFile {
+        rusty_meter_macros: MACRO_FILE_VERSION,
+        macros: macros.to_vec(),
+    };
+    let mut text = serde_json::to_string_pretty(&file).expect("macros serialize to JSON");
+    text.push('\n');
+    text
+}
+
+/// Read a macro library file. A bare list of macros or a single macro is
+/// accepted too.
+pub fn parse_macro_file(text: &str) -> Result<Vec<ScpiMacro>, String> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Content {
+        File(MacroFile),
+        List(Vec<ScpiMacro>),
+        Single(ScpiMacro),
+    }
+    match serde_json::from_str(text) {
+        Ok(Content::File(file)) if file.rusty_meter_macros > MACRO_FILE_VERSION => Err(format!(
+            "Macro file version {} is newer than this RustyMeter supports",
+            file.rusty_meter_macros
+        )),
+        Ok(Content::File(file)) => Ok(file.macros),
+        Ok(Content::List(macros)) => Ok(macros),
+        Ok(Content::Single(m)) => Ok(vec![m]),
+        Err(_) => Err("Not a RustyMeter macro file".to_owned()),
+    }
+}
+
+/// What to do with an imported macro whose ID exists with other content.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportPolicy {
+    /// Add the import under a new ID next to the existing macro.
+    #[default]
+    KeepBoth,
+    /// Overwrite the existing macro, keeping its place in the list.
+    Replace,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub replaced: usize,
+    /// Identical to a macro already in the list.
+    pub unchanged: usize,
+}
+
+impl std::fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} added, {} replaced, {} unchanged",
+            self.added, self.replaced, self.unchanged
+        )
+    }
+}
+
+/// Merge `imported` into `existing`, matching macros by ID.
+pub fn import_macros(
+    existing: &mut Vec<ScpiMacro>,
+    imported: Vec<ScpiMacro>,
+    policy: ImportPolicy,
+) -> ImportSummary {
+    let mut summary = ImportSummary::default();
+    for mut m in imported {
+        match existing.iter().position(|e| e.id == m.id) {
+            None => {
+                existing.push(m);
+                summary.added += 1;
+            }
+            Some(idx) if existing[idx] == m => summary.unchanged += 1,
+            Some(idx) if policy == ImportPolicy::Replace => {
+                existing[idx] = m;
+                summary.replaced += 1;
+            }
+            Some(_) => {
+                let base = new_macro_id();
+                m.id = (0..)
+                    .map(|n| match n {
+                        0 => base.clone(),
+                        n => format!("{base}-{n}"),
+                    })
+                    .find(|id| existing.iter().all(|e| e.id != *id))
+                    .expect("an unused ID exists");
+                if existing.iter().any(|e| e.name == m.name) {
+                    m.name = format!("{} (imported)", m.name);
+                }
+                existing.push(m);
+                summary.added += 1;
+            }
+        }
+    }
+    summary
 }
 
 /// UI settings replayed as the built-in connect bootstrap.
@@ -167,15 +286,8 @@ pub fn range_table_meter(idn: &str) -> String {
 pub fn bootstrap_commands(family: ScpiFamily, s: &BootstrapSettings) -> Vec<String> {
     match family {
         ScpiFamily::OwonMeas => {
-            let mut cmds = vec![
-                format!("RATE {}\n", s.rate_opt),
-                format!(
-                    "SYST:BEEP:STATe {}\n",
-                    if s.beeper_enabled { "ON" } else { "OFF" }
-                ),
-                format!("CONT:THREshold {}\n", s.cont_threshold),
-                format!("DIOD:THREshold {}\n", s.diod_threshold),
-            ];
+            let mut cmds = vec![format!("RATE {}\n", s.rate_opt)];
+            cmds.extend(beeper_threshold_commands(family, s));
             if s.lock_remote {
                 cmds.push("SYST:REM\n".to_owned());
             }
@@ -186,6 +298,22 @@ pub fn bootstrap_commands(family: ScpiFamily, s: &BootstrapSettings) -> Vec<Stri
     }
 }
 
+/// Beeper and continuity / diode thresholds: part of the bootstrap, and sent
+/// on their own when a profile changes them on a live link.
+pub fn beeper_threshold_commands(family: ScpiFamily, s: &BootstrapSettings) -> Vec<String> {
+    match family {
+        ScpiFamily::OwonMeas => vec![
+            format!(
+                "SYST:BEEP:STATe {}\n",
+                if s.beeper_enabled { "ON" } else { "OFF" }
+            ),
+            format!("CONT:THREshold {}\n", s.cont_threshold),
+            format!("DIOD:THREshold {}\n", s.diod_threshold),
+        ],
+        ScpiFamily::OwonXdm6000 | ScpiFamily::Unknown => Vec::new(),
+    }
+}
+
 /// Split a user-edited body into wire commands. Queries are dropped so `MEAS?` / `FUNC?`
 /// cannot desync the serial task's `ScpiMode`.
 pub fn parse_macro_body(body: &str) -> ParsedMacro {
@@ -223,6 +351,118 @@ pub fn is_query(cmd: &str) -> bool {
     cmd.trim().trim_end_matches(['\r', '\n']).ends_with('?')
 }
 
+/// Whether a passthrough line makes the meter answer: any `;`-separated
+/// command whose header ends in `?`, including queries with parameters
+/// (`MEAS:VOLT? 5`). The meter answers such a line with one reply line.
+pub fn expects_reply(line: &str) -> bool {
+    line.split(';').any(|cmd| {
+        cmd.split_whitespace()
+            .next()
+            .is_some_and(|header| header.ends_with('?'))
+    })
+}
+
+/// Parameter a compact Owon command takes.
+#[derive(Clone, Copy)]
+pub(crate) enum Param {
+    None,
+    Any,
+    Number,
+    OnOff,
+    Rate,
+}
+
+/// Compact Owon commands besides `CONFigure`, each node in its short (upper
+/// case) or long form, `[NODE]` optional. The SCPI console completes from it
+/// and [`crate::scpi_lint`] checks macros against it.
+pub(crate) const OWON_MEAS_HEADERS: &[(&str, Param)] = &[
+    ("*IDN?", Param::None),
+    ("*RST", Param::None),
+    ("*CLS", Param::None),
+    ("MEASure?", Param::None),
+    ("FUNCtion?", Param::None),
+    ("RATE", Param::Rate),
+    ("RATE?", Param::None),
+    ("AUTO", Param::None),
+    ("AUTO?", Param::None),
+    ("RANGE", Param::Any),
+    ("RANGE?", Param::None),
+    ("CONTinuity:THREshold", Param::Number),
+    ("CONTinuity:THREshold?", Param::None),
+    ("DIODe:THREshold", Param::Number),
+    ("DIODe:THREshold?", Param::None),
+    ("SYSTem:BEEPer:[STATe]", Param::OnOff),
+    ("SYSTem:BEEPer:[STATe]?", Param::None),
+    ("SYSTem:REMote", Param::None),
+    ("SYSTem:LOCal", Param::None),
+    ("SYSTem:ERRor:[NEXT]?", Param::None),
+];
+
+/// Completion candidates for the compact Owon dialect: each header in its
+/// short form without optional nodes, expanded with its usual parameters.
+fn owon_meas_vocabulary() -> Vec<String> {
+    // `conf_prefixes` lists the long form first, then the short one.
+    let conf = MeterMode::ALL.into_iter().filter_map(|mode| {
+        let prefix = mode.conf_prefixes().get(1)?;
+        Some(match RangeCmd::new("OWON XDM1041", mode) {
+            Some(table) => format!("CONF:{prefix} {}", table.get_opt(0).1),
+            None => format!("CONF:{prefix}"),
+        })
+    });
+    let headers = OWON_MEAS_HEADERS.iter().flat_map(|&(pattern, kind)| {
+        let (pattern, query) = match pattern.strip_suffix('?') {
+            Some(pattern) => (pattern, "?"),
+            None => (pattern, ""),
+        };
+        let short: Vec<String> = pattern
+            .split(':')
+            .filter(|node| !node.starts_with('['))
+            .map(|node| node.chars().filter(|c| !c.is_ascii_lowercase()).collect())
+            .collect();
+        let header = format!("{}{query}", short.join(":"));
+        let params: Vec<String> = match kind {
+            Param::None => vec![String::new()],
+            Param::Any | Param::Number => vec![" ".to_owned()],
+            Param::OnOff => vec![" ON".to_owned(), " OFF".to_owned()],
+            Param::Rate => {
+                let rate = RateCmd::default();
+                (0..rate.len())
+                    .map(|i| format!(" {}", rate.get_opt(i).1))
+                    .collect()
+            }
+        };
+        params
+            .into_iter()
+            .map(move |param| format!("{header}{param}"))
+    });
+    headers.chain(conf).collect()
+}
+
+/// Vocabulary entries that start with `input`, ignoring case.
+pub fn scpi_completions(input: &str) -> Vec<String> {
+    let input = input.trim_start().to_ascii_uppercase();
+    owon_meas_vocabulary()
+        .into_iter()
+        .filter(|cmd| cmd.to_ascii_uppercase().starts_with(&input))
+        .collect()
+}
+
+/// What Tab turns `input` into: the only completion, or the longest prefix
+/// all completions share. `None` when that adds nothing.
+pub fn complete_scpi(input: &str) -> Option<String> {
+    let matches = scpi_completions(input);
+    let first = matches.first()?;
+    let shared = matches.iter().fold(first.len(), |len, cmd| {
+        first
+            .bytes()
+            .zip(cmd.bytes())
+            .take(len)
+            .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+            .count()
+    });
+    (shared > input.trim_start().len()).then(|| first[..shared].to_owned())
+}
+
 /// One FUNC/RATE/BEEP/AUTO(/RANGE) poll, applied atomically.
 ///
 /// Compact Owon `RANGE?` returns the live window even in autorange (`50 mV` on
@@ -431,7 +671,7 @@ pub fn is_recordable_scpi(cmd: &str) -> bool {
     true
 }
 
-fn strip_comment(line: &str) -> &str {
+pub fn strip_comment(line: &str) -> &str {
     let slash = line.find("//");
     let hash = line.find('#');
     match (slash, hash) {
@@ -447,6 +687,72 @@ mod tests {
     use super::*;
     use crate::multimeter::MeterMode;
 
+    fn named(id: &str, name: &str, body: &str) -> ScpiMacro {
+        let mut m = ScpiMacro::new(name);
+        m.id = id.to_owned();
+        m.body = body.to_owned();
+        m
+    }
+
+    #[test]
+    fn macro_files_round_trip() {
+        let mut m = named("a1", "VDC 5V", "CONF:VOLT:DC 5\n");
+        m.applies_to = MacroTarget::Model("XDM1041".to_owned());
+        m.run_on_connect = true;
+        let text = export_macros(std::slice::from_ref(&m));
+        assert!(text.contains("\"rusty_meter_macros\": 1"));
+        assert_eq!(parse_macro_file(&text), Ok(vec![m.clone()]));
+
+        let single = parse_macro_file(r#"{"name": "Fast", "body": "RATE F"}"#).unwrap();
+        assert_eq!(single[0].name, "Fast");
+        assert!(!single[0].id.is_empty());
+        assert_eq!(single[0].applies_to, MacroTarget::OwonMeas);
+        let list = parse_macro_file(r#"[{"id": "x", "name": "A", "body": ""}]"#).unwrap();
+        assert_eq!(list[0].id, "x");
+
+        let unnamed = parse_macro_file(
+            r#"[{"name": "A", "body": ""}, {"name": "B", "body": ""}, {"name": "C", "body": ""}]"#,
+        )
+        .unwrap();
+        assert_eq!(unnamed.len(), 3);
+        for (i, m) in unnamed.iter().enumerate() {
+            assert!(unnamed[i + 1..].iter().all(|o| o.id != m.id), "{m:?}");
+        }
+
+        assert!(parse_macro_file(r#"{"rusty_meter_macros": 9, "macros": []}"#).is_err());
+        assert!(parse_macro_file("name = 'toml'").is_err());
+    }
+
+    #[test]
+    fn import_handles_id_clashes() {
+        let mut existing = vec![named("a", "Fast", "RATE F"), named("b", "Slow", "RATE S")];
+        let imported = vec![
+            named("a", "Fast", "RATE F"),
+            named("b", "Slow", "RATE S\nSYST:BEEP:STATe OFF"),
+            named("c", "VAC", "CONF:VOLT:AC"),
+        ];
+        let summary = import_macros(&mut existing, imported.clone(), ImportPolicy::KeepBoth);
+        assert_eq!(
+            summary,
+            ImportSummary {
+                added: 2,
+                replaced: 0,
+                unchanged: 1
+            }
+        );
+        assert_eq!(existing.len(), 4);
+        assert_eq!(existing[1].body, "RATE S");
+        let copy = &existing[2];
+        assert_ne!(copy.id, "b");
+        assert_eq!(copy.name, "Slow (imported)");
+
+        let mut existing = vec![named("a", "Fast", "RATE F"), named("b", "Slow", "RATE S")];
+        let summary = import_macros(&mut existing, imported, ImportPolicy::Replace);
+        assert_eq!(summary.replaced, 1);
+        assert_eq!(existing.len(), 3);
+        assert_eq!(existing[1].body, "RATE S\nSYST:BEEP:STATe OFF");
+    }
+
     #[test]
     fn idn_model_from_standard_reply() {
         assert_eq!(idn_model("OWON,XDM1041,12345,V4.8.0"), "XDM1041");
@@ -515,6 +821,14 @@ mod tests {
         let s = settings();
         assert!(bootstrap_commands(ScpiFamily::OwonXdm6000, &s).is_empty());
         assert!(bootstrap_commands(ScpiFamily::Unknown, &s).is_empty());
+        assert!(beeper_threshold_commands(ScpiFamily::OwonXdm6000, &s).is_empty());
+    }
+
+    #[test]
+    fn beeper_thresholds_match_bootstrap() {
+        let s = settings();
+        let cmds = beeper_threshold_commands(ScpiFamily::OwonMeas, &s);
+        assert_eq!(cmds[..], bootstrap_commands(ScpiFamily::OwonMeas, &s)[1..4]);
     }
 
     #[test]
@@ -698,4 +1012,27 @@ CONF:VOLT:AC 500V
         assert!(!looks_like_idn("+3.210000E-01"));
         assert!(!looks_like_idn(""));
     }
+
+    #[test]
+    fn expects_reply_finds_queries_with_parameters() {
+        assert!(expects_reply("*IDN?"));
+        assert!(expects_reply("MEAS:VOLT? 5\n"));
+        assert!(expects_reply("CONF:VOLT:DC 5; MEAS?"));
+        assert!(!expects_reply("CONF:VOLT:DC 5"));
+        assert!(!expects_reply("SYST:BEEP:STATe OFF;RATE F"));
+        assert!(!expects_reply(""));
+    }
+
+    #[test]
+    fn completes_from_vocabulary() {
+        assert_eq!(scpi_completions("syst:beep?"), ["SYST:BEEP?"]);
+        assert_eq!(scpi_completions("rate "), ["RATE S", "RATE M", "RATE F"]);
+        assert_eq!(scpi_completions("CONF:CURR").len(), 2);
+        assert!(scpi_completions("XYZ").is_empty());
+        assert_eq!(complete_scpi("*i").as_deref(), Some("*IDN?"));
+        assert_eq!(complete_scpi("conf:c"), None);
+        assert_eq!(complete_scpi("conf:cu").as_deref(), Some("CONF:CURR:"));
+        assert_eq!(complete_scpi("RATE "), None);
+        assert_eq!(complete_scpi("nope"), None);
+    }
 }
@@ -0,0 +1,556 @@
+//! Sandboxed test scripts in [Rhai](https://rhai.rs).
+//!
+//! A script runs on its own thread with no file, network or module access of
+//! its own; everything it may do is a function registered here. Calls that
+//! touch the meter, the recorder or the UI become a [`ScriptRequest`] that
+//! the UI thread answers (`app::scripts`), so a waiting script never stalls
+//! drawing. Results files go to one configured folder only.
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    time::{Duration, Instant},
+};
+
+use rhai::{Dynamic, Engine, EvalAltResult, ImmutableString};
+
+use crate::http_api::{ApiCommand, parse_mode};
+use crate::stable::{StableDetector, StableEvent};
+
+/// Longest a script waits for a fresh reading.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Sleeps and waits are cut into slices this long so Stop acts promptly.
+const SLICE: Duration = Duration::from_millis(20);
+
+/// Something only the UI thread can do for a script.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptRequest {
+    /// Mode, range, rate, macro or recorder change, as over the HTTP API.
+    Command(ApiCommand),
+    /// Latest reading of a live channel (`m1`, `m2`, a derived name).
+    Reading(String),
+    Unit(String),
+    /// Set command for a meter channel.
+    Scpi {
+        channel: String,
+        cmd: String,
+    },
+    /// Query on the main meter, answered with the reply line.
+    Query(String),
+    /// Record the current readings, as the manual record button does.
+    Record,
+    Log {
+        label: String,
+        value: f64,
+    },
+    Print(String),
+    Beep,
+    Alarm(String),
+    Message(String),
+    /// Yes / No dialog.
+    Confirm(String),
+    /// Text entry dialog; Cancel answers with an error.
+    Input(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptValue {
+    None,
+    Bool(bool),
+    Text(String),
+    Reading { value: f64, time: f64 },
+}
+
+pub type ScriptReply = Result<ScriptValue, String>;
+
+pub struct ScriptCall {
+    pub request: ScriptRequest,
+    pub reply: mpsc::Sender<ScriptReply>,
+}
+
+/// What a running script needs from the app.
+#[derive(Clone)]
+pub struct ScriptHost {
+    pub calls: mpsc::Sender<ScriptCall>,
+    pub stop: Arc<AtomicBool>,
+    /// Wakes the UI thread so it answers without waiting for input.
+    pub wake: Arc<dyn Fn() + Send + Sync>,
+    /// Folder for `write_file` / `append_file`, empty to refuse them.
+    pub results_dir: PathBuf,
+}
+
+type RhaiResult<T> = Result<T, Box<EvalAltResult>>;
+
+impl ScriptHost {
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    fn call(&self, request: ScriptRequest) -> RhaiResult<ScriptValue> {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        self.calls
+            .send(ScriptCall {
+                request,
+                reply: reply_tx,
+            })
+            .map_err(|_| "The app stopped answering")?;
+        (self.wake)();
+        loop {
+            if self.stopped() {
+                return Err("Stopped".into());
+            }
+            match reply_rx.recv_timeout(SLICE) {
+                Ok(reply) => return reply.map_err(Into::into),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    return Err("The app stopped answering".into());
+                }
+            }
+        }
+    }
+
+    fn call_unit(&self, request: ScriptRequest) -> RhaiResult<()> {
+        self.call(request).map(|_| ())
+    }
+
+    fn sleep(&self, duration: Duration) -> RhaiResult<()> {
+        let deadline = Instant::now() + duration;
+        loop {
+            if self.stopped() {
+                return Err("Stopped".into());
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(());
+            }
+            std::thread::sleep(left.min(SLICE));
+        }
+    }
+
+    /// Value and UI time of the latest reading, `None` while there is none.
+    fn reading(&self, channel: &str) -> RhaiResult<Option<(f64, f64)>> {
+        match self.call(ScriptRequest::Reading(channel.to_owned()))? {
+            ScriptValue::Reading { value, time } => Ok(Some((value, time))),
+            _ => Ok(None),
+        }
+    }
+
+    fn read(&self, channel: &str) -> RhaiResult<f64> {
+        self.reading(channel)?
+            .map(|(value, _)| value)
+            .ok_or_else(|| format!("No reading on {channel}").into())
+    }
+
+    /// The first reading newer than the one current at the call.
+    fn read_new(&self, channel: &str) -> RhaiResult<f64> {
+        let after = self.reading(channel)?.map_or(f64::NEG_INFINITY, |(_, t)| t);
+        let deadline = Instant::now() + READ_TIMEOUT;
+        loop {
+            if let Some((value, time)) = self.reading(channel)? {
+                if time > after {
+                    return Ok(value);
+                }
+            }
+            if Instant::now() >= deadline {
+                return Err(format!("No new reading on {channel}").into());
+            }
+            self.sleep(SLICE)?;
+        }
+    }
+
+    /// Feed new readings to a [`StableDetector`] until one settles.
+    fn wait_stable(
+        &self,
+        channel: &str,
+        tolerance_pct: f64,
+        samples: usize,
+        timeout: Duration,
+    ) -> RhaiResult<f64> {
+        let mut detector = StableDetector::new(tolerance_pct, samples);
+        let mut last = f64::NEG_INFINITY;
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some((value, time)) = self.reading(channel)? {
+                if time > last {
+                    last = time;
+                    if let Some(StableEvent::Settled(mean)) = detector.push(value) {
+                        return Ok(mean);
+                    }
+                }
+            }
+            if Instant::now() >= deadline {
+                return Err(format!("{channel} did not settle in time").into());
+            }
+            self.sleep(SLICE)?;
+        }
+    }
+
+    fn write_results(&self, name: &str, text: &str, append: bool) -> RhaiResult<()> {
+        let path = results_path(&self.results_dir, name)?;
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&path)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()).into())
+    }
+}
+
+/// Where a results file called `name` goes. Only plain file names inside
+/// the results folder are allowed.
+pub fn results_path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    if dir.as_os_str().is_empty() {
+        return Err("Set a results folder in the Scripts window first".to_owned());
+    }
+    if name.trim().is_empty() || name.starts_with('.') || name.contains(['/', '\\', ':']) {
+        return Err(format!("'{name}' is not a plain file name"));
+    }
+    Ok(dir.join(name))
+}
+
+fn number(value: &Dynamic) -> RhaiResult<f64> {
+    value
+        .as_float()
+        .or_else(|_| value.as_int().map(|i| i as f64))
+        .map_err(|_| format!("Expected a number, got {}", value.type_name()).into())
+}
+
+fn millis(value: &Dynamic) -> RhaiResult<Duration> {
+    Ok(Duration::from_secs_f64(number(value)?.max(0.0) / 1000.0))
+}
+
+/// An engine with the meter, recorder and dialog functions bound to `host`,
+/// and the sandbox limits applied.
+pub fn build_engine(host: &ScriptHost) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.set_max_call_levels(64);
+    engine.set_max_expr_depths(64, 64);
+    engine.set_max_string_size(1 << 20);
+    engine.set_max_array_size(100_000);
+    engine.set_max_map_size(10_000);
+
+    let h = host.clone();
+    engine.on_progress(move |_| h.stopped().then(|| Dynamic::from("Stopped")));
+    let h = host.clone();
+    engine.on_print(move |text| {
+        let _ = h.call(ScriptRequest::Print(text.to_owned()));
+    });
+    let h = host.clone();
+    engine.on_debug(move |text, _, pos| {
+        let _ = h.call(ScriptRequest::Print(format!("[{pos}] {text}")));
+    });
+
+    let h = host.clone();
+    engine.register_fn("set_mode", move |mode: ImmutableString| -> RhaiResult<()> {
+        let mode = parse_mode(&mode).ok_or_else(|| format!("Unknown mode '{mode}'"))?;
+        h.call_unit(ScriptRequest::Command(ApiCommand::SetMode(mode)))
+    });
+    let h = host.clone();
+    engine.register_fn("set_range", move |range: ImmutableString| {
+        h.call_unit(ScriptRequest::Command(ApiCommand::SetRange(range.into())))
+    });
+    let h = host.clone();
+    engine.register_fn("set_rate", move |rate: ImmutableString| {
+        h.call_unit(ScriptRequest::Command(ApiCommand::SetRate(rate.into())))
+    });
+    let h = host.clone();
+    engine.register_fn("run_macro", move |name: ImmutableString| {
+        h.call_unit(ScriptRequest::Command(ApiCommand::RunMacro(name.into())))
+    });
+    let h = host.clone();
+    engine.register_fn("start_recording", move || {
+        h.call_unit(ScriptRequest::Command(ApiCommand::Recording(true)))
+    });
+    let h = host.clone();
+    engine.register_fn("stop_recording", move || {
+        h.call_unit(ScriptRequest::Command(ApiCommand::Recording(false)))
+    });
+
+    let h = host.clone();
+    engine.register_fn("read", move || h.read("m1"));
+    let h = host.clone();
+    engine.register_fn("read", move |channel: ImmutableString| h.read(&channel));
+    let h = host.clone();
+    engine.register_fn("read_new", move || h.read_new("m1"));
+    let h = host.clone();
+    engine.register_fn("read_new", move |channel: ImmutableString| {
+        h.read_new(&channel)
+    });
+    let h = host.clone();
+    engine.register_fn(
+        "wait_stable",
+        move |tolerance_pct: Dynamic, samples: i64, timeout_ms: Dynamic| {
+            h.wait_stable(
+                "m1",
+                number(&tolerance_pct)?,
+                samples.max(2) as usize,
+                millis(&timeout_ms)?,
+            )
+        },
+    );
+    let h = host.clone();
+    engine.register_fn(
+        "wait_stable",
+        move |channel: ImmutableString,
+              tolerance_pct: Dynamic,
+              samples: i64,
+              timeout_ms: Dynamic| {
+            h.wait_stable(
+                &channel,
+                number(&tolerance_pct)?,
+                samples.max(2) as usize,
+                millis(&timeout_ms)?,
+            )
+        },
+    );
+    let h = host.clone();
+    engine.register_fn("unit", move || -> RhaiResult<String> {
+        match h.call(ScriptRequest::Unit("m1".to_owned()))? {
+            ScriptValue::Text(unit) => Ok(unit),
+            _ => Ok(String::new()),
+        }
+    });
+    let h = host.clone();
+    engine.register_fn(
+        "unit",
+        move |channel: ImmutableString| -> RhaiResult<String> {
+            match h.call(ScriptRequest::Unit(channel.into()))? {
+                ScriptValue::Text(unit) => Ok(unit),
+                _ => Ok(String::new()),
+            }
+        },
+    );
+
+    let h = host.clone();
+    engine.register_fn("scpi", move |cmd: ImmutableString| {
+        h.call_unit(ScriptRequest::Scpi {
+            channel: "m1".to_owned(),
+            cmd: cmd.into(),
+        })
+    });
+    let h = host.clone();
+    engine.register_fn(
+        "scpi",
+        move |channel: ImmutableString, cmd: ImmutableString| {
+            h.call_unit(ScriptRequest::Scpi {
+                channel: channel.into(),
+                cmd: cmd.into(),
+            })
+        },
+    );
+    let h = host.clone();
+    engine.register_fn("query", move |cmd: ImmutableString| -> RhaiResult<String> {
+        match h.call(ScriptRequest::Query(cmd.into()))? {
+            ScriptValue::Text(reply) => Ok(reply),
+            _ => Err("The meter sent no reply".into()),
+        }
+    });
+
+    let h = host.clone();
+    engine.register_fn("record", move || h.call_unit(ScriptRequest::Record));
+    let h = host.clone();
+    engine.register_fn("log", move |label: ImmutableString, value: Dynamic| {
+        h.call_unit(ScriptRequest::Log {
+            label: label.into(),
+            value: number(&value)?,
+        })
+    });
+    let h = host.clone();
+    engine.register_fn("sleep", move |ms: Dynamic| h.sleep(millis(&ms)?));
+    let h = host.clone();
+    engine.register_fn("beep", move || h.call_unit(ScriptRequest::Beep));
+    let h = host.clone();
+    engine.register_fn("alarm", move |text: ImmutableString| {
+        h.call_unit(ScriptRequest::Alarm(text.into()))
+    });
+    let h = host.clone();
+    engine.register_fn("message", move |text: ImmutableString| {
+        h.call_unit(ScriptRequest::Message(text.into()))
+    });
+    let h = host.clone();
+    engine.register_fn(
+        "confirm",
+        move |text: ImmutableString| -> RhaiResult<bool> {
+            Ok(h.call(ScriptRequest::Confirm(text.into()))? == ScriptValue::Bool(true))
+        },
+    );
+    let h = host.clone();
+    engine.register_fn(
+        "input",
+        move |prompt: ImmutableString| -> RhaiResult<String> {
+            match h.call(ScriptRequest::Input(prompt.into()))? {
+                ScriptValue::Text(text) => Ok(text),
+                _ => Ok(String::new()),
+            }
+        },
+    );
+    let h = host.clone();
+    engine.register_fn(
+        "write_file",
+        move |name: ImmutableString, text: ImmutableString| h.write_results(&name, &text, false),
+    );
+    let h = host.clone();
+    engine.register_fn(
+        "append_file",
+        move |name: ImmutableString, text: ImmutableString| h.write_results(&name, &text, true),
+    );
+    engine
+}
+
+/// Compile and run `source` to the end. Blocks; call it on a script thread.
+pub fn run_script(source: &str, host: &ScriptHost) -> Result<(), String> {
+    let engine = build_engine(host);
+    let ast = engine.compile(source).map_err(|e| e.to_string())?;
+    match engine.run_ast(&ast) {
+        Ok(()) => Ok(()),
+        Err(_) if host.stopped() => Err("Stopped".to_owned()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Run `source` against a fake app answering with `answer`; returns the
+    /// result and every request the script made.
+    fn run_with(
+        source: &str,
+        answer: impl Fn(&ScriptRequest) -> ScriptReply + Send + 'static,
+    ) -> (Result<(), String>, Vec<ScriptRequest>) {
+        let (calls, rx) = mpsc::channel::<ScriptCall>();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let app = std::thread::spawn(move || {
+            for call in rx {
+                let _ = call.reply.send(answer(&call.request));
+                log.lock().unwrap().push(call.request);
+            }
+        });
+        let host = ScriptHost {
+            calls,
+            stop: Arc::new(AtomicBool::new(false)),
+            wake: Arc::new(|| {}),
+            results_dir: PathBuf::new(),
+        };
+        let result = run_script(source, &host);
+        drop(host);
+        app.join().unwrap();
+        let seen = seen.lock().unwrap().clone();
+        (result, seen)
+    }
+
+    #[test]
+    fn drives_meter_and_recorder() {
+        let (result, seen) = run_with(
+            r#"
+                set_mode("VDC");
+                let v = read();
+                if v > 1 { log("high", v); }
+                print(`${unit()} ${v}`);
+            "#,
+            |request| match request {
+                ScriptRequest::Reading(_) => Ok(ScriptValue::Reading {
+                    value: 2.5,
+                    time: 1.0,
+                }),
+                ScriptRequest::Unit(_) => Ok(ScriptValue::Text("V".to_owned())),
+                _ => Ok(ScriptValue::None),
+            },
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            seen,
+            vec![
+                ScriptRequest::Command(ApiCommand::SetMode(crate::multimeter::MeterMode::Vdc)),
+                ScriptRequest::Reading("m1".to_owned()),
+                ScriptRequest::Log {
+                    label: "high".to_owned(),
+                    value: 2.5
+                },
+                ScriptRequest::Unit("m1".to_owned()),
+                ScriptRequest::Print("V 2.5".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn app_errors_reach_the_script() {
+        let (result, _) = run_with(
+            r#"
+                let ok = false;
+                try { set_range("9kV"); } catch (e) { ok = e.contains("Unknown range"); }
+                if !ok { throw "not caught"; }
+                set_range("9kV");
+            "#,
+            |_| Err("Unknown range '9kV'".to_owned()),
+        );
+        assert!(result.unwrap_err().contains("Unknown range"));
+    }
+
+    #[test]
+    fn waits_for_a_stable_reading() {
+        let time = Arc::new(Mutex::new(0.0));
+        let (result, _) = run_with(
+            "let v = wait_stable(1, 3, 5000); if v != 2.0 { throw v; }",
+            move |_| {
+                let mut t = time.lock().unwrap();
+                *t += 1.0;
+                let value = if *t < 4.0 { *t * 10.0 } else { 2.0 };
+                Ok(ScriptValue::Reading { value, time: *t })
+            },
+        );
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn sandbox_refuses_modules_eval_and_paths() {
+        assert!(
+            run_with(r#"import "os" as os;"#, |_| Ok(ScriptValue::None))
+                .0
+                .is_err()
+        );
+        assert!(
+            run_with(r#"eval("1 + 1");"#, |_| Ok(ScriptValue::None))
+                .0
+                .is_err()
+        );
+        assert!(
+            run_with(r#"write_file("out.csv", "x");"#, |_| Ok(ScriptValue::None))
+                .0
+                .unwrap_err()
+                .contains("results folder")
+        );
+        let dir = Path::new("/tmp/results");
+        assert_eq!(
+            results_path(dir, "run1.csv"),
+            Ok(PathBuf::from("/tmp/results/run1.csv"))
+        );
+        assert!(results_path(dir, "../x").is_err());
+        assert!(results_path(dir, "a/b.txt").is_err());
+        assert!(results_path(dir, ".hidden").is_err());
+    }
+
+    #[test]
+    fn stop_ends_a_busy_loop() {
+        let (calls, _rx) = mpsc::channel();
+        let host = ScriptHost {
+            calls,
+            stop: Arc::new(AtomicBool::new(true)),
+            wake: Arc::new(|| {}),
+            results_dir: PathBuf::new(),
+        };
+        assert_eq!(run_script("loop {}", &host), Err("Stopped".to_owned()));
+    }
+}
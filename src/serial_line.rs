@@ -0,0 +1,151 @@
+//! Serial line settings: baud rate, data bits, parity, stop bits and flow
+//! control, shown as the usual `115200 8N1` shorthand.
+//!
+//! The app turns these into a `mio_serial` builder, so they apply before the
+//! port is opened.
+
+use serde::{Deserialize, Serialize};
+
+/// Baud rates offered in the settings; any other rate can be typed in.
+pub const COMMON_BAUD_RATES: [u32; 9] = [
+    1_200, 2_400, 4_800, 9_600, 19_200, 38_400, 57_600, 115_200, 230_400,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineParity {
+    None,
+    Odd,
+    Even,
+}
+
+impl LineParity {
+    pub const ALL: [Self; 3] = [Self::None, Self::Odd, Self::Even];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Odd => "Odd",
+            Self::Even => "Even",
+        }
+    }
+
+    fn letter(self) -> char {
+        match self {
+            Self::None => 'N',
+            Self::Odd => 'O',
+            Self::Even => 'E',
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FlowControl {
+    None,
+    /// XON/XOFF
+    Software,
+    /// RTS/CTS
+    Hardware,
+}
+
+impl FlowControl {
+    pub const ALL: [Self; 3] = [Self::None, Self::Software, Self::Hardware];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Software => "XON/XOFF",
+            Self::Hardware => "RTS/CTS",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SerialLine {
+    pub baud: u32,
+    pub data_bits: u8, // 5 to 8
+    pub parity: LineParity,
+    pub stop_bits: u8, // 1 or 2
+    pub flow_control: FlowControl,
+}
+
+impl Default for SerialLine {
+    /// OWON XDM: 115200 8N1.
+    fn default() -> Self {
+        Self::new(115_200, 8, LineParity::None, 1)
+    }
+}
+
+impl SerialLine {
+    pub const fn new(baud: u32, data_bits: u8, parity: LineParity, stop_bits: u8) -> Self {
+        Self {
+            baud,
+            data_bits,
+            parity,
+            stop_bits,
+            flow_control: FlowControl::None,
+        }
+    }
+
+    /// `9600 8N1`, with the flow control appended when there is one.
+    pub fn label(&self) -> String {
+        let mut label = format!(
+            "{} {}{}{}",
+            self.baud,
+            self.data_bits,
+            self.parity.letter(),
+            self.stop_bits
+        );
+        if self.flow_control != FlowControl::None {
+            label.push(' ');
+            label.push_str(self.flow_control.label());
+        }
+        label
+    }
+
+    /// Reject settings no serial port supports, before trying to open one.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(50..=4_000_000).contains(&self.baud) {
+            return Err(format!("Unsupported baud rate {}", self.baud));
+        }
+        if !(5..=8).contains(&self.data_bits) {
+            return Err(format!("Unsupported data bits {}", self.data_bits));
+        }
+        if !(1..=2).contains(&self.stop_bits) {
+            return Err(format!("Unsupported stop bits {}", self.stop_bits));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_and_validates_lines() {
+        assert_eq!(SerialLine::default().label(), "115200 8N1");
+        let mut victor = SerialLine::new(19_200, 7, LineParity::Odd, 1);
+        assert_eq!(victor.label(), "19200 7O1");
+        victor.flow_control = FlowControl::Hardware;
+        assert_eq!(victor.label(), "19200 7O1 RTS/CTS");
+        assert_eq!(victor.validate(), Ok(()));
+
+        let line = |baud, data_bits, stop_bits| SerialLine {
+            baud,
+            data_bits,
+            stop_bits,
+            ..SerialLine::default()
+        };
+        assert!(line(0, 8, 1).validate().is_err());
+        assert!(line(9_600, 9, 1).validate().is_err());
+        assert!(line(9_600, 8, 3).validate().is_err());
+        assert_eq!(line(250_000, 5, 2).validate(), Ok(()));
+    }
+
+    #[test]
+    fn fills_missing_fields_from_defaults() {
+        let line: SerialLine = serde_json::from_str(r#"{"baud": 9600}"#).unwrap();
+        assert_eq!(line.label(), "9600 8N1");
+    }
+}
@@ -0,0 +1,297 @@
+//! Keyboard shortcuts: bindable actions and `Ctrl+Shift+F5` style key chords.
+//!
+//! Chords are stored as text so settings stay readable. Key names are egui's
+//! (`A`, `1`, `F5`, `PageUp`, `Space`); the app resolves them to keys.
+
+use serde::{Deserialize, Serialize};
+
+use crate::multimeter::MeterMode;
+
+/// Something a shortcut can do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Mode(MeterMode),
+    RangeUp,
+    RangeDown,
+    NextRate,
+    Hold,
+    Rel,
+    ToggleRecording,
+    RecordPoint,
+}
+
+impl Action {
+    /// Every action, in overview order.
+    pub fn all() -> Vec<Self> {
+        MeterMode::ALL
+            .into_iter()
+            .map(Self::Mode)
+            .chain([
+                Self::RangeUp,
+                Self::RangeDown,
+                Self::NextRate,
+                Self::Hold,
+                Self::Rel,
+                Self::ToggleRecording,
+                Self::RecordPoint,
+            ])
+            .collect()
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Self::Mode(mode) => format!("Mode {}", mode.button_label()),
+            Self::RangeUp => "Range up".to_owned(),
+            Self::RangeDown => "Range down".to_owned(),
+            Self::NextRate => "Next sampling rate".to_owned(),
+            Self::Hold => "Hold".to_owned(),
+            Self::Rel => "REL (relative to current reading)".to_owned(),
+            Self::ToggleRecording => "Start/stop recording".to_owned(),
+            Self::RecordPoint => "Record point".to_owned(),
+        }
+    }
+}
+
+/// A key plus modifiers. `ctrl` means Cmd on macOS.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chord {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub key: String,
+}
+
+impl Chord {
+    /// Parse `Ctrl+Shift+F5`. Empty text is `Ok(None)`, i.e. unbound.
+    pub fn parse(spec: &str) -> Result<Option<Self>, String> {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            return Ok(None);
+        }
+        let mut parts: Vec<&str> = spec.split('+').map(str::trim).collect();
+        let key = parts.pop().unwrap_or_default();
+        if key.is_empty() {
+            return Err(format!("'{spec}' has no key"));
+        }
+        let mut chord = Self {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            key: if key.chars().count() == 1 {
+                key.to_uppercase()
+            } else {
+                key.to_owned()
+            },
+        };
+        for modifier in parts {
+            let flag = match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" | "command" => &mut chord.ctrl,
+                "alt" | "option" => &mut chord.alt,
+                "shift" => &mut chord.shift,
+                _ => return Err(format!("Unknown modifier '{modifier}' in '{spec}'")),
+            };
+            *flag = true;
+        }
+        Ok(Some(chord))
+    }
+
+    pub fn matches(&self, ctrl: bool, alt: bool, shift: bool, key: &str) -> bool {
+        self.ctrl == ctrl
+            && self.alt == alt
+            && self.shift == shift
+            && self.key.eq_ignore_ascii_case(key)
+    }
+}
+
+impl std::fmt::Display for Chord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (on, name) in [
+            (self.ctrl, "Ctrl+"),
+            (self.alt, "Alt+"),
+            (self.shift, "Shift+"),
+        ] {
+            if on {
+                f.write_str(name)?;
+            }
+        }
+        f.write_str(&self.key)
+    }
+}
+
+/// Normalised form of a chord, or the text as typed if it does not parse.
+pub fn normalize_chord(spec: &str) -> String {
+    match Chord::parse(spec) {
+        Ok(Some(chord)) => chord.to_string(),
+        Ok(None) => String::new(),
+        Err(_) => spec.trim().to_owned(),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShortcutSettings {
+    pub enabled: bool, // Persistent
+    /// Chord text per action; missing or empty means unbound.
+    pub keys: Vec<(Action, String)>, // Persistent
+    /// Key a keyboard-emulating foot pedal sends. It works even while a text
+    /// field has focus, so pick a key nothing else uses (e.g. F13).
+    pub pedal_key: String, // Persistent
+    pub pedal_action: Action, // Persistent
+}
+
+impl Default for ShortcutSettings {
+    fn default() -> Self {
+        let mode_keys = [
+            (MeterMode::Vdc, "1"),
+            (MeterMode::Vac, "2"),
+            (MeterMode::Adc, "3"),
+            (MeterMode::Aac, "4"),
+            (MeterMode::Res, "5"),
+            (MeterMode::Cap, "6"),
+            (MeterMode::Freq, "7"),
+            (MeterMode::Per, "8"),
+            (MeterMode::Diod, "9"),
+            (MeterMode::Cont, "0"),
+        ];
+        let keys = mode_keys
+            .into_iter()
+            .map(|(mode, key)| (Action::Mode(mode), key))
+            .chain([
+                (Action::RangeUp, "PageUp"),
+                (Action::RangeDown, "PageDown"),
+                (Action::NextRate, "R"),
+                (Action::Hold, "H"),
+                (Action::Rel, "Z"),
+                (Action::ToggleRecording, "Ctrl+R"),
+                (Action::RecordPoint, "Space"),
+            ])
+            .map(|(action, key)| (action, key.to_owned()))
+            .collect();
+        Self {
+            enabled: true,
+            keys,
+            pedal_key: String::new(),
+            pedal_action: Action::RecordPoint,
+        }
+    }
+}
+
+impl ShortcutSettings {
+    pub fn key(&self, action: Action) -> &str {
+        self.keys
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map_or("", |(_, key)| key.as_str())
+    }
+
+    pub fn set_key(&mut self, action: Action, spec: &str) {
+        let spec = normalize_chord(spec);
+        match self.keys.iter_mut().find(|(a, _)| *a == action) {
+            Some((_, key)) => *key = spec,
+            None => self.keys.push((action, spec)),
+        }
+    }
+
+    /// Action bound to a key press, if any.
+    pub fn action_for(&self, ctrl: bool, alt: bool, shift: bool, key: &str) -> Option<Action> {
+        self.keys.iter().find_map(|(action, spec)| {
+            let chord = Chord::parse(spec).ok()??;
+            chord.matches(ctrl, alt, shift, key).then_some(*action)
+        })
+    }
+}
+
+/// Chords bound more than once among `(label, chord)` pairs, each with the
+/// labels using it.
+pub fn duplicate_chords(bindings: &[(String, String)]) -> Vec<(String, Vec<String>)> {
+    let mut seen: Vec<(String, Vec<String>)> = Vec::new();
+    for (label, spec) in bindings {
+        let Ok(Some(chord)) = Chord::parse(spec) else {
+            continue;
+        };
+        let chord = chord.to_string();
+        match seen
+            .iter_mut()
+            .find(|(c, _)| c.eq_ignore_ascii_case(&chord))
+        {
+            Some((_, labels)) => labels.push(label.clone()),
+            None => seen.push((chord, vec![label.clone()])),
+        }
+    }
+    seen.retain(|(_, labels)| labels.len() > 1);
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_prints_chords() {
+        let chord = Chord::parse(" shift + ctrl+f5 ").unwrap().unwrap();
+        assert_eq!(
+            chord,
+            Chord {
+                ctrl: true,
+                alt: false,
+                shift: true,
+                key: "f5".to_owned()
+            }
+        );
+        assert_eq!(chord.to_string(), "Ctrl+Shift+f5");
+        assert_eq!(normalize_chord("cmd+option+r"), "Ctrl+Alt+R");
+        assert_eq!(normalize_chord("  "), "");
+        assert_eq!(Chord::parse(""), Ok(None));
+        assert!(Chord::parse("Ctrl+").is_err());
+        assert!(Chord::parse("Hyper+A").is_err());
+        assert!(chord.matches(true, false, true, "F5"));
+        assert!(!chord.matches(true, false, false, "f5"));
+    }
+
+    #[test]
+    fn bindings_resolve_and_report_clashes() {
+        let mut settings = ShortcutSettings::default();
+        assert_eq!(
+            settings.action_for(false, false, false, "1"),
+            Some(Action::Mode(MeterMode::Vdc))
+        );
+        assert_eq!(
+            settings.action_for(true, false, false, "R"),
+            Some(Action::ToggleRecording)
+        );
+        assert_eq!(
+            settings.action_for(false, false, false, "R"),
+            Some(Action::NextRate)
+        );
+        assert_eq!(settings.action_for(false, false, true, "R"), None);
+
+        settings.set_key(Action::Hold, "shift+r");
+        assert_eq!(settings.key(Action::Hold), "Shift+R");
+        settings.set_key(Action::Mode(MeterMode::Temp), "ctrl+t");
+        assert_eq!(
+            settings.action_for(true, false, false, "T"),
+            Some(Action::Mode(MeterMode::Temp))
+        );
+
+        let bindings = vec![
+            ("Hold".to_owned(), "Shift+R".to_owned()),
+            ("Macro A".to_owned(), "shift+r".to_owned()),
+            ("Macro B".to_owned(), "F9".to_owned()),
+            ("Macro C".to_owned(), "f9".to_owned()),
+            ("Unbound".to_owned(), String::new()),
+        ];
+        assert_eq!(
+            duplicate_chords(&bindings),
+            vec![
+                (
+                    "Shift+R".to_owned(),
+                    vec!["Hold".to_owned(), "Macro A".to_owned()]
+                ),
+                (
+                    "F9".to_owned(),
+                    vec!["Macro B".to_owned(), "Macro C".to_owned()]
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Detects when a reading has settled, e.g. a part clipped into the probes.
+//!
+//! A reading is stable once the last `samples` values all lie within
+//! `tolerance_pct` of their mean. The settled value is then held until a
+//! reading leaves that band again (part removed, probes opened), after which
+//! the next settled reading counts as a new part.
+
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StableEvent {
+    /// A new stable value, the mean of the settling window.
+    Settled(f64),
+    /// The held value was left, waiting for the next one.
+    Released,
+}
+
+#[derive(Clone, Debug)]
+pub struct StableDetector {
+    pub tolerance_pct: f64,
+    pub samples: usize,
+    window: VecDeque<f64>,
+    held: Option<f64>,
+}
+
+impl StableDetector {
+    pub fn new(tolerance_pct: f64, samples: usize) -> Self {
+        Self {
+            tolerance_pct,
+            samples,
+            window: VecDeque::new(),
+            held: None,
+        }
+    }
+
+    /// Currently held stable value.
+    pub fn held(&self) -> Option<f64> {
+        self.held
+    }
+
+    fn within(&self, value: f64, reference: f64) -> bool {
+        (value - reference).abs() <= self.tolerance_pct / 100.0 * reference.abs()
+    }
+
+    /// Feed one reading. Non-finite readings (open probes, overload) clear
+    /// the window and release a held value.
+    pub fn push(&mut self, value: f64) -> Option<StableEvent> {
+        let needed = self.samples.max(1);
+        if !value.is_finite() {
+            self.window.clear();
+            return self.held.take().map(|_| StableEvent::Released);
+        }
+        if let Some(held) = self.held {
+            if self.within(value, held) {
+                return None;
+            }
+            self.held = None;
+            self.window.clear();
+            self.window.push_back(value);
+            return Some(StableEvent::Released);
+        }
+        self.window.push_back(value);
+        while self.window.len() > needed {
+            self.window.pop_front();
+        }
+        if self.window.len() < needed {
+            return None;
+        }
+        let mean = self.window.iter().sum::<f64>() / needed as f64;
+        if self.window.iter().all(|&x| self.within(x, mean)) {
+            self.held = Some(mean);
+            self.window.clear();
+            Some(StableEvent::Settled(mean))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settles_once_per_part() {
+        let mut det = StableDetector::new(1.0, 3);
+        assert_eq!(det.push(f64::NAN), None);
+        assert_eq!(det.push(500.0), None); // still settling
+        assert_eq!(det.push(99.0), None);
+        assert_eq!(det.push(100.0), None);
+        let Some(StableEvent::Settled(v)) = det.push(100.5) else {
+            panic!("expected a settled reading");
+        };
+        assert!((v - 99.833_333).abs() < 1e-5);
+        // Holding: small noise is ignored, no second event for the same part
+        assert_eq!(det.push(100.2), None);
+        assert_eq!(det.push(99.5), None);
+        // Part removed
+        assert_eq!(det.push(f64::INFINITY), Some(StableEvent::Released));
+        assert_eq!(det.held(), None);
+        for _ in 0..2 {
+            assert_eq!(det.push(220.0), None);
+        }
+        assert_eq!(det.push(220.0), Some(StableEvent::Settled(220.0)));
+    }
+
+    #[test]
+    fn jump_to_other_value_releases() {
+        let mut det = StableDetector::new(0.5, 2);
+        det.push(10.0);
+        assert_eq!(det.push(10.0), Some(StableEvent::Settled(10.0)));
+        assert_eq!(det.push(12.0), Some(StableEvent::Released));
+        // The jump reading starts the new window
+        assert_eq!(det.push(12.0), Some(StableEvent::Settled(12.0)));
+    }
+}
@@ -0,0 +1,243 @@
+//! Descriptive statistics and binning for the histogram tab.
+
+/// Summary of a sample set. `std_dev` is the sample standard deviation
+/// (n - 1), zero for a single sample.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Summary {
+    /// σ relative to the mean in ppm, `None` when the mean is zero.
+    pub fn relative_ppm(&self) -> Option<f64> {
+        if self.mean == 0.0 {
+            None
+        } else {
+            Some(self.std_dev / self.mean.abs() * 1e6)
+        }
+    }
+
+    /// Expected count of a bin of `bin_width` centred on `x` under a normal
+    /// distribution with this mean and σ.
+    pub fn normal_count(&self, x: f64, bin_width: f64) -> f64 {
+        self.count as f64 * bin_width * normal_pdf(x, self.mean, self.std_dev)
+    }
+}
+
+/// Mean, σ, min and max over the finite values. Uses Welford's update so a
+/// few µV of noise on a 10 V reference does not cancel out.
+pub fn summarize(values: &[f64]) -> Option<Summary> {
+    let mut count = 0usize;
+    let mut mean = 0.0;
+    let mut m2 = 0.0;
+    let mut min = f64::INFINITY;
+    let mut max = f64::NEG_INFINITY;
+    for &x in values.iter().filter(|x| x.is_finite()) {
+        count += 1;
+        let delta = x - mean;
+        mean += delta / count as f64;
+        m2 += delta * (x - mean);
+        min = min.min(x);
+        max = max.max(x);
+    }
+    if count == 0 {
+        return None;
+    }
+    let std_dev = if count > 1 {
+        (m2 / (count - 1) as f64).sqrt()
+    } else {
+        0.0
+    };
+    Some(Summary {
+        count,
+        mean,
+        std_dev,
+        min,
+        max,
+    })
+}
+
+pub fn normal_pdf(x: f64, mean: f64, std_dev: f64) -> f64 {
+    if std_dev <= 0.0 {
+        return 0.0;
+    }
+    let z = (x - mean) / std_dev;
+    (-0.5 * z * z).exp() / (std_dev * (2.0 * std::f64::consts::PI).sqrt())
+}
+
+/// Histogram bin counts over `[range_start, range_start + counts.len() * bin_width]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistogramBins {
+    pub counts: Vec<usize>,
+    pub range_start: f64,
+    pub bin_width: f64,
+    pub min: f64,
+    pub max: f64,
+    /// A fixed bin width was requested but would need more than `max_bins`
+    /// bins, so the range was split into `max_bins` instead.
+    pub width_clamped: bool,
+}
+
+impl HistogramBins {
+    pub fn bin_center(&self, i: usize) -> f64 {
+        self.range_start + (i as f64 + 0.5) * self.bin_width
+    }
+}
+
+/// Bin `values`. A positive `bin_width` (in measurement units) wins and
+/// aligns bin edges to multiples of it; otherwise `num_bins` is used, with
+/// 0 meaning the square root rule. Never more than `max_bins` bins.
+pub fn histogram_bins(
+    values: &[f64],
+    num_bins: usize,
+    max_bins: usize,
+    bin_width: f64,
+) -> Option<HistogramBins> {
+    if values.is_empty() {
+        return None;
+    }
+    let max_bins = max_bins.max(1);
+    // Calculate min and max for binning
+    let (min, max) = values
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &x| {
+            (min.min(x), max.max(x))
+        });
+    // Ensure valid range, handle single-value case
+    let range_width = if min == max {
+        if min == 0.0 {
+            1.0 // Avoid zero range for zero values
+        } else {
+            min.abs() * 0.1 // 10% of value for single value
+        }
+    } else {
+        max - min
+    };
+    let mut range_start = if min == max {
+        min - range_width / 2.0
+    } else {
+        min
+    };
+
+    let mut width_clamped = false;
+    let (num_bins, bin_width) = if bin_width > 0.0 {
+        // Fixed width, edges on multiples of the width
+        let start = (min / bin_width).floor() * bin_width;
+        let needed = ((max - start) / bin_width).floor() as usize + 1;
+        if needed <= max_bins {
+            range_start = start;
+            (needed, bin_width)
+        } else {
+            width_clamped = true;
+            (max_bins, range_width / max_bins as f64)
+        }
+    } else {
+        // Determine number of bins
+        let num_bins = if num_bins == 0 {
+            // Auto-bin using square root rule, capped at max_bins
+            let sqrt_bins = (values.len() as f64).sqrt().ceil() as usize;
+            sqrt_bins.min(max_bins).max(1) // Ensure at least one bin
+        } else {
+            num_bins.max(1) // Ensure at least one bin
+        };
+        (num_bins, range_width / num_bins as f64)
+    };
+    let range_end = range_start + bin_width * num_bins as f64;
+
+    // Create bins
+    let mut counts = vec![0; num_bins];
+    for &value in values {
+        if value >= range_start && value <= range_end {
+            let bin_index = ((value - range_start) / bin_width).floor() as usize;
+            let bin_index = bin_index.min(num_bins - 1); // Clamp to last bin
+            counts[bin_index] += 1;
+        }
+    }
+
+    Some(HistogramBins {
+        counts,
+        range_start,
+        bin_width,
+        min,
+        max,
+        width_clamped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarize_known_set() {
+        let s = summarize(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]).unwrap();
+        assert_eq!(s.count, 8);
+        assert_eq!(s.mean, 5.0);
+        assert!((s.std_dev - (32.0f64 / 7.0).sqrt()).abs() < 1e-12);
+        assert_eq!((s.min, s.max), (2.0, 9.0));
+    }
+
+    #[test]
+    fn summarize_skips_non_finite_and_handles_single() {
+        assert_eq!(summarize(&[]), None);
+        assert_eq!(summarize(&[f64::NAN]), None);
+        let s = summarize(&[1.5, f64::NAN, f64::INFINITY]).unwrap();
+        assert_eq!((s.count, s.mean, s.std_dev), (1, 1.5, 0.0));
+    }
+
+    #[test]
+    fn summarize_keeps_small_noise_on_large_offset() {
+        let values: Vec<f64> = (0..1000)
+            .map(|i| 10.0 + if i % 2 == 0 { 1e-6 } else { -1e-6 })
+            .collect();
+        let s = summarize(&values).unwrap();
+        assert!((s.std_dev - 1e-6).abs() < 1e-9);
+        assert!((s.relative_ppm().unwrap() - 0.1).abs() < 1e-3);
+    }
+
+    #[test]
+    fn normal_pdf_peak_and_degenerate() {
+        assert!((normal_pdf(0.0, 0.0, 1.0) - 0.398_942_280_4).abs() < 1e-9);
+        assert_eq!(normal_pdf(1.0, 1.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn bins_by_count_cover_range() {
+        let values = [0.0, 1.0, 2.0, 3.0, 4.0];
+        let bins = histogram_bins(&values, 4, 100, 0.0).unwrap();
+        assert_eq!(bins.counts, vec![1, 1, 1, 2]);
+        assert_eq!(bins.range_start, 0.0);
+        assert_eq!(bins.bin_width, 1.0);
+        assert_eq!(bins.bin_center(0), 0.5);
+    }
+
+    #[test]
+    fn bins_by_width_align_to_multiples() {
+        let values = [0.95, 1.02, 1.04, 1.31];
+        let bins = histogram_bins(&values, 0, 100, 0.1).unwrap();
+        assert!(!bins.width_clamped);
+        assert!((bins.range_start - 0.9).abs() < 1e-12);
+        assert_eq!(bins.counts, vec![1, 2, 0, 0, 1]);
+    }
+
+    #[test]
+    fn bins_by_width_clamp_to_max_bins() {
+        let values = [0.0, 1.0];
+        let bins = histogram_bins(&values, 0, 10, 0.001).unwrap();
+        assert!(bins.width_clamped);
+        assert_eq!(bins.counts.len(), 10);
+        assert_eq!(bins.counts.iter().sum::<usize>(), 2);
+    }
+
+    #[test]
+    fn bins_single_value() {
+        let bins = histogram_bins(&[5.0, 5.0], 0, 100, 0.0).unwrap();
+        assert_eq!(bins.counts.iter().sum::<usize>(), 2);
+        assert!(bins.range_start < 5.0);
+        assert_eq!(histogram_bins(&[], 0, 100, 0.0), None);
+    }
+}